@@ -1,15 +1,21 @@
-use axum::{extract::State, Json, http::StatusCode};
+use axum::{body::{Body, Bytes}, extract::{Path, Query, State}, Json, http::{HeaderMap, StatusCode, header}, response::{IntoResponse, Response}};
+use futures::StreamExt;
+use serde::Deserialize;
 use std::sync::Arc;
-use serde_json;
-use crate::service::{EncryptionService, EncryptRequest, EncryptResponse, DecryptRequest, DecryptResponse, GenericResponse};
+use tracing::warn;
+use crate::auth;
+use crate::cache::{CacheEntryPage, CacheStats};
+use crate::monitoring;
+use crate::service::{EncryptionService, ServiceError, EncryptRequest, EncryptResponse, DecryptRequest, DecryptResponse, VerifyResponse, RewrapRequest, RewrapResponse, RotateRequest, RotateResponse, UpdateRequest, DeleteResponse, ReadinessReport, GenericResponse, BatchItemResult};
 
 /// 健康检查处理函数
 #[axum::debug_handler]
 pub async fn health_check(
     State(service): State<Arc<EncryptionService>>,
 ) -> (StatusCode, Json<GenericResponse<serde_json::Value>>) {
-    // 调用服务健康检查
-    match service.health_check().await {
+    // 综合健康检查：配置有效且有健康的CRUD API实例
+    let report = service.readiness();
+    match report.health_result() {
         Ok(_) => {
             let response = GenericResponse {
                 success: true,
@@ -17,7 +23,9 @@ pub async fn health_check(
                 data: Some(serde_json::json!({ 
                     "service_id": service.get_service_id(), 
                     "service_role": service.get_service_role(),
-                    "status": "ok" 
+                    "effective_role": service.get_effective_role(),
+                    "status": "ok",
+                    "subsystems": report,
                 })),
             };
             (StatusCode::OK, Json(response))
@@ -25,41 +33,176 @@ pub async fn health_check(
         Err(e) => {
             let response = GenericResponse {
                 success: false,
-                message: format!("服务健康检查失败: {}", e),
-                data: None,
+                message: format!("服务健康检查失败: {}", service.client_error(&e)),
+                data: Some(serde_json::json!({ "subsystems": report })),
             };
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(response))
+            (e.status_code(), Json(response))
         },
     }
 }
 
+/// 存活检查处理函数，进程能处理请求即返回200
+#[axum::debug_handler]
+pub async fn liveness() -> (StatusCode, Json<GenericResponse<serde_json::Value>>) {
+    let response = GenericResponse {
+        success: true,
+        message: "服务存活".to_string(),
+        data: Some(serde_json::json!({ "status": "ok" })),
+    };
+    (StatusCode::OK, Json(response))
+}
+
+/// 就绪检查处理函数，无法处理请求时返回503
+///
+/// CRUD API全部不可用但本地缓存可用时仍返回200，data.degraded为true
+#[axum::debug_handler]
+pub async fn readiness(
+    State(service): State<Arc<EncryptionService>>,
+) -> (StatusCode, Json<GenericResponse<ReadinessReport>>) {
+    let report = service.readiness();
+    let (status, message) = if report.ready {
+        (StatusCode::OK, "服务已就绪")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "服务未就绪")
+    };
+    let response = GenericResponse {
+        success: report.ready,
+        message: message.to_string(),
+        data: Some(report),
+    };
+    (status, Json(response))
+}
+
+/// 服务状态处理函数
+#[axum::debug_handler]
+pub async fn status(
+    State(service): State<Arc<EncryptionService>>,
+) -> (StatusCode, Json<GenericResponse<serde_json::Value>>) {
+    let maintenance = service.is_in_maintenance();
+    let response = GenericResponse {
+        success: true,
+        message: "获取服务状态成功".to_string(),
+        data: Some(serde_json::json!({
+            "service_id": service.get_service_id(),
+            "service_role": service.get_service_role(),
+            "effective_role": service.get_effective_role(),
+            "mode": if maintenance { "maintenance" } else { "normal" },
+            "test_instance": service.get_test_instance_manager().get_test_instance(),
+        })),
+    };
+    (StatusCode::OK, Json(response))
+}
+
+/// 版本信息处理函数，返回构建版本、git提交、构建时间和当前运行模式
+#[axum::debug_handler]
+pub async fn version(
+    State(service): State<Arc<EncryptionService>>,
+) -> (StatusCode, Json<GenericResponse<serde_json::Value>>) {
+    let build_timestamp: i64 = env!("BUILD_TIMESTAMP").parse().unwrap_or_default();
+    let build_time = chrono::DateTime::from_timestamp(build_timestamp, 0)
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_default();
+    let response = GenericResponse {
+        success: true,
+        message: "获取版本信息成功".to_string(),
+        data: Some(serde_json::json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "git_sha": env!("BUILD_GIT_SHA"),
+            "build_time": build_time,
+            "service_id": service.get_service_id(),
+            "service_role": service.get_service_role(),
+            "scheduler_strategy": service.get_config().crud_api.strategy,
+        })),
+    };
+    (StatusCode::OK, Json(response))
+}
+
+/// 指标处理函数，以Prometheus文本格式返回
+#[axum::debug_handler]
+pub async fn metrics() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        monitoring::render(),
+    )
+}
+
+/// 实例状态处理函数，返回各CRUD API实例的健康状态和Test实例状态
+#[axum::debug_handler]
+pub async fn instances_status(
+    State(service): State<Arc<EncryptionService>>,
+) -> (StatusCode, Json<GenericResponse<serde_json::Value>>) {
+    let response = GenericResponse {
+        success: true,
+        message: "获取实例状态成功".to_string(),
+        data: Some(serde_json::json!({
+            "instances": service.get_scheduler().get_all_instance_status(),
+            "test_instance": service.get_test_instance_manager().get_test_instance(),
+        })),
+    };
+    (StatusCode::OK, Json(response))
+}
+
 /// 加密处理函数
+///
+/// 数据已持久化且配置了Location模板时返回201和Location响应头，否则返回200
+#[utoipa::path(
+    post,
+    path = "/encrypt",
+    tag = "加解密",
+    request_body = EncryptRequest,
+    responses(
+        (status = 200, description = "加密成功，数据未持久化或未配置Location模板", body = GenericResponse<EncryptResponse>),
+        (status = 201, description = "加密成功且已持久化", body = GenericResponse<EncryptResponse>,
+            headers(("Location" = String, description = "资源地址"))),
+        (status = 400, description = "请求参数无效", body = GenericResponse<EncryptResponse>),
+        (status = 401, description = "访问令牌缺失、无效或已过期"),
+        (status = 403, description = "服务角色不允许加密", body = GenericResponse<EncryptResponse>),
+    ),
+    security(("bearer" = [])),
+)]
 #[axum::debug_handler]
 pub async fn encrypt(
     State(service): State<Arc<EncryptionService>>,
     Json(request): Json<EncryptRequest>,
-) -> (StatusCode, Json<GenericResponse<EncryptResponse>>) {
+) -> Response {
     match service.encrypt(request).await {
         Ok(response) => {
+            let location = response.location.clone();
             let response = GenericResponse {
                 success: true,
                 message: "加密成功".to_string(),
                 data: Some(response),
             };
-            (StatusCode::OK, Json(response))
+            match location {
+                Some(location) => (StatusCode::CREATED, [(header::LOCATION, location)], Json(response)).into_response(),
+                None => (StatusCode::OK, Json(response)).into_response(),
+            }
         },
         Err(e) => {
-            let response = GenericResponse {
+            let response: GenericResponse<EncryptResponse> = GenericResponse {
                 success: false,
-                message: format!("加密失败: {}", e),
+                message: format!("加密失败: {}", service.client_error(&e)),
                 data: None,
             };
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(response))
+            (e.status_code(), Json(response)).into_response()
         },
     }
 }
 
 /// 解密处理函数
+#[utoipa::path(
+    post,
+    path = "/decrypt",
+    tag = "加解密",
+    request_body = DecryptRequest,
+    responses(
+        (status = 200, description = "解密成功", body = GenericResponse<DecryptResponse>),
+        (status = 400, description = "请求参数无效或解密失败", body = GenericResponse<DecryptResponse>),
+        (status = 401, description = "访问令牌缺失、无效或已过期"),
+        (status = 403, description = "服务角色不允许解密", body = GenericResponse<DecryptResponse>),
+    ),
+    security(("bearer" = [])),
+)]
 #[axum::debug_handler]
 pub async fn decrypt(
     State(service): State<Arc<EncryptionService>>,
@@ -77,51 +220,480 @@ pub async fn decrypt(
         Err(e) => {
             let response = GenericResponse {
                 success: false,
-                message: format!("解密失败: {}", e),
+                message: format!("解密失败: {}", service.client_error(&e)),
                 data: None,
             };
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(response))
+            (e.status_code(), Json(response))
         },
     }
 }
 
+/// 密码校验处理函数
+#[utoipa::path(
+    post,
+    path = "/decrypt/verify",
+    tag = "加解密",
+    request_body = DecryptRequest,
+    responses(
+        (status = 200, description = "校验完成，响应不包含明文；密码错误时valid为false", body = GenericResponse<VerifyResponse>),
+        (status = 401, description = "访问令牌缺失、无效或已过期"),
+        (status = 403, description = "服务角色不允许解密", body = GenericResponse<VerifyResponse>),
+    ),
+    security(("bearer" = [])),
+)]
+#[axum::debug_handler]
+pub async fn verify(
+    State(service): State<Arc<EncryptionService>>,
+    Json(request): Json<DecryptRequest>,
+) -> (StatusCode, Json<GenericResponse<VerifyResponse>>) {
+    match service.verify(request).await {
+        Ok(response) => {
+            let response = GenericResponse {
+                success: true,
+                message: "校验完成".to_string(),
+                data: Some(response),
+            };
+            (StatusCode::OK, Json(response))
+        },
+        Err(e) => {
+            let response = GenericResponse {
+                success: false,
+                message: format!("校验失败: {}", service.client_error(&e)),
+                data: None,
+            };
+            (e.status_code(), Json(response))
+        },
+    }
+}
+
+/// 字段重新加密处理函数
+#[utoipa::path(
+    post,
+    path = "/rewrap",
+    tag = "加解密",
+    request_body = RewrapRequest,
+    responses(
+        (status = 200, description = "重新加密成功", body = GenericResponse<RewrapResponse>),
+        (status = 400, description = "请求参数无效或解密失败", body = GenericResponse<RewrapResponse>),
+        (status = 401, description = "访问令牌缺失、无效或已过期"),
+        (status = 503, description = "CRUD API不可用", body = GenericResponse<RewrapResponse>),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn rewrap(
+    State(service): State<Arc<EncryptionService>>,
+    Json(request): Json<RewrapRequest>,
+) -> (StatusCode, Json<GenericResponse<RewrapResponse>>) {
+    match service.rewrap(request).await {
+        Ok(response) => {
+            let response = GenericResponse {
+                success: true,
+                message: "重新加密成功".to_string(),
+                data: Some(response),
+            };
+            (StatusCode::OK, Json(response))
+        },
+        Err(e) => {
+            let response = GenericResponse {
+                success: false,
+                message: format!("重新加密失败: {}", service.client_error(&e)),
+                data: None,
+            };
+            (e.status_code(), Json(response))
+        },
+    }
+}
+
+/// 密钥轮换处理函数
+#[utoipa::path(
+    post,
+    path = "/rotate",
+    tag = "加解密",
+    request_body = RotateRequest,
+    responses(
+        (status = 200, description = "密钥轮换成功", body = GenericResponse<RotateResponse>),
+        (status = 400, description = "请求参数无效或解密失败", body = GenericResponse<RotateResponse>),
+        (status = 401, description = "访问令牌缺失、无效或已过期"),
+        (status = 403, description = "服务角色不允许轮换", body = GenericResponse<RotateResponse>),
+    ),
+    security(("bearer" = [])),
+)]
+pub async fn rotate(
+    State(service): State<Arc<EncryptionService>>,
+    Json(request): Json<RotateRequest>,
+) -> (StatusCode, Json<GenericResponse<RotateResponse>>) {
+    match service.rotate(request).await {
+        Ok(response) => {
+            let response = GenericResponse {
+                success: true,
+                message: "密钥轮换成功".to_string(),
+                data: Some(response),
+            };
+            (StatusCode::OK, Json(response))
+        },
+        Err(e) => {
+            let response = GenericResponse {
+                success: false,
+                message: format!("密钥轮换失败: {}", service.client_error(&e)),
+                data: None,
+            };
+            (e.status_code(), Json(response))
+        },
+    }
+}
+
+/// 更新资源处理函数
+#[utoipa::path(
+    put,
+    path = "/resource/{resource_type}/{resource_id}",
+    tag = "资源",
+    params(
+        ("resource_type" = String, Path, description = "资源类型"),
+        ("resource_id" = String, Path, description = "资源ID"),
+    ),
+    request_body = UpdateRequest,
+    responses(
+        (status = 200, description = "更新成功，CRUD API不可用时数据已缓存等待重放", body = GenericResponse<EncryptResponse>),
+        (status = 400, description = "请求参数无效", body = GenericResponse<EncryptResponse>),
+        (status = 401, description = "访问令牌缺失、无效或已过期"),
+        (status = 403, description = "服务角色不允许更新", body = GenericResponse<EncryptResponse>),
+        (status = 404, description = "资源不存在", body = GenericResponse<EncryptResponse>),
+    ),
+    security(("bearer" = [])),
+)]
+#[axum::debug_handler]
+pub async fn update_resource(
+    State(service): State<Arc<EncryptionService>>,
+    Path((resource_type, resource_id)): Path<(String, String)>,
+    Json(request): Json<UpdateRequest>,
+) -> (StatusCode, Json<GenericResponse<EncryptResponse>>) {
+    match service.update(&resource_type, &resource_id, request).await {
+        Ok(response) => {
+            let response = GenericResponse {
+                success: true,
+                message: "更新成功".to_string(),
+                data: Some(response),
+            };
+            (StatusCode::OK, Json(response))
+        },
+        Err(e) => {
+            let response = GenericResponse {
+                success: false,
+                message: format!("更新失败: {}", service.client_error(&e)),
+                data: None,
+            };
+            (e.status_code(), Json(response))
+        },
+    }
+}
+
+/// 删除资源处理函数
+#[utoipa::path(
+    delete,
+    path = "/resource/{resource_type}/{resource_id}",
+    tag = "资源",
+    params(
+        ("resource_type" = String, Path, description = "资源类型"),
+        ("resource_id" = String, Path, description = "资源ID"),
+    ),
+    responses(
+        (status = 200, description = "删除成功", body = GenericResponse<DeleteResponse>),
+        (status = 401, description = "访问令牌缺失、无效或已过期"),
+        (status = 403, description = "服务角色不允许删除", body = GenericResponse<DeleteResponse>),
+        (status = 404, description = "资源不存在", body = GenericResponse<DeleteResponse>),
+        (status = 503, description = "CRUD API不可用", body = GenericResponse<DeleteResponse>),
+    ),
+    security(("bearer" = [])),
+)]
+#[axum::debug_handler]
+pub async fn delete_resource(
+    State(service): State<Arc<EncryptionService>>,
+    Path((resource_type, resource_id)): Path<(String, String)>,
+) -> (StatusCode, Json<GenericResponse<DeleteResponse>>) {
+    match service.delete(&resource_type, &resource_id).await {
+        Ok(response) => {
+            let response = GenericResponse {
+                success: true,
+                message: "删除成功".to_string(),
+                data: Some(response),
+            };
+            (StatusCode::OK, Json(response))
+        },
+        Err(e) => {
+            let response = GenericResponse {
+                success: false,
+                message: format!("删除失败: {}", service.client_error(&e)),
+                data: None,
+            };
+            (e.status_code(), Json(response))
+        },
+    }
+}
+
+/// 流式加解密的密码请求头
+const STREAM_PASSWORD_HEADER: &str = "x-encryption-password";
+/// 流式加解密的资源类型请求头
+const STREAM_RESOURCE_TYPE_HEADER: &str = "x-resource-type";
+/// 流式加密的算法请求头，可选
+const STREAM_ALGORITHM_HEADER: &str = "x-encryption-algorithm";
+
+/// 读取流式加解密的请求头，返回密码、资源类型和算法
+fn stream_params(headers: &HeaderMap) -> Result<(String, String, Option<String>), ServiceError> {
+    let header_value = |name: &str| headers.get(name)
+        .map(|value| value.to_str()
+            .map(|value| value.to_string())
+            .map_err(|_| ServiceError::InvalidRequest(format!("请求头 {} 不是有效的字符串", name))))
+        .transpose();
+    let password = header_value(STREAM_PASSWORD_HEADER)?
+        .ok_or_else(|| ServiceError::InvalidRequest(format!("缺少请求头 {}", STREAM_PASSWORD_HEADER)))?;
+    let resource_type = header_value(STREAM_RESOURCE_TYPE_HEADER)?
+        .ok_or_else(|| ServiceError::InvalidRequest(format!("缺少请求头 {}", STREAM_RESOURCE_TYPE_HEADER)))?;
+    let algorithm = header_value(STREAM_ALGORITHM_HEADER)?;
+    Ok((password, resource_type, algorithm))
+}
+
+/// 流式加解密开始前的错误响应
+fn stream_error(service: &EncryptionService, operation: &str, e: &ServiceError) -> Response {
+    let response = GenericResponse::<()> {
+        success: false,
+        message: format!("{}失败: {}", operation, service.client_error(e)),
+        data: None,
+    };
+    (e.status_code(), Json(response)).into_response()
+}
+
+/// 二进制流响应
+fn octet_stream(body: Body) -> Response {
+    ([(header::CONTENT_TYPE, "application/octet-stream")], body).into_response()
+}
+
+/// 流式加密处理函数，请求体为原始明文，响应体为分块密文
+pub async fn encrypt_stream(
+    State(service): State<Arc<EncryptionService>>,
+    headers: HeaderMap,
+    body: Body,
+) -> Response {
+    let encryptor = match stream_params(&headers) {
+        Ok((password, resource_type, algorithm)) => service.stream_encryptor(&password, &resource_type, algorithm.as_deref()).await,
+        Err(e) => Err(e),
+    };
+    let encryptor = match encryptor {
+        Ok(encryptor) => encryptor,
+        Err(e) => return stream_error(&service, "流式加密", &e),
+    };
+
+    // 边读取边加密，只缓存不足一个分块的明文
+    let output = futures::stream::unfold(Some((body.into_data_stream(), encryptor)), |state| async move {
+        let (mut input, mut encryptor) = state?;
+        loop {
+            let result = match input.next().await {
+                Some(Ok(data)) => encryptor.update(data).await,
+                Some(Err(e)) => Err(anyhow::anyhow!("读取请求体失败: {}", e)),
+                None => {
+                    let last = encryptor.finish().await.map(Bytes::from);
+                    return Some((last, None));
+                },
+            };
+            match result {
+                Ok((next, ciphertext)) if ciphertext.is_empty() => encryptor = next,
+                Ok((next, ciphertext)) => return Some((Ok(Bytes::from(ciphertext)), Some((input, next)))),
+                Err(e) => {
+                    warn!("流式加密中断: {:?}", e);
+                    return Some((Err(e), None));
+                },
+            }
+        }
+    });
+    octet_stream(Body::from_stream(output))
+}
+
+/// 流式解密处理函数，请求体为分块密文，响应体为原始明文
+pub async fn decrypt_stream(
+    State(service): State<Arc<EncryptionService>>,
+    headers: HeaderMap,
+    body: Body,
+) -> Response {
+    let mut decryptor = match stream_params(&headers)
+        .and_then(|(password, resource_type, _)| service.stream_decryptor(&password, &resource_type))
+    {
+        Ok(decryptor) => decryptor,
+        Err(e) => return stream_error(&service, "流式解密", &e),
+    };
+
+    // 第一个分块通过认证后再返回响应，密码错误或密文无效时可以返回错误状态码
+    let mut input = body.into_data_stream();
+    let mut first = Vec::new();
+    while first.is_empty() && !decryptor.is_done() {
+        let result = match input.next().await {
+            Some(Ok(data)) => decryptor.update(data).await,
+            Some(Err(e)) => {
+                let e = ServiceError::InvalidRequest(format!("读取请求体失败: {}", e));
+                return stream_error(&service, "流式解密", &e);
+            },
+            None => decryptor.finish().map(|_| (decryptor, Vec::new())),
+        };
+        match result {
+            Ok((next, plaintext)) => (decryptor, first) = (next, plaintext),
+            Err(e) => return stream_error(&service, "流式解密", &ServiceError::from_crypto(e)),
+        }
+    }
+
+    // 之后的分块逐块校验后输出，中途校验失败时中断响应
+    let rest = futures::stream::unfold(Some((input, decryptor)), |state| async move {
+        let (mut input, mut decryptor) = state?;
+        loop {
+            let result = match input.next().await {
+                Some(Ok(data)) => decryptor.update(data).await,
+                Some(Err(e)) => Err(anyhow::anyhow!("读取请求体失败: {}", e)),
+                None => match decryptor.finish() {
+                    Ok(()) => return None,
+                    Err(e) => Err(e),
+                },
+            };
+            match result {
+                Ok((next, plaintext)) if plaintext.is_empty() => decryptor = next,
+                Ok((next, plaintext)) => return Some((Ok(Bytes::from(plaintext)), Some((input, next)))),
+                Err(e) => {
+                    warn!("流式解密中断: {:?}", e);
+                    return Some((Err(e), None));
+                },
+            }
+        }
+    });
+    let output = futures::stream::once(async move { Ok::<_, anyhow::Error>(Bytes::from(first)) }).chain(rest);
+    octet_stream(Body::from_stream(output))
+}
+
 /// 批量加密处理函数
+#[utoipa::path(
+    post,
+    path = "/batch/encrypt",
+    tag = "批量操作",
+    request_body = Vec<EncryptRequest>,
+    responses(
+        (status = 200, description = "批量加密完成，各条目结果见data", body = GenericResponse<Vec<BatchItemResult<EncryptResponse>>>),
+        (status = 207, description = "开启BATCH_AGGREGATE_STATUS时部分条目失败", body = GenericResponse<Vec<BatchItemResult<EncryptResponse>>>),
+        (status = 401, description = "访问令牌缺失、无效或已过期"),
+    ),
+    security(("bearer" = [])),
+)]
 #[axum::debug_handler]
 pub async fn batch_encrypt(
     State(service): State<Arc<EncryptionService>>,
     Json(requests): Json<Vec<EncryptRequest>>,
-) -> (StatusCode, Json<GenericResponse<Vec<EncryptResponse>>>) {
+) -> (StatusCode, Json<GenericResponse<Vec<BatchItemResult<EncryptResponse>>>>) {
     match service.batch_encrypt(requests).await {
-        Ok(responses) => {
+        Ok(results) => {
+            let (status, message) = batch_outcome("批量加密", &results, service.get_config().batch.aggregate_status);
             let response = GenericResponse {
-                success: true,
-                message: "批量加密成功".to_string(),
-                data: Some(responses),
+                success: status.is_success(),
+                message,
+                data: Some(results),
             };
-            (StatusCode::OK, Json(response))
+            (status, Json(response))
         },
         Err(e) => {
             let response = GenericResponse {
                 success: false,
-                message: format!("批量加密失败: {}", e),
+                message: format!("批量加密失败: {}", service.client_error(&e)),
                 data: None,
             };
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(response))
+            (e.status_code(), Json(response))
         },
     }
 }
 
 /// 批量解密处理函数
+#[utoipa::path(
+    post,
+    path = "/batch/decrypt",
+    tag = "批量操作",
+    request_body = Vec<DecryptRequest>,
+    responses(
+        (status = 200, description = "批量解密完成，各条目结果见data", body = GenericResponse<Vec<BatchItemResult<DecryptResponse>>>),
+        (status = 207, description = "开启BATCH_AGGREGATE_STATUS时部分条目失败", body = GenericResponse<Vec<BatchItemResult<DecryptResponse>>>),
+        (status = 401, description = "访问令牌缺失、无效或已过期"),
+    ),
+    security(("bearer" = [])),
+)]
 #[axum::debug_handler]
 pub async fn batch_decrypt(
     State(service): State<Arc<EncryptionService>>,
     Json(requests): Json<Vec<DecryptRequest>>,
-) -> (StatusCode, Json<GenericResponse<Vec<DecryptResponse>>>) {
+) -> (StatusCode, Json<GenericResponse<Vec<BatchItemResult<DecryptResponse>>>>) {
     match service.batch_decrypt(requests).await {
+        Ok(results) => {
+            let (status, message) = batch_outcome("批量解密", &results, service.get_config().batch.aggregate_status);
+            let response = GenericResponse {
+                success: status.is_success(),
+                message,
+                data: Some(results),
+            };
+            (status, Json(response))
+        },
+        Err(e) => {
+            let response = GenericResponse {
+                success: false,
+                message: format!("批量解密失败: {}", service.client_error(&e)),
+                data: None,
+            };
+            (e.status_code(), Json(response))
+        },
+    }
+}
+
+/// 汇总批量结果的状态码和消息
+///
+/// 未开启汇总时总是返回200；开启后全部成功返回200，全部失败且状态码相同时返回该状态码并给出共同原因，其余返回207
+fn batch_outcome<T>(operation: &str, results: &[BatchItemResult<T>], aggregate: bool) -> (StatusCode, String) {
+    let failed: Vec<&BatchItemResult<T>> = results.iter().filter(|result| !result.success).collect();
+    let summary = format!("{}完成: 成功 {} 项，失败 {} 项", operation, results.len() - failed.len(), failed.len());
+    if !aggregate || failed.is_empty() {
+        return (StatusCode::OK, summary);
+    }
+
+    if failed.len() < results.len() {
+        return (StatusCode::MULTI_STATUS, summary);
+    }
+
+    // 全部失败时，状态码一致则使用该状态码，原因一致则作为共同原因返回
+    let first = failed[0];
+    let status = if failed.iter().all(|result| result.status == first.status) {
+        StatusCode::from_u16(first.status).unwrap_or(StatusCode::MULTI_STATUS)
+    } else {
+        StatusCode::MULTI_STATUS
+    };
+    let message = match first.error {
+        Some(ref cause) if failed.iter().all(|result| result.error == first.error) => {
+            format!("{}全部失败: {}", operation, cause)
+        },
+        _ => format!("{}全部失败: 共 {} 项", operation, failed.len()),
+    };
+    (status, message)
+}
+
+/// 批量校验处理函数
+#[utoipa::path(
+    post,
+    path = "/batch/verify",
+    tag = "批量操作",
+    request_body = Vec<DecryptRequest>,
+    responses(
+        (status = 200, description = "批量校验完成，响应不包含明文", body = GenericResponse<Vec<VerifyResponse>>),
+        (status = 401, description = "访问令牌缺失、无效或已过期"),
+    ),
+    security(("bearer" = [])),
+)]
+#[axum::debug_handler]
+pub async fn batch_verify(
+    State(service): State<Arc<EncryptionService>>,
+    Json(requests): Json<Vec<DecryptRequest>>,
+) -> (StatusCode, Json<GenericResponse<Vec<VerifyResponse>>>) {
+    match service.batch_verify(requests).await {
         Ok(responses) => {
             let response = GenericResponse {
                 success: true,
-                message: "批量解密成功".to_string(),
+                message: "批量校验完成".to_string(),
                 data: Some(responses),
             };
             (StatusCode::OK, Json(response))
@@ -129,7 +701,141 @@ pub async fn batch_decrypt(
         Err(e) => {
             let response = GenericResponse {
                 success: false,
-                message: format!("批量解密失败: {}", e),
+                message: format!("批量校验失败: {}", service.client_error(&e)),
+                data: None,
+            };
+            (e.status_code(), Json(response))
+        },
+    }
+}
+
+/// 缓存统计处理函数
+#[axum::debug_handler]
+pub async fn cache_stats(
+    State(service): State<Arc<EncryptionService>>,
+) -> (StatusCode, Json<GenericResponse<CacheStats>>) {
+    match service.get_cache_manager().stats() {
+        Ok(stats) => {
+            let response = GenericResponse {
+                success: true,
+                message: "获取缓存统计成功".to_string(),
+                data: Some(stats),
+            };
+            (StatusCode::OK, Json(response))
+        },
+        Err(e) => {
+            let response = GenericResponse {
+                success: false,
+                message: format!("获取缓存统计失败: {}", service.client_error(&e.into())),
+                data: None,
+            };
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(response))
+        },
+    }
+}
+
+/// 单页最大条目数
+const MAX_PAGE_LIMIT: usize = 1000;
+
+/// 分页查询参数
+#[derive(Debug, Deserialize)]
+pub struct PageQuery {
+    #[serde(default)]
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+/// 缓存条目列表处理函数，只返回元数据
+#[axum::debug_handler]
+pub async fn cache_entries(
+    State(service): State<Arc<EncryptionService>>,
+    Query(query): Query<PageQuery>,
+) -> (StatusCode, Json<GenericResponse<CacheEntryPage>>) {
+    let limit = query.limit.unwrap_or(100).clamp(1, MAX_PAGE_LIMIT);
+    match service.get_cache_manager().list_entries(query.offset, limit) {
+        Ok(page) => {
+            let response = GenericResponse {
+                success: true,
+                message: "获取缓存条目成功".to_string(),
+                data: Some(page),
+            };
+            (StatusCode::OK, Json(response))
+        },
+        Err(e) => {
+            let response = GenericResponse {
+                success: false,
+                message: format!("获取缓存条目失败: {}", service.client_error(&e.into())),
+                data: None,
+            };
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(response))
+        },
+    }
+}
+
+/// 立即执行一轮健康检查处理函数，返回检查后的实例状态
+#[axum::debug_handler]
+pub async fn force_health_check(
+    State(service): State<Arc<EncryptionService>>,
+) -> (StatusCode, Json<GenericResponse<serde_json::Value>>) {
+    let scheduler = service.get_scheduler();
+    match scheduler.request_health_check().await {
+        Ok(checked) => {
+            let message = if checked {
+                "健康检查完成"
+            } else {
+                "距上一轮健康检查不足最小间隔，返回上一轮检查结果"
+            };
+            let response = GenericResponse {
+                success: true,
+                message: message.to_string(),
+                data: Some(serde_json::json!({
+                    "checked": checked,
+                    "instances": scheduler.get_all_instance_status(),
+                })),
+            };
+            (StatusCode::OK, Json(response))
+        },
+        Err(e) => {
+            let response = GenericResponse {
+                success: false,
+                message: format!("健康检查失败: {}", service.client_error(&e.into())),
+                data: None,
+            };
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(response))
+        },
+    }
+}
+
+/// 令牌签发请求
+#[derive(Debug, Deserialize)]
+pub struct TokenRequest {
+    /// 令牌主体，通常为调用方标识
+    pub subject: String,
+}
+
+/// 签发JWT处理函数，用于联调和测试
+#[axum::debug_handler]
+pub async fn issue_token(
+    State(service): State<Arc<EncryptionService>>,
+    Json(request): Json<TokenRequest>,
+) -> (StatusCode, Json<GenericResponse<serde_json::Value>>) {
+    let jwt_config = &service.get_config().jwt;
+    match auth::issue_token(jwt_config, &request.subject) {
+        Ok(token) => {
+            let response = GenericResponse {
+                success: true,
+                message: "签发令牌成功".to_string(),
+                data: Some(serde_json::json!({
+                    "token": token,
+                    "expires_in": jwt_config.expires_in,
+                })),
+            };
+            (StatusCode::OK, Json(response))
+        },
+        Err(e) => {
+            let response = GenericResponse {
+                success: false,
+                message: format!("签发令牌失败: {}", service.client_error(&e.into())),
                 data: None,
             };
             (StatusCode::INTERNAL_SERVER_ERROR, Json(response))
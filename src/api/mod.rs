@@ -1,28 +1,1120 @@
-use axum::Router;
+use axum::{Json, Router};
+use axum::body::Body;
+use axum::extract::{ConnectInfo, DefaultBodyLimit, Request, State};
+use axum::http::{HeaderName, HeaderValue, Method, StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{MethodRouter, get, post, put};
+use std::net::SocketAddr;
 use std::sync::Arc;
-use crate::service::EncryptionService;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tracing::{Instrument, warn};
+use crate::config::{AuthMode, CorsConfig};
+use crate::service::{EncryptionService, GenericResponse};
+use crate::auth;
+use crate::rate_limit::ClientRateLimiter;
+use crate::request_id;
 
 // 导入处理函数
 mod handlers;
+mod openapi;
+
+/// 并发上限响应头
+const CONCURRENCY_LIMIT_HEADER: &str = "x-concurrency-limit";
+
+/// 剩余并发数响应头
+const CONCURRENCY_REMAINING_HEADER: &str = "x-concurrency-remaining";
+
+/// 请求签名请求头
+const SIGNATURE_HEADER: &str = "x-signature";
+
+/// 请求签名时间戳请求头
+const TIMESTAMP_HEADER: &str = "x-timestamp";
+
+/// 流式接口请求体摘要请求头：请求体SHA-256的十六进制编码，流式接口的签名覆盖该摘要而不是请求体
+const CONTENT_SHA256_HEADER: &str = "x-content-sha256";
+
+/// 签名覆盖请求体摘要、边读取边校验请求体的流式接口
+const STREAM_PATHS: [&str; 2] = ["/encrypt/stream", "/decrypt/stream"];
+
+/// 正在处理的请求数
+static IN_FLIGHT_REQUESTS: AtomicUsize = AtomicUsize::new(0);
+
+/// 获取正在处理的请求数
+pub fn in_flight_requests() -> usize {
+    IN_FLIGHT_REQUESTS.load(Ordering::Relaxed)
+}
 
 /// 创建API路由
 pub fn create_router(
     service: Arc<EncryptionService>,
 ) -> Router {
-    // 创建基础路由
-    let router = Router::new()
-        // 健康检查路由
-        .route("/health", axum::routing::get(handlers::health_check))
+    let limits = service.get_config().limits.clone();
+    let rate_limit = service.get_config().rate_limit.clone();
+    let batch_body_limit = DefaultBodyLimit::max(service.get_config().server.max_batch_body_size);
+
+    // 管理接口路由，需要管理令牌
+    let admin_routes = Router::new()
+        .route("/cache/entries", get(handlers::cache_entries))
+        .route("/token", post(handlers::issue_token))
+        .route("/health-check", post(handlers::force_health_check))
+        .route_layer(middleware::from_fn_with_state(service.clone(), require_admin_token));
+
+    // 加解密路由，按配置校验JWT或请求签名，每个接口使用独立的并发限制，互不抢占
+    let crypto_routes = Router::new()
         // 加密路由
-        .route("/encrypt", axum::routing::post(handlers::encrypt))
+        .route("/encrypt", with_concurrency_limit(post(handlers::encrypt), limits.encrypt, limits.expose_headers))
         // 解密路由
-        .route("/decrypt", axum::routing::post(handlers::decrypt))
+        .route("/decrypt", with_concurrency_limit(post(handlers::decrypt), limits.decrypt, limits.expose_headers))
+        // 密码校验路由，不返回明文
+        .route("/decrypt/verify", with_concurrency_limit(post(handlers::verify), limits.verify, limits.expose_headers))
         // 批量加密路由
-        .route("/batch/encrypt", axum::routing::post(handlers::batch_encrypt))
+        .route("/batch/encrypt", with_concurrency_limit(post(handlers::batch_encrypt).layer(batch_body_limit), limits.batch_encrypt, limits.expose_headers))
         // 批量解密路由
-        .route("/batch/decrypt", axum::routing::post(handlers::batch_decrypt))
+        .route("/batch/decrypt", with_concurrency_limit(post(handlers::batch_decrypt).layer(batch_body_limit), limits.batch_decrypt, limits.expose_headers))
+        // 批量校验路由
+        .route("/batch/verify", with_concurrency_limit(post(handlers::batch_verify).layer(batch_body_limit), limits.batch_verify, limits.expose_headers))
+        // 字段重新加密路由
+        .route("/rewrap", with_concurrency_limit(post(handlers::rewrap), limits.rewrap, limits.expose_headers))
+        .route("/rotate", with_concurrency_limit(post(handlers::rotate), limits.rotate, limits.expose_headers))
+        .route("/encrypt/stream", with_concurrency_limit(post(handlers::encrypt_stream), limits.stream, limits.expose_headers))
+        .route("/decrypt/stream", with_concurrency_limit(post(handlers::decrypt_stream), limits.stream, limits.expose_headers))
+        // 更新和删除资源路由，代理到CRUD API
+        .route("/resource/:resource_type/:resource_id", put(handlers::update_resource).delete(handlers::delete_resource))
+        // 请求体超过限制时返回413，批量接口使用单独的限制；流式接口不读取完整请求体，不受限制
+        .layer(DefaultBodyLimit::max(service.get_config().server.max_body_size));
+
+    let crypto_routes = match service.get_config().auth.mode {
+        AuthMode::Jwt => crypto_routes.route_layer(middleware::from_fn_with_state(service.clone(), require_jwt)),
+        AuthMode::Hmac => {
+            let state = SignatureState {
+                service: service.clone(),
+                replay_guard: Arc::new(auth::SignatureReplayGuard::new()),
+            };
+            crypto_routes.route_layer(middleware::from_fn_with_state(state, require_signature))
+        }
+        AuthMode::None => crypto_routes,
+    };
+
+    // 按客户端限流，在认证之前执行
+    let crypto_routes = if rate_limit.requests_per_second > 0.0 {
+        let state = RateLimitState {
+            limiter: Arc::new(ClientRateLimiter::new(rate_limit.requests_per_second, rate_limit.burst)),
+            key_header: rate_limit.key_header,
+            batch_per_item: rate_limit.batch_per_item,
+            batch_body_limit: service.get_config().server.max_batch_body_size,
+        };
+        crypto_routes.route_layer(middleware::from_fn_with_state(state, limit_rate))
+    } else {
+        crypto_routes
+    };
+
+    // 创建基础路由
+    let router = Router::new()
+        // 健康检查路由
+        .route("/health", get(handlers::health_check))
+        // 存活检查路由
+        .route("/livez", get(handlers::liveness))
+        // 就绪检查路由
+        .route("/readyz", get(handlers::readiness))
+        // 服务状态路由
+        .route("/status", get(handlers::status))
+        // 版本信息路由
+        .route("/version", get(handlers::version))
+        // OpenAPI文档和Swagger UI路由
+        .route("/openapi.json", get(openapi::openapi_json))
+        .route("/docs", get(openapi::swagger_ui))
+        // 指标路由
+        .route("/metrics", get(handlers::metrics))
+        // 实例状态路由
+        .route("/instances/status", get(handlers::instances_status))
+        // 缓存统计路由
+        .route("/cache/stats", get(handlers::cache_stats))
+        .merge(crypto_routes)
+        // 管理接口
+        .nest("/admin", admin_routes)
+        // 请求ID上下文
+        .layer(middleware::from_fn(request_id_context))
+        // 统计正在处理的请求数
+        .layer(middleware::from_fn(track_in_flight))
         // 应用状态
-        .with_state(service);
+        .with_state(service.clone());
+
+    // 跨域层在最外层，预检请求不经过认证和限流
+    match cors_layer(&service.get_config().cors, &service.get_config().auth.mode) {
+        Some(cors) => router.layer(cors),
+        None => router,
+    }
+}
+
+/// 按配置创建跨域层，未配置允许的来源时返回None，浏览器的跨域请求将被拒绝
+///
+/// 配置已在启动时验证，这里忽略无法解析的值
+fn cors_layer(config: &CorsConfig, auth_mode: &AuthMode) -> Option<CorsLayer> {
+    if config.allowed_origins.is_empty() {
+        return None;
+    }
+
+    let origins = if config.allowed_origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(config.allowed_origins.iter().filter_map(|origin| HeaderValue::from_str(origin).ok()))
+    };
+    let methods: Vec<Method> = config.allowed_methods.iter()
+        .filter_map(|method| Method::from_bytes(method.as_bytes()).ok())
+        .collect();
+    let mut headers: Vec<HeaderName> = config.allowed_headers.iter()
+        .filter_map(|name| HeaderName::from_bytes(name.as_bytes()).ok())
+        .collect();
+    // 认证方式需要的请求头总是允许，否则浏览器的预检请求会失败
+    match auth_mode {
+        AuthMode::Jwt => headers.push(header::AUTHORIZATION),
+        AuthMode::Hmac => headers.extend([
+            HeaderName::from_static(SIGNATURE_HEADER),
+            HeaderName::from_static(TIMESTAMP_HEADER),
+            HeaderName::from_static(CONTENT_SHA256_HEADER),
+        ]),
+        AuthMode::None => {},
+    }
+
+    Some(CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods(methods)
+        .allow_headers(headers)
+        .allow_credentials(config.allow_credentials)
+        .expose_headers([
+            HeaderName::from_static(request_id::REQUEST_ID_HEADER),
+            header::LOCATION,
+            header::RETRY_AFTER,
+            HeaderName::from_static(CONCURRENCY_LIMIT_HEADER),
+            HeaderName::from_static(CONCURRENCY_REMAINING_HEADER),
+        ])
+        .max_age(Duration::from_secs(config.max_age)))
+}
+
+/// 统计正在处理的请求数，请求被取消时同样减少计数
+async fn track_in_flight(request: Request, next: Next) -> Response {
+    struct Guard;
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            IN_FLIGHT_REQUESTS.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    IN_FLIGHT_REQUESTS.fetch_add(1, Ordering::Relaxed);
+    let _guard = Guard;
+    next.run(request).await
+}
+
+/// 请求ID上下文：沿用请求携带的X-Request-Id，缺少时生成新ID
+///
+/// 请求ID放入上下文供出站的CRUD API请求传递，同时记录在包裹整个请求的tracing span中，并在响应头中返回
+async fn request_id_context(request: Request, next: Next) -> Response {
+    let id = request.headers()
+        .get(request_id::REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty())
+        .map(|value| value.to_string())
+        .unwrap_or_else(request_id::generate);
+    let span = tracing::info_span!(
+        "request",
+        request_id = %id,
+        method = %request.method(),
+        path = %request.uri().path(),
+    );
+
+    let mut response = request_id::scope(id.clone(), next.run(request)).instrument(span).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(request_id::REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// 从Authorization请求头中提取Bearer令牌
+fn bearer_token(request: &Request) -> Option<&str> {
+    request.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+}
+
+/// 错误响应
+fn error_response(status: StatusCode, message: &str) -> Response {
+    let response: GenericResponse<()> = GenericResponse {
+        success: false,
+        message: message.to_string(),
+        data: None,
+    };
+    (status, Json(response)).into_response()
+}
+
+/// 校验JWT：Authorization: Bearer <token>，缺少、过期或签名无效时返回401
+async fn require_jwt(
+    State(service): State<Arc<EncryptionService>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(token) = bearer_token(&request) else {
+        return error_response(StatusCode::UNAUTHORIZED, "缺少访问令牌");
+    };
+
+    match auth::validate_token(&service.get_config().jwt, token) {
+        Ok(_) => next.run(request).await,
+        Err(e) => {
+            let expired = e.downcast_ref::<jsonwebtoken::errors::Error>()
+                .is_some_and(|e| matches!(e.kind(), jsonwebtoken::errors::ErrorKind::ExpiredSignature));
+            if expired {
+                error_response(StatusCode::UNAUTHORIZED, "访问令牌已过期")
+            } else {
+                error_response(StatusCode::UNAUTHORIZED, "访问令牌无效")
+            }
+        },
+    }
+}
+
+/// 请求签名校验状态
+#[derive(Clone)]
+struct SignatureState {
+    service: Arc<EncryptionService>,
+    /// 有效期内已使用的签名
+    replay_guard: Arc<auth::SignatureReplayGuard>,
+}
+
+/// 校验请求签名：X-Timestamp 和 X-Signature 请求头，缺少、过期、签名无效或重复使用时返回401
+///
+/// 签名覆盖完整请求体，需要先读取请求体再交给处理函数；
+/// 流式接口的签名覆盖 X-Content-SHA256 请求头中的请求体摘要，请求体边转发边校验，不受请求体大小限制
+async fn require_signature(
+    State(SignatureState { service, replay_guard }): State<SignatureState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (parts, body) = request.into_parts();
+    let header_value = |name: &str| parts.headers.get(name).and_then(|value| value.to_str().ok());
+    let (Some(timestamp), Some(signature)) = (header_value(TIMESTAMP_HEADER), header_value(SIGNATURE_HEADER)) else {
+        return error_response(StatusCode::UNAUTHORIZED, "缺少请求签名或时间戳");
+    };
+    let (timestamp, signature) = (timestamp.to_string(), signature.to_string());
+    let path_and_query = parts.uri.path_and_query().map(|value| value.as_str()).unwrap_or_else(|| parts.uri.path());
+
+    if STREAM_PATHS.contains(&parts.uri.path()) {
+        let Some(digest) = header_value(CONTENT_SHA256_HEADER) else {
+            return error_response(StatusCode::UNAUTHORIZED, "流式接口缺少请求体摘要");
+        };
+        let Some(expected) = auth::decode_hex(digest).filter(|expected| expected.len() == 32) else {
+            return error_response(StatusCode::UNAUTHORIZED, "请求体摘要格式无效");
+        };
+        if let Err(e) = auth::verify_signature(
+            &service.get_config().auth,
+            &replay_guard,
+            &timestamp,
+            parts.method.as_str(),
+            path_and_query,
+            digest.as_bytes(),
+            &signature,
+        ) {
+            return error_response(StatusCode::UNAUTHORIZED, &e.to_string());
+        }
+        return next.run(Request::from_parts(parts, digest_checked_body(body, expected))).await;
+    }
+
+    // 与接口的请求体限制一致
+    let server = &service.get_config().server;
+    let body_limit = if parts.uri.path().starts_with("/batch/") { server.max_batch_body_size } else { server.max_body_size };
+    let bytes = match axum::body::to_bytes(body, body_limit).await {
+        Ok(bytes) => bytes,
+        Err(_) => return error_response(StatusCode::PAYLOAD_TOO_LARGE, "请求体过大"),
+    };
+
+    match auth::verify_signature(
+        &service.get_config().auth,
+        &replay_guard,
+        &timestamp,
+        parts.method.as_str(),
+        path_and_query,
+        &bytes,
+        &signature,
+    ) {
+        Ok(()) => next.run(Request::from_parts(parts, Body::from(bytes))).await,
+        Err(e) => error_response(StatusCode::UNAUTHORIZED, &e.to_string()),
+    }
+}
+
+/// 边转发请求体边计算SHA-256，读完后与签名覆盖的摘要比较
+///
+/// 摘要不一致时请求体以错误结束，流式处理函数不会输出最后一块数据，响应被中止
+fn digest_checked_body(body: Body, expected: Vec<u8>) -> Body {
+    use futures::StreamExt;
+    use sha2::{Digest, Sha256};
+
+    let stream = futures::stream::unfold(Some((body.into_data_stream(), Sha256::new(), expected)), |state| async move {
+        let (mut input, mut hasher, expected) = state?;
+        match input.next().await {
+            Some(Ok(data)) => {
+                hasher.update(&data);
+                Some((Ok(data), Some((input, hasher, expected))))
+            },
+            Some(Err(e)) => Some((Err(axum::BoxError::from(e)), None)),
+            None if hasher.finalize().as_slice() == expected.as_slice() => None,
+            None => {
+                warn!("流式请求体与签名中的摘要不一致，中止请求");
+                Some((Err(axum::BoxError::from("请求体与签名中的摘要不一致")), None))
+            },
+        }
+    });
+    Body::from_stream(stream)
+}
+
+/// 校验管理接口令牌：Authorization: Bearer <ADMIN_TOKEN>，未配置令牌时拒绝所有请求
+async fn require_admin_token(
+    State(service): State<Arc<EncryptionService>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let provided = bearer_token(&request);
+
+    let (status, message) = match (&service.get_config().server.admin_token, provided) {
+        (Some(expected), Some(provided)) if constant_time_eq(expected.as_bytes(), provided.as_bytes()) => {
+            return next.run(request).await;
+        },
+        (None, _) => (StatusCode::FORBIDDEN, "未配置管理接口令牌，管理接口不可用"),
+        _ => (StatusCode::UNAUTHORIZED, "管理接口令牌无效"),
+    };
+    error_response(status, message)
+}
+
+/// 按固定时间比较两个字节串，避免通过响应时间猜测令牌
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// 客户端限流状态
+#[derive(Debug, Clone)]
+struct RateLimitState {
+    limiter: Arc<ClientRateLimiter>,
+    /// 用于识别客户端的请求头
+    key_header: Option<String>,
+    /// 批量接口是否按条目数计数
+    batch_per_item: bool,
+    /// 按条目计数时允许读取的最大请求体字节数，与批量接口的限制一致
+    batch_body_limit: usize,
+}
+
+/// 按客户端限流，超出限制时返回429和Retry-After响应头
+async fn limit_rate(
+    State(state): State<RateLimitState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let key = client_key(&request, state.key_header.as_deref());
+
+    // 批量接口按条目数计数，需要先读取请求体再交给处理函数
+    let (request, cost) = if state.batch_per_item && request.uri().path().starts_with("/batch/") {
+        let (parts, body) = request.into_parts();
+        let bytes = match axum::body::to_bytes(body, state.batch_body_limit).await {
+            Ok(bytes) => bytes,
+            Err(_) => return error_response(StatusCode::PAYLOAD_TOO_LARGE, "请求体过大"),
+        };
+        // 无法解析时按一次请求计数，由处理函数返回解析错误
+        let cost = serde_json::from_slice::<Vec<serde::de::IgnoredAny>>(&bytes)
+            .map(|items| items.len().max(1))
+            .unwrap_or(1);
+        (Request::from_parts(parts, Body::from(bytes)), cost)
+    } else {
+        (request, 1)
+    };
+
+    match state.limiter.acquire(&key, u32::try_from(cost).unwrap_or(u32::MAX)) {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => {
+            let mut response = error_response(StatusCode::TOO_MANY_REQUESTS, "请求过于频繁，请稍后重试");
+            let retry_after_secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+            response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(retry_after_secs.max(1)));
+            response
+        },
+    }
+}
+
+/// 识别客户端：优先使用配置的请求头，缺少时使用客户端IP
+fn client_key(request: &Request, key_header: Option<&str>) -> String {
+    if let Some(name) = key_header
+        && let Some(value) = request.headers().get(name).and_then(|value| value.to_str().ok())
+    {
+        return format!("header:{}", value);
+    }
+    request.extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| format!("ip:{}", addr.ip()))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// 单个接口的并发限制
+#[derive(Debug, Clone)]
+struct ConcurrencyLimit {
+    semaphore: Arc<Semaphore>,
+    max: usize,
+    /// 是否在响应头中返回剩余并发数
+    expose_headers: bool,
+}
+
+/// 为路由添加并发限制，超出限制的请求排队等待；max为0时不限制
+fn with_concurrency_limit<S>(method_router: MethodRouter<S>, max: usize, expose_headers: bool) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    if max == 0 {
+        return method_router;
+    }
+
+    let limit = ConcurrencyLimit {
+        semaphore: Arc::new(Semaphore::new(max)),
+        max,
+        expose_headers,
+    };
+    method_router.layer(middleware::from_fn_with_state(limit, limit_concurrency))
+}
+
+/// 获取并发许可后处理请求，按配置在响应头中返回并发上限和获取许可时的剩余并发数
+async fn limit_concurrency(
+    State(limit): State<ConcurrencyLimit>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let _permit = limit.semaphore.acquire().await.expect("并发限制信号量已关闭");
+    let remaining = limit.semaphore.available_permits();
+
+    let mut response = next.run(request).await;
+    if limit.expose_headers {
+        let headers = response.headers_mut();
+        headers.insert(CONCURRENCY_LIMIT_HEADER, HeaderValue::from(limit.max));
+        headers.insert(CONCURRENCY_REMAINING_HEADER, HeaderValue::from(remaining));
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+
+    /// 启动使用JWT认证的服务，返回服务地址和配置
+    async fn serve_with_jwt() -> (String, Arc<AppConfig>) {
+        serve(&[("AUTH_MODE", "jwt"), ("JWT_SECRET", "handler-test-secret-0123456789")]).await
+    }
+
+    /// 按附加配置启动服务，返回服务地址和配置；未指定CRUD API实例时使用不可达的地址
+    async fn serve(vars: &[(&str, &str)]) -> (String, Arc<AppConfig>) {
+        let mut vars = vars.to_vec();
+        vars.push(("CRUD_API_WRITE_INSTANCE_URL", "http://127.0.0.1:1"));
+        vars.push(("CACHE_BACKEND", "memory"));
+        let config = Arc::new(AppConfig::from_vars(&vars).unwrap());
+        let service = Arc::new(EncryptionService::new(config.clone()));
+        service.get_scheduler().perform_health_check().await.unwrap();
+        let app = create_router(service);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).into_future());
+        (url, config)
+    }
+
+    /// 携带令牌请求加密接口，返回状态码和响应消息
+    async fn encrypt_with_token(url: &str, token: Option<&str>) -> (StatusCode, String) {
+        let mut request = reqwest::Client::new()
+            .post(format!("{}/encrypt", url))
+            .json(&serde_json::json!({}));
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().await.unwrap();
+        let status = StatusCode::from_u16(response.status().as_u16()).unwrap();
+        let body: serde_json::Value = response.json().await.unwrap_or_default();
+        (status, body["message"].as_str().unwrap_or_default().to_string())
+    }
+
+    #[tokio::test]
+    async fn valid_token_passes_authentication() {
+        let (url, config) = serve_with_jwt().await;
+        let token = auth::issue_token(&config.jwt, "tester").unwrap();
+
+        let (status, _) = encrypt_with_token(&url, Some(&token)).await;
+        assert_ne!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn missing_token_is_rejected() {
+        let (url, _) = serve_with_jwt().await;
+
+        let (status, message) = encrypt_with_token(&url, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(message, "缺少访问令牌");
+    }
+
+    #[tokio::test]
+    async fn expired_token_is_rejected() {
+        let (url, config) = serve_with_jwt().await;
+        let mut jwt = config.jwt.clone();
+        jwt.expires_in = -60;
+        let token = auth::issue_token(&jwt, "tester").unwrap();
+
+        let (status, message) = encrypt_with_token(&url, Some(&token)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(message, "访问令牌已过期");
+    }
+
+    #[tokio::test]
+    async fn token_signed_with_other_secret_is_rejected() {
+        let (url, config) = serve_with_jwt().await;
+        let mut jwt = config.jwt.clone();
+        jwt.secret = "another-secret-0123456789".to_string();
+        let token = auth::issue_token(&jwt, "tester").unwrap();
+
+        let (status, message) = encrypt_with_token(&url, Some(&token)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(message, "访问令牌无效");
+    }
+
+    #[tokio::test]
+    async fn health_stays_public() {
+        let (url, _) = serve_with_jwt().await;
+
+        let response = reqwest::get(format!("{}/health", url)).await.unwrap();
+        assert_ne!(response.status().as_u16(), StatusCode::UNAUTHORIZED.as_u16());
+    }
+
+    #[tokio::test]
+    async fn rate_limit_returns_429_with_retry_after() {
+        let (url, _) = serve(&[("AUTH_MODE", "none"), ("RATE_LIMIT_RPS", "0.5"), ("RATE_LIMIT_BURST", "2")]).await;
+
+        for _ in 0..2 {
+            let (status, _) = encrypt_with_token(&url, None).await;
+            assert_ne!(status, StatusCode::TOO_MANY_REQUESTS);
+        }
+        let response = reqwest::Client::new()
+            .post(format!("{}/encrypt", url))
+            .json(&serde_json::json!({}))
+            .send().await.unwrap();
+        assert_eq!(response.status().as_u16(), StatusCode::TOO_MANY_REQUESTS.as_u16());
+        // 每秒补充0.5个令牌，缺1个令牌需要等待2秒
+        assert_eq!(response.headers()[header::RETRY_AFTER.as_str()], "2");
+    }
+
+    /// 发送请求头和部分请求体后停止发送，处理函数一直等待请求体，占住接口的并发许可
+    async fn stall_request(url: &str, path: &str) -> tokio::net::TcpStream {
+        use tokio::io::AsyncWriteExt;
+
+        let mut stream = tokio::net::TcpStream::connect(url.trim_start_matches("http://")).await.unwrap();
+        let head = format!("POST {} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: 100\r\n\r\n[", path);
+        stream.write_all(head.as_bytes()).await.unwrap();
+        stream
+    }
+
+    #[tokio::test]
+    async fn saturated_batch_endpoint_does_not_block_decrypt() {
+        let (url, _) = serve(&[("AUTH_MODE", "none"), ("LIMIT_BATCH_ENCRYPT", "1"), ("LIMIT_DECRYPT", "2"), ("LIMIT_EXPOSE_HEADERS", "true")]).await;
+        let stalled = stall_request(&url, "/batch/encrypt").await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // 批量加密接口的许可已被占满，新的批量请求只能等待
+        let client = reqwest::Client::new();
+        let batch = client.post(format!("{}/batch/encrypt", url))
+            .json(&serde_json::json!([]))
+            .timeout(Duration::from_millis(300))
+            .send().await;
+        assert!(batch.unwrap_err().is_timeout());
+
+        // 解密接口使用独立的并发限制，不受影响
+        let decrypt = client.post(format!("{}/decrypt", url))
+            .json(&serde_json::json!({}))
+            .timeout(Duration::from_secs(5))
+            .send().await.unwrap();
+        assert_eq!(decrypt.headers()[CONCURRENCY_LIMIT_HEADER], "2");
+        assert_eq!(decrypt.headers()[CONCURRENCY_REMAINING_HEADER], "1");
+
+        // 占用许可的请求结束后批量接口恢复
+        drop(stalled);
+        let batch = client.post(format!("{}/batch/encrypt", url))
+            .json(&serde_json::json!([]))
+            .timeout(Duration::from_secs(5))
+            .send().await.unwrap();
+        assert_eq!(batch.headers()[CONCURRENCY_LIMIT_HEADER], "1");
+    }
+
+    /// 模拟CRUD API实例收到的请求：路径和X-Request-Id
+    type Seen = Arc<std::sync::Mutex<Vec<(String, Option<String>)>>>;
+
+    fn record_request(seen: &Seen, request: &Request) {
+        let request_id = request.headers().get(request_id::REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        seen.lock().unwrap().push((request.uri().path().to_string(), request_id));
+    }
+
+    /// 启动模拟的CRUD API实例，写入的记录ID固定为7，返回实例地址和收到的请求
+    async fn mock_crud() -> (String, Seen) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let seen = Seen::default();
+        let app = Router::new()
+            .route("/health", get(|State(seen): State<Seen>, request: Request| async move {
+                record_request(&seen, &request);
+                Json(serde_json::json!({ "status": "ok" }))
+            }))
+            .route("/:resource_type", post(|State(seen): State<Seen>, request: Request| async move {
+                record_request(&seen, &request);
+                Json(serde_json::json!({ "success": true, "message": "ok", "data": { "id": "7" } }))
+            }))
+            .with_state(seen.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, seen)
+    }
+
+    #[tokio::test]
+    async fn persisted_encrypt_returns_201_with_location() {
+        let (crud_url, _) = mock_crud().await;
+        let (url, _) = serve(&[
+            ("AUTH_MODE", "none"),
+            ("CRUD_API_WRITE_INSTANCE_URL", &crud_url),
+            ("LOCATION_HEADER_TEMPLATE", "{crud_base}/{resource_type}/{id}"),
+        ]).await;
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/encrypt", url))
+            .json(&serde_json::json!({ "data": "secret", "password": "password", "resource_type": "user" }))
+            .send().await.unwrap();
+        assert_eq!(response.status().as_u16(), StatusCode::CREATED.as_u16());
+        assert_eq!(response.headers()[header::LOCATION.as_str()], format!("{}/user/7", crud_url).as_str());
+    }
+
+    #[tokio::test]
+    async fn unpersisted_encrypt_returns_200_without_location() {
+        let (url, _) = serve(&[("AUTH_MODE", "none"), ("LOCATION_HEADER_TEMPLATE", "{crud_base}/{resource_type}/{id}")]).await;
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/encrypt", url))
+            .json(&serde_json::json!({ "data": "secret", "password": "password", "resource_type": "user" }))
+            .send().await.unwrap();
+        assert_eq!(response.status().as_u16(), StatusCode::OK.as_u16());
+        assert!(response.headers().get(header::LOCATION.as_str()).is_none());
+    }
+
+    #[tokio::test]
+    async fn request_id_propagated_to_crud_api() {
+        let (crud_url, seen) = mock_crud().await;
+        let (url, _) = serve(&[
+            ("AUTH_MODE", "none"),
+            ("CRUD_API_WRITE_INSTANCE_URL", &crud_url),
+            ("REQUEST_ID_HEALTH_CHECK", "true"),
+        ]).await;
+
+        reqwest::Client::new()
+            .post(format!("{}/encrypt", url))
+            .header(request_id::REQUEST_ID_HEADER, "edge-request-1")
+            .json(&serde_json::json!({ "data": "secret", "password": "password", "resource_type": "user" }))
+            .send().await.unwrap();
+
+        let seen = seen.lock().unwrap().clone();
+        let (_, health_id) = seen.iter().find(|(path, _)| path == "/health").unwrap();
+        assert!(health_id.as_deref().is_some_and(|id| id.starts_with("health-")));
+        let (_, write_id) = seen.iter().find(|(path, _)| path == "/user").unwrap();
+        assert_eq!(write_id.as_deref(), Some("edge-request-1"));
+    }
+
+    #[tokio::test]
+    async fn request_id_not_propagated_when_disabled() {
+        let (crud_url, seen) = mock_crud().await;
+        let (url, _) = serve(&[
+            ("AUTH_MODE", "none"),
+            ("CRUD_API_WRITE_INSTANCE_URL", &crud_url),
+            ("REQUEST_ID_PROPAGATION", "false"),
+        ]).await;
+
+        reqwest::Client::new()
+            .post(format!("{}/encrypt", url))
+            .header(request_id::REQUEST_ID_HEADER, "edge-request-1")
+            .json(&serde_json::json!({ "data": "secret", "password": "password", "resource_type": "user" }))
+            .send().await.unwrap();
+
+        assert!(seen.lock().unwrap().iter().all(|(_, request_id)| request_id.is_none()));
+    }
+
+    #[tokio::test]
+    async fn batch_reports_per_item_results() {
+        let (url, _) = serve(&[("AUTH_MODE", "none")]).await;
+        let client = reqwest::Client::new();
+        let encrypted: serde_json::Value = client.post(format!("{}/encrypt", url))
+            .json(&serde_json::json!({ "data": "secret", "password": "password", "resource_type": "user", "dry_run": true }))
+            .send().await.unwrap()
+            .json().await.unwrap();
+        let encrypted_data = encrypted["data"]["encrypted_data"].as_str().unwrap();
+
+        let response = client.post(format!("{}/batch/decrypt", url))
+            .json(&serde_json::json!([
+                { "encrypted_data": encrypted_data, "password": "password", "resource_type": "user" },
+                { "encrypted_data": encrypted_data, "password": "wrong-password", "resource_type": "user" },
+                { "password": "password", "resource_type": "user" },
+            ]))
+            .send().await.unwrap();
+        assert_eq!(response.status().as_u16(), StatusCode::OK.as_u16());
+
+        let body: serde_json::Value = response.json().await.unwrap();
+        let items = body["data"].as_array().unwrap();
+        let results: Vec<_> = items.iter().map(|item| (item["index"].as_u64().unwrap(), item["success"].as_bool().unwrap())).collect();
+        assert_eq!(results, [(0, true), (1, false), (2, false)]);
+        assert_eq!(items[0]["data"]["data"], "secret");
+        assert!(items[1]["error"].is_string() && items[2]["error"].is_string());
+        assert!(items[1..].iter().all(|item| item["data"].is_null()));
+    }
+
+    /// 启动健康检查结果可切换的模拟CRUD API实例，初始为不可用
+    async fn switchable_crud() -> (String, Arc<std::sync::atomic::AtomicBool>) {
+        let up = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let crud_url = format!("http://{}", listener.local_addr().unwrap());
+        let crud = Router::new()
+            .route("/health", get(|State(up): State<Arc<std::sync::atomic::AtomicBool>>| async move {
+                if up.load(Ordering::SeqCst) {
+                    Json(serde_json::json!({ "status": "ok" })).into_response()
+                } else {
+                    StatusCode::SERVICE_UNAVAILABLE.into_response()
+                }
+            }))
+            .with_state(up.clone());
+        tokio::spawn(async move { axum::serve(listener, crud).await.unwrap() });
+        (crud_url, up)
+    }
+
+    #[tokio::test]
+    async fn admin_health_check_sweeps_on_demand() {
+        let (crud_url, up) = switchable_crud().await;
+        let (url, _) = serve(&[
+            ("AUTH_MODE", "none"),
+            ("ADMIN_TOKEN", "admin-test-token"),
+            ("CRUD_API_WRITE_INSTANCE_URL", &crud_url),
+            ("CRUD_API_HEALTH_CHECK_MIN_INTERVAL", "0"),
+            ("CRUD_API_HEALTHY_THRESHOLD", "1"),
+        ]).await;
+        let client = reqwest::Client::new();
+        let force = || client.post(format!("{}/admin/health-check", url)).bearer_auth("admin-test-token").send();
+
+        let unauthorized = client.post(format!("{}/admin/health-check", url)).send().await.unwrap();
+        assert_eq!(unauthorized.status().as_u16(), StatusCode::UNAUTHORIZED.as_u16());
+
+        // 实例恢复后无需等待下一轮定时检查即可重新加入轮换
+        let body: serde_json::Value = force().await.unwrap().json().await.unwrap();
+        assert_eq!(body["data"]["checked"], true);
+        assert_eq!(body["data"]["instances"][0]["status"], "unhealthy");
+        up.store(true, Ordering::SeqCst);
+        let response = force().await.unwrap();
+        assert_eq!(response.status().as_u16(), StatusCode::OK.as_u16());
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["data"]["checked"], true);
+        assert_eq!(body["data"]["instances"][0]["url"], crud_url.as_str());
+        assert_eq!(body["data"]["instances"][0]["status"], "healthy");
+    }
+
+    #[tokio::test]
+    async fn admin_health_check_respects_min_interval() {
+        let (crud_url, up) = switchable_crud().await;
+        let (url, _) = serve(&[("AUTH_MODE", "none"), ("ADMIN_TOKEN", "admin-test-token"), ("CRUD_API_WRITE_INSTANCE_URL", &crud_url)]).await;
+
+        // 启动时刚完成一轮检查，最小间隔内返回上一轮结果
+        up.store(true, Ordering::SeqCst);
+        let body: serde_json::Value = reqwest::Client::new()
+            .post(format!("{}/admin/health-check", url))
+            .bearer_auth("admin-test-token")
+            .send().await.unwrap()
+            .json().await.unwrap();
+        assert_eq!(body["data"]["checked"], false);
+        assert_eq!(body["data"]["instances"][0]["status"], "unhealthy");
+    }
+
+    #[tokio::test]
+    async fn admin_cache_entries_are_paginated_metadata() {
+        let (url, _) = serve(&[("AUTH_MODE", "none"), ("ADMIN_TOKEN", "admin-test-token")]).await;
+        let client = reqwest::Client::new();
+        for resource_type in ["user", "order", "payment"] {
+            client.post(format!("{}/encrypt", url))
+                .json(&serde_json::json!({ "data": "top-secret-plaintext", "password": "top-secret-password", "resource_type": resource_type }))
+                .send().await.unwrap();
+        }
+
+        let unauthorized = client.get(format!("{}/admin/cache/entries", url)).send().await.unwrap();
+        assert_eq!(unauthorized.status().as_u16(), StatusCode::UNAUTHORIZED.as_u16());
+
+        let page = |offset: usize| client.get(format!("{}/admin/cache/entries?offset={}&limit=2", url, offset))
+            .bearer_auth("admin-test-token")
+            .send();
+        let first = page(0).await.unwrap().text().await.unwrap();
+        let second = page(2).await.unwrap().text().await.unwrap();
+        for body in [&first, &second] {
+            assert!(!body.contains("top-secret"), "缓存条目列表泄露了敏感字段: {}", body);
+        }
+
+        let first: serde_json::Value = serde_json::from_str(&first).unwrap();
+        let second: serde_json::Value = serde_json::from_str(&second).unwrap();
+        assert_eq!(first["data"]["has_more"], true);
+        assert_eq!(second["data"]["has_more"], false);
+        let resource_types: Vec<_> = [&first, &second].iter()
+            .flat_map(|page| page["data"]["entries"].as_array().unwrap().clone())
+            .map(|entry| {
+                assert_eq!(entry["data_type"], "encrypt");
+                assert_eq!(entry["flushed"], false);
+                assert!(entry["timestamp"].is_u64());
+                entry["resource_type"].as_str().unwrap().to_string()
+            })
+            .collect();
+        assert_eq!(resource_types, ["user", "order", "payment"]);
+
+        // 解密数据条目不需要写入CRUD API
+        let encrypted: serde_json::Value = client.post(format!("{}/encrypt", url))
+            .json(&serde_json::json!({ "data": "secret", "password": "password", "resource_type": "user", "dry_run": true }))
+            .send().await.unwrap()
+            .json().await.unwrap();
+        client.post(format!("{}/decrypt", url))
+            .json(&serde_json::json!({ "encrypted_data": encrypted["data"]["encrypted_data"], "password": "password", "resource_type": "user" }))
+            .send().await.unwrap();
+        let last: serde_json::Value = page(3).await.unwrap().json().await.unwrap();
+        assert_eq!(last["data"]["entries"][0]["data_type"], "decrypt");
+        assert_eq!(last["data"]["entries"][0]["flushed"], true);
+    }
+
+    #[tokio::test]
+    async fn instances_status_reports_scheduler_health() {
+        let (crud_url, _) = mock_crud().await;
+        let (url, _) = serve(&[
+            ("AUTH_MODE", "none"),
+            ("CRUD_API_WRITE_INSTANCE_URL", &crud_url),
+            ("CRUD_API_READ_INSTANCE_URL", "http://127.0.0.1:1"),
+        ]).await;
+
+        let body: serde_json::Value = reqwest::get(format!("{}/instances/status", url)).await.unwrap()
+            .json().await.unwrap();
+        let mut instances: Vec<_> = body["data"]["instances"].as_array().unwrap().iter()
+            .map(|instance| {
+                assert!(instance["id"].is_string());
+                (instance["url"].as_str().unwrap().to_string(), instance["status"].as_str().unwrap().to_string())
+            })
+            .collect();
+        instances.sort();
+        assert_eq!(instances, [
+            ("http://127.0.0.1:1".to_string(), "unhealthy".to_string()),
+            (crud_url, "healthy".to_string()),
+        ]);
+        assert!(body["data"]["test_instance"].is_null());
+    }
+
+    /// 启动带并发限制的测试路由：处理函数记录进入次数，等待放行后才返回
+    async fn serve_gated(max: usize, expose_headers: bool) -> (String, Arc<AtomicUsize>, Arc<Semaphore>) {
+        let entered = Arc::new(AtomicUsize::new(0));
+        let gate = Arc::new(Semaphore::new(0));
+        let handler = get(|State((entered, gate)): State<(Arc<AtomicUsize>, Arc<Semaphore>)>| async move {
+            entered.fetch_add(1, Ordering::SeqCst);
+            let _permit = gate.acquire().await.unwrap();
+        });
+        let app = Router::new()
+            .route("/work", with_concurrency_limit(handler, max, expose_headers))
+            .with_state((entered.clone(), gate.clone()));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/work", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, entered, gate)
+    }
+
+    /// 依次发起请求，每个请求进入处理函数后再发起下一个，全部进入后放行并返回响应
+    async fn overlapping_requests(url: &str, count: usize, entered: &AtomicUsize, gate: &Semaphore) -> Vec<reqwest::Response> {
+        let mut requests = Vec::new();
+        for i in 0..count {
+            requests.push(tokio::spawn(reqwest::get(url.to_string())));
+            while entered.load(Ordering::SeqCst) <= i {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        }
+        gate.add_permits(count);
+        let mut responses = Vec::new();
+        for request in requests {
+            responses.push(request.await.unwrap().unwrap());
+        }
+        responses
+    }
+
+    #[tokio::test]
+    async fn remaining_capacity_declines_as_concurrency_rises() {
+        let (url, entered, gate) = serve_gated(3, true).await;
+        let responses = overlapping_requests(&url, 3, &entered, &gate).await;
+
+        let remaining: Vec<_> = responses.iter()
+            .map(|response| {
+                assert_eq!(response.headers()[CONCURRENCY_LIMIT_HEADER], "3");
+                response.headers()[CONCURRENCY_REMAINING_HEADER].to_str().unwrap().to_string()
+            })
+            .collect();
+        assert_eq!(remaining, ["2", "1", "0"]);
+    }
+
+    #[tokio::test]
+    async fn capacity_headers_hidden_by_default() {
+        let (url, entered, gate) = serve_gated(3, false).await;
+        let responses = overlapping_requests(&url, 1, &entered, &gate).await;
+        assert!(responses[0].headers().get(CONCURRENCY_REMAINING_HEADER).is_none());
+        assert!(responses[0].headers().get(CONCURRENCY_LIMIT_HEADER).is_none());
+    }
+
+    #[tokio::test]
+    async fn graceful_shutdown_drains_in_flight_requests() {
+        // 写入接口放慢响应，停止信号到达时请求仍在处理中
+        let received = Arc::new(tokio::sync::Notify::new());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let crud_url = format!("http://{}", listener.local_addr().unwrap());
+        let crud = Router::new()
+            .route("/health", get(|| async { Json(serde_json::json!({ "status": "ok" })) }))
+            .route("/:resource_type", post(|State(received): State<Arc<tokio::sync::Notify>>| async move {
+                received.notify_one();
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                Json(serde_json::json!({ "success": true, "message": "ok", "data": { "id": "7" } }))
+            }))
+            .with_state(received.clone());
+        tokio::spawn(async move { axum::serve(listener, crud).await.unwrap() });
+
+        let vars = [("AUTH_MODE", "none"), ("CRUD_API_WRITE_INSTANCE_URL", crud_url.as_str()), ("CACHE_BACKEND", "memory")];
+        let service = Arc::new(EncryptionService::new(Arc::new(AppConfig::from_vars(&vars).unwrap())));
+        service.get_scheduler().perform_health_check().await.unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(axum::serve(listener, create_router(service).into_make_service_with_connect_info::<SocketAddr>())
+            .with_graceful_shutdown(async { shutdown_rx.await.ok(); })
+            .into_future());
+
+        let request = tokio::spawn(reqwest::Client::new()
+            .post(format!("{}/encrypt", url))
+            .json(&serde_json::json!({ "data": "secret", "password": "password", "resource_type": "user" }))
+            .send());
+        received.notified().await;
+        shutdown_tx.send(()).unwrap();
+
+        // 正在处理的请求正常完成后服务器才退出
+        let response = request.await.unwrap().unwrap();
+        assert!(response.status().is_success());
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["data"]["resource_id"], "7");
+        tokio::time::timeout(Duration::from_secs(2), server).await.unwrap().unwrap().unwrap();
+        assert!(reqwest::get(format!("{}/health", url)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn all_role_forbidden_batch_returns_403_with_common_cause() {
+        let (url, _) = serve(&[("AUTH_MODE", "none"), ("SERVICE_ROLE", "decrypt"), ("BATCH_AGGREGATE_STATUS", "true")]).await;
+        let response = reqwest::Client::new()
+            .post(format!("{}/batch/encrypt", url))
+            .json(&serde_json::json!([
+                { "data": "one", "password": "password", "resource_type": "user" },
+                { "data": "two", "password": "password", "resource_type": "order" },
+            ]))
+            .send().await.unwrap();
+        assert_eq!(response.status().as_u16(), StatusCode::FORBIDDEN.as_u16());
+
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["success"], false);
+        assert!(body["message"].as_str().unwrap().starts_with("批量加密全部失败: "));
+        let items = body["data"].as_array().unwrap();
+        assert_eq!(items.len(), 2);
+        assert!(items.iter().all(|item| item["status"] == 403 && item["error"].is_string()));
+    }
+
+    #[tokio::test]
+    async fn mixed_batch_returns_207_with_per_item_status() {
+        let (url, _) = serve(&[("AUTH_MODE", "none"), ("BATCH_AGGREGATE_STATUS", "true")]).await;
+        let client = reqwest::Client::new();
+        let encrypted: serde_json::Value = client.post(format!("{}/encrypt", url))
+            .json(&serde_json::json!({ "data": "secret", "password": "password", "resource_type": "user", "dry_run": true }))
+            .send().await.unwrap()
+            .json().await.unwrap();
+        let encrypted_data = encrypted["data"]["encrypted_data"].as_str().unwrap();
+
+        let response = client.post(format!("{}/batch/decrypt", url))
+            .json(&serde_json::json!([
+                { "encrypted_data": encrypted_data, "password": "password", "resource_type": "user" },
+                { "encrypted_data": encrypted_data, "password": "wrong-password", "resource_type": "user" },
+            ]))
+            .send().await.unwrap();
+        assert_eq!(response.status().as_u16(), StatusCode::MULTI_STATUS.as_u16());
+
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["message"], "批量解密完成: 成功 1 项，失败 1 项");
+        let statuses: Vec<_> = body["data"].as_array().unwrap().iter().map(|item| item["status"].as_u64().unwrap()).collect();
+        assert_eq!(statuses[0], 200);
+        assert!(StatusCode::from_u16(statuses[1] as u16).unwrap().is_client_error());
+    }
+
+    #[tokio::test]
+    async fn status_reports_test_instance_health() {
+        let (url, _) = serve(&[("AUTH_MODE", "none")]).await;
+        let body: serde_json::Value = reqwest::get(format!("{}/status", url)).await.unwrap()
+            .json().await.unwrap();
+        assert_eq!(body["data"]["mode"], "normal");
+        assert!(body["data"]["test_instance"].is_null());
+    }
+
+    /// 按HMAC签名规则对签名原文计算签名
+    fn hmac_signature(secret: &str, message: &str) -> String {
+        use hmac::{Hmac, Mac};
+        let mut mac = <Hmac<sha2::Sha256> as Mac>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(message.as_bytes());
+        mac.finalize().into_bytes().iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    /// 发送签名的流式请求，签名覆盖digest_of的SHA-256摘要
+    async fn signed_stream(url: &str, path: &str, body: Vec<u8>, digest_of: &[u8]) -> reqwest::Response {
+        use sha2::Digest;
+        let digest: String = sha2::Sha256::digest(digest_of).iter().map(|byte| format!("{:02x}", byte)).collect();
+        let timestamp = chrono::Utc::now().timestamp().to_string();
+        let signature = hmac_signature("stream-hmac-secret-0123", &format!("{}.POST.{}.{}", timestamp, path, digest));
+        reqwest::Client::new()
+            .post(format!("{}{}", url, path))
+            .header("x-encryption-password", "password")
+            .header("x-resource-type", "user")
+            .header(TIMESTAMP_HEADER, timestamp)
+            .header(CONTENT_SHA256_HEADER, digest)
+            .header(SIGNATURE_HEADER, signature)
+            .body(body)
+            .send().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn signed_streams_are_not_limited_by_body_size() {
+        let (url, _) = serve(&[
+            ("AUTH_MODE", "hmac"),
+            ("AUTH_HMAC_SECRET", "stream-hmac-secret-0123"),
+            ("MAX_BODY_SIZE", "1024"),
+        ]).await;
+        let plaintext: Vec<u8> = (0..256 * 1024).map(|i| (i % 251) as u8).collect();
+
+        let response = signed_stream(&url, "/encrypt/stream", plaintext.clone(), &plaintext).await;
+        assert_eq!(response.status().as_u16(), StatusCode::OK.as_u16());
+        let ciphertext = response.bytes().await.unwrap().to_vec();
+        let response = signed_stream(&url, "/decrypt/stream", ciphertext.clone(), &ciphertext).await;
+        assert_eq!(response.status().as_u16(), StatusCode::OK.as_u16());
+        assert_eq!(response.bytes().await.unwrap().to_vec(), plaintext);
+
+        // 请求体与签名中的摘要不一致时响应被中止，不会返回完整的密文
+        // 签名覆盖另一份数据的摘要，避免与上面的请求在同一秒内生成相同签名而被当作重放拒绝
+        let mut tampered = plaintext.clone();
+        tampered[0] ^= 1;
+        let response = signed_stream(&url, "/encrypt/stream", plaintext.clone(), &tampered).await;
+        assert_eq!(response.status().as_u16(), StatusCode::OK.as_u16());
+        assert!(response.bytes().await.is_err());
 
-    router
+        // 缺少摘要请求头时拒绝
+        let timestamp = chrono::Utc::now().timestamp().to_string();
+        let response = reqwest::Client::new()
+            .post(format!("{}/encrypt/stream", url))
+            .header(TIMESTAMP_HEADER, &timestamp)
+            .header(SIGNATURE_HEADER, hmac_signature("stream-hmac-secret-0123", &format!("{}.POST./encrypt/stream.", timestamp)))
+            .body(plaintext)
+            .send().await.unwrap();
+        assert_eq!(response.status().as_u16(), StatusCode::UNAUTHORIZED.as_u16());
+    }
 }
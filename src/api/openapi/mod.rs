@@ -0,0 +1,74 @@
+use axum::Json;
+use axum::response::Html;
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+use super::handlers;
+
+/// 由处理函数和请求、响应类型生成的OpenAPI文档
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "加密服务 API"),
+    paths(
+        handlers::encrypt,
+        handlers::decrypt,
+        handlers::verify,
+        handlers::batch_encrypt,
+        handlers::batch_decrypt,
+        handlers::batch_verify,
+        handlers::rewrap,
+        handlers::rotate,
+        handlers::update_resource,
+        handlers::delete_resource,
+    ),
+    modifiers(&BearerAuth),
+    tags(
+        (name = "加解密", description = "单条数据加密、解密和密钥轮换"),
+        (name = "批量操作", description = "请求体为数组，按条目返回结果"),
+        (name = "资源", description = "通过CRUD API管理资源记录"),
+    ),
+)]
+struct ApiDoc;
+
+/// 注册JWT Bearer认证方案
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).bearer_format("JWT").build()),
+        );
+    }
+}
+
+/// Swagger UI页面，静态资源从CDN加载，文档来自 /openapi.json
+const SWAGGER_UI_HTML: &str = r##"<!DOCTYPE html>
+<html lang="zh-CN">
+<head>
+  <meta charset="utf-8">
+  <title>加密服务 API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>
+    window.onload = () => {
+      window.ui = SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });
+    };
+  </script>
+</body>
+</html>
+"##;
+
+/// OpenAPI文档处理函数
+pub async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+/// Swagger UI处理函数
+pub async fn swagger_ui() -> Html<&'static str> {
+    Html(SWAGGER_UI_HTML)
+}
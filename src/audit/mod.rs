@@ -0,0 +1,133 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::sync::Mutex;
+
+use anyhow::Result;
+use chrono::Utc;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+use crate::request_id;
+
+/// 审计链的起始哈希
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// 启动时读取末尾多少字节查找上一条记录，单条记录远小于该长度
+const TAIL_BYTES: u64 = 8192;
+
+/// 审计记录，不包含明文和密码
+///
+/// 每条记录的 prev_hash 为上一条记录的 hash，hash 为不含 hash 字段的记录JSON的SHA-256，
+/// 删除、插入或修改任意一条记录都会使之后的哈希链校验失败
+#[derive(Debug, Serialize)]
+struct AuditRecord<'a> {
+    /// 记录时间（RFC 3339）
+    timestamp: String,
+    /// 操作类型：encrypt、decrypt、update 等
+    operation: &'a str,
+    resource_type: &'a str,
+    resource_id: Option<&'a str>,
+    /// 处理请求的服务ID
+    service_id: &'a str,
+    /// 请求ID，用于关联访问日志
+    request_id: Option<String>,
+    /// 操作是否成功
+    success: bool,
+    /// 密文的SHA-256，无法确定密文时为空
+    ciphertext_sha256: Option<String>,
+    /// 上一条记录的哈希
+    prev_hash: &'a str,
+}
+
+/// 审计日志，以JSON Lines格式追加写入文件，每次写入后落盘
+#[derive(Debug)]
+pub struct AuditLog {
+    service_id: String,
+    /// 日志文件和最后一条记录的哈希
+    state: Mutex<(File, String)>,
+}
+
+impl AuditLog {
+    /// 打开审计日志文件，已有记录时从最后一条记录的哈希继续哈希链
+    pub fn open(path: &str, service_id: String) -> Result<Self> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)?;
+        let last_hash = resume_chain(&mut file)?;
+        info!("审计日志写入 {}", path);
+
+        Ok(Self {
+            service_id,
+            state: Mutex::new((file, last_hash)),
+        })
+    }
+
+    /// 记录一次操作，写入失败只记录日志，不影响请求
+    pub fn record(&self, operation: &str, resource_type: &str, resource_id: Option<&str>, ciphertext: Option<&str>, success: bool) {
+        if let Err(e) = self.append(operation, resource_type, resource_id, ciphertext, success) {
+            warn!("写入审计日志失败: {:?}", e);
+        }
+    }
+
+    fn append(&self, operation: &str, resource_type: &str, resource_id: Option<&str>, ciphertext: Option<&str>, success: bool) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let (ref mut file, ref mut prev_hash) = *state;
+
+        let record = AuditRecord {
+            timestamp: Utc::now().to_rfc3339(),
+            operation,
+            resource_type,
+            resource_id,
+            service_id: &self.service_id,
+            request_id: request_id::current(),
+            success,
+            ciphertext_sha256: ciphertext.map(|c| hex(&Sha256::digest(c.as_bytes()))),
+            prev_hash,
+        };
+        let body = serde_json::to_string(&record)?;
+        let hash = hex(&Sha256::digest(body.as_bytes()));
+
+        // 在记录末尾追加hash字段，校验时去掉该字段即可还原参与哈希的内容
+        let line = format!("{},\"hash\":\"{}\"}}\n", &body[..body.len() - 1], hash);
+        file.write_all(line.as_bytes())?;
+        file.sync_data()?;
+        *prev_hash = hash;
+        Ok(())
+    }
+}
+
+/// 读取文件最后一条记录的哈希，文件为空时返回起始哈希
+///
+/// 最后一行没有换行符时先补齐，避免新记录接在不完整的行后面
+fn resume_chain(file: &mut File) -> Result<String> {
+    let len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(TAIL_BYTES)))?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+    if !tail.is_empty() && !tail.ends_with(b"\n") {
+        file.write_all(b"\n")?;
+    }
+    let tail = String::from_utf8_lossy(&tail);
+
+    let Some(line) = tail.lines().rev().find(|line| !line.trim().is_empty()) else {
+        return Ok(GENESIS_HASH.to_string());
+    };
+    let hash = serde_json::from_str::<serde_json::Value>(line).ok()
+        .and_then(|record| record.get("hash").and_then(|hash| hash.as_str()).map(|hash| hash.to_string()));
+    match hash {
+        Some(hash) => Ok(hash),
+        None => {
+            // 最后一行不完整（例如写入时进程退出），从起始哈希开始新的哈希链，校验时可以发现断点
+            warn!("审计日志最后一条记录无法解析，从起始哈希开始新的哈希链");
+            Ok(GENESIS_HASH.to_string())
+        },
+    }
+}
+
+/// 十六进制编码
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use anyhow::Result;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::config::{AuthConfig, JwtConfig};
+
+/// JWT声明
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Claims {
+    /// 令牌主体，通常为调用方标识
+    pub sub: String,
+    /// 签发时间（秒）
+    pub iat: i64,
+    /// 过期时间（秒）
+    pub exp: i64,
+}
+
+/// 为指定主体签发令牌，有效期为JWT_EXPIRES_IN
+pub fn issue_token(config: &JwtConfig, subject: &str) -> Result<String> {
+    let now = Utc::now().timestamp();
+    let claims = Claims {
+        sub: subject.to_string(),
+        iat: now,
+        exp: now + config.expires_in,
+    };
+    let token = jsonwebtoken::encode(
+        &Header::new(Algorithm::HS256),
+        &claims,
+        &EncodingKey::from_secret(config.secret.as_bytes()),
+    )?;
+    Ok(token)
+}
+
+/// 校验令牌签名和过期时间，返回令牌声明
+pub fn validate_token(config: &JwtConfig, token: &str) -> Result<Claims> {
+    let mut validation = Validation::new(Algorithm::HS256);
+    // 过期时间不留宽限
+    validation.leeway = 0;
+    let data = jsonwebtoken::decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(config.secret.as_bytes()),
+        &validation,
+    )?;
+    Ok(data.claims)
+}
+
+/// 请求签名校验失败的原因
+#[derive(Debug, thiserror::Error)]
+pub enum SignatureError {
+    /// 时间戳不是整数秒
+    #[error("请求时间戳无效")]
+    InvalidTimestamp,
+    /// 时间戳与服务器时间偏差过大，可能是重放请求
+    #[error("请求时间戳已过期")]
+    StaleTimestamp,
+    /// 签名格式错误或与请求内容不一致
+    #[error("请求签名无效")]
+    InvalidSignature,
+    /// 签名在时间戳有效期内已被使用过
+    #[error("请求签名已使用")]
+    ReplayedSignature,
+}
+
+/// 记录有效期内已使用的请求签名，拒绝原样重放的请求
+#[derive(Debug, Default)]
+pub struct SignatureReplayGuard {
+    /// 已使用的签名及其失效时间（Unix秒）
+    seen: Mutex<HashMap<Vec<u8>, i64>>,
+}
+
+impl SignatureReplayGuard {
+    /// 创建空的签名记录
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录签名，签名在失效前已出现过时返回false；同时清理已失效的记录
+    fn record(&self, signature: &[u8], expires_at: i64, now: i64) -> bool {
+        let mut seen = self.seen.lock().unwrap();
+        seen.retain(|_, expires| *expires >= now);
+        if seen.contains_key(signature) {
+            return false;
+        }
+        seen.insert(signature.to_vec(), expires_at);
+        true
+    }
+}
+
+/// 校验请求签名：X-Signature 为 HMAC-SHA256(密钥, "{X-Timestamp}.{方法}.{路径和查询}.{请求体}") 的十六进制编码
+///
+/// 时间戳为Unix秒，与服务器时间的偏差超过AUTH_HMAC_MAX_SKEW时拒绝；
+/// 偏差范围内同一签名只能使用一次
+pub fn verify_signature(
+    config: &AuthConfig,
+    replay_guard: &SignatureReplayGuard,
+    timestamp: &str,
+    method: &str,
+    path_and_query: &str,
+    body: &[u8],
+    signature: &str,
+) -> Result<(), SignatureError> {
+    verify_signature_at(config, replay_guard, timestamp, method, path_and_query, body, signature, Utc::now().timestamp())
+}
+
+/// 以指定的当前时间校验请求签名
+#[allow(clippy::too_many_arguments)]
+fn verify_signature_at(
+    config: &AuthConfig,
+    replay_guard: &SignatureReplayGuard,
+    timestamp: &str,
+    method: &str,
+    path_and_query: &str,
+    body: &[u8],
+    signature: &str,
+    now: i64,
+) -> Result<(), SignatureError> {
+    let signed_at: i64 = timestamp.parse().map_err(|_| SignatureError::InvalidTimestamp)?;
+    if now.abs_diff(signed_at) > config.hmac_max_skew {
+        return Err(SignatureError::StaleTimestamp);
+    }
+
+    // 配置验证时已检查hmac认证方式下必须配置密钥
+    let secret = config.hmac_secret.as_deref().unwrap_or_default();
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(secret.as_bytes())
+        .expect("HMAC可以接受任意长度的密钥");
+    for part in [timestamp.as_bytes(), method.as_bytes(), path_and_query.as_bytes()] {
+        mac.update(part);
+        mac.update(b".");
+    }
+    mac.update(body);
+
+    let signature = decode_hex(signature).ok_or(SignatureError::InvalidSignature)?;
+    mac.verify_slice(&signature).map_err(|_| SignatureError::InvalidSignature)?;
+
+    // 按解码后的字节记录，大小写不同的同一签名也视为重放；时间戳超出偏差后签名自然失效
+    let expires_at = signed_at.saturating_add(config.hmac_max_skew as i64);
+    if !replay_guard.record(&signature, expires_at, now) {
+        return Err(SignatureError::ReplayedSignature);
+    }
+    Ok(())
+}
+
+/// 解码十六进制字符串，大小写均可
+pub(crate) fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| value.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AuthMode;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn config() -> AuthConfig {
+        AuthConfig {
+            mode: AuthMode::Hmac,
+            hmac_secret: Some("test-secret".to_string()),
+            hmac_max_skew: 300,
+        }
+    }
+
+    fn sign(timestamp: &str, method: &str, path: &str, body: &[u8]) -> String {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(b"test-secret").unwrap();
+        mac.update(format!("{}.{}.{}.", timestamp, method, path).as_bytes());
+        mac.update(body);
+        mac.finalize().into_bytes().iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    fn verify(timestamp: &str, method: &str, path: &str, body: &[u8], signature: &str, now: i64) -> Result<(), SignatureError> {
+        verify_signature_at(&config(), &SignatureReplayGuard::new(), timestamp, method, path, body, signature, now)
+    }
+
+    #[test]
+    fn accepts_valid_signature() {
+        let ts = NOW.to_string();
+        let signature = sign(&ts, "POST", "/decrypt", b"{}");
+        assert!(verify(&ts, "POST", "/decrypt", b"{}", &signature, NOW).is_ok());
+        assert!(verify(&ts, "POST", "/decrypt", b"{}", &signature.to_uppercase(), NOW).is_ok());
+    }
+
+    #[test]
+    fn rejects_tampered_body() {
+        let ts = NOW.to_string();
+        let signature = sign(&ts, "POST", "/decrypt", br#"{"id":"1"}"#);
+        let result = verify(&ts, "POST", "/decrypt", br#"{"id":"2"}"#, &signature, NOW);
+        assert!(matches!(result, Err(SignatureError::InvalidSignature)));
+    }
+
+    #[test]
+    fn rejects_signature_for_other_method_or_path() {
+        let ts = NOW.to_string();
+        let signature = sign(&ts, "DELETE", "/resource/user/1", b"");
+        let result = verify(&ts, "DELETE", "/resource/user/2", b"", &signature, NOW);
+        assert!(matches!(result, Err(SignatureError::InvalidSignature)));
+        let result = verify(&ts, "PUT", "/resource/user/1", b"", &signature, NOW);
+        assert!(matches!(result, Err(SignatureError::InvalidSignature)));
+
+        let signature = sign(&ts, "POST", "/decrypt/verify", b"{}");
+        let result = verify(&ts, "POST", "/decrypt", b"{}", &signature, NOW);
+        assert!(matches!(result, Err(SignatureError::InvalidSignature)));
+    }
+
+    #[test]
+    fn rejects_timestamp_outside_skew() {
+        let ts = (NOW - 301).to_string();
+        let signature = sign(&ts, "POST", "/decrypt", b"{}");
+        let result = verify(&ts, "POST", "/decrypt", b"{}", &signature, NOW);
+        assert!(matches!(result, Err(SignatureError::StaleTimestamp)));
+
+        let result = verify("soon", "POST", "/decrypt", b"{}", &signature, NOW);
+        assert!(matches!(result, Err(SignatureError::InvalidTimestamp)));
+    }
+
+    #[test]
+    fn rejects_replayed_signature_within_skew() {
+        let guard = SignatureReplayGuard::new();
+        let ts = NOW.to_string();
+        let signature = sign(&ts, "POST", "/decrypt", b"{}");
+        let check = |signature: &str, now| verify_signature_at(&config(), &guard, &ts, "POST", "/decrypt", b"{}", signature, now);
+
+        assert!(check(&signature, NOW).is_ok());
+        assert!(matches!(check(&signature, NOW + 10), Err(SignatureError::ReplayedSignature)));
+        assert!(matches!(check(&signature.to_uppercase(), NOW + 10), Err(SignatureError::ReplayedSignature)));
+        // 超出偏差范围后由时间戳检查拒绝
+        assert!(matches!(check(&signature, NOW + 301), Err(SignatureError::StaleTimestamp)));
+    }
+
+    #[test]
+    fn failed_verification_does_not_consume_signature() {
+        let guard = SignatureReplayGuard::new();
+        let ts = NOW.to_string();
+        let signature = sign(&ts, "POST", "/decrypt", b"{}");
+        let tampered = verify_signature_at(&config(), &guard, &ts, "POST", "/decrypt", b"{ }", &signature, NOW);
+        assert!(matches!(tampered, Err(SignatureError::InvalidSignature)));
+        assert!(verify_signature_at(&config(), &guard, &ts, "POST", "/decrypt", b"{}", &signature, NOW).is_ok());
+    }
+}
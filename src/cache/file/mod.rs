@@ -0,0 +1,738 @@
+use std::fs::{self, File, OpenOptions};
+use std::collections::HashSet;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+use flate2::Compression;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use tracing::{info, warn, error};
+use anyhow::Result;
+
+use crate::config::CacheConfig;
+use crate::crypto::CacheCipher;
+use super::{CacheBackend, CacheDataType, CacheEntry, CacheEntryMetadata, CacheEntryPage, CacheStats, current_timestamp};
+
+/// 加密缓存行的前缀，不带前缀的行为明文JSON
+const ENCRYPTED_LINE_PREFIX: &str = "enc:";
+
+/// 已处理条目的归档子目录名
+const PROCESSED_DIR_NAME: &str = "processed";
+
+/// 未压缩缓存文件的后缀
+const PLAIN_SUFFIX: &str = ".jsonl";
+
+/// gzip压缩缓存文件的后缀
+const COMPRESSED_SUFFIX: &str = ".jsonl.gz";
+
+/// 是否为gzip压缩的缓存文件
+fn is_compressed(path: &Path) -> bool {
+    path.to_string_lossy().ends_with(COMPRESSED_SUFFIX)
+}
+
+/// 将行压缩为一个完整的gzip成员，多个成员直接拼接仍是合法的gzip文件
+fn gzip_member(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::with_capacity(data.len() / 2), Compression::default());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+/// 返回由完整gzip成员组成的前缀长度，写入中断时末尾可能残留不完整的成员
+fn complete_gzip_len(bytes: &[u8]) -> usize {
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let mut decoder = flate2::bufread::GzDecoder::new(&bytes[offset..]);
+        if io::copy(&mut decoder, &mut io::sink()).is_err() {
+            break;
+        }
+        let next = bytes.len() - decoder.into_inner().len();
+        if next <= offset {
+            break;
+        }
+        offset = next;
+    }
+    offset
+}
+
+/// JSONL文件缓存后端，每行一个条目，按时间间隔切换文件
+#[derive(Debug)]
+pub struct FileCacheBackend {
+    /// 缓存目录
+    cache_dir: String,
+    /// 备用缓存目录，主目录写入失败时使用
+    secondary_dir: Option<String>,
+    /// 临时文件前缀
+    temp_file_prefix: String,
+    /// 临时文件更新间隔（秒）
+    update_interval: u64,
+    /// 临时文件保留时间（秒）
+    retention_time: u64,
+    /// 缓存加密器，未配置缓存密钥时为None
+    cipher: Option<Arc<CacheCipher>>,
+    /// 是否以gzip压缩写入新条目
+    compression: bool,
+    /// 缓存文件写锁，追加写入和重写文件互斥；保存本进程已检查过末尾完整性的压缩文件
+    write_lock: Mutex<Option<String>>,
+}
+
+impl FileCacheBackend {
+    /// 创建文件缓存后端
+    pub fn new(config: &CacheConfig, encryption_key: Option<&str>, secondary_dir: Option<String>) -> Self {
+        let cache_dir = config.cache_dir.clone();
+
+        // 创建缓存目录
+        if let Err(e) = fs::create_dir_all(&cache_dir) {
+            error!("无法创建缓存目录: {:?}", e);
+        }
+        if let Some(ref dir) = secondary_dir
+            && let Err(e) = fs::create_dir_all(dir)
+        {
+            error!("无法创建备用缓存目录: {:?}", e);
+        }
+
+        let cipher = match encryption_key {
+            Some(key) => Some(Arc::new(CacheCipher::new(key).expect("无法创建缓存加密器"))),
+            None => {
+                warn!("未配置缓存加密密钥，缓存文件将不保存密码和解密明文");
+                None
+            },
+        };
+
+        Self {
+            cache_dir,
+            secondary_dir,
+            temp_file_prefix: config.temp_file_prefix.clone(),
+            update_interval: config.update_interval,
+            retention_time: config.retention_time,
+            cipher,
+            compression: config.compression,
+            write_lock: Mutex::new(None),
+        }
+    }
+
+    /// 获取当前缓存文件路径
+    fn get_current_cache_file(&self, dir: &str) -> String {
+        let suffix = if self.compression { COMPRESSED_SUFFIX } else { PLAIN_SUFFIX };
+        let file_name = format!("{}_{}{}", self.temp_file_prefix, current_timestamp() / self.update_interval, suffix);
+        format!("{}/{}", dir, file_name)
+    }
+
+    /// 缓存目录列表，主目录在前
+    fn cache_dirs(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.cache_dir.as_str()).chain(self.secondary_dir.as_deref())
+    }
+
+    /// 序列化缓存条目为一行，配置了缓存密钥时加密整行
+    fn encode_line(&self, entry: &CacheEntry) -> Result<String> {
+        let json_str = serde_json::to_string(entry)?;
+        match self.cipher {
+            Some(ref cipher) => Ok(format!("{}{}", ENCRYPTED_LINE_PREFIX, cipher.encrypt(json_str.as_bytes())?)),
+            None => Ok(json_str),
+        }
+    }
+
+    /// 解析一行缓存数据并升级到当前版本
+    fn parse_line(&self, line: &str) -> Result<CacheEntry> {
+        self.decode_line(line)?.upgrade(line)
+    }
+
+    /// 解析一行缓存数据，兼容加密行和明文行
+    fn decode_line(&self, line: &str) -> Result<CacheEntry> {
+        let entry = match line.strip_prefix(ENCRYPTED_LINE_PREFIX) {
+            Some(encoded) => {
+                let cipher = self.cipher.as_ref()
+                    .ok_or_else(|| anyhow::anyhow!("缓存条目已加密，但未配置缓存加密密钥"))?;
+                serde_json::from_slice(&cipher.decrypt(encoded)?)?
+            },
+            None => serde_json::from_str(line)?,
+        };
+        Ok(entry)
+    }
+
+    /// 向指定目录的当前缓存文件追加一行并落盘，返回文件路径
+    ///
+    /// checked_file为本进程已检查过末尾完整性的压缩文件，由调用方在写锁内保存
+    fn append_line(&self, dir: &str, line: &str, checked_file: &mut Option<String>) -> Result<String> {
+        // 打开或创建缓存文件
+        let file_path = self.get_current_cache_file(dir);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .read(true)
+            .open(&file_path)?;
+
+        let mut record = Vec::with_capacity(line.len() + 2);
+        let mut file_len = file.metadata()?.len();
+        if self.compression {
+            // 本进程首次写入已有的压缩文件时，截掉上次写入中断残留的不完整gzip成员，否则其后的条目都无法读取
+            if file_len > 0 && checked_file.as_deref() != Some(file_path.as_str()) {
+                let mut bytes = Vec::new();
+                file.seek(SeekFrom::Start(0))?;
+                file.read_to_end(&mut bytes)?;
+                let complete_len = complete_gzip_len(&bytes) as u64;
+                if complete_len < file_len {
+                    warn!("缓存文件 {} 末尾存在不完整的压缩数据，可能由上次写入中断导致，已截断", file_path);
+                    file.set_len(complete_len)?;
+                    file_len = complete_len;
+                }
+            }
+            *checked_file = Some(file_path.clone());
+            let mut plain = Vec::with_capacity(line.len() + 1);
+            plain.extend_from_slice(line.as_bytes());
+            plain.push(b'\n');
+            record = gzip_member(&plain)?;
+        } else {
+            // 上次写入中断时文件末尾可能残留不完整的行，先换行，避免新条目与其拼接后一起损坏
+            if file_len > 0 {
+                let mut last_byte = [0u8; 1];
+                file.seek(SeekFrom::End(-1))?;
+                file.read_exact(&mut last_byte)?;
+                if last_byte[0] != b'\n' {
+                    warn!("缓存文件 {} 末尾存在不完整的条目，可能由上次写入中断导致", file_path);
+                    record.push(b'\n');
+                }
+            }
+            record.extend_from_slice(line.as_bytes());
+            record.push(b'\n');
+        }
+
+        // 整行一次写入并落盘，进程崩溃时最多丢失正在写入的这一行
+        file.write_all(&record)?;
+        file.sync_data()?;
+
+        // 新建的缓存文件需要同步目录，确保文件本身在崩溃后仍然存在
+        if file_len == 0 {
+            File::open(dir)?.sync_all()?;
+        }
+
+        Ok(file_path)
+    }
+
+    /// 逐行读取缓存文件，压缩文件透明解压
+    ///
+    /// 压缩文件末尾的不完整gzip成员只记录警告，此前的行仍然返回
+    fn read_lines(&self, path: &Path) -> Result<Box<dyn Iterator<Item = io::Result<String>>>> {
+        let file = File::open(path)?;
+        if !is_compressed(path) {
+            return Ok(Box::new(BufReader::new(file).lines()));
+        }
+        let path = path.to_path_buf();
+        let lines = BufReader::new(MultiGzDecoder::new(file)).lines()
+            .map_while(move |line| match line {
+                Ok(line) => Some(Ok(line)),
+                Err(e) => {
+                    warn!("压缩缓存文件 {:?} 末尾存在不完整的数据: {}", path, e);
+                    None
+                },
+            });
+        Ok(Box::new(lines))
+    }
+
+    /// 写入行，压缩文件写入为一个gzip成员
+    fn write_lines(file: &mut File, lines: &[&str], compressed: bool) -> Result<()> {
+        let mut plain = Vec::new();
+        for line in lines {
+            plain.extend_from_slice(line.as_bytes());
+            plain.push(b'\n');
+        }
+        if compressed {
+            file.write_all(&gzip_member(&plain)?)?;
+        } else {
+            file.write_all(&plain)?;
+        }
+        Ok(())
+    }
+
+    /// 获取主目录和备用目录中的所有缓存文件路径，按文件名排序
+    fn cache_files(&self) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        for dir in self.cache_dirs() {
+            let entries = match fs::read_dir(dir) {
+                Ok(entries) => entries,
+                // 主目录不可读时仍然返回备用目录中的文件
+                Err(e) if dir != self.cache_dir => {
+                    warn!("无法读取备用缓存目录 {}: {:?}", dir, e);
+                    continue;
+                },
+                Err(e) => return Err(e.into()),
+            };
+            files.extend(entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.is_file() && (path.extension() == Some("jsonl".as_ref()) || is_compressed(path))));
+        }
+        files.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+        Ok(files)
+    }
+
+    /// 读取单个缓存文件中的条目
+    fn read_file_entries(&self, path: &Path) -> Result<Vec<CacheEntry>> {
+        let mut entries = Vec::new();
+        for line in self.read_lines(path)? {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            match self.parse_line(&line) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => warn!("无法解析缓存条目: {:?}, 文件: {:?}", e, path),
+            }
+        }
+        Ok(entries)
+    }
+
+    /// 将行追加到缓存文件所在目录的processed子目录中的同名文件
+    fn archive_lines(&self, path: &Path, lines: &[&str]) -> Result<()> {
+        let dir = path.parent()
+            .ok_or_else(|| anyhow::anyhow!("缓存文件路径无效: {:?}", path))?
+            .join(PROCESSED_DIR_NAME);
+        fs::create_dir_all(&dir)?;
+        let file_name = path.file_name()
+            .ok_or_else(|| anyhow::anyhow!("缓存文件路径无效: {:?}", path))?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(file_name))?;
+        Self::write_lines(&mut file, lines, is_compressed(path))?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    /// 用剩余的行重写缓存文件，通过临时文件原子替换，没有剩余行时删除文件
+    fn rewrite_file(&self, path: &Path, lines: &[&str]) -> Result<()> {
+        if lines.is_empty() {
+            fs::remove_file(path)?;
+            return Ok(());
+        }
+
+        let mut temp_path = path.as_os_str().to_owned();
+        temp_path.push(".tmp");
+        let temp_path = PathBuf::from(temp_path);
+        {
+            let mut file = File::create(&temp_path)?;
+            Self::write_lines(&mut file, lines, is_compressed(path))?;
+            file.sync_data()?;
+        }
+        fs::rename(&temp_path, path)?;
+        if let Some(dir) = path.parent() {
+            File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    }
+}
+
+impl CacheBackend for FileCacheBackend {
+    fn write(&self, mut entry: CacheEntry) -> Result<()> {
+        // 未加密时不落盘密码和明文
+        if self.cipher.is_none() {
+            entry.clear_secrets();
+        }
+
+        // 序列化缓存条目
+        let line = self.encode_line(&entry)?;
+
+        let mut checked_file = self.write_lock.lock().unwrap();
+
+        // 主目录写入失败时写入备用目录，避免单块磁盘故障导致数据丢失
+        let file_path = match (self.append_line(&self.cache_dir, &line, &mut checked_file), &self.secondary_dir) {
+            (Err(e), Some(secondary_dir)) => {
+                warn!("写入主缓存目录失败，改为写入备用目录 {}: {:?}", secondary_dir, e);
+                self.append_line(secondary_dir, &line, &mut checked_file)?
+            },
+            (result, _) => result?,
+        };
+
+        info!("缓存数据已写入文件: {}", file_path);
+        Ok(())
+    }
+
+    /// 读取主目录和备用目录中所有缓存文件的条目
+    fn read_all(&self) -> Result<Vec<CacheEntry>> {
+        let mut all_entries = Vec::new();
+        for path in self.cache_files()? {
+            all_entries.extend(self.read_file_entries(&path)?);
+        }
+        Ok(all_entries)
+    }
+
+    /// 清理过期的缓存文件
+    fn clean_expired(&self) -> Result<()> {
+        let current_timestamp = current_timestamp();
+
+        // 已处理的归档文件同样按保留时间清理
+        let processed_files = self.cache_dirs()
+            .filter_map(|dir| fs::read_dir(Path::new(dir).join(PROCESSED_DIR_NAME)).ok())
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file());
+
+        for path in self.cache_files()?.into_iter().chain(processed_files) {
+            // 获取文件的修改时间
+            let metadata = fs::metadata(&path)?;
+            let modified_time = metadata.modified()?
+                .duration_since(UNIX_EPOCH)?
+                .as_secs();
+
+            // 检查文件是否过期
+            if current_timestamp - modified_time > self.retention_time {
+                if let Err(e) = fs::remove_file(&path) {
+                    warn!("无法删除过期缓存文件: {:?}", e);
+                } else {
+                    info!("已删除过期缓存文件: {:?}", path);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 从缓存文件中移除指定条目，并追加到同目录下的processed子目录归档
+    ///
+    /// 持有写锁完成读取和重写，期间不会有新条目追加到同一文件
+    fn mark_processed(&self, entry_ids: &HashSet<String>) -> Result<usize> {
+        let _guard = self.write_lock.lock().unwrap();
+
+        let mut processed_total = 0;
+        for path in self.cache_files()? {
+            let content = self.read_lines(&path)?.collect::<io::Result<Vec<String>>>()?;
+            let (processed, remaining): (Vec<&str>, Vec<&str>) = content.iter()
+                .map(String::as_str)
+                .filter(|line| !line.is_empty())
+                .partition(|line| self.parse_line(line)
+                    .map(|entry| entry_ids.contains(&entry.id))
+                    .unwrap_or(false));
+            if processed.is_empty() {
+                continue;
+            }
+
+            // 先归档再重写，崩溃时条目可能重复出现但不会丢失
+            self.archive_lines(&path, &processed)?;
+            self.rewrite_file(&path, &remaining)?;
+            processed_total += processed.len();
+
+            if processed_total == entry_ids.len() {
+                break;
+            }
+        }
+
+        Ok(processed_total)
+    }
+
+    /// 按行统计，字节数为行的长度，压缩文件按解压后计算
+    fn stats(&self) -> Result<CacheStats> {
+        let mut stats = CacheStats::default();
+
+        for path in self.cache_files()? {
+            stats.files += 1;
+            for line in self.read_lines(&path)? {
+                let line = line?;
+                if line.is_empty() {
+                    continue;
+                }
+                let bytes = line.len() as u64;
+                let type_stats = match self.decode_line(&line) {
+                    Ok(CacheEntry { data_type: CacheDataType::Encrypt(_), .. }) => &mut stats.encrypt,
+                    Ok(CacheEntry { data_type: CacheDataType::Decrypt(_), .. }) => &mut stats.decrypt,
+                    Err(_) => continue,
+                };
+                type_stats.entries += 1;
+                type_stats.bytes += bytes;
+                stats.total_entries += 1;
+                stats.total_bytes += bytes;
+            }
+        }
+
+        Ok(stats)
+    }
+
+    /// 按文件名和行顺序逐行读取，只解析到当前页为止
+    fn list_entries(&self, offset: usize, limit: usize) -> Result<CacheEntryPage> {
+        let files = self.cache_files()?;
+
+        let mut entries = Vec::with_capacity(limit);
+        let mut index = 0;
+        for path in files {
+            for line in self.read_lines(&path)? {
+                let line = line?;
+                if line.is_empty() {
+                    continue;
+                }
+                let entry = match self.parse_line(&line) {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        warn!("无法解析缓存条目: {:?}, 文件: {:?}", e, path);
+                        continue;
+                    },
+                };
+
+                if index >= offset {
+                    // 多读一条用于判断是否还有后续条目
+                    if entries.len() == limit {
+                        return Ok(CacheEntryPage { offset, limit, entries, has_more: true });
+                    }
+                    entries.push(CacheEntryMetadata::from(&entry));
+                }
+                index += 1;
+            }
+        }
+
+        Ok(CacheEntryPage { offset, limit, entries, has_more: false })
+    }
+
+    /// 同步缓存目录
+    fn flush(&self) -> Result<()> {
+        let _guard = self.write_lock.lock().unwrap();
+        for dir in self.cache_dirs() {
+            File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::{CACHE_SCHEMA_VERSION, DecryptCacheData, EncryptCacheData};
+    use crate::config::AppConfig;
+
+    /// 在系统临时目录下创建空的缓存目录
+    fn backend(name: &str, encryption_key: Option<&str>) -> (FileCacheBackend, PathBuf) {
+        backend_with(name, encryption_key, &[])
+    }
+
+    /// 在系统临时目录下创建空的缓存目录，附加额外的缓存配置
+    fn backend_with(name: &str, encryption_key: Option<&str>, vars: &[(&str, &str)]) -> (FileCacheBackend, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("encryption-service-cache-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        (open_backend(&dir, encryption_key, vars), dir)
+    }
+
+    /// 打开已有的缓存目录，模拟进程重启
+    fn open_backend(dir: &Path, encryption_key: Option<&str>, vars: &[(&str, &str)]) -> FileCacheBackend {
+        let mut vars = vars.to_vec();
+        vars.push(("CRUD_API_WRITE_INSTANCE_URL", "http://localhost"));
+        vars.push(("CACHE_DIR", dir.to_str().unwrap()));
+        let config = AppConfig::from_vars(&vars).unwrap();
+        FileCacheBackend::new(&config.cache, encryption_key, config.failover.cache_secondary_dir.clone())
+    }
+
+    fn decrypt_entry(id: &str) -> CacheEntry {
+        CacheEntry {
+            version: CACHE_SCHEMA_VERSION,
+            id: id.to_string(),
+            timestamp: current_timestamp(),
+            data_type: CacheDataType::Decrypt(DecryptCacheData {
+                encrypted_data: "ciphertext".to_string(),
+                password: "secret-password".to_string(),
+                resource_type: "user".to_string(),
+                resource_id: Some("1".to_string()),
+                decrypted_data: "secret-plaintext".to_string(),
+            }),
+            service_id: "test".to_string(),
+        }
+    }
+
+    fn encrypt_entry(id: &str) -> CacheEntry {
+        CacheEntry {
+            data_type: CacheDataType::Encrypt(EncryptCacheData {
+                data: "plaintext".to_string(),
+                password: String::new(),
+                resource_type: "user".to_string(),
+                encrypted_data: format!("ciphertext-{}", id),
+                persisted: false,
+                resource_id: None,
+                content_id: None,
+            }),
+            ..decrypt_entry(id)
+        }
+    }
+
+    fn entry_ids(backend: &FileCacheBackend) -> Vec<String> {
+        backend.read_all().unwrap().into_iter().map(|entry| entry.id).collect()
+    }
+
+    /// 截掉缓存文件末尾的若干字节，模拟写入中途进程崩溃
+    fn truncate_tail(backend: &FileCacheBackend, bytes: u64) {
+        let path = &backend.cache_files().unwrap()[0];
+        let file = OpenOptions::new().write(true).open(path).unwrap();
+        let len = file.metadata().unwrap().len();
+        file.set_len(len - bytes).unwrap();
+    }
+
+    /// 读取目录下所有缓存文件的原始内容
+    fn raw_contents(backend: &FileCacheBackend) -> String {
+        backend.cache_files().unwrap().iter().map(|path| fs::read_to_string(path).unwrap()).collect()
+    }
+
+    #[test]
+    fn plaintext_and_password_not_persisted_without_key() {
+        let (backend, dir) = backend("no-key", None);
+        backend.write(decrypt_entry("a")).unwrap();
+        let mut encrypt = encrypt_entry("b");
+        if let CacheDataType::Encrypt(ref mut data) = encrypt.data_type {
+            data.data = "secret-encrypt-plaintext".to_string();
+            data.password = "secret-encrypt-password".to_string();
+        }
+        backend.write(encrypt).unwrap();
+
+        let raw = raw_contents(&backend);
+        assert!(!raw.contains("secret-password"));
+        assert!(!raw.contains("secret-plaintext"));
+        assert!(!raw.contains("secret-encrypt"));
+        let entries = backend.read_all().unwrap();
+        let CacheDataType::Decrypt(data) = &entries[0].data_type else {
+            panic!("缓存条目类型错误");
+        };
+        assert_eq!(data.encrypted_data, "ciphertext");
+        assert!(data.password.is_empty() && data.decrypted_data.is_empty());
+        // 加密数据条目只保留重放需要的密文
+        let CacheDataType::Encrypt(data) = &entries[1].data_type else {
+            panic!("缓存条目类型错误");
+        };
+        assert_eq!(data.encrypted_data, "ciphertext-b");
+        assert!(data.password.is_empty() && data.data.is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn encrypted_lines_keep_plaintext_and_password() {
+        let (backend, dir) = backend("with-key", Some("cache-encryption-key"));
+        backend.write(decrypt_entry("a")).unwrap();
+
+        let raw = raw_contents(&backend);
+        assert!(raw.starts_with(ENCRYPTED_LINE_PREFIX));
+        assert!(!raw.contains("secret-password") && !raw.contains("secret-plaintext"));
+        let CacheDataType::Decrypt(data) = &backend.read_all().unwrap()[0].data_type else {
+            panic!("缓存条目类型错误");
+        };
+        assert_eq!(data.password, "secret-password");
+        assert_eq!(data.decrypted_data, "secret-plaintext");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn debug_output_redacts_secrets() {
+        let debug = format!("{:?}", decrypt_entry("a"));
+        assert!(!debug.contains("secret-password"));
+        assert!(!debug.contains("secret-plaintext"));
+        assert!(debug.contains("ciphertext"));
+    }
+
+    #[test]
+    fn truncated_line_keeps_earlier_entries_and_next_append_starts_new_line() {
+        let (backend, dir) = backend("truncated", None);
+        backend.write(decrypt_entry("a")).unwrap();
+        backend.write(decrypt_entry("b")).unwrap();
+        truncate_tail(&backend, 10);
+
+        let backend = open_backend(&dir, None, &[]);
+        assert_eq!(entry_ids(&backend), ["a"]);
+
+        backend.write(decrypt_entry("c")).unwrap();
+        let raw = raw_contents(&backend);
+        let lines: Vec<&str> = raw.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(backend.decode_line(lines[1]).is_err());
+        assert_eq!(backend.decode_line(lines[2]).unwrap().id, "c");
+        assert_eq!(entry_ids(&backend), ["a", "c"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn compressed_entries_round_trip_alongside_plain_files() {
+        let (plain, dir) = backend("compression", None);
+        plain.write(encrypt_entry("plain")).unwrap();
+
+        // 开启压缩后新条目写入.jsonl.gz文件，已有的未压缩文件仍然可读
+        let compressed = open_backend(&dir, None, &[("CACHE_COMPRESSION", "true")]);
+        compressed.write(encrypt_entry("gz1")).unwrap();
+        compressed.write(decrypt_entry("gz2")).unwrap();
+
+        let files = compressed.cache_files().unwrap();
+        assert_eq!(files.len(), 2);
+        let gz = files.iter().find(|path| is_compressed(path)).unwrap();
+        assert_eq!(fs::read(gz).unwrap()[..2], [0x1f, 0x8b]);
+
+        let mut ids = entry_ids(&compressed);
+        ids.sort();
+        assert_eq!(ids, ["gz1", "gz2", "plain"]);
+        let entries = compressed.read_all().unwrap();
+        let CacheDataType::Encrypt(ref data) = entries.iter().find(|entry| entry.id == "gz1").unwrap().data_type else {
+            panic!("缓存条目类型错误");
+        };
+        assert_eq!(data.encrypted_data, "ciphertext-gz1");
+
+        // 关闭压缩后已有的压缩文件同样可读
+        let mut ids = entry_ids(&open_backend(&dir, None, &[]));
+        ids.sort();
+        assert_eq!(ids, ["gz1", "gz2", "plain"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn truncated_gzip_member_is_dropped_before_next_append() {
+        let vars = [("CACHE_COMPRESSION", "true")];
+        let (backend, dir) = backend_with("truncated-gzip", None, &vars);
+        backend.write(decrypt_entry("a")).unwrap();
+        backend.write(decrypt_entry("b")).unwrap();
+        truncate_tail(&backend, 10);
+
+        // 重启后首次写入前检查文件末尾
+        let backend = open_backend(&dir, None, &vars);
+        assert_eq!(entry_ids(&backend), ["a"]);
+
+        backend.write(decrypt_entry("c")).unwrap();
+        assert_eq!(entry_ids(&backend), ["a", "c"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn stats_break_down_by_data_type() {
+        let (backend, dir) = backend("stats", None);
+        for entry in [encrypt_entry("e1"), decrypt_entry("d1"), encrypt_entry("e2")] {
+            backend.write(entry).unwrap();
+        }
+
+        let raw = raw_contents(&backend);
+        let line_bytes = |marker: &str| raw.lines().filter(|line| line.contains(marker)).map(|line| line.len() as u64).sum::<u64>();
+        let stats = backend.stats().unwrap();
+        assert_eq!(stats.files, 1);
+        assert_eq!((stats.encrypt.entries, stats.decrypt.entries, stats.total_entries), (2, 1, 3));
+        assert_eq!(stats.encrypt.bytes, line_bytes("\"Encrypt\""));
+        assert_eq!(stats.decrypt.bytes, line_bytes("\"Decrypt\""));
+        assert_eq!(stats.total_bytes, stats.encrypt.bytes + stats.decrypt.bytes);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn primary_write_failure_falls_back_to_secondary() {
+        let secondary = std::env::temp_dir().join(format!("encryption-service-cache-{}-secondary", std::process::id()));
+        let _ = fs::remove_dir_all(&secondary);
+        let (backend, dir) = backend_with("primary-failure", None, &[("CACHE_SECONDARY_DIR", secondary.to_str().unwrap())]);
+        backend.write(encrypt_entry("primary")).unwrap();
+
+        // 用同名文件替换主缓存目录，使主目录写入失败
+        fs::remove_dir_all(&dir).unwrap();
+        fs::write(&dir, b"").unwrap();
+        backend.write(encrypt_entry("secondary")).unwrap();
+        assert_eq!(fs::read_dir(&secondary).unwrap().count(), 1);
+
+        // 主目录恢复后同时读取两个目录中的条目
+        fs::remove_file(&dir).unwrap();
+        fs::create_dir_all(&dir).unwrap();
+        backend.write(encrypt_entry("recovered")).unwrap();
+        let mut ids = entry_ids(&backend);
+        ids.sort();
+        assert_eq!(ids, ["recovered", "secondary"]);
+    }
+
+    #[test]
+    fn write_fails_without_secondary() {
+        let (backend, dir) = backend_with("no-secondary", None, &[]);
+        fs::remove_dir_all(&dir).unwrap();
+        fs::write(&dir, b"").unwrap();
+        assert!(backend.write(encrypt_entry("lost")).is_err());
+        fs::remove_file(&dir).unwrap();
+    }
+}
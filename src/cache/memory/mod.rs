@@ -0,0 +1,162 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+use tracing::warn;
+use anyhow::Result;
+
+use super::{CacheBackend, CacheDataType, CacheEntry, CacheStats, current_timestamp};
+
+/// 内存缓存后端，条目只保存在进程内存中，进程退出后丢失
+///
+/// 达到最大条目数时丢弃最早的条目，优先保留加密数据时先丢弃最早的解密数据条目；不保存密码和解密明文
+#[derive(Debug)]
+pub struct InMemoryCacheBackend {
+    /// 最大条目数
+    capacity: usize,
+    /// 条目保留时间（秒）
+    retention_time: u64,
+    /// 缓存已满时是否优先保留加密数据条目
+    prioritize_encrypt: bool,
+    /// 按写入顺序排列的条目
+    entries: Mutex<VecDeque<CacheEntry>>,
+}
+
+impl InMemoryCacheBackend {
+    /// 创建内存缓存后端
+    pub fn new(capacity: usize, retention_time: u64, prioritize_encrypt: bool) -> Self {
+        Self {
+            capacity,
+            retention_time,
+            prioritize_encrypt,
+            entries: Mutex::new(VecDeque::new()),
+        }
+    }
+}
+
+impl CacheBackend for InMemoryCacheBackend {
+    fn write(&self, mut entry: CacheEntry) -> Result<()> {
+        entry.clear_secrets();
+
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.capacity {
+            // 加密数据必须重放，解密数据通常可以丢弃
+            let index = self.prioritize_encrypt
+                .then(|| entries.iter().position(|entry| matches!(entry.data_type, CacheDataType::Decrypt(_))))
+                .flatten()
+                .unwrap_or(0);
+            if let Some(dropped) = entries.remove(index) {
+                warn!("内存缓存已满（{} 条），丢弃条目 {}", self.capacity, dropped.id);
+            }
+        }
+        entries.push_back(entry);
+        Ok(())
+    }
+
+    fn read_all(&self) -> Result<Vec<CacheEntry>> {
+        Ok(self.entries.lock().unwrap().iter().cloned().collect())
+    }
+
+    fn clean_expired(&self) -> Result<()> {
+        let current_timestamp = current_timestamp();
+        self.entries.lock().unwrap()
+            .retain(|entry| current_timestamp.saturating_sub(entry.timestamp) <= self.retention_time);
+        Ok(())
+    }
+
+    fn mark_processed(&self, entry_ids: &HashSet<String>) -> Result<usize> {
+        let mut entries = self.entries.lock().unwrap();
+        let before = entries.len();
+        entries.retain(|entry| !entry_ids.contains(&entry.id));
+        Ok(before - entries.len())
+    }
+
+    /// 字节数按条目序列化后的JSON长度计算
+    fn stats(&self) -> Result<CacheStats> {
+        let mut stats = CacheStats::default();
+        for entry in self.entries.lock().unwrap().iter() {
+            let bytes = serde_json::to_vec(entry)?.len() as u64;
+            let type_stats = match entry.data_type {
+                CacheDataType::Encrypt(_) => &mut stats.encrypt,
+                CacheDataType::Decrypt(_) => &mut stats.decrypt,
+            };
+            type_stats.entries += 1;
+            type_stats.bytes += bytes;
+            stats.total_entries += 1;
+            stats.total_bytes += bytes;
+        }
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::{CACHE_SCHEMA_VERSION, DecryptCacheData, EncryptCacheData};
+
+    fn entry(id: &str, encrypt: bool) -> CacheEntry {
+        let data_type = if encrypt {
+            CacheDataType::Encrypt(EncryptCacheData {
+                data: String::new(),
+                password: String::new(),
+                resource_type: "user".to_string(),
+                encrypted_data: id.to_string(),
+                persisted: false,
+                resource_id: None,
+                content_id: None,
+            })
+        } else {
+            CacheDataType::Decrypt(DecryptCacheData {
+                encrypted_data: id.to_string(),
+                password: String::new(),
+                resource_type: "user".to_string(),
+                resource_id: None,
+                decrypted_data: String::new(),
+            })
+        };
+        CacheEntry {
+            version: CACHE_SCHEMA_VERSION,
+            id: id.to_string(),
+            timestamp: current_timestamp(),
+            data_type,
+            service_id: "test".to_string(),
+        }
+    }
+
+    fn ids(backend: &InMemoryCacheBackend) -> Vec<String> {
+        backend.read_all().unwrap().into_iter().map(|entry| entry.id).collect()
+    }
+
+    #[test]
+    fn drops_oldest_entry_when_full() {
+        let backend = InMemoryCacheBackend::new(2, 3600, false);
+        for (id, encrypt) in [("e1", true), ("d1", false), ("e2", true)] {
+            backend.write(entry(id, encrypt)).unwrap();
+        }
+        assert_eq!(ids(&backend), ["d1", "e2"]);
+    }
+
+    #[test]
+    fn drops_decrypt_entries_first_when_prioritizing_encrypt() {
+        let backend = InMemoryCacheBackend::new(2, 3600, true);
+        for (id, encrypt) in [("e1", true), ("d1", false), ("e2", true)] {
+            backend.write(entry(id, encrypt)).unwrap();
+        }
+        assert_eq!(ids(&backend), ["e1", "e2"]);
+
+        // 没有解密数据条目时仍丢弃最早的条目
+        backend.write(entry("e3", true)).unwrap();
+        assert_eq!(ids(&backend), ["e2", "e3"]);
+    }
+
+    #[test]
+    fn stats_break_down_by_data_type() {
+        let backend = InMemoryCacheBackend::new(10, 3600, false);
+        for (id, encrypt) in [("e1", true), ("d1", false), ("e2", true)] {
+            backend.write(entry(id, encrypt)).unwrap();
+        }
+
+        let stats = backend.stats().unwrap();
+        assert_eq!((stats.encrypt.entries, stats.decrypt.entries, stats.total_entries), (2, 1, 3));
+        assert!(stats.encrypt.bytes > 0 && stats.decrypt.bytes > 0);
+        assert_eq!(stats.total_bytes, stats.encrypt.bytes + stats.decrypt.bytes);
+    }
+}
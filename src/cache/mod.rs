@@ -1,10 +1,27 @@
-use std::fs::{self, File, OpenOptions};
-use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::collections::HashSet;
+use std::fmt::{self, Debug};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use serde::{Deserialize, Serialize};
-use tracing::{info, warn, error};
+use sha2::{Digest, Sha256};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::{info, error};
 use anyhow::Result;
 
+use crate::config::{CacheBackendType, CacheConfig};
+use crate::monitoring;
+
+mod file;
+mod memory;
+
+pub use file::FileCacheBackend;
+pub use memory::InMemoryCacheBackend;
+
+/// 条目ID序号，同一进程内单调递增
+static ENTRY_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
 /// 缓存数据类型
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub enum CacheDataType {
@@ -15,184 +32,457 @@ pub enum CacheDataType {
 }
 
 /// 加密缓存数据
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Deserialize, Serialize, Clone)]
 pub struct EncryptCacheData {
+    /// 明文，未配置缓存加密密钥时不落盘，为空字符串；重放和导入只需要密文
+    #[serde(default)]
     pub data: String,
+    /// 未配置缓存加密密钥时不落盘，为空字符串
+    #[serde(default)]
     pub password: String,
     pub resource_type: String,
     pub encrypted_data: String,
+    /// 是否已写入CRUD API，未写入的条目会在实例恢复后重放
+    #[serde(default)]
+    pub persisted: bool,
+    /// 更新已有资源时的资源ID，重放时更新该记录而不是新建
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource_id: Option<String>,
+    /// 内容寻址模式下新建记录使用的资源ID，写入时计算，重放时不再需要密码和明文
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_id: Option<String>,
 }
 
 /// 解密缓存数据
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Deserialize, Serialize, Clone)]
 pub struct DecryptCacheData {
     pub encrypted_data: String,
+    /// 未配置缓存加密密钥时不落盘，为空字符串
+    #[serde(default)]
     pub password: String,
     pub resource_type: String,
     pub resource_id: Option<String>,
+    /// 未配置缓存加密密钥时不落盘，为空字符串
+    #[serde(default)]
     pub decrypted_data: String,
 }
 
+// 调试输出隐藏密码和明文，避免写入日志
+impl Debug for EncryptCacheData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptCacheData")
+            .field("data", &"***")
+            .field("password", &"***")
+            .field("resource_type", &self.resource_type)
+            .field("encrypted_data", &self.encrypted_data)
+            .field("persisted", &self.persisted)
+            .field("resource_id", &self.resource_id)
+            .field("content_id", &self.content_id)
+            .finish()
+    }
+}
+
+impl Debug for DecryptCacheData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DecryptCacheData")
+            .field("encrypted_data", &self.encrypted_data)
+            .field("password", &"***")
+            .field("resource_type", &self.resource_type)
+            .field("resource_id", &self.resource_id)
+            .field("decrypted_data", &"***")
+            .finish()
+    }
+}
+
+/// 当前缓存条目结构版本
+pub const CACHE_SCHEMA_VERSION: u32 = 3;
+
+/// 缺少版本字段的条目为v1
+fn default_schema_version() -> u32 {
+    1
+}
+
 /// 缓存条目
+///
+/// 版本历史：
+/// - v1：timestamp、data_type
+/// - v2：新增 version、service_id
+/// - v3：新增 id
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct CacheEntry {
+    /// 结构版本
+    #[serde(default = "default_schema_version")]
+    pub version: u32,
+    /// 条目ID，v3之前的条目读取时由行内容摘要生成
+    #[serde(default)]
+    pub id: String,
     /// 时间戳
     pub timestamp: u64,
     /// 数据类型
     pub data_type: CacheDataType,
+    /// 写入条目的服务ID
+    #[serde(default)]
+    pub service_id: String,
+}
+
+impl CacheEntry {
+    /// 不加密保存时清空条目中的密码和明文
+    fn clear_secrets(&mut self) {
+        match self.data_type {
+            CacheDataType::Encrypt(ref mut data) => {
+                data.password.clear();
+                data.data.clear();
+            },
+            CacheDataType::Decrypt(ref mut data) => {
+                data.password.clear();
+                data.decrypted_data.clear();
+            },
+        }
+    }
+
+    /// 将旧版本条目升级到当前版本，缺失字段使用默认值
+    fn upgrade(mut self, line: &str) -> Result<Self> {
+        if self.version > CACHE_SCHEMA_VERSION {
+            anyhow::bail!("不支持的缓存条目版本: {}", self.version);
+        }
+        if self.version < 2 && self.service_id.is_empty() {
+            // v1条目没有记录服务ID
+            self.service_id = String::from("unknown");
+        }
+        if self.version < 3 && self.id.is_empty() {
+            // 旧条目没有ID，使用原始行的摘要，保证多次读取得到相同的ID
+            self.id = Sha256::digest(line.as_bytes())[..16].iter().map(|b| format!("{:02x}", b)).collect();
+        }
+        self.version = CACHE_SCHEMA_VERSION;
+        Ok(self)
+    }
+}
+
+/// 单个数据类型的缓存统计
+#[derive(Debug, Default, Serialize, Clone)]
+pub struct CacheTypeStats {
+    /// 条目数
+    pub entries: u64,
+    /// 字节数
+    pub bytes: u64,
+}
+
+/// 缓存统计
+#[derive(Debug, Default, Serialize, Clone)]
+pub struct CacheStats {
+    /// 缓存文件数，内存后端为0
+    pub files: u64,
+    /// 条目总数
+    pub total_entries: u64,
+    /// 字节总数
+    pub total_bytes: u64,
+    /// 加密数据条目统计
+    pub encrypt: CacheTypeStats,
+    /// 解密数据条目统计
+    pub decrypt: CacheTypeStats,
+}
+
+/// 缓存条目元数据，不包含密码和明文
+#[derive(Debug, Serialize, Clone)]
+pub struct CacheEntryMetadata {
+    /// 条目ID
+    pub id: String,
+    /// 时间戳
+    pub timestamp: u64,
+    /// 数据类型：encrypt 或 decrypt
+    pub data_type: &'static str,
+    pub resource_type: String,
+    pub resource_id: Option<String>,
+    /// 写入条目的服务ID
+    pub service_id: String,
+    /// 是否不再需要写入CRUD API：加密数据条目已写入时为true；解密数据条目只记录读取结果，总是为true
+    pub flushed: bool,
+}
+
+impl From<&CacheEntry> for CacheEntryMetadata {
+    fn from(entry: &CacheEntry) -> Self {
+        let (data_type, resource_type, resource_id, flushed) = match entry.data_type {
+            CacheDataType::Encrypt(ref data) => ("encrypt", data.resource_type.clone(), data.resource_id.clone(), data.persisted),
+            CacheDataType::Decrypt(ref data) => ("decrypt", data.resource_type.clone(), data.resource_id.clone(), true),
+        };
+        Self {
+            id: entry.id.clone(),
+            timestamp: entry.timestamp,
+            data_type,
+            resource_type,
+            resource_id,
+            service_id: entry.service_id.clone(),
+            flushed,
+        }
+    }
+}
+
+/// 缓存条目分页结果
+#[derive(Debug, Serialize, Clone)]
+pub struct CacheEntryPage {
+    pub offset: usize,
+    pub limit: usize,
+    pub entries: Vec<CacheEntryMetadata>,
+    /// 是否还有后续条目
+    pub has_more: bool,
+}
+
+/// 获取当前时间戳（秒）
+fn current_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("无法获取当前时间")
+        .as_secs()
+}
+
+/// 故障缓存后端，保存CRUD API不可用期间的数据，等待重放或导入
+pub trait CacheBackend: Debug + Send + Sync {
+    /// 写入一个缓存条目
+    fn write(&self, entry: CacheEntry) -> Result<()>;
+
+    /// 读取所有缓存条目，按写入顺序排列
+    fn read_all(&self) -> Result<Vec<CacheEntry>>;
+
+    /// 清理超过保留时间的条目
+    fn clean_expired(&self) -> Result<()>;
+
+    /// 将指定ID的条目标记为已处理，不再返回，返回处理的条目数
+    fn mark_processed(&self, entry_ids: &HashSet<String>) -> Result<usize>;
+
+    /// 按数据类型统计条目数和字节数
+    fn stats(&self) -> Result<CacheStats>;
+
+    /// 分页列出缓存条目元数据
+    fn list_entries(&self, offset: usize, limit: usize) -> Result<CacheEntryPage> {
+        let entries = self.read_all()?;
+        let has_more = entries.len() > offset.saturating_add(limit);
+        let entries = entries.iter()
+            .skip(offset)
+            .take(limit)
+            .map(CacheEntryMetadata::from)
+            .collect();
+        Ok(CacheEntryPage { offset, limit, entries, has_more })
+    }
+
+    /// 等待正在进行的写入完成，停止服务前调用
+    fn flush(&self) -> Result<()> {
+        Ok(())
+    }
 }
 
 /// 缓存管理器
 #[derive(Debug, Clone)]
 pub struct CacheManager {
-    /// 缓存目录
-    cache_dir: String,
-    /// 临时文件前缀
-    temp_file_prefix: String,
-    /// 临时文件更新间隔（秒）
-    update_interval: u64,
-    /// 临时文件保留时间（秒）
+    /// 服务ID，写入每个缓存条目
+    service_id: String,
+    /// 缓存保留时间（秒），同时作为清理间隔
     retention_time: u64,
+    /// 缓存后端
+    backend: Arc<dyn CacheBackend>,
 }
 
 impl CacheManager {
-    /// 创建新的缓存管理器实例
-    pub fn new() -> Self {
-        // 默认配置
-        let cache_dir = String::from("data/cache");
-        let temp_file_prefix = String::from("crud_api_cache");
-        let update_interval = 3600; // 1小时
-        let retention_time = 86400; // 24小时
-
-        // 创建缓存目录
-        if let Err(e) = fs::create_dir_all(&cache_dir) {
-            error!("无法创建缓存目录: {:?}", e);
-        }
+    /// 创建新的缓存管理器实例，按配置选择缓存后端
+    ///
+    /// 文件后端的缓存目录是否可写已在配置验证时检查；prioritize_encrypt为内存后端已满时是否优先保留加密数据条目
+    pub fn new(
+        service_id: String,
+        config: &CacheConfig,
+        encryption_key: Option<&str>,
+        secondary_dir: Option<String>,
+        prioritize_encrypt: bool,
+    ) -> Self {
+        let backend: Arc<dyn CacheBackend> = match config.backend {
+            CacheBackendType::File => Arc::new(FileCacheBackend::new(config, encryption_key, secondary_dir)),
+            CacheBackendType::Memory => {
+                info!("使用内存缓存后端，最多保存 {} 条，进程重启后缓存数据丢失", config.memory_capacity);
+                Arc::new(InMemoryCacheBackend::new(config.memory_capacity, config.retention_time, prioritize_encrypt))
+            },
+        };
 
         Self {
-            cache_dir,
-            temp_file_prefix,
-            update_interval,
-            retention_time,
+            service_id,
+            retention_time: config.retention_time,
+            backend,
         }
     }
 
-    /// 获取当前时间戳（秒）
-    fn get_current_timestamp(&self) -> u64 {
-        SystemTime::now()
+    /// 生成条目ID，由服务ID、纳秒时间戳和进程内序号组成，同一秒内的多次写入也不会重复
+    fn next_entry_id(&self) -> String {
+        let nanos = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .expect("无法获取当前时间")
-            .as_secs()
-    }
-
-    /// 获取当前缓存文件路径
-    fn get_current_cache_file(&self) -> String {
-        let timestamp = self.get_current_timestamp();
-        let file_name = format!("{}_{}.jsonl", self.temp_file_prefix, timestamp / self.update_interval);
-        format!("{}/{}", self.cache_dir, file_name)
+            .as_nanos();
+        let sequence = ENTRY_SEQUENCE.fetch_add(1, Ordering::Relaxed);
+        format!("{}-{:x}-{:x}", self.service_id, nanos, sequence)
     }
 
     /// 写入缓存数据
     pub fn write_cache(&self, data_type: CacheDataType) -> Result<()> {
+        let data_type_label = match data_type {
+            CacheDataType::Encrypt(_) => "encrypt",
+            CacheDataType::Decrypt(_) => "decrypt",
+        };
+
         let cache_entry = CacheEntry {
-            timestamp: self.get_current_timestamp(),
+            version: CACHE_SCHEMA_VERSION,
+            id: self.next_entry_id(),
+            timestamp: current_timestamp(),
             data_type,
+            service_id: self.service_id.clone(),
         };
+        self.backend.write(cache_entry)?;
 
-        // 序列化缓存条目
-        let json_str = serde_json::to_string(&cache_entry)?;
-
-        // 打开或创建缓存文件
-        let file_path = self.get_current_cache_file();
-        let file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&file_path)?;
+        metrics::counter!(monitoring::CACHE_WRITES_TOTAL, "data_type" => data_type_label).increment(1);
+        Ok(())
+    }
 
-        // 写入缓存条目
-        let mut writer = BufWriter::new(file);
-        writeln!(writer, "{}", json_str)?;
-        writer.flush()?;
+    /// 等待正在进行的写入完成，停止服务前调用
+    pub fn flush(&self) -> Result<()> {
+        self.backend.flush()
+    }
 
-        info!("缓存数据已写入文件: {}", file_path);
-        Ok(())
+    /// 将指定ID的条目标记为已处理，返回处理的条目数
+    pub fn mark_processed(&self, entry_ids: &HashSet<String>) -> Result<usize> {
+        if entry_ids.is_empty() {
+            return Ok(0);
+        }
+        self.backend.mark_processed(entry_ids)
     }
 
     /// 读取所有缓存数据
     pub fn read_all_cache(&self) -> Result<Vec<CacheEntry>> {
-        let mut all_entries = Vec::new();
-
-        // 遍历所有缓存文件
-        let entries = fs::read_dir(&self.cache_dir)?;
-        for entry in entries {
-            let entry = entry?;
-            let path = entry.path();
-            
-            // 只处理JSONL文件
-            if path.is_file() && path.extension() == Some("jsonl".as_ref()) {
-                let file = File::open(&path)?;
-                let reader = BufReader::new(file);
-
-                // 读取文件中的所有条目
-                for line in reader.lines() {
-                    let line = line?;
-                    if !line.is_empty() {
-                        match serde_json::from_str::<CacheEntry>(&line) {
-                            Ok(entry) => all_entries.push(entry),
-                            Err(e) => {
-                                warn!("无法解析缓存条目: {:?}, 行内容: {}", e, line);
-                            },
-                        }
-                    }
-                }
-            }
-        }
+        self.backend.read_all()
+    }
 
-        Ok(all_entries)
+    /// 分页列出缓存条目元数据
+    pub fn list_entries(&self, offset: usize, limit: usize) -> Result<CacheEntryPage> {
+        self.backend.list_entries(offset, limit)
     }
 
-    /// 清理过期的缓存文件
-    pub fn clean_expired_cache(&self) -> Result<()> {
-        let current_timestamp = self.get_current_timestamp();
-        let entries = fs::read_dir(&self.cache_dir)?;
-
-        for entry in entries {
-            let entry = entry?;
-            let path = entry.path();
-            
-            // 只处理JSONL文件
-            if path.is_file() && path.extension() == Some("jsonl".as_ref()) {
-                // 获取文件的修改时间
-                let metadata = fs::metadata(&path)?;
-                let modified_time = metadata.modified()?
-                    .duration_since(UNIX_EPOCH)?
-                    .as_secs();
-
-                // 检查文件是否过期
-                if current_timestamp - modified_time > self.retention_time {
-                    if let Err(e) = fs::remove_file(&path) {
-                        warn!("无法删除过期缓存文件: {:?}", e);
-                    } else {
-                        info!("已删除过期缓存文件: {:?}", path);
-                    }
-                }
-            }
-        }
+    /// 统计缓存数据，按数据类型分别统计条目数和字节数
+    pub fn stats(&self) -> Result<CacheStats> {
+        self.backend.stats()
+    }
 
-        Ok(())
+    /// 清理过期的缓存数据
+    pub fn clean_expired_cache(&self) -> Result<()> {
+        self.backend.clean_expired()
     }
 
     /// 启动定期清理任务
-    pub async fn start_cleanup_task(&self) {
+    pub async fn start_cleanup_task(&self, mut shutdown: watch::Receiver<bool>) -> JoinHandle<()> {
         let cache_manager = self.clone();
         tokio::spawn(async move {
             let mut interval = tokio::time::interval(Duration::from_secs(cache_manager.retention_time));
             loop {
-                interval.tick().await;
+                tokio::select! {
+                    _ = interval.tick() => {},
+                    _ = shutdown.changed() => break,
+                }
                 if let Err(e) = cache_manager.clean_expired_cache() {
                     error!("清理过期缓存失败: {:?}", e);
                 }
             }
-        });
+            info!("缓存清理任务已停止");
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// v1条目：没有version、id和service_id字段，加密数据也没有persisted和resource_id
+    const V1_LINE: &str = r#"{"timestamp":1700000000,"data_type":{"Encrypt":{"data":"plaintext","password":"password","resource_type":"user","encrypted_data":"ciphertext"}}}"#;
+
+    fn parse(line: &str) -> Result<CacheEntry> {
+        serde_json::from_str::<CacheEntry>(line)?.upgrade(line)
+    }
+
+    #[test]
+    fn v1_entry_upgraded_with_defaults() {
+        let entry = parse(V1_LINE).unwrap();
+        assert_eq!(entry.version, CACHE_SCHEMA_VERSION);
+        assert_eq!(entry.service_id, "unknown");
+        assert_eq!(entry.timestamp, 1700000000);
+        let CacheDataType::Encrypt(data) = entry.data_type else {
+            panic!("缓存条目类型错误");
+        };
+        assert_eq!(data.encrypted_data, "ciphertext");
+        assert!(!data.persisted);
+        assert_eq!(data.resource_id, None);
+
+        // 同一行多次读取得到相同的ID，标记处理状态时可以按ID匹配
+        assert!(!entry.id.is_empty());
+        assert_eq!(parse(V1_LINE).unwrap().id, entry.id);
+    }
+
+    #[test]
+    fn current_entry_keeps_its_fields() {
+        let line = format!(r#"{{"version":{},"id":"abc","timestamp":1,"service_id":"svc","data_type":{{"Encrypt":{{"data":"d","resource_type":"user","encrypted_data":"c","persisted":true}}}}}}"#, CACHE_SCHEMA_VERSION);
+        let entry = parse(&line).unwrap();
+        assert_eq!((entry.id.as_str(), entry.service_id.as_str()), ("abc", "svc"));
+    }
+
+    #[test]
+    fn newer_schema_version_is_rejected() {
+        let line = format!(r#"{{"version":{},"timestamp":1,"data_type":{{"Decrypt":{{"encrypted_data":"c","password":"","resource_type":"user"}}}}}}"#, CACHE_SCHEMA_VERSION + 1);
+        assert!(parse(&line).unwrap_err().to_string().contains("不支持的缓存条目版本"));
+    }
+
+    #[test]
+    fn cache_backend_selects_in_memory_storage() {
+        // 缓存目录不存在且无法创建时，内存后端仍能通过配置验证，不会在磁盘上写入任何文件
+        let dir = std::env::temp_dir().join(format!("cache-backend-{}", std::process::id()));
+        std::fs::write(&dir, "").unwrap();
+        let cache_dir = dir.join("cache");
+        let vars = [
+            ("CRUD_API_WRITE_INSTANCE_URL", "http://localhost"),
+            ("CACHE_BACKEND", "memory"),
+            ("CACHE_DIR", cache_dir.to_str().unwrap()),
+            ("CACHE_MEMORY_CAPACITY", "2"),
+        ];
+        let config = crate::config::AppConfig::from_vars(&vars).unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.cache.backend, CacheBackendType::Memory);
+
+        let manager = CacheManager::new("svc".to_string(), &config.cache, None, None, false);
+        for encrypted_data in ["c1", "c2", "c3"] {
+            manager.write_cache(CacheDataType::Decrypt(DecryptCacheData {
+                encrypted_data: encrypted_data.to_string(),
+                password: String::new(),
+                resource_type: "user".to_string(),
+                resource_id: None,
+                decrypted_data: String::new(),
+            })).unwrap();
+        }
+        let entries = manager.read_all_cache().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|entry| entry.service_id == "svc"));
+        assert!(!cache_dir.exists());
+
+        // 文件后端要求缓存目录可写
+        let file_vars = [("CACHE_BACKEND", "file"), vars[0], vars[2]];
+        assert!(crate::config::AppConfig::from_vars(&file_vars).unwrap().validate().is_err());
+        std::fs::remove_file(&dir).unwrap();
+
+        let invalid = [("CACHE_BACKEND", "redis"), vars[0]];
+        assert!(crate::config::AppConfig::from_vars(&invalid).unwrap_err().to_string().contains("无效的缓存后端类型"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn metadata_reports_flushed_from_entry() {
+        let entry = parse(V1_LINE).unwrap();
+        assert!(!CacheEntryMetadata::from(&entry).flushed);
+
+        let mut persisted = entry.clone();
+        if let CacheDataType::Encrypt(ref mut data) = persisted.data_type {
+            data.persisted = true;
+        }
+        assert!(CacheEntryMetadata::from(&persisted).flushed);
+
+        let line = r#"{"version":3,"id":"d","timestamp":1,"service_id":"svc","data_type":{"Decrypt":{"encrypted_data":"c","resource_type":"user","resource_id":null}}}"#;
+        assert!(CacheEntryMetadata::from(&parse(line).unwrap()).flushed);
+    }
+}
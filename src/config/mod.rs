@@ -1,10 +1,18 @@
+use std::collections::HashMap;
 use std::env;
-use serde::Deserialize;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+use reqwest::RequestBuilder;
+use reqwest::header::{HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
 use tracing::info;
 use anyhow::Result;
 
 /// 调度策略枚举
-#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub enum SchedulerStrategy {
     /// 单容器模式
     #[serde(rename = "single")]
@@ -17,8 +25,273 @@ pub enum SchedulerStrategy {
     LoadBalance,
 }
 
+/// 多个可用实例之间的选择策略，用于读写分离和负载均衡模式
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum SelectionPolicy {
+    /// 平滑加权轮询
+    #[serde(rename = "round_robin")]
+    RoundRobin,
+    /// 按权重随机选择
+    #[serde(rename = "random")]
+    Random,
+    /// 选择最久未使用的实例
+    #[serde(rename = "least_recently_used")]
+    LeastRecentlyUsed,
+}
+
+impl FromStr for SelectionPolicy {
+    type Err = anyhow::Error;
+
+    /// 解析选择策略：round_robin、random 或 least_recently_used
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "round_robin" => Ok(SelectionPolicy::RoundRobin),
+            "random" => Ok(SelectionPolicy::Random),
+            "least_recently_used" => Ok(SelectionPolicy::LeastRecentlyUsed),
+            _ => anyhow::bail!("无效的实例选择策略: {}，可选值: round_robin, random, least_recently_used", s),
+        }
+    }
+}
+
+/// Test实例持续不健康时的处理方式
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum UnhealthyAction {
+    /// 只记录状态
+    #[serde(rename = "none")]
+    None,
+    /// 销毁Test实例
+    #[serde(rename = "teardown")]
+    Teardown,
+    /// 销毁后重新创建Test实例
+    #[serde(rename = "reprovision")]
+    Reprovision,
+}
+
+impl FromStr for UnhealthyAction {
+    type Err = anyhow::Error;
+
+    /// 解析处理方式：none、teardown 或 reprovision
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "none" => Ok(UnhealthyAction::None),
+            "teardown" => Ok(UnhealthyAction::Teardown),
+            "reprovision" => Ok(UnhealthyAction::Reprovision),
+            _ => anyhow::bail!("无效的Test实例不健康处理方式: {}，可选值: none, teardown, reprovision", s),
+        }
+    }
+}
+
+/// Argon2无法分配所需内存时的处理方式
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum Argon2AllocFailurePolicy {
+    /// 返回容量不足错误
+    #[serde(rename = "error")]
+    Error,
+    /// 加密时改用较轻的备用参数，并记录告警和指标
+    #[serde(rename = "fallback")]
+    Fallback,
+}
+
+impl FromStr for Argon2AllocFailurePolicy {
+    type Err = anyhow::Error;
+
+    /// 解析处理方式：error 或 fallback
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "error" => Ok(Argon2AllocFailurePolicy::Error),
+            "fallback" => Ok(Argon2AllocFailurePolicy::Fallback),
+            _ => anyhow::bail!("无效的Argon2内存分配失败处理方式: {}，可选值: error, fallback", s),
+        }
+    }
+}
+
+/// 提醒发送目标类型
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum NotifierType {
+    /// 企业微信群机器人
+    #[serde(rename = "wechat")]
+    Wechat,
+    /// Slack Incoming Webhook
+    #[serde(rename = "slack")]
+    Slack,
+    /// 通用JSON Webhook
+    #[serde(rename = "generic")]
+    Generic,
+}
+
+impl FromStr for NotifierType {
+    type Err = anyhow::Error;
+
+    /// 解析提醒发送目标类型：wechat、slack 或 generic
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "wechat" => Ok(NotifierType::Wechat),
+            "slack" => Ok(NotifierType::Slack),
+            "generic" => Ok(NotifierType::Generic),
+            _ => anyhow::bail!("无效的提醒发送目标类型: {}，可选值: wechat, slack, generic", s),
+        }
+    }
+}
+
+/// 加解密接口的认证方式
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum AuthMode {
+    /// Authorization: Bearer <JWT>
+    #[serde(rename = "jwt")]
+    Jwt,
+    /// 共享密钥对请求体和时间戳的HMAC-SHA256签名
+    #[serde(rename = "hmac")]
+    Hmac,
+    /// 不认证，仅用于由网关完成认证的内网部署
+    #[serde(rename = "none")]
+    None,
+}
+
+impl FromStr for AuthMode {
+    type Err = anyhow::Error;
+
+    /// 解析认证方式：jwt、hmac 或 none
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "jwt" => Ok(AuthMode::Jwt),
+            "hmac" => Ok(AuthMode::Hmac),
+            "none" => Ok(AuthMode::None),
+            _ => anyhow::bail!("无效的认证方式: {}，可选值: jwt, hmac, none", s),
+        }
+    }
+}
+
+/// 故障缓存后端类型
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum CacheBackendType {
+    /// JSONL文件，进程重启后仍然保留
+    #[serde(rename = "file")]
+    File,
+    /// 进程内存，重启后丢失，适合没有可写卷的临时环境
+    #[serde(rename = "memory")]
+    Memory,
+}
+
+impl FromStr for CacheBackendType {
+    type Err = anyhow::Error;
+
+    /// 解析故障缓存后端类型：file 或 memory
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "file" => Ok(CacheBackendType::File),
+            "memory" => Ok(CacheBackendType::Memory),
+            _ => anyhow::bail!("无效的缓存后端类型: {}，可选值: file, memory", s),
+        }
+    }
+}
+
+/// 明文填充方案
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub enum PaddingScheme {
+    /// 不填充
+    #[serde(rename = "none")]
+    None,
+    /// 填充到下一个2的幂
+    #[serde(rename = "power_of_two")]
+    PowerOfTwo,
+    /// 填充到固定块大小的整数倍
+    #[serde(rename = "block")]
+    Block(usize),
+}
+
+impl FromStr for PaddingScheme {
+    type Err = anyhow::Error;
+
+    /// 解析填充方案：none、power_of_two 或块大小（字节数）
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "none" | "" => Ok(PaddingScheme::None),
+            "power_of_two" => Ok(PaddingScheme::PowerOfTwo),
+            _ => match s.parse::<usize>() {
+                Ok(0) | Err(_) => anyhow::bail!("无效的明文填充方案: {}", s),
+                Ok(block) => Ok(PaddingScheme::Block(block)),
+            },
+        }
+    }
+}
+
+/// 密文Base64编码变体
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum Base64Variant {
+    /// 标准字母表，带填充
+    #[serde(rename = "standard")]
+    Standard,
+    /// URL安全字母表，带填充
+    #[serde(rename = "url_safe")]
+    UrlSafe,
+    /// 标准字母表，无填充
+    #[serde(rename = "standard_no_pad")]
+    StandardNoPad,
+    /// URL安全字母表，无填充
+    #[serde(rename = "url_safe_no_pad")]
+    UrlSafeNoPad,
+}
+
+impl FromStr for Base64Variant {
+    type Err = anyhow::Error;
+
+    /// 解析Base64变体：standard、url_safe、standard_no_pad 或 url_safe_no_pad
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "standard" => Ok(Base64Variant::Standard),
+            "url_safe" => Ok(Base64Variant::UrlSafe),
+            "standard_no_pad" => Ok(Base64Variant::StandardNoPad),
+            "url_safe_no_pad" => Ok(Base64Variant::UrlSafeNoPad),
+            _ => anyhow::bail!("无效的Base64变体: {}", s),
+        }
+    }
+}
+
+/// 维护窗口，每天UTC时间的 [start, end) 区间，以当天的分钟数表示，结束时间早于开始时间时表示跨越午夜
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct MaintenanceWindow {
+    start: u32,
+    end: u32,
+}
+
+impl MaintenanceWindow {
+    /// 判断当天的某一分钟是否处于维护窗口内
+    pub fn contains(&self, minute_of_day: u32) -> bool {
+        if self.start <= self.end {
+            self.start <= minute_of_day && minute_of_day < self.end
+        } else {
+            minute_of_day >= self.start || minute_of_day < self.end
+        }
+    }
+}
+
+impl FromStr for MaintenanceWindow {
+    type Err = anyhow::Error;
+
+    /// 解析维护窗口：HH:MM-HH:MM（UTC）
+    fn from_str(s: &str) -> Result<Self> {
+        let parse_time = |time: &str| -> Result<u32> {
+            let (hour, minute) = time.trim().split_once(':')
+                .ok_or_else(|| anyhow::anyhow!("无效的维护窗口时间: {}", time))?;
+            let (hour, minute): (u32, u32) = (hour.parse()?, minute.parse()?);
+            if hour > 23 || minute > 59 {
+                anyhow::bail!("无效的维护窗口时间: {}", time);
+            }
+            Ok(hour * 60 + minute)
+        };
+
+        let (start, end) = s.split_once('-')
+            .ok_or_else(|| anyhow::anyhow!("无效的维护窗口: {}，格式应为 HH:MM-HH:MM", s))?;
+        let window = MaintenanceWindow { start: parse_time(start)?, end: parse_time(end)? };
+        if window.start == window.end {
+            anyhow::bail!("无效的维护窗口: {}，开始时间与结束时间相同", s);
+        }
+        Ok(window)
+    }
+}
+
 /// CRUD API实例配置
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct CrudApiInstance {
     /// 实例ID
     pub id: String,
@@ -26,48 +299,378 @@ pub struct CrudApiInstance {
     pub url: String,
     /// 实例类型：read, write, mixed
     pub instance_type: String,
-    /// 连接超时时间（毫秒）
-    #[allow(dead_code)]
+    /// 请求超时时间（毫秒），对该实例的数据请求和健康检查生效
     pub timeout: u64,
     /// 重试次数
-    #[allow(dead_code)]
     pub retries: u32,
+    /// 负载均衡权重
+    pub weight: u32,
+    /// 数据请求的认证请求头
+    pub headers: AuthHeaders,
+    /// 健康检查的认证请求头，未配置时使用数据请求头
+    pub health_headers: Option<AuthHeaders>,
+    /// 健康检查路径，拼接在实例URL之后
+    #[serde(default = "default_health_path")]
+    pub health_path: String,
+    /// 健康检查响应的判定规则
+    #[serde(default)]
+    pub health_expect: HealthExpectation,
+}
+
+/// 默认健康检查路径
+fn default_health_path() -> String {
+    "/health".to_string()
+}
+
+/// 健康检查响应的判定规则
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct HealthExpectation {
+    /// 期望的状态码，未配置时任意2xx状态码都视为健康
+    pub status: Option<u16>,
+    /// 期望的JSON字段值，未配置时不检查响应体
+    pub field: Option<HealthField>,
+}
+
+impl Default for HealthExpectation {
+    /// 默认要求2xx状态码且响应体为 {"status": "ok"}
+    fn default() -> Self {
+        Self {
+            status: None,
+            field: Some(HealthField {
+                path: "status".to_string(),
+                value: "ok".to_string(),
+            }),
+        }
+    }
+}
+
+/// 健康检查响应体中期望的字段值，格式为 字段路径=值，嵌套字段以点分隔，如 data.state=UP
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub struct HealthField {
+    /// 字段路径
+    pub path: String,
+    /// 期望值，非字符串字段按JSON值比较，如 true、1
+    pub value: String,
+}
+
+impl HealthField {
+    /// 判断响应体中的字段是否等于期望值
+    pub fn matches(&self, body: &serde_json::Value) -> bool {
+        let field = self.path.split('.')
+            .try_fold(body, |value, key| value.get(key));
+        match field {
+            Some(serde_json::Value::String(actual)) => *actual == self.value,
+            Some(actual) => serde_json::from_str::<serde_json::Value>(&self.value).is_ok_and(|expected| expected == *actual),
+            None => false,
+        }
+    }
+}
+
+impl FromStr for HealthField {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once('=') {
+            Some((path, value)) if !path.trim().is_empty() && path.split('.').all(|key| !key.trim().is_empty()) => Ok(HealthField {
+                path: path.trim().to_string(),
+                value: value.trim().to_string(),
+            }),
+            _ => anyhow::bail!("无效的健康检查字段配置: {}，格式应为 字段路径=值，如 status=ok", s),
+        }
+    }
+}
+
+impl TryFrom<String> for HealthField {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<HealthField> for String {
+    fn from(field: HealthField) -> Self {
+        format!("{}={}", field.path, field.value)
+    }
+}
+
+impl CrudApiInstance {
+    /// 健康检查使用的认证请求头
+    pub fn health_check_headers(&self) -> &AuthHeaders {
+        self.health_headers.as_ref().unwrap_or(&self.headers)
+    }
+
+    /// 单次请求的超时时间
+    pub fn request_timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout)
+    }
+}
+
+/// 认证请求头列表，Debug输出时隐藏请求头的值
+#[derive(Deserialize, Serialize, Clone, Default, PartialEq)]
+pub struct AuthHeaders(Vec<(String, String)>);
+
+impl AuthHeaders {
+    /// 为请求附加认证请求头
+    pub fn apply(&self, mut builder: RequestBuilder) -> RequestBuilder {
+        for (name, value) in &self.0 {
+            builder = builder.header(name, value);
+        }
+        builder
+    }
+}
+
+impl fmt::Debug for AuthHeaders {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.iter().map(|(name, _)| format!("{}: ***", name)))
+            .finish()
+    }
+}
+
+impl FromStr for AuthHeaders {
+    type Err = anyhow::Error;
+
+    /// 解析认证请求头：Name: Value，多个请求头以分号分隔
+    fn from_str(s: &str) -> Result<Self> {
+        let headers = s.split(';')
+            .map(|pair| pair.trim())
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (name, value) = pair.split_once(':')
+                    .ok_or_else(|| anyhow::anyhow!("无效的请求头配置: {}，格式应为 Name: Value", pair))?;
+                let (name, value) = (name.trim(), value.trim());
+                HeaderName::from_bytes(name.as_bytes())
+                    .map_err(|_| anyhow::anyhow!("无效的请求头名称: {}", name))?;
+                HeaderValue::from_str(value)
+                    .map_err(|_| anyhow::anyhow!("请求头 {} 的值无效", name))?;
+                Ok((name.to_string(), value.to_string()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(AuthHeaders(headers))
+    }
+}
+
+/// 资源类型对应的环境变量后缀：转为大写，字母和数字以外的字符替换为下划线
+fn resource_type_var_suffix(resource_type: &str) -> String {
+    resource_type.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect()
+}
+
+/// 按变量名查找配置值，未设置时返回None
+type Lookup<'a> = &'a dyn Fn(&str) -> Option<String>;
+
+/// 读取可选的认证请求头配置，未设置环境变量时返回None
+fn optional_headers(lookup: Lookup, var: &str) -> Result<Option<AuthHeaders>> {
+    lookup(var).map(|headers| headers.parse()).transpose()
+}
+
+/// 读取实例的健康检查路径和判定规则：{prefix}_HEALTH_PATH、{prefix}_HEALTH_STATUS、{prefix}_HEALTH_FIELD
+///
+/// 未设置的配置项沿用base，没有base时使用默认值；HEALTH_FIELD设置为空时不检查响应体
+fn health_settings(lookup: Lookup, prefix: &str, base: Option<(&str, &HealthExpectation)>) -> Result<(String, HealthExpectation)> {
+    let var = |suffix: &str| lookup(&format!("{}_{}", prefix, suffix));
+    let default_expect = base.map(|(_, expect)| expect.clone()).unwrap_or_default();
+    let health_path = var("HEALTH_PATH")
+        .or_else(|| base.map(|(path, _)| path.to_string()))
+        .unwrap_or_else(default_health_path);
+    let status = match var("HEALTH_STATUS") {
+        Some(status) if status.is_empty() => None,
+        Some(status) => Some(status.parse()?),
+        None => default_expect.status,
+    };
+    let field = match var("HEALTH_FIELD") {
+        Some(field) if field.is_empty() => None,
+        Some(field) => Some(field.parse()?),
+        None => default_expect.field,
+    };
+    Ok((health_path, HealthExpectation { status, field }))
+}
+
+/// 读取读写分离模式下编号从1开始的其他读或写实例，直到某个编号未配置URL为止
+///
+/// 未单独配置的超时、重试和请求头沿用base（编号0的实例）的配置
+fn indexed_instances(lookup: Lookup, kind: &str, base: &CrudApiInstance) -> Result<Vec<CrudApiInstance>> {
+    let var = |name: String| lookup(&name);
+    let mut instances = Vec::new();
+    let mut index = 1;
+    while let Some(url) = var(format!("CRUD_API_{}_INSTANCE_{}_URL", kind, index)).filter(|url| !url.is_empty()) {
+        let headers = match optional_headers(lookup, &format!("CRUD_API_{}_INSTANCE_{}_HEADERS", kind, index))? {
+            Some(headers) => headers,
+            None => base.headers.clone(),
+        };
+        let health_headers = match optional_headers(lookup, &format!("CRUD_API_{}_INSTANCE_{}_HEALTH_HEADERS", kind, index))? {
+            Some(headers) => Some(headers),
+            None => base.health_headers.clone(),
+        };
+        let (health_path, health_expect) = health_settings(lookup, &format!("CRUD_API_{}_INSTANCE_{}", kind, index), Some((&base.health_path, &base.health_expect)))?;
+        instances.push(CrudApiInstance {
+            id: format!("{}-{:02}", base.instance_type, index + 1),
+            url,
+            instance_type: base.instance_type.clone(),
+            timeout: var(format!("CRUD_API_{}_INSTANCE_{}_TIMEOUT", kind, index)).map(|t| t.parse()).transpose()?.unwrap_or(base.timeout),
+            retries: var(format!("CRUD_API_{}_INSTANCE_{}_RETRIES", kind, index)).map(|r| r.parse()).transpose()?.unwrap_or(base.retries),
+            weight: var(format!("CRUD_API_{}_INSTANCE_{}_WEIGHT", kind, index)).map(|w| w.parse()).transpose()?.unwrap_or(1),
+            headers,
+            health_headers,
+            health_path,
+            health_expect,
+        });
+        index += 1;
+    }
+    Ok(instances)
+}
+
+/// 将环境变量改变的配置项（from_env与defaults不同的部分）覆盖到target
+///
+/// 对象逐字段比较；长度相同的数组逐项比较，长度不同时整体覆盖
+fn overlay_changed(target: &mut serde_json::Value, defaults: &serde_json::Value, from_env: &serde_json::Value) {
+    if defaults == from_env {
+        return;
+    }
+    match (target, defaults, from_env) {
+        (serde_json::Value::Object(target), serde_json::Value::Object(defaults), serde_json::Value::Object(from_env)) => {
+            for (key, value) in from_env {
+                match (target.get_mut(key), defaults.get(key)) {
+                    (Some(target_value), Some(default_value)) => overlay_changed(target_value, default_value, value),
+                    _ => { target.insert(key.clone(), value.clone()); },
+                }
+            }
+        },
+        (serde_json::Value::Array(target), serde_json::Value::Array(defaults), serde_json::Value::Array(from_env))
+            if target.len() == from_env.len() && defaults.len() == from_env.len() =>
+        {
+            for ((target_item, default_item), item) in target.iter_mut().zip(defaults).zip(from_env) {
+                overlay_changed(target_item, default_item, item);
+            }
+        },
+        (target, _, from_env) => *target = from_env.clone(),
+    }
+}
+
+/// 校验CRUD API实例URL：必须是带主机名的http或https地址，且不能包含查询参数或片段
+fn validate_instance_url(id: &str, raw: &str) -> Result<()> {
+    let url = url::Url::parse(raw)
+        .map_err(|e| anyhow::anyhow!("CRUD API实例 {} 的URL无效: {}（{}），需要形如 http://host:port 的地址", id, raw, e))?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        anyhow::bail!("CRUD API实例 {} 的URL必须使用http或https协议: {}", id, raw);
+    }
+    if url.host_str().is_none_or(|host| host.is_empty()) {
+        anyhow::bail!("CRUD API实例 {} 的URL缺少主机名: {}", id, raw);
+    }
+    if url.query().is_some() || url.fragment().is_some() {
+        anyhow::bail!("CRUD API实例 {} 的URL不能包含查询参数或片段: {}", id, raw);
+    }
+    Ok(())
+}
+
+/// 校验Argon2id参数有效且不超过解密时允许的上限
+fn check_argon2_params(memory: u32, iterations: u32, parallelism: u32, key_length: u32) -> Result<()> {
+    argon2::Params::new(memory, iterations, parallelism, Some(key_length as usize))
+        .map_err(|e| anyhow::anyhow!("无效的Argon2参数: {}", e))?;
+    if memory > crate::crypto::MAX_ARGON2_MEMORY
+        || iterations > crate::crypto::MAX_ARGON2_ITERATIONS
+        || parallelism > crate::crypto::MAX_ARGON2_PARALLELISM
+    {
+        anyhow::bail!(
+            "Argon2参数超过上限: 内存最多 {} KiB，迭代最多 {} 次，并行度最多 {}",
+            crate::crypto::MAX_ARGON2_MEMORY, crate::crypto::MAX_ARGON2_ITERATIONS, crate::crypto::MAX_ARGON2_PARALLELISM
+        );
+    }
+    Ok(())
+}
+
+/// 创建目录并写入、删除一个探测文件，确认目录可写
+pub(crate) fn check_dir_writable(dir: &str) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let probe = std::path::Path::new(dir).join(format!(".write_probe_{}", std::process::id()));
+    std::fs::write(&probe, b"")?;
+    std::fs::remove_file(&probe)
 }
 
 /// 应用配置结构体
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct AppConfig {
     /// 服务器配置
     pub server: ServerConfig,
     /// JWT配置
     pub jwt: JwtConfig,
+    /// 加解密接口认证配置
+    pub auth: AuthConfig,
     /// 加密配置
     pub encryption: EncryptionConfig,
     /// 服务角色配置
     pub service: ServiceRoleConfig,
     /// CRUD API服务配置
     pub crud_api: CrudApiConfig,
+    /// 接口并发限制配置
+    pub limits: ConcurrencyLimitConfig,
+    /// 故障转移（Test实例）配置
+    pub failover: FailoverConfig,
+    /// 请求ID传递配置
+    pub request_id: RequestIdConfig,
+    /// 批量操作配置
+    pub batch: BatchConfig,
+    /// 提醒配置
+    pub reminder: ReminderConfig,
+    /// 客户端限流配置
+    pub rate_limit: RateLimitConfig,
+    /// 资源ID格式配置
+    pub resource_id: ResourceIdConfig,
+    /// 本地缓存配置
+    pub cache: CacheConfig,
+    /// HTTP客户端连接池配置
+    pub http_client: HttpClientConfig,
+    /// 审计日志配置
+    pub audit: AuditConfig,
+    /// 跨域配置
+    pub cors: CorsConfig,
+}
+
+/// 加解密接口认证配置
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct AuthConfig {
+    /// 认证方式
+    pub mode: AuthMode,
+    /// HMAC签名密钥，与JWT密钥相互独立，hmac认证方式下必须配置
+    pub hmac_secret: Option<String>,
+    /// 签名时间戳与服务器时间允许的最大偏差（秒），超过时视为重放请求
+    pub hmac_max_skew: u64,
 }
 
 /// 服务器配置
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct ServerConfig {
     /// 服务器地址
     pub host: String,
     /// 服务器端口
     pub port: u16,
-    /// 是否启用HTTPS
-    #[allow(dead_code)]
+    /// 是否启用HTTPS，启用时由服务自身终止TLS
     pub https: bool,
+    /// TLS证书路径（PEM格式），启用HTTPS时必须配置
+    pub tls_cert_path: Option<String>,
+    /// TLS私钥路径（PEM格式），启用HTTPS时必须配置
+    pub tls_key_path: Option<String>,
+    /// 加密成功持久化后Location响应头模板，支持 {crud_base}、{resource_type}、{id} 占位符，未配置时不返回
+    pub location_template: Option<String>,
+    /// 是否向客户端返回详细错误信息，关闭时只返回通用信息，详细信息仅记录到日志
+    pub verbose_errors: bool,
+    /// 管理接口令牌，未配置时管理接口不可用
+    pub admin_token: Option<String>,
+    /// 单条加解密等接口的最大请求体字节数
+    pub max_body_size: usize,
+    /// 批量接口的最大请求体字节数
+    pub max_batch_body_size: usize,
 }
 
 /// JWT配置
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct JwtConfig {
     /// JWT密钥
     pub secret: String,
     /// JWT过期时间（秒）
-    #[allow(dead_code)]
     pub expires_in: i64,
     /// JWT刷新时间（秒）
     #[allow(dead_code)]
@@ -75,66 +678,432 @@ pub struct JwtConfig {
 }
 
 /// 加密配置
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct EncryptionConfig {
-    /// 加密算法
+    /// 默认加密算法
     pub algorithm: String,
+    /// 启用的加密算法，请求和资源类型只能选择其中之一
+    pub enabled_algorithms: Vec<String>,
+    /// 按资源类型指定的加密算法
+    pub resource_type_algorithms: HashMap<String, String>,
     /// 密钥长度
     pub key_length: u32,
-    /// 迭代次数
+    /// 密钥派生算法：hkdf、pbkdf2、argon2id
+    pub key_derivation: String,
+    /// 迭代次数，用于PBKDF2
     pub iterations: u32,
+    /// 解密时接受的PBKDF2最大迭代次数，密文头部记录的次数超过该值时拒绝解密
+    pub max_iterations: u32,
+    /// Argon2id内存开销（KiB）
+    pub argon2_memory: u32,
+    /// Argon2id迭代次数
+    pub argon2_iterations: u32,
+    /// Argon2id并行度
+    pub argon2_parallelism: u32,
+    /// 解密时接受的Argon2id最大内存开销（KiB）
+    pub argon2_max_memory: u32,
+    /// 解密时接受的Argon2id最大迭代次数
+    pub argon2_max_iterations: u32,
+    /// 解密时接受的Argon2id最大并行度
+    pub argon2_max_parallelism: u32,
+    /// Argon2id无法分配所需内存时的处理方式
+    pub argon2_alloc_failure: Argon2AllocFailurePolicy,
+    /// 备用Argon2id内存开销（KiB），内存分配失败且处理方式为fallback时用于加密
+    pub argon2_fallback_memory: u32,
+    /// 备用Argon2id迭代次数
+    pub argon2_fallback_iterations: u32,
+    /// 备用Argon2id并行度
+    pub argon2_fallback_parallelism: u32,
     /// 盐值
     pub salt: String,
+    /// 按资源类型配置的盐值，与每次加密的随机盐值一起参与密钥派生，未配置的资源类型不使用
+    pub resource_type_salts: HashMap<String, String>,
+    /// 是否在密文中附带明文校验和，解密后校验
+    pub plaintext_checksum: bool,
+    /// 是否在加密前以zlib压缩明文，压缩后没有变小时不压缩
+    pub compression: bool,
+    /// 启用压缩的最小明文字节数，较小的明文不压缩
+    pub compression_min_size: usize,
+    /// 明文填充方案，用于隐藏明文长度
+    pub padding: PaddingScheme,
+    /// 启用填充的资源类型，为空时对所有资源类型生效
+    pub padding_resource_types: Vec<String>,
+    /// 是否在密文中记录资源类型，并在解密时要求请求的资源类型与之一致
+    pub resource_type_guard: bool,
+    /// 是否将资源类型绑定为附加认证数据，以其他资源类型解密时认证失败；关闭时生成旧版（版本1）密文，便于滚动升级
+    pub bind_resource_type: bool,
+    /// 流式加密的分块大小（字节）
+    pub stream_chunk_size: usize,
+    /// 派生密钥缓存最大条目数，0表示不启用
+    pub key_cache_size: usize,
+    /// 派生密钥缓存有效期（秒）
+    pub key_cache_ttl: u64,
+    /// 密钥派生耗时告警阈值（毫秒），0表示不告警
+    pub kdf_slow_threshold_ms: u64,
+    /// 内容寻址模式的哈希密钥，配置后资源ID由明文的带密钥哈希生成，相同内容复用同一条记录
+    pub content_id_key: Option<String>,
+    /// 缓存文件加密密钥，未配置时缓存文件不保存密码和解密明文
+    pub cache_encryption_key: Option<String>,
+    /// 解密时依次尝试的密文Base64变体
+    pub base64_variants: Vec<Base64Variant>,
+    /// 解密路径中解码和解压后允许的最大字节数，超过时在分配内存前拒绝
+    pub max_decoded_size: usize,
 }
 
 /// 服务角色配置
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct ServiceRoleConfig {
     /// 服务角色：encrypt, decrypt, mixed
     pub role: String,
     /// 服务ID
     pub id: String,
+    /// mixed角色下根据实例健康状态自动收窄有效角色
+    pub auto_downgrade: bool,
+    /// 维护窗口，窗口内服务只读，拒绝加密操作
+    pub maintenance_schedule: Vec<MaintenanceWindow>,
 }
 
 /// CRUD API服务配置
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct CrudApiConfig {
     /// CRUD API实例列表
     pub instances: Vec<CrudApiInstance>,
     /// 调度策略
     pub strategy: SchedulerStrategy,
+    /// 多个可用实例之间的选择策略
+    pub selection_policy: SelectionPolicy,
     /// 健康检查间隔（秒）
     pub health_check_interval: u64,
+    /// 按需健康检查的最小间隔（秒），间隔内的请求沿用上一轮检查结果
+    pub health_check_min_interval: u64,
+    /// 连续多少次健康检查失败后将实例标记为不健康
+    pub unhealthy_threshold: u32,
+    /// 不健康的实例连续多少次健康检查成功后重新标记为健康
+    pub healthy_threshold: u32,
     /// 连接超时时间（毫秒）
     pub timeout: u64,
     /// 重试次数
     #[allow(dead_code)]
     pub retries: u32,
+    /// 是否合并并发的相同资源获取请求，同一资源同时只向CRUD API请求一次
+    pub single_flight: bool,
+    /// 连续失败多少次后熔断实例，0表示不启用熔断
+    pub breaker_failure_threshold: u32,
+    /// 熔断后多久（秒）允许一次探测请求
+    pub breaker_open_duration: u64,
+    /// 熔断后连续多少次健康检查成功即恢复
+    pub breaker_success_threshold: u32,
+    /// 健康检查响应体最大字节数，超过时视为不健康
+    pub health_response_max_bytes: usize,
+}
+
+/// HTTP客户端连接池配置，所有对外请求共用一个客户端
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct HttpClientConfig {
+    /// 每个主机最多保留的空闲连接数
+    pub pool_max_idle_per_host: usize,
+    /// 空闲连接保留时间（秒），0表示不回收
+    pub pool_idle_timeout: u64,
+    /// TCP keep-alive探测间隔（秒），0表示不启用
+    pub tcp_keepalive: u64,
+}
+
+/// 审计日志配置
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct AuditConfig {
+    /// 审计日志文件路径，未配置时不记录审计日志
+    pub log_path: Option<String>,
+}
+
+/// 跨域（CORS）配置，未配置允许的来源时不启用
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CorsConfig {
+    /// 允许的来源，`*` 表示任意来源
+    pub allowed_origins: Vec<String>,
+    /// 允许的请求方法
+    pub allowed_methods: Vec<String>,
+    /// 允许的请求头，认证方式需要的请求头会自动加入
+    pub allowed_headers: Vec<String>,
+    /// 是否允许携带凭据（Cookie、Authorization等）
+    pub allow_credentials: bool,
+    /// 预检结果缓存时间（秒）
+    pub max_age: u64,
+}
+
+/// 接口并发限制配置，0表示不限制
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ConcurrencyLimitConfig {
+    /// /encrypt 最大并发数
+    pub encrypt: usize,
+    /// /decrypt 最大并发数
+    pub decrypt: usize,
+    /// /decrypt/verify 最大并发数
+    pub verify: usize,
+    /// /batch/encrypt 最大并发数
+    pub batch_encrypt: usize,
+    /// /batch/decrypt 最大并发数
+    pub batch_decrypt: usize,
+    /// /batch/verify 最大并发数
+    pub batch_verify: usize,
+    /// /rewrap 最大并发数
+    pub rewrap: usize,
+    /// /rotate 最大并发数
+    pub rotate: usize,
+    /// /encrypt/stream 和 /decrypt/stream 各自的最大并发数
+    pub stream: usize,
+    /// 是否在响应头中返回并发上限和剩余并发数，供客户端自行降速
+    pub expose_headers: bool,
+}
+
+/// 故障转移（Test实例）配置
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct FailoverConfig {
+    /// 允许触发Test实例创建的资源类型，为空时所有资源类型都可以触发
+    pub provision_resource_types: Vec<String>,
+    /// 内存缓存已满时是否优先保留加密数据条目
+    pub prioritize_encrypt: bool,
+    /// 缓存重放间隔（秒），写实例健康时将未写入的加密数据重放到CRUD API，0表示不重放
+    pub replay_interval: u64,
+    /// 备用缓存目录，主缓存目录写入失败时写入该目录
+    pub cache_secondary_dir: Option<String>,
+    /// 启动后是否在写实例可用时立即重放已有缓存
+    pub startup_replay: bool,
+    /// 启动重放的最大条目数，0表示不限制
+    pub startup_replay_limit: usize,
+    /// Test实例健康检查间隔（秒），0表示不检查
+    pub test_instance_health_interval: u64,
+    /// Test实例连续多少次健康检查失败后视为不健康
+    pub test_instance_unhealthy_threshold: u32,
+    /// Test实例不健康时的处理方式
+    pub test_instance_unhealthy_action: UnhealthyAction,
+    /// Test实例创建接口，返回新实例的id和url，未配置时无法创建Test实例
+    pub test_instance_provision_url: Option<String>,
+    /// Test实例有效期（秒），过期后发送提醒
+    pub test_instance_ttl: u64,
+}
+
+/// 请求ID传递配置
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RequestIdConfig {
+    /// 是否将请求的X-Request-Id传递给CRUD API
+    pub propagate: bool,
+    /// 健康检查请求是否携带X-Request-Id（每轮检查生成一个）
+    pub health_check: bool,
+}
+
+/// 资源ID格式配置，资源ID会拼接到CRUD API的请求地址中
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ResourceIdConfig {
+    /// 资源ID必须完整匹配的正则表达式，未配置时只检查URL不安全字符
+    pub pattern: Option<String>,
+    /// 资源ID包含URL不安全字符时进行百分号编码，关闭时拒绝请求
+    pub encode_unsafe: bool,
+}
+
+/// 本地缓存配置，CRUD API不可用时加解密数据写入该目录
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CacheConfig {
+    /// 缓存后端
+    pub backend: CacheBackendType,
+    /// 内存缓存后端的最大条目数，超过时丢弃最早的条目
+    pub memory_capacity: usize,
+    /// 缓存目录
+    pub cache_dir: String,
+    /// 缓存文件切换间隔（秒），每个间隔写入一个新文件
+    pub update_interval: u64,
+    /// 缓存文件保留时间（秒），超过后清理
+    pub retention_time: u64,
+    /// 缓存文件名前缀
+    pub temp_file_prefix: String,
+    /// 是否以gzip压缩写入缓存文件，已有的未压缩文件仍然可以读取
+    pub compression: bool,
+    /// 内存中解密结果缓存的最大条目数（LRU），0表示不启用
+    pub decrypt_cache_size: usize,
+    /// 解密结果缓存有效期（秒）
+    pub decrypt_cache_ttl: u64,
+}
+
+/// 批量操作配置
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct BatchConfig {
+    /// 批量操作中同时处理的最大条目数
+    pub concurrency: usize,
+    /// 单次批量请求允许的最大条目数
+    pub max_size: usize,
+    /// 严格模式：任一条目失败时整批失败
+    pub strict: bool,
+    /// 按各条目结果汇总响应状态码：全部失败且原因相同时返回该状态码，部分失败时返回207
+    pub aggregate_status: bool,
+}
+
+/// 提醒配置
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ReminderConfig {
+    /// 提醒发送目标类型
+    pub notifier_type: NotifierType,
+    /// 提醒Webhook URL，未配置时不发送提醒
+    pub webhook_url: Option<String>,
+    /// 每个通道每个时间窗口最多发送的提醒数，0表示不限制
+    pub rate_limit: u32,
+    /// 限流时间窗口（秒）
+    pub rate_window: u64,
+    /// Test实例过期提醒内容模板，支持 {id}、{url}、{age_hours}、{created_at}、{expired_at} 占位符
+    pub template: String,
+    /// Test实例过期后重复提醒的间隔（秒），0表示只在过期时提醒一次
+    pub repeat_interval: u64,
+}
+
+/// 客户端限流配置
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RateLimitConfig {
+    /// 每个客户端每秒允许的请求数，0表示不限流
+    pub requests_per_second: f64,
+    /// 每个客户端允许的突发请求数
+    pub burst: u32,
+    /// 用于识别客户端的请求头，未配置时按客户端IP限流
+    pub key_header: Option<String>,
+    /// 批量接口是否按条目数计数
+    pub batch_per_item: bool,
 }
 
 impl AppConfig {
     /// 从环境变量加载配置
     pub fn from_env() -> Result<Self> {
         info!("从环境变量加载配置");
+        Self::load(&|name| env::var(name).ok())
+    }
+
+    /// 从给定的变量列表加载配置，未列出的变量使用默认值
+    ///
+    /// 未列出CACHE_DIR时使用系统临时目录，验证配置和写入缓存都不会在仓库目录中留下文件
+    #[cfg(test)]
+    pub(crate) fn from_vars(vars: &[(&str, &str)]) -> Result<Self> {
+        let cache_dir = std::env::temp_dir().join(format!("encryption-service-test-cache-{}", std::process::id()));
+        Self::load(&|name| vars.iter().find(|(key, _)| *key == name).map(|(_, value)| value.to_string())
+            .or_else(|| (name == "CACHE_DIR").then(|| cache_dir.display().to_string())))
+    }
+
+    /// 从配置文件加载配置，支持TOML（.toml）和YAML（.yaml/.yml）
+    ///
+    /// 文件需要包含完整的配置结构；同时设置了环境变量时，环境变量改变的配置项覆盖文件中的值
+    pub fn from_file(path: &Path) -> Result<Self> {
+        info!("从配置文件加载配置: {}", path.display());
+
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("无法读取配置文件 {}: {}", path.display(), e))?;
+        let mut config: serde_json::Value = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(&content)?,
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&content)?,
+            _ => anyhow::bail!("不支持的配置文件格式: {}，仅支持 .toml、.yaml、.yml", path.display()),
+        };
+
+        // 以未设置任何环境变量时的默认配置为基准，找出环境变量改变的配置项
+        // 写实例URL是必填项，未设置时两边使用相同的占位值，不产生差异
+        let placeholder = |name: &str| (name == "CRUD_API_WRITE_INSTANCE_URL").then(|| "http://localhost".to_string());
+        let defaults = serde_json::to_value(Self::load(&placeholder)?)?;
+        let from_env = serde_json::to_value(Self::load(&|name| env::var(name).ok().or_else(|| placeholder(name)))?)?;
+        overlay_changed(&mut config, &defaults, &from_env);
+
+        let mut config: Self = serde_json::from_value(config)?;
+        config.normalize_instance_urls();
+        Ok(config)
+    }
+
+    /// 按变量名查找配置值加载配置
+    fn load(lookup: Lookup) -> Result<Self> {
+        let var = |name: &str| lookup(name).ok_or(env::VarError::NotPresent);
+
         
         // 获取后端类型
-        let backend_type = env::var("CRUD_API_BACKEND_TYPE").unwrap_or("read_write_split".to_string());
+        let backend_type = var("CRUD_API_BACKEND_TYPE").unwrap_or("read_write_split".to_string());
         
         // 读写分离配置参数
         // 必须配置写实例URL，否则容器启动失败
-        let write_instance_url = env::var("CRUD_API_WRITE_INSTANCE_URL")
+        let write_instance_url = var("CRUD_API_WRITE_INSTANCE_URL")
             .map_err(|_| anyhow::anyhow!("CRUD_API_WRITE_INSTANCE_URL环境变量必须设置"))?;
-        let write_instance_timeout = env::var("CRUD_API_WRITE_INSTANCE_TIMEOUT").unwrap_or("5000".to_string()).parse()?;
-        let write_instance_retries = env::var("CRUD_API_WRITE_INSTANCE_RETRIES").unwrap_or("3".to_string()).parse()?;
+        let write_instance_timeout = var("CRUD_API_WRITE_INSTANCE_TIMEOUT").unwrap_or("5000".to_string()).parse()?;
+        let write_instance_retries = var("CRUD_API_WRITE_INSTANCE_RETRIES").unwrap_or("3".to_string()).parse()?;
+        // 数据请求和健康检查分别使用各自的认证请求头，未配置健康检查请求头时使用数据请求头
+        let write_instance_headers: AuthHeaders = var("CRUD_API_WRITE_INSTANCE_HEADERS").unwrap_or_default().parse()?;
+        let write_instance_health_headers = optional_headers(lookup, "CRUD_API_WRITE_INSTANCE_HEALTH_HEADERS")?;
+        let (write_instance_health_path, write_instance_health_expect) = health_settings(lookup, "CRUD_API_WRITE_INSTANCE", None)?;
         
         // 读实例URL默认与写实例URL相同，支持单独配置
-        let read_instance_url = env::var("CRUD_API_READ_INSTANCE_URL").unwrap_or(write_instance_url.clone());
-        let read_instance_timeout = env::var("CRUD_API_READ_INSTANCE_TIMEOUT").unwrap_or("5000".to_string()).parse()?;
-        let read_instance_retries = env::var("CRUD_API_READ_INSTANCE_RETRIES").unwrap_or("3".to_string()).parse()?;
+        let read_instance_url = var("CRUD_API_READ_INSTANCE_URL").unwrap_or(write_instance_url.clone());
+        let read_instance_timeout = var("CRUD_API_READ_INSTANCE_TIMEOUT").unwrap_or("5000".to_string()).parse()?;
+        let read_instance_retries = var("CRUD_API_READ_INSTANCE_RETRIES").unwrap_or("3".to_string()).parse()?;
+        let read_instance_headers = match optional_headers(lookup, "CRUD_API_READ_INSTANCE_HEADERS")? {
+            Some(headers) => headers,
+            None => write_instance_headers.clone(),
+        };
+        let read_instance_health_headers = match optional_headers(lookup, "CRUD_API_READ_INSTANCE_HEALTH_HEADERS")? {
+            Some(headers) => Some(headers),
+            None => write_instance_health_headers.clone(),
+        };
+        // 读实例未单独配置健康检查路径和判定规则时与写实例相同
+        let (read_instance_health_path, read_instance_health_expect) = health_settings(
+            lookup,
+            "CRUD_API_READ_INSTANCE",
+            Some((&write_instance_health_path, &write_instance_health_expect)),
+        )?;
         
         // 健康检查间隔
-        let health_check_interval = env::var("CRUD_API_HEALTH_CHECK_INTERVAL").unwrap_or("30".to_string()).parse()?;
+        let health_check_interval = var("CRUD_API_HEALTH_CHECK_INTERVAL").unwrap_or("30".to_string()).parse()?;
         
+        // 默认加密算法，未单独配置启用的算法时只启用默认算法
+        let encryption_algorithm = var("ENCRYPTION_ALGORITHM").unwrap_or("aes-256-gcm".to_string());
+        let iterations: u32 = var("ENCRYPTION_ITERATIONS").unwrap_or("100000".to_string()).parse()?;
+        // 未配置时为迭代次数的2倍，调低迭代次数后仍可解密此前的密文
+        let max_iterations = var("ENCRYPTION_MAX_ITERATIONS").ok().map(|m| m.parse()).transpose()?
+            .unwrap_or(iterations.saturating_mul(2).clamp(1, crate::crypto::MAX_PBKDF2_ITERATIONS));
+        let argon2_memory: u32 = var("ENCRYPTION_ARGON2_MEMORY").unwrap_or("19456".to_string()).parse()?;
+        let argon2_iterations: u32 = var("ENCRYPTION_ARGON2_ITERATIONS").unwrap_or("2".to_string()).parse()?;
+        let argon2_parallelism: u32 = var("ENCRYPTION_ARGON2_PARALLELISM").unwrap_or("1".to_string()).parse()?;
+        let argon2_fallback_memory: u32 = var("ENCRYPTION_ARGON2_FALLBACK_MEMORY").unwrap_or("7168".to_string()).parse()?;
+        let argon2_fallback_iterations: u32 = var("ENCRYPTION_ARGON2_FALLBACK_ITERATIONS").unwrap_or("5".to_string()).parse()?;
+        let argon2_fallback_parallelism: u32 = var("ENCRYPTION_ARGON2_FALLBACK_PARALLELISM").unwrap_or("1".to_string()).parse()?;
+        let argon2_alloc_failure: Argon2AllocFailurePolicy = var("ENCRYPTION_ARGON2_ALLOC_FAILURE").unwrap_or("error".to_string()).parse()?;
+        // 未配置时只接受本服务自己会生成的参数：主参数，处理方式为fallback时还包括备用参数
+        let (memory_ceiling, iterations_ceiling, parallelism_ceiling) = if argon2_alloc_failure == Argon2AllocFailurePolicy::Fallback {
+            (
+                argon2_memory.max(argon2_fallback_memory),
+                argon2_iterations.max(argon2_fallback_iterations),
+                argon2_parallelism.max(argon2_fallback_parallelism),
+            )
+        } else {
+            (argon2_memory, argon2_iterations, argon2_parallelism)
+        };
+        let argon2_max_memory = var("ENCRYPTION_ARGON2_MAX_MEMORY").ok().map(|m| m.parse()).transpose()?.unwrap_or(memory_ceiling);
+        let argon2_max_iterations = var("ENCRYPTION_ARGON2_MAX_ITERATIONS").ok().map(|m| m.parse()).transpose()?.unwrap_or(iterations_ceiling);
+        let argon2_max_parallelism = var("ENCRYPTION_ARGON2_MAX_PARALLELISM").ok().map(|m| m.parse()).transpose()?.unwrap_or(parallelism_ceiling);
+        let enabled_algorithms = var("ENCRYPTION_ENABLED_ALGORITHMS").unwrap_or(encryption_algorithm.clone())
+            .split(',')
+            .map(|a| a.trim().to_string())
+            .filter(|a| !a.is_empty())
+            .collect();
+        let resource_type_algorithms = var("ENCRYPTION_RESOURCE_TYPE_ALGORITHMS").unwrap_or_default()
+            .split(',')
+            .map(|pair| pair.trim())
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| match pair.split_once(':') {
+                Some((resource_type, algorithm)) => Ok((resource_type.trim().to_string(), algorithm.trim().to_string())),
+                None => anyhow::bail!("无效的资源类型加密算法配置: {}，格式应为 资源类型:算法", pair),
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+        // 资源类型盐值：ENCRYPTION_SALT_RESOURCE_TYPES 列出资源类型，盐值从 ENCRYPTION_SALT_<资源类型> 读取
+        let resource_type_salts = var("ENCRYPTION_SALT_RESOURCE_TYPES").unwrap_or_default()
+            .split(',')
+            .map(|t| t.trim())
+            .filter(|t| !t.is_empty())
+            .map(|resource_type| {
+                let name = format!("ENCRYPTION_SALT_{}", resource_type_var_suffix(resource_type));
+                match var(&name) {
+                    Ok(salt) => Ok((resource_type.to_string(), salt)),
+                    Err(_) => anyhow::bail!("资源类型 {} 未配置盐值 {}", resource_type, name),
+                }
+            })
+            .collect::<Result<HashMap<_, _>>>()?;
+
         // 根据后端类型动态配置实例列表
         let (instances, strategy) = match backend_type.as_str() {
             // 单容器模式：读实例和写实例指向同一个URL
@@ -148,6 +1117,11 @@ impl AppConfig {
                         instance_type: "write".to_string(),
                         timeout: write_instance_timeout,
                         retries: write_instance_retries,
+                        weight: 1,
+                        headers: write_instance_headers.clone(),
+                        health_headers: write_instance_health_headers.clone(),
+                        health_path: write_instance_health_path.clone(),
+                        health_expect: write_instance_health_expect.clone(),
                     },
                     // 读实例，指向同一个URL
                     CrudApiInstance {
@@ -156,32 +1130,15 @@ impl AppConfig {
                         instance_type: "read".to_string(),
                         timeout: read_instance_timeout,
                         retries: read_instance_retries,
+                        weight: 1,
+                        headers: read_instance_headers.clone(),
+                        health_headers: read_instance_health_headers.clone(),
+                        health_path: read_instance_health_path.clone(),
+                        health_expect: read_instance_health_expect.clone(),
                     },
                 ];
                 (instances, SchedulerStrategy::Single)
             },
-            // 读写分离模式：读实例和写实例指向不同的URL
-            "read_write_split" => {
-                let instances = vec![
-                    // 写实例
-                    CrudApiInstance {
-                        id: "write-01".to_string(),
-                        url: write_instance_url,
-                        instance_type: "write".to_string(),
-                        timeout: write_instance_timeout,
-                        retries: write_instance_retries,
-                    },
-                    // 读实例
-                    CrudApiInstance {
-                        id: "read-01".to_string(),
-                        url: read_instance_url,
-                        instance_type: "read".to_string(),
-                        timeout: read_instance_timeout,
-                        retries: read_instance_retries,
-                    },
-                ];
-                (instances, SchedulerStrategy::ReadWriteSplit)
-            },
             // 负载均衡模式：多个混合实例
             "load_balance" => {
                 // 加载负载均衡实例配置
@@ -189,11 +1146,15 @@ impl AppConfig {
                 let mut index = 0;
                 loop {
                     // 尝试读取第index个实例的配置
-                    let instance_id = env::var(format!("CRUD_API_INSTANCE_{}_ID", index)).unwrap_or_default();
-                    let instance_url = env::var(format!("CRUD_API_INSTANCE_{}_URL", index)).unwrap_or_default();
-                    let instance_type = env::var(format!("CRUD_API_INSTANCE_{}_TYPE", index)).unwrap_or("mixed".to_string());
-                    let instance_timeout = env::var(format!("CRUD_API_INSTANCE_{}_TIMEOUT", index)).unwrap_or("5000".to_string()).parse()?;
-                    let instance_retries = env::var(format!("CRUD_API_INSTANCE_{}_RETRIES", index)).unwrap_or("3".to_string()).parse()?;
+                    let instance_id = var(&format!("CRUD_API_INSTANCE_{}_ID", index)).unwrap_or_default();
+                    let instance_url = var(&format!("CRUD_API_INSTANCE_{}_URL", index)).unwrap_or_default();
+                    let instance_type = var(&format!("CRUD_API_INSTANCE_{}_TYPE", index)).unwrap_or("mixed".to_string());
+                    let instance_timeout = var(&format!("CRUD_API_INSTANCE_{}_TIMEOUT", index)).unwrap_or("5000".to_string()).parse()?;
+                    let instance_retries = var(&format!("CRUD_API_INSTANCE_{}_RETRIES", index)).unwrap_or("3".to_string()).parse()?;
+                    let instance_weight = var(&format!("CRUD_API_INSTANCE_{}_WEIGHT", index)).unwrap_or("1".to_string()).parse()?;
+                    let instance_headers = var(&format!("CRUD_API_INSTANCE_{}_HEADERS", index)).unwrap_or_default().parse()?;
+                    let instance_health_headers = optional_headers(lookup, &format!("CRUD_API_INSTANCE_{}_HEALTH_HEADERS", index))?;
+                    let (instance_health_path, instance_health_expect) = health_settings(lookup, &format!("CRUD_API_INSTANCE_{}", index), None)?;
                     
                     // 如果没有配置实例ID或URL，说明已经没有更多实例了
                     if instance_id.is_empty() || instance_url.is_empty() {
@@ -206,6 +1167,11 @@ impl AppConfig {
                         instance_type,
                         timeout: instance_timeout,
                         retries: instance_retries,
+                        weight: instance_weight,
+                        headers: instance_headers,
+                        health_headers: instance_health_headers,
+                        health_path: instance_health_path,
+                        health_expect: instance_health_expect,
                     });
                     
                     index += 1;
@@ -219,14 +1185,19 @@ impl AppConfig {
                         instance_type: "mixed".to_string(),
                         timeout: write_instance_timeout,
                         retries: write_instance_retries,
+                        weight: 1,
+                        headers: write_instance_headers.clone(),
+                        health_headers: write_instance_health_headers.clone(),
+                        health_path: write_instance_health_path.clone(),
+                        health_expect: write_instance_health_expect.clone(),
                     });
                 }
                 
                 (instances, SchedulerStrategy::LoadBalance)
             },
-            // 默认使用读写分离模式
+            // 读写分离模式（默认）：读实例和写实例指向不同的URL，可以各配置多个
             _ => {
-                let instances = vec![
+                let mut instances = vec![
                     // 写实例
                     CrudApiInstance {
                         id: "write-01".to_string(),
@@ -234,6 +1205,11 @@ impl AppConfig {
                         instance_type: "write".to_string(),
                         timeout: write_instance_timeout,
                         retries: write_instance_retries,
+                        weight: 1,
+                        headers: write_instance_headers.clone(),
+                        health_headers: write_instance_health_headers.clone(),
+                        health_path: write_instance_health_path.clone(),
+                        health_expect: write_instance_health_expect.clone(),
                     },
                     // 读实例
                     CrudApiInstance {
@@ -242,55 +1218,471 @@ impl AppConfig {
                         instance_type: "read".to_string(),
                         timeout: read_instance_timeout,
                         retries: read_instance_retries,
+                        weight: 1,
+                        headers: read_instance_headers.clone(),
+                        health_headers: read_instance_health_headers.clone(),
+                        health_path: read_instance_health_path.clone(),
+                        health_expect: read_instance_health_expect.clone(),
                     },
                 ];
+
+                // 其他读写实例：CRUD_API_WRITE_INSTANCE_{n}_URL、CRUD_API_READ_INSTANCE_{n}_URL，n从1开始
+                let additional_writes = indexed_instances(lookup, "WRITE", &instances[0])?;
+                let additional_reads = indexed_instances(lookup, "READ", &instances[1])?;
+                instances.extend(additional_writes);
+                instances.extend(additional_reads);
+
                 (instances, SchedulerStrategy::ReadWriteSplit)
             },
         };
 
-        let config = Self {
+        let mut config = Self {
             server: ServerConfig {
-                host: env::var("SERVER_HOST").unwrap_or("0.0.0.0".to_string()),
-                port: env::var("SERVER_PORT").unwrap_or("9999".to_string()).parse()?,
-                https: env::var("HTTPS").unwrap_or("false".to_string()).parse()?,
+                host: var("SERVER_HOST").unwrap_or("0.0.0.0".to_string()),
+                port: var("SERVER_PORT").unwrap_or("9999".to_string()).parse()?,
+                https: var("HTTPS").unwrap_or("false".to_string()).parse()?,
+                tls_cert_path: var("TLS_CERT_PATH").ok().filter(|p| !p.is_empty()),
+                tls_key_path: var("TLS_KEY_PATH").ok().filter(|p| !p.is_empty()),
+                location_template: var("LOCATION_HEADER_TEMPLATE").ok().filter(|t| !t.is_empty()),
+                // 未配置时调试构建默认返回详细错误，发布构建默认只返回通用错误
+                verbose_errors: var("VERBOSE_ERRORS").unwrap_or(cfg!(debug_assertions).to_string()).parse()?,
+                admin_token: var("ADMIN_TOKEN").ok().filter(|t| !t.is_empty()),
+                max_body_size: var("MAX_BODY_SIZE").unwrap_or("1048576".to_string()).parse()?,
+                max_batch_body_size: var("MAX_BATCH_BODY_SIZE").unwrap_or("4194304".to_string()).parse()?,
             },
             jwt: JwtConfig {
-                secret: env::var("JWT_SECRET").unwrap_or("12345678901234567890".to_string()),
-                expires_in: env::var("JWT_EXPIRES_IN").unwrap_or("3600".to_string()).parse()?,
-                refresh_in: env::var("JWT_REFRESH_IN").unwrap_or("86400".to_string()).parse()?,
+                secret: var("JWT_SECRET").unwrap_or("12345678901234567890".to_string()),
+                expires_in: var("JWT_EXPIRES_IN").unwrap_or("3600".to_string()).parse()?,
+                refresh_in: var("JWT_REFRESH_IN").unwrap_or("86400".to_string()).parse()?,
+            },
+            auth: AuthConfig {
+                mode: var("AUTH_MODE").unwrap_or("jwt".to_string()).parse()?,
+                hmac_secret: var("AUTH_HMAC_SECRET").ok().filter(|secret| !secret.is_empty()),
+                hmac_max_skew: var("AUTH_HMAC_MAX_SKEW").unwrap_or("300".to_string()).parse()?,
             },
             encryption: EncryptionConfig {
-                algorithm: env::var("ENCRYPTION_ALGORITHM").unwrap_or("aes-256-gcm".to_string()),
-                key_length: env::var("ENCRYPTION_KEY_LENGTH").unwrap_or("32".to_string()).parse()?,
-                iterations: env::var("ENCRYPTION_ITERATIONS").unwrap_or("100000".to_string()).parse()?,
-                salt: env::var("ENCRYPTION_SALT").unwrap_or("default_salt".to_string()),
+                algorithm: encryption_algorithm,
+                enabled_algorithms,
+                resource_type_algorithms,
+                key_length: var("ENCRYPTION_KEY_LENGTH").unwrap_or("32".to_string()).parse()?,
+                key_derivation: var("ENCRYPTION_KEY_DERIVATION").unwrap_or("hkdf".to_string()),
+                iterations,
+                max_iterations,
+                argon2_memory,
+                argon2_iterations,
+                argon2_parallelism,
+                argon2_max_memory,
+                argon2_max_iterations,
+                argon2_max_parallelism,
+                argon2_alloc_failure,
+                argon2_fallback_memory,
+                argon2_fallback_iterations,
+                argon2_fallback_parallelism,
+                salt: var("ENCRYPTION_SALT").unwrap_or("default_salt".to_string()),
+                resource_type_salts,
+                plaintext_checksum: var("ENCRYPTION_PLAINTEXT_CHECKSUM").unwrap_or("false".to_string()).parse()?,
+                compression: var("ENCRYPTION_COMPRESSION").unwrap_or("false".to_string()).parse()?,
+                compression_min_size: var("ENCRYPTION_COMPRESSION_MIN_SIZE").unwrap_or("1024".to_string()).parse()?,
+                padding: var("ENCRYPTION_PADDING").unwrap_or("none".to_string()).parse()?,
+                padding_resource_types: var("ENCRYPTION_PADDING_RESOURCE_TYPES").unwrap_or_default()
+                    .split(',')
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty())
+                    .collect(),
+                resource_type_guard: var("ENCRYPTION_RESOURCE_TYPE_GUARD").unwrap_or("false".to_string()).parse()?,
+                bind_resource_type: var("ENCRYPTION_BIND_RESOURCE_TYPE").unwrap_or("true".to_string()).parse()?,
+                stream_chunk_size: var("STREAM_CHUNK_SIZE").unwrap_or("65536".to_string()).parse()?,
+                key_cache_size: var("KEY_CACHE_SIZE").unwrap_or("0".to_string()).parse()?,
+                key_cache_ttl: var("KEY_CACHE_TTL").unwrap_or("300".to_string()).parse()?,
+                kdf_slow_threshold_ms: var("KDF_SLOW_THRESHOLD_MS").unwrap_or("500".to_string()).parse()?,
+                content_id_key: var("CONTENT_ID_KEY").ok().filter(|k| !k.is_empty()),
+                cache_encryption_key: var("CACHE_ENCRYPTION_KEY").ok().filter(|k| !k.is_empty()),
+                base64_variants: var("DECRYPT_BASE64_VARIANTS")
+                    .unwrap_or("standard,url_safe,standard_no_pad,url_safe_no_pad".to_string())
+                    .split(',')
+                    .map(|v| v.trim())
+                    .filter(|v| !v.is_empty())
+                    .map(|v| v.parse())
+                    .collect::<Result<Vec<_>>>()?,
+                max_decoded_size: var("DECRYPT_MAX_DECODED_BYTES").unwrap_or("16777216".to_string()).parse()?,
             },
             service: ServiceRoleConfig {
-                role: env::var("SERVICE_ROLE").unwrap_or("mixed".to_string()),
-                id: env::var("SERVICE_ID").unwrap_or("encryption-01".to_string()),
+                role: var("SERVICE_ROLE").unwrap_or("mixed".to_string()),
+                id: var("SERVICE_ID").unwrap_or("encryption-01".to_string()),
+                auto_downgrade: var("SERVICE_ROLE_AUTO_DOWNGRADE").unwrap_or("false".to_string()).parse()?,
+                maintenance_schedule: var("MAINTENANCE_SCHEDULE").unwrap_or_default()
+                    .split(',')
+                    .map(|w| w.trim())
+                    .filter(|w| !w.is_empty())
+                    .map(|w| w.parse())
+                    .collect::<Result<Vec<_>>>()?,
             },
             crud_api: CrudApiConfig {
                 instances,
                 strategy,
+                selection_policy: var("CRUD_API_SELECTION_POLICY").unwrap_or("round_robin".to_string()).parse()?,
                 health_check_interval,
+                health_check_min_interval: var("CRUD_API_HEALTH_CHECK_MIN_INTERVAL").unwrap_or("5".to_string()).parse()?,
+                unhealthy_threshold: var("CRUD_API_UNHEALTHY_THRESHOLD").unwrap_or("3".to_string()).parse()?,
+                healthy_threshold: var("CRUD_API_HEALTHY_THRESHOLD").unwrap_or("2".to_string()).parse()?,
                 timeout: write_instance_timeout, // 默认使用写实例的超时时间
                 retries: write_instance_retries, // 默认使用写实例的重试次数
+                single_flight: var("CRUD_API_SINGLE_FLIGHT").unwrap_or("true".to_string()).parse()?,
+                breaker_failure_threshold: var("CIRCUIT_BREAKER_FAILURE_THRESHOLD").unwrap_or("5".to_string()).parse()?,
+                breaker_open_duration: var("CIRCUIT_BREAKER_OPEN_DURATION").unwrap_or("30".to_string()).parse()?,
+                breaker_success_threshold: var("CIRCUIT_BREAKER_SUCCESS_THRESHOLD").unwrap_or("2".to_string()).parse()?,
+                health_response_max_bytes: var("HEALTH_RESPONSE_MAX_BYTES").unwrap_or("65536".to_string()).parse()?,
+            },
+            limits: ConcurrencyLimitConfig {
+                encrypt: var("LIMIT_ENCRYPT").unwrap_or("0".to_string()).parse()?,
+                decrypt: var("LIMIT_DECRYPT").unwrap_or("0".to_string()).parse()?,
+                verify: var("LIMIT_VERIFY").unwrap_or("0".to_string()).parse()?,
+                batch_encrypt: var("LIMIT_BATCH_ENCRYPT").unwrap_or("0".to_string()).parse()?,
+                batch_decrypt: var("LIMIT_BATCH_DECRYPT").unwrap_or("0".to_string()).parse()?,
+                batch_verify: var("LIMIT_BATCH_VERIFY").unwrap_or("0".to_string()).parse()?,
+                rewrap: var("LIMIT_REWRAP").unwrap_or("0".to_string()).parse()?,
+                rotate: var("LIMIT_ROTATE").unwrap_or("0".to_string()).parse()?,
+                stream: var("LIMIT_STREAM").unwrap_or("0".to_string()).parse()?,
+                expose_headers: var("LIMIT_EXPOSE_HEADERS").unwrap_or("false".to_string()).parse()?,
+            },
+            failover: FailoverConfig {
+                provision_resource_types: var("TEST_INSTANCE_RESOURCE_TYPES").unwrap_or_default()
+                    .split(',')
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty())
+                    .collect(),
+                prioritize_encrypt: var("CACHE_PRIORITIZE_ENCRYPT").unwrap_or("false".to_string()).parse()?,
+                replay_interval: var("CACHE_REPLAY_INTERVAL").unwrap_or("60".to_string()).parse()?,
+                cache_secondary_dir: var("CACHE_SECONDARY_DIR").ok().filter(|d| !d.is_empty()),
+                startup_replay: var("CACHE_STARTUP_REPLAY").unwrap_or("true".to_string()).parse()?,
+                startup_replay_limit: var("CACHE_STARTUP_REPLAY_LIMIT").unwrap_or("1000".to_string()).parse()?,
+                test_instance_health_interval: var("TEST_INSTANCE_HEALTH_INTERVAL").unwrap_or("60".to_string()).parse()?,
+                test_instance_unhealthy_threshold: var("TEST_INSTANCE_UNHEALTHY_THRESHOLD").unwrap_or("3".to_string()).parse()?,
+                test_instance_unhealthy_action: var("TEST_INSTANCE_UNHEALTHY_ACTION").unwrap_or("none".to_string()).parse()?,
+                test_instance_provision_url: var("TEST_INSTANCE_PROVISION_URL").ok().filter(|u| !u.is_empty()),
+                test_instance_ttl: var("TEST_INSTANCE_TTL").unwrap_or("172800".to_string()).parse()?,
+            },
+            request_id: RequestIdConfig {
+                propagate: var("REQUEST_ID_PROPAGATION").unwrap_or("true".to_string()).parse()?,
+                health_check: var("REQUEST_ID_HEALTH_CHECK").unwrap_or("false".to_string()).parse()?,
+            },
+            resource_id: ResourceIdConfig {
+                pattern: var("RESOURCE_ID_PATTERN").ok().filter(|p| !p.is_empty()),
+                encode_unsafe: var("RESOURCE_ID_ENCODE").unwrap_or("false".to_string()).parse()?,
+            },
+            cache: CacheConfig {
+                backend: var("CACHE_BACKEND").unwrap_or("file".to_string()).parse()?,
+                memory_capacity: var("CACHE_MEMORY_CAPACITY").unwrap_or("10000".to_string()).parse()?,
+                cache_dir: var("CACHE_DIR").unwrap_or("data/cache".to_string()),
+                update_interval: var("CACHE_UPDATE_INTERVAL").unwrap_or("3600".to_string()).parse()?,
+                retention_time: var("CACHE_RETENTION_TIME").unwrap_or("86400".to_string()).parse()?,
+                temp_file_prefix: var("CACHE_FILE_PREFIX").unwrap_or("crud_api_cache".to_string()),
+                compression: var("CACHE_COMPRESSION").unwrap_or("false".to_string()).parse()?,
+                decrypt_cache_size: var("DECRYPT_CACHE_SIZE").unwrap_or("0".to_string()).parse()?,
+                decrypt_cache_ttl: var("DECRYPT_CACHE_TTL").unwrap_or("60".to_string()).parse()?,
+            },
+            http_client: HttpClientConfig {
+                pool_max_idle_per_host: var("HTTP_POOL_MAX_IDLE_PER_HOST").unwrap_or("32".to_string()).parse()?,
+                pool_idle_timeout: var("HTTP_POOL_IDLE_TIMEOUT").unwrap_or("90".to_string()).parse()?,
+                tcp_keepalive: var("HTTP_TCP_KEEPALIVE").unwrap_or("60".to_string()).parse()?,
+            },
+            audit: AuditConfig {
+                log_path: var("AUDIT_LOG_PATH").ok().filter(|path| !path.is_empty()),
+            },
+            cors: CorsConfig {
+                allowed_origins: var("CORS_ALLOWED_ORIGINS").unwrap_or_default()
+                    .split(',')
+                    .map(|o| o.trim().to_string())
+                    .filter(|o| !o.is_empty())
+                    .collect(),
+                allowed_methods: var("CORS_ALLOWED_METHODS").unwrap_or("GET,POST,PUT,DELETE".to_string())
+                    .split(',')
+                    .map(|m| m.trim().to_uppercase())
+                    .filter(|m| !m.is_empty())
+                    .collect(),
+                allowed_headers: var("CORS_ALLOWED_HEADERS").unwrap_or("content-type,x-request-id".to_string())
+                    .split(',')
+                    .map(|h| h.trim().to_lowercase())
+                    .filter(|h| !h.is_empty())
+                    .collect(),
+                allow_credentials: var("CORS_ALLOW_CREDENTIALS").unwrap_or("false".to_string()).parse()?,
+                max_age: var("CORS_MAX_AGE").unwrap_or("600".to_string()).parse()?,
+            },
+            batch: BatchConfig {
+                concurrency: var("BATCH_CONCURRENCY").unwrap_or("8".to_string()).parse()?,
+                max_size: var("MAX_BATCH_SIZE").unwrap_or("1000".to_string()).parse()?,
+                strict: var("BATCH_STRICT").unwrap_or("false".to_string()).parse()?,
+                aggregate_status: var("BATCH_AGGREGATE_STATUS").unwrap_or("false".to_string()).parse()?,
+            },
+            rate_limit: RateLimitConfig {
+                requests_per_second: var("RATE_LIMIT_RPS").unwrap_or("0".to_string()).parse()?,
+                burst: var("RATE_LIMIT_BURST").unwrap_or("20".to_string()).parse()?,
+                key_header: var("RATE_LIMIT_KEY_HEADER").ok().filter(|h| !h.is_empty()),
+                batch_per_item: var("RATE_LIMIT_BATCH_PER_ITEM").unwrap_or("false".to_string()).parse()?,
+            },
+            reminder: ReminderConfig {
+                notifier_type: var("NOTIFIER_TYPE").unwrap_or("wechat".to_string()).parse()?,
+                // 兼容只配置了企业微信机器人URL的部署
+                webhook_url: var("NOTIFIER_WEBHOOK_URL").or_else(|_| var("WECHAT_WEBHOOK_URL")).ok().filter(|u| !u.is_empty()),
+                rate_limit: var("REMINDER_RATE_LIMIT").unwrap_or("3".to_string()).parse()?,
+                rate_window: var("REMINDER_RATE_WINDOW").unwrap_or("3600".to_string()).parse()?,
+                template: var("TEST_INSTANCE_REMINDER_TEMPLATE")
+                    .unwrap_or("Test实例 {id}（{url}）已存在{age_hours}小时，超过有效期，请及时处理".to_string()),
+                repeat_interval: var("TEST_INSTANCE_REMINDER_INTERVAL").unwrap_or("21600".to_string()).parse()?,
             },
         };
+        config.normalize_instance_urls();
         
         Ok(config)
     }
+
+    /// 去掉CRUD API实例URL末尾的斜杠，避免拼接请求路径时出现双斜杠
+    fn normalize_instance_urls(&mut self) {
+        for instance in &mut self.crud_api.instances {
+            let trimmed = instance.url.trim_end_matches('/');
+            if trimmed.len() != instance.url.len() {
+                instance.url = trimmed.to_string();
+            }
+        }
+    }
     
     /// 验证配置
     pub fn validate(&self) -> Result<()> {
         info!("验证配置");
         
         // 验证服务角色
-        let valid_roles = vec!["encrypt", "decrypt", "mixed"];
+        let valid_roles = ["encrypt", "decrypt", "mixed"];
         if !valid_roles.contains(&self.service.role.as_str()) {
             anyhow::bail!("无效的服务角色: {}", self.service.role);
         }
         
+        // 验证启用的加密算法
+        let valid_algorithms = ["aes-256-gcm", "chacha20-poly1305", "aes-256-cbc-hmac"];
+        for algorithm in &self.encryption.enabled_algorithms {
+            if !valid_algorithms.contains(&algorithm.as_str()) {
+                anyhow::bail!("无效的加密算法: {}，可选值: {}", algorithm, valid_algorithms.join(", "));
+            }
+        }
+        if !valid_algorithms.contains(&self.encryption.algorithm.as_str()) {
+            anyhow::bail!("无效的默认加密算法: {}，可选值: {}", self.encryption.algorithm, valid_algorithms.join(", "));
+        }
+        if !self.encryption.enabled_algorithms.contains(&self.encryption.algorithm) {
+            anyhow::bail!("默认加密算法 {} 未启用", self.encryption.algorithm);
+        }
+        for (resource_type, algorithm) in &self.encryption.resource_type_algorithms {
+            if !self.encryption.enabled_algorithms.contains(algorithm) {
+                anyhow::bail!("资源类型 {} 指定的加密算法 {} 未启用", resource_type, algorithm);
+            }
+        }
+
+        for (resource_type, salt) in &self.encryption.resource_type_salts {
+            if salt.is_empty() {
+                anyhow::bail!("资源类型 {} 的盐值不能为空", resource_type);
+            }
+        }
+
+        // 验证密钥长度，支持的算法都使用256位密钥
+        if self.encryption.key_length != 32 {
+            anyhow::bail!("无效的密钥长度: {}，{} 需要32字节密钥", self.encryption.key_length, valid_algorithms.join(", "));
+        }
+
+        // 验证密钥派生算法
+        let valid_key_derivations = ["hkdf", "pbkdf2", "argon2id"];
+        if !valid_key_derivations.contains(&self.encryption.key_derivation.as_str()) {
+            anyhow::bail!("无效的密钥派生算法: {}，可选值: {}", self.encryption.key_derivation, valid_key_derivations.join(", "));
+        }
+        if self.encryption.key_derivation == "pbkdf2"
+            && (self.encryption.iterations == 0 || self.encryption.iterations > crate::crypto::MAX_PBKDF2_ITERATIONS)
+        {
+            anyhow::bail!("PBKDF2迭代次数必须在 1 到 {} 之间", crate::crypto::MAX_PBKDF2_ITERATIONS);
+        }
+        if self.encryption.max_iterations == 0 || self.encryption.max_iterations > crate::crypto::MAX_PBKDF2_ITERATIONS {
+            anyhow::bail!("ENCRYPTION_MAX_ITERATIONS必须在 1 到 {} 之间", crate::crypto::MAX_PBKDF2_ITERATIONS);
+        }
+        if self.encryption.key_derivation == "pbkdf2" && self.encryption.max_iterations < self.encryption.iterations {
+            anyhow::bail!("ENCRYPTION_MAX_ITERATIONS不能小于ENCRYPTION_ITERATIONS，否则新加密的数据无法解密");
+        }
+        if self.encryption.argon2_max_memory > crate::crypto::MAX_ARGON2_MEMORY
+            || self.encryption.argon2_max_iterations > crate::crypto::MAX_ARGON2_ITERATIONS
+            || self.encryption.argon2_max_parallelism > crate::crypto::MAX_ARGON2_PARALLELISM
+        {
+            anyhow::bail!(
+                "ENCRYPTION_ARGON2_MAX_*超过上限: 内存最多 {} KiB，迭代最多 {} 次，并行度最多 {}",
+                crate::crypto::MAX_ARGON2_MEMORY, crate::crypto::MAX_ARGON2_ITERATIONS, crate::crypto::MAX_ARGON2_PARALLELISM
+            );
+        }
+        if self.encryption.key_derivation == "argon2id" {
+            let encryption = &self.encryption;
+            check_argon2_params(encryption.argon2_memory, encryption.argon2_iterations, encryption.argon2_parallelism, encryption.key_length)?;
+            if encryption.argon2_alloc_failure == Argon2AllocFailurePolicy::Fallback {
+                check_argon2_params(
+                    encryption.argon2_fallback_memory,
+                    encryption.argon2_fallback_iterations,
+                    encryption.argon2_fallback_parallelism,
+                    encryption.key_length,
+                ).map_err(|e| anyhow::anyhow!("备用{}", e))?;
+                if encryption.argon2_fallback_memory >= encryption.argon2_memory {
+                    anyhow::bail!("备用Argon2内存开销必须小于ENCRYPTION_ARGON2_MEMORY");
+                }
+            }
+            let mut profiles = vec![(encryption.argon2_memory, encryption.argon2_iterations, encryption.argon2_parallelism)];
+            if encryption.argon2_alloc_failure == Argon2AllocFailurePolicy::Fallback {
+                profiles.push((encryption.argon2_fallback_memory, encryption.argon2_fallback_iterations, encryption.argon2_fallback_parallelism));
+            }
+            for (memory, iterations, parallelism) in profiles {
+                if memory > encryption.argon2_max_memory
+                    || iterations > encryption.argon2_max_iterations
+                    || parallelism > encryption.argon2_max_parallelism
+                {
+                    anyhow::bail!("ENCRYPTION_ARGON2_MAX_*不能小于加密使用的Argon2参数，否则新加密的数据无法解密");
+                }
+            }
+        }
+
+        // 验证密文Base64变体
+        if self.encryption.base64_variants.is_empty() {
+            anyhow::bail!("至少需要配置一种密文Base64变体");
+        }
+        if self.encryption.max_decoded_size == 0 {
+            anyhow::bail!("解码后最大字节数必须大于0");
+        }
+        if self.encryption.stream_chunk_size == 0 || self.encryption.stream_chunk_size > crate::crypto::MAX_STREAM_CHUNK_SIZE {
+            anyhow::bail!("流式加密分块大小必须在1到{}字节之间", crate::crypto::MAX_STREAM_CHUNK_SIZE);
+        }
+
+        // 验证认证配置
+        match self.auth.mode {
+            AuthMode::Hmac => match self.auth.hmac_secret {
+                None => anyhow::bail!("认证方式为hmac时必须配置AUTH_HMAC_SECRET"),
+                Some(ref secret) if secret.len() < 16 => anyhow::bail!("AUTH_HMAC_SECRET长度不能少于16字节"),
+                Some(_) => {},
+            },
+            AuthMode::Jwt | AuthMode::None => {},
+        }
+
+        // 验证批量并发数
+        if self.batch.concurrency == 0 {
+            anyhow::bail!("批量操作并发数必须大于0");
+        }
+        if self.batch.max_size == 0 {
+            anyhow::bail!("批量请求最大条目数必须大于0");
+        }
+
+        // 验证客户端限流配置
+        if !self.rate_limit.requests_per_second.is_finite() || self.rate_limit.requests_per_second < 0.0 {
+            anyhow::bail!("客户端每秒请求数必须是非负数");
+        }
+        if self.rate_limit.requests_per_second > 0.0 && self.rate_limit.burst == 0 {
+            anyhow::bail!("客户端突发请求数必须大于0");
+        }
+
+        // 验证提醒限流窗口
+        if self.reminder.rate_limit > 0 && self.reminder.rate_window == 0 {
+            anyhow::bail!("提醒限流时间窗口必须大于0");
+        }
+
+        // 验证内容寻址哈希密钥长度
+        if let Some(ref key) = self.encryption.content_id_key
+            && key.len() < 16
+        {
+            anyhow::bail!("内容寻址哈希密钥长度至少为16个字符");
+        }
+
+        // 验证缓存加密密钥长度
+        if let Some(ref key) = self.encryption.cache_encryption_key
+            && key.len() < 16
+        {
+            anyhow::bail!("缓存加密密钥长度至少为16个字符");
+        }
+
+        // 验证Test实例有效期
+        if self.failover.test_instance_ttl == 0 {
+            anyhow::bail!("Test实例有效期必须大于0");
+        }
+
+        // 验证缓存配置，使用文件后端时缓存目录必须可写，否则故障期间的数据无处落盘
+        if self.cache.update_interval == 0 {
+            anyhow::bail!("缓存文件切换间隔必须大于0");
+        }
+        if self.cache.retention_time == 0 {
+            anyhow::bail!("缓存文件保留时间必须大于0");
+        }
+        if self.cache.temp_file_prefix.is_empty() || self.cache.temp_file_prefix.contains(['/', '\\']) {
+            anyhow::bail!("无效的缓存文件名前缀: {}", self.cache.temp_file_prefix);
+        }
+        if self.cache.decrypt_cache_size > 0 && self.cache.decrypt_cache_ttl == 0 {
+            anyhow::bail!("解密结果缓存有效期必须大于0");
+        }
+        match self.cache.backend {
+            CacheBackendType::File => {
+                for dir in std::iter::once(&self.cache.cache_dir).chain(self.failover.cache_secondary_dir.as_ref()) {
+                    check_dir_writable(dir)
+                        .map_err(|e| anyhow::anyhow!("缓存目录 {} 不可写: {}", dir, e))?;
+                }
+            },
+            CacheBackendType::Memory => {
+                if self.cache.memory_capacity == 0 {
+                    anyhow::bail!("内存缓存最大条目数必须大于0");
+                }
+            },
+        }
+
+        // 审计日志所在目录必须可写
+        if let Some(ref path) = self.audit.log_path {
+            let dir = Path::new(path).parent()
+                .map(|dir| dir.to_string_lossy().to_string())
+                .filter(|dir| !dir.is_empty())
+                .unwrap_or_else(|| ".".to_string());
+            check_dir_writable(&dir)
+                .map_err(|e| anyhow::anyhow!("审计日志目录 {} 不可写: {}", dir, e))?;
+        }
+
+        // 验证跨域配置
+        for origin in &self.cors.allowed_origins {
+            if origin != "*" && (HeaderValue::from_str(origin).is_err() || !origin.contains("://")) {
+                anyhow::bail!("无效的CORS来源: {}，应为 scheme://host[:port] 或 *", origin);
+            }
+        }
+        if self.cors.allow_credentials && self.cors.allowed_origins.iter().any(|o| o == "*") {
+            anyhow::bail!("允许携带凭据时CORS_ALLOWED_ORIGINS不能为*，请列出具体来源");
+        }
+        for method in &self.cors.allowed_methods {
+            reqwest::Method::from_bytes(method.as_bytes())
+                .map_err(|_| anyhow::anyhow!("无效的CORS请求方法: {}", method))?;
+        }
+        for header in &self.cors.allowed_headers {
+            HeaderName::from_bytes(header.as_bytes())
+                .map_err(|_| anyhow::anyhow!("无效的CORS请求头: {}", header))?;
+        }
+
+        // 启用HTTPS时证书和私钥必须存在且可读
+        if self.server.https {
+            for (name, path) in [("TLS_CERT_PATH", &self.server.tls_cert_path), ("TLS_KEY_PATH", &self.server.tls_key_path)] {
+                let path = path.as_ref()
+                    .ok_or_else(|| anyhow::anyhow!("启用HTTPS时必须配置{}", name))?;
+                std::fs::File::open(path)
+                    .map_err(|e| anyhow::anyhow!("无法读取{}指定的文件 {}: {}", name, path, e))?;
+            }
+        }
+
+        // 验证资源ID格式
+        if let Some(ref pattern) = self.resource_id.pattern {
+            regex::Regex::new(pattern)
+                .map_err(|e| anyhow::anyhow!("无效的资源ID格式 {}: {}", pattern, e))?;
+        }
+
+        // 验证请求体大小限制
+        if self.server.max_body_size == 0 || self.server.max_batch_body_size == 0 {
+            anyhow::bail!("最大请求体字节数必须大于0");
+        }
+
+        // 验证管理接口令牌长度
+        if let Some(ref token) = self.server.admin_token
+            && token.len() < 16
+        {
+            anyhow::bail!("管理接口令牌长度至少为16个字符");
+        }
+
         // 验证JWT密钥长度
         if self.jwt.secret.len() < 16 {
             anyhow::bail!("JWT密钥长度至少为16个字符");
@@ -307,9 +1699,21 @@ impl AppConfig {
                 anyhow::bail!("CRUD API实例ID不能为空");
             }
             if instance.url.is_empty() {
-                anyhow::bail!("CRUD API实例URL不能为空");
+                anyhow::bail!("CRUD API实例 {} 的URL不能为空", instance.id);
+            }
+            validate_instance_url(&instance.id, &instance.url)?;
+            if !instance.health_path.starts_with('/') {
+                anyhow::bail!("CRUD API实例 {} 的健康检查路径必须以/开头: {}", instance.id, instance.health_path);
+            }
+            if let Some(status) = instance.health_expect.status
+                && !(100..=599).contains(&status)
+            {
+                anyhow::bail!("CRUD API实例 {} 的健康检查状态码无效: {}", instance.id, status);
+            }
+            if instance.weight == 0 {
+                anyhow::bail!("CRUD API实例 {} 的权重必须大于0", instance.id);
             }
-            let valid_instance_types = vec!["read", "write", "mixed"];
+            let valid_instance_types = ["read", "write", "mixed"];
             if !valid_instance_types.contains(&instance.instance_type.as_str()) {
                 anyhow::bail!("无效的CRUD API实例类型: {}", instance.instance_type);
             }
@@ -335,7 +1739,7 @@ impl AppConfig {
             },
             SchedulerStrategy::LoadBalance => {
                 // 负载均衡模式需要至少一个实例
-                if self.crud_api.instances.len() < 1 {
+                if self.crud_api.instances.is_empty() {
                     anyhow::bail!("负载均衡模式需要至少一个CRUD API实例");
                 }
             },
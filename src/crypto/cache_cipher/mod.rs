@@ -0,0 +1,69 @@
+use std::fmt;
+
+use aes_gcm::Aes256Gcm;
+use aes_gcm::aead::{self, Aead, KeyInit};
+use anyhow::Result;
+use base64::{Engine as _, engine::general_purpose};
+use hkdf::Hkdf;
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+use super::NONCE_LEN;
+
+/// 缓存密钥派生的上下文信息，与业务数据密钥区分
+const CACHE_KEY_INFO: &[u8] = b"encryption-service cache";
+
+/// 缓存文件加密器
+///
+/// 由缓存密钥一次性派生AES-256-GCM密钥，每行使用随机nonce加密，输出 base64(nonce | 密文)
+pub struct CacheCipher {
+    cipher: Aes256Gcm,
+}
+
+impl fmt::Debug for CacheCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CacheCipher").finish_non_exhaustive()
+    }
+}
+
+impl CacheCipher {
+    /// 由缓存密钥创建加密器
+    pub fn new(secret: &str) -> Result<Self> {
+        let mut key = Zeroizing::new([0u8; 32]);
+        Hkdf::<Sha256>::new(None, secret.as_bytes())
+            .expand(CACHE_KEY_INFO, key.as_mut())
+            .map_err(|e| anyhow::anyhow!("缓存密钥派生失败: {:?}", e))?;
+        let cipher = Aes256Gcm::new_from_slice(key.as_ref())
+            .map_err(|e| anyhow::anyhow!("无法创建缓存加密器: {:?}", e))?;
+        Ok(Self { cipher })
+    }
+
+    /// 加密一行缓存数据
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<String> {
+        let mut nonce_bytes = [0u8; NONCE_LEN];
+        getrandom::getrandom(&mut nonce_bytes)
+            .map_err(|e| anyhow::anyhow!("生成随机nonce失败: {:?}", e))?;
+        let nonce = aead::Nonce::<Aes256Gcm>::from_slice(&nonce_bytes);
+
+        let ciphertext = self.cipher.encrypt(nonce, plaintext)
+            .map_err(|e| anyhow::anyhow!("缓存数据加密失败: {:?}", e))?;
+
+        let mut combined = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+        combined.extend_from_slice(&nonce_bytes);
+        combined.extend_from_slice(&ciphertext);
+        Ok(general_purpose::STANDARD.encode(combined))
+    }
+
+    /// 解密一行缓存数据
+    pub fn decrypt(&self, encoded: &str) -> Result<Vec<u8>> {
+        let combined = general_purpose::STANDARD.decode(encoded)?;
+        if combined.len() < NONCE_LEN {
+            anyhow::bail!("缓存数据长度不足");
+        }
+        let (nonce_bytes, ciphertext) = combined.split_at(NONCE_LEN);
+        let nonce = aead::Nonce::<Aes256Gcm>::from_slice(nonce_bytes);
+
+        self.cipher.decrypt(nonce, ciphertext)
+            .map_err(|e| anyhow::anyhow!("缓存数据解密失败，缓存密钥可能已变更: {:?}", e))
+    }
+}
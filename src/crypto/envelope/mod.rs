@@ -0,0 +1,428 @@
+use std::io::{Read, Write};
+
+use anyhow::Result;
+use flate2::Compression;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use sha2::{Digest, Sha256};
+
+use super::CryptoError;
+use crate::config::PaddingScheme;
+
+/// 信封魔数
+pub const MAGIC: &[u8; 3] = b"ESV";
+/// 信封版本1：只有头部作为附加认证数据
+pub const VERSION_HEADER_AAD: u8 = 1;
+/// 当前信封版本：附加认证数据还包含资源类型，密文只能以加密时的资源类型解密
+pub const VERSION: u8 = 2;
+
+/// 算法ID：AES-256-GCM
+pub const ALGORITHM_AES_256_GCM: u8 = 1;
+/// 算法ID：ChaCha20-Poly1305
+pub const ALGORITHM_CHACHA20_POLY1305: u8 = 2;
+/// 算法ID：AES-256-CBC + HMAC-SHA256
+pub const ALGORITHM_AES_256_CBC_HMAC: u8 = 3;
+
+/// 密钥派生ID：HKDF-SHA256
+pub const KDF_HKDF_SHA256: u8 = 1;
+/// 密钥派生ID：PBKDF2-HMAC-SHA256
+pub const KDF_PBKDF2_SHA256: u8 = 2;
+/// 密钥派生ID：Argon2id
+pub const KDF_ARGON2ID: u8 = 3;
+
+/// 标志位：明文末尾附带校验和
+pub const FLAG_CHECKSUM: u8 = 0b0000_0001;
+
+/// 标志位：明文经过填充
+pub const FLAG_PADDED: u8 = 0b0000_0010;
+
+/// 标志位：头部记录了资源类型
+pub const FLAG_RESOURCE_TYPE: u8 = 0b0000_0100;
+
+/// 标志位：头部携带随机盐值
+pub const FLAG_SALT: u8 = 0b0000_1000;
+
+/// 标志位：头部记录了参与密钥派生的盐值标识
+pub const FLAG_SALT_ID: u8 = 0b0001_0000;
+
+/// 标志位：头部记录了密钥派生参数
+pub const FLAG_KDF_PARAMS: u8 = 0b0010_0000;
+
+/// 标志位：明文经过zlib压缩
+pub const FLAG_COMPRESSED: u8 = 0b0100_0000;
+
+/// 标志位：密钥由主密钥按随机盐值扩展得到，主密钥以固定盐值派生，可以被派生密钥缓存复用
+pub const FLAG_MASTER_KEY: u8 = 0b1000_0000;
+
+// 8个标志位已全部使用，再增加标志需要提升信封版本
+
+/// 随机盐值长度
+pub const SALT_LEN: usize = 16;
+
+/// 明文校验和长度（截断的SHA-256）
+pub const CHECKSUM_LEN: usize = 8;
+
+/// 密钥派生参数段长度：内存(4) | 迭代次数(4) | 并行度(4)
+pub const KDF_PARAMS_LEN: usize = 12;
+
+/// Argon2内存开销上限（KiB），解密时拒绝超过上限的参数，避免按密文指定的参数耗尽内存
+pub const MAX_ARGON2_MEMORY: u32 = 256 * 1024;
+/// Argon2迭代次数上限
+pub const MAX_ARGON2_ITERATIONS: u32 = 64;
+/// Argon2并行度上限
+pub const MAX_ARGON2_PARALLELISM: u32 = 64;
+
+/// PBKDF2迭代次数上限，解密时拒绝超过上限的参数，避免按密文指定的迭代次数占满CPU
+pub const MAX_PBKDF2_ITERATIONS: u32 = 10_000_000;
+
+/// 密钥派生参数：Argon2id使用全部字段，PBKDF2只使用迭代次数，其余字段为0
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KdfParams {
+    /// 内存开销（KiB）
+    pub memory: u32,
+    /// 迭代次数
+    pub iterations: u32,
+    /// 并行度
+    pub parallelism: u32,
+}
+
+impl KdfParams {
+    /// PBKDF2参数，只记录迭代次数
+    pub fn pbkdf2(iterations: u32) -> Self {
+        Self {
+            memory: 0,
+            iterations,
+            parallelism: 0,
+        }
+    }
+
+    /// 按密钥派生算法检查参数是否在允许的范围内
+    pub fn check(&self, kdf: u8) -> Result<()> {
+        if kdf == KDF_PBKDF2_SHA256 {
+            if self.iterations == 0 || self.iterations > MAX_PBKDF2_ITERATIONS {
+                return Err(CryptoError::InvalidEnvelope(format!(
+                    "PBKDF2迭代次数无效: {}，允许范围为 1 到 {}", self.iterations, MAX_PBKDF2_ITERATIONS
+                )).into());
+            }
+            return Ok(());
+        }
+        if self.memory > MAX_ARGON2_MEMORY || self.iterations > MAX_ARGON2_ITERATIONS || self.parallelism > MAX_ARGON2_PARALLELISM {
+            return Err(CryptoError::InvalidEnvelope(format!(
+                "Argon2参数超过上限: 内存 {} KiB，迭代 {} 次，并行度 {}", self.memory, self.iterations, self.parallelism
+            )).into());
+        }
+        Ok(())
+    }
+}
+
+/// 密文信封头部
+///
+/// 布局：`魔数(3) | 版本(1) | 算法ID(1) | KDF ID(1) | 标志位(1) | [资源类型长度(1) | 资源类型] | [盐值(16)] | [盐值标识长度(1) | 盐值标识] | [密钥派生参数(12)]`，
+/// 资源类型段仅在设置了 `FLAG_RESOURCE_TYPE` 时存在，盐值段仅在设置了 `FLAG_SALT` 时存在，盐值标识段仅在设置了 `FLAG_SALT_ID` 时存在，
+/// 密钥派生参数段仅在设置了 `FLAG_KDF_PARAMS` 时存在。
+/// AEAD算法的头部之后依次为nonce和密文，头部整体作为附加认证数据；AES-256-CBC-HMAC的头部之后依次为
+/// IV、密文和HMAC标签，标签覆盖附加认证数据、IV和密文。篡改头部或以其他资源类型解密会导致解密失败。
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvelopeHeader {
+    /// 信封版本
+    pub version: u8,
+    /// 算法ID
+    pub algorithm: u8,
+    /// 密钥派生ID
+    pub kdf: u8,
+    /// 标志位
+    pub flags: u8,
+    /// 加密时记录的资源类型
+    pub resource_type: Option<String>,
+    /// 加密时使用的随机盐值
+    pub salt: Option<[u8; SALT_LEN]>,
+    /// 加密时与随机盐值一起参与密钥派生的资源类型盐值标识
+    pub salt_id: Option<String>,
+    /// 加密时使用的密钥派生参数
+    pub kdf_params: Option<KdfParams>,
+}
+
+impl EnvelopeHeader {
+    /// 头部固定部分长度
+    pub const LEN: usize = MAGIC.len() + 4;
+
+    /// 创建当前版本的信封头部
+    pub fn new(algorithm: u8, kdf: u8, flags: u8) -> Self {
+        Self {
+            version: VERSION,
+            algorithm,
+            kdf,
+            flags,
+            resource_type: None,
+            salt: None,
+            salt_id: None,
+            kdf_params: None,
+        }
+    }
+
+    /// 在头部记录资源类型
+    pub fn with_resource_type(mut self, resource_type: &str) -> Result<Self> {
+        if resource_type.len() > u8::MAX as usize {
+            return Err(CryptoError::InvalidEnvelope(format!("资源类型过长: {} 字节", resource_type.len())).into());
+        }
+        self.flags |= FLAG_RESOURCE_TYPE;
+        self.resource_type = Some(resource_type.to_string());
+        Ok(self)
+    }
+
+    /// 是否设置了指定标志位
+    pub fn has_flag(&self, flag: u8) -> bool {
+        self.flags & flag != 0
+    }
+
+    /// 在头部记录随机盐值
+    pub fn with_salt(mut self, salt: [u8; SALT_LEN]) -> Self {
+        self.flags |= FLAG_SALT;
+        self.salt = Some(salt);
+        self
+    }
+
+    /// 在头部记录资源类型盐值标识
+    pub fn with_salt_id(mut self, salt_id: &str) -> Result<Self> {
+        if salt_id.len() > u8::MAX as usize {
+            return Err(CryptoError::InvalidEnvelope(format!("盐值标识过长: {} 字节", salt_id.len())).into());
+        }
+        self.flags |= FLAG_SALT_ID;
+        self.salt_id = Some(salt_id.to_string());
+        Ok(self)
+    }
+
+    /// 在头部记录密钥派生参数
+    pub fn with_kdf_params(mut self, params: KdfParams) -> Self {
+        self.flags |= FLAG_KDF_PARAMS;
+        self.kdf_params = Some(params);
+        self
+    }
+
+    /// 序列化头部
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::LEN);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&[self.version, self.algorithm, self.kdf, self.flags]);
+        if let Some(ref resource_type) = self.resource_type {
+            bytes.push(resource_type.len() as u8);
+            bytes.extend_from_slice(resource_type.as_bytes());
+        }
+        if let Some(ref salt) = self.salt {
+            bytes.extend_from_slice(salt);
+        }
+        if let Some(ref salt_id) = self.salt_id {
+            bytes.push(salt_id.len() as u8);
+            bytes.extend_from_slice(salt_id.as_bytes());
+        }
+        if let Some(ref params) = self.kdf_params {
+            bytes.extend_from_slice(&params.memory.to_be_bytes());
+            bytes.extend_from_slice(&params.iterations.to_be_bytes());
+            bytes.extend_from_slice(&params.parallelism.to_be_bytes());
+        }
+        bytes
+    }
+
+    /// 解析头部，返回头部和剩余数据；不以魔数开头的数据视为旧版无头部格式，返回None
+    pub fn parse(data: &[u8]) -> Result<Option<(Self, &[u8])>> {
+        if !data.starts_with(MAGIC) {
+            return Ok(None);
+        }
+        if data.len() < Self::LEN {
+            return Err(CryptoError::InvalidEnvelope("头部长度不足".to_string()).into());
+        }
+
+        let mut header = Self {
+            version: data[MAGIC.len()],
+            algorithm: data[MAGIC.len() + 1],
+            kdf: data[MAGIC.len() + 2],
+            flags: data[MAGIC.len() + 3],
+            resource_type: None,
+            salt: None,
+            salt_id: None,
+            kdf_params: None,
+        };
+        if header.version == 0 || header.version > VERSION {
+            return Err(CryptoError::InvalidEnvelope(format!("不支持的信封版本: {}", header.version)).into());
+        }
+
+        let mut rest = &data[Self::LEN..];
+        if header.has_flag(FLAG_RESOURCE_TYPE) {
+            let (&len, tail) = rest.split_first()
+                .ok_or_else(|| CryptoError::InvalidEnvelope("缺少资源类型段".to_string()))?;
+            if tail.len() < len as usize {
+                return Err(CryptoError::InvalidEnvelope("资源类型段长度不足".to_string()).into());
+            }
+            let (resource_type, tail) = tail.split_at(len as usize);
+            header.resource_type = Some(String::from_utf8(resource_type.to_vec())
+                .map_err(|_| CryptoError::InvalidEnvelope("资源类型不是有效的UTF-8".to_string()))?);
+            rest = tail;
+        }
+        if header.has_flag(FLAG_SALT) {
+            if rest.len() < SALT_LEN {
+                return Err(CryptoError::InvalidEnvelope("盐值段长度不足".to_string()).into());
+            }
+            let (salt, tail) = rest.split_at(SALT_LEN);
+            let mut salt_bytes = [0u8; SALT_LEN];
+            salt_bytes.copy_from_slice(salt);
+            header.salt = Some(salt_bytes);
+            rest = tail;
+        }
+        if header.has_flag(FLAG_SALT_ID) {
+            let (&len, tail) = rest.split_first()
+                .ok_or_else(|| CryptoError::InvalidEnvelope("缺少盐值标识段".to_string()))?;
+            if tail.len() < len as usize {
+                return Err(CryptoError::InvalidEnvelope("盐值标识段长度不足".to_string()).into());
+            }
+            let (salt_id, tail) = tail.split_at(len as usize);
+            header.salt_id = Some(String::from_utf8(salt_id.to_vec())
+                .map_err(|_| CryptoError::InvalidEnvelope("盐值标识不是有效的UTF-8".to_string()))?);
+            rest = tail;
+        }
+        if header.has_flag(FLAG_KDF_PARAMS) {
+            if rest.len() < KDF_PARAMS_LEN {
+                return Err(CryptoError::InvalidEnvelope("密钥派生参数段长度不足".to_string()).into());
+            }
+            let (params, tail) = rest.split_at(KDF_PARAMS_LEN);
+            let read_u32 = |offset: usize| u32::from_be_bytes([params[offset], params[offset + 1], params[offset + 2], params[offset + 3]]);
+            let params = KdfParams {
+                memory: read_u32(0),
+                iterations: read_u32(4),
+                parallelism: read_u32(8),
+            };
+            params.check(header.kdf)?;
+            header.kdf_params = Some(params);
+            rest = tail;
+        }
+
+        Ok(Some((header, rest)))
+    }
+}
+
+/// 计算附加认证数据：版本1为头部；版本2起在头部后追加资源类型（8字节大端长度前缀）
+pub fn associated_data(header_bytes: &[u8], version: u8, resource_type: &str) -> Vec<u8> {
+    let mut aad = header_bytes.to_vec();
+    if version > VERSION_HEADER_AAD {
+        aad.extend_from_slice(&(resource_type.len() as u64).to_be_bytes());
+        aad.extend_from_slice(resource_type.as_bytes());
+    }
+    aad
+}
+
+/// 获取算法ID对应的算法名称
+pub fn algorithm_name(algorithm: u8) -> &'static str {
+    match algorithm {
+        ALGORITHM_AES_256_GCM => "AES-256-GCM",
+        ALGORITHM_CHACHA20_POLY1305 => "ChaCha20-Poly1305",
+        ALGORITHM_AES_256_CBC_HMAC => "AES-256-CBC-HMAC-SHA256",
+        _ => "未知算法",
+    }
+}
+
+/// 获取加密算法配置名称对应的ID
+pub fn algorithm_id(algorithm: &str) -> Option<u8> {
+    match algorithm {
+        "aes-256-gcm" => Some(ALGORITHM_AES_256_GCM),
+        "chacha20-poly1305" => Some(ALGORITHM_CHACHA20_POLY1305),
+        "aes-256-cbc-hmac" => Some(ALGORITHM_AES_256_CBC_HMAC),
+        _ => None,
+    }
+}
+
+/// 获取密钥派生算法ID对应的名称
+pub fn kdf_name(kdf: u8) -> &'static str {
+    match kdf {
+        KDF_HKDF_SHA256 => "HKDF-SHA256",
+        KDF_PBKDF2_SHA256 => "PBKDF2-SHA256",
+        KDF_ARGON2ID => "Argon2id",
+        _ => "未知算法",
+    }
+}
+
+/// 获取密钥派生配置名称对应的ID
+pub fn kdf_id(key_derivation: &str) -> Option<u8> {
+    match key_derivation {
+        "hkdf" => Some(KDF_HKDF_SHA256),
+        "pbkdf2" => Some(KDF_PBKDF2_SHA256),
+        "argon2id" => Some(KDF_ARGON2ID),
+        _ => None,
+    }
+}
+
+/// 计算明文校验和
+pub fn checksum(data: &[u8]) -> [u8; CHECKSUM_LEN] {
+    let digest = Sha256::digest(data);
+    let mut checksum = [0u8; CHECKSUM_LEN];
+    checksum.copy_from_slice(&digest[..CHECKSUM_LEN]);
+    checksum
+}
+
+/// 按填充方案填充明文：追加0x80后补0至目标长度，保证可以无歧义地去除
+pub fn pad(payload: &mut Vec<u8>, scheme: &PaddingScheme) {
+    let min_len = payload.len() + 1;
+    let target_len = match scheme {
+        PaddingScheme::None => return,
+        PaddingScheme::PowerOfTwo => min_len.next_power_of_two(),
+        PaddingScheme::Block(block) => min_len.div_ceil(*block) * block,
+    };
+    payload.push(0x80);
+    payload.resize(target_len, 0);
+}
+
+/// 去除填充
+pub fn unpad(payload: &mut Vec<u8>) -> Result<()> {
+    let marker = payload.iter().rposition(|&b| b != 0)
+        .filter(|&i| payload[i] == 0x80)
+        .ok_or_else(|| CryptoError::InvalidEnvelope("无效的明文填充".to_string()))?;
+    payload.truncate(marker);
+    Ok(())
+}
+
+/// 以zlib压缩明文
+pub fn compress(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::with_capacity(data.len() / 2), Compression::default());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+/// 解压明文，解压后超过limit字节时停止解压并返回错误，避免压缩炸弹耗尽内存
+pub fn decompress(data: &[u8], limit: usize) -> Result<Vec<u8>> {
+    let mut plaintext = Vec::new();
+    ZlibDecoder::new(data)
+        .take(limit as u64 + 1)
+        .read_to_end(&mut plaintext)
+        .map_err(|e| CryptoError::InvalidEnvelope(format!("明文解压失败: {}", e)))?;
+    if plaintext.len() > limit {
+        return Err(CryptoError::TooLarge { limit }.into());
+    }
+    Ok(plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_params(kdf: u8, params: KdfParams) -> Result<Option<KdfParams>> {
+        let bytes = EnvelopeHeader::new(ALGORITHM_AES_256_GCM, kdf, 0).with_kdf_params(params).to_bytes();
+        let (header, _) = EnvelopeHeader::parse(&bytes)?.unwrap();
+        Ok(header.kdf_params)
+    }
+
+    #[test]
+    fn parses_pbkdf2_iterations() {
+        let params = KdfParams::pbkdf2(600_000);
+        assert_eq!(parse_params(KDF_PBKDF2_SHA256, params).unwrap(), Some(params));
+    }
+
+    #[test]
+    fn rejects_pbkdf2_iterations_out_of_range() {
+        assert!(parse_params(KDF_PBKDF2_SHA256, KdfParams::pbkdf2(0)).is_err());
+        assert!(parse_params(KDF_PBKDF2_SHA256, KdfParams::pbkdf2(MAX_PBKDF2_ITERATIONS + 1)).is_err());
+    }
+
+    #[test]
+    fn rejects_argon2_params_over_limit() {
+        let params = KdfParams { memory: MAX_ARGON2_MEMORY + 1, iterations: 2, parallelism: 1 };
+        assert!(parse_params(KDF_ARGON2ID, params).is_err());
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+/// 缓存键，由进程内随机密钥对密码和派生参数做HMAC得到，不可逆
+type CacheKey = [u8; 32];
+
+/// 缓存的派生密钥，移除时自动清零
+struct CachedKey {
+    key: Zeroizing<Vec<u8>>,
+    inserted_at: Instant,
+}
+
+/// 缓存内容
+#[derive(Default)]
+struct KeyCacheState {
+    entries: HashMap<CacheKey, CachedKey>,
+    /// 访问顺序，队首为最久未使用
+    order: VecDeque<CacheKey>,
+}
+
+/// 派生密钥缓存（有界LRU + TTL）
+///
+/// 只缓存派生出的密钥，不保存密码；缓存键是带进程随机密钥的HMAC，无法反推出密码。
+pub struct KeyCache {
+    /// 最大缓存条目数
+    capacity: usize,
+    /// 缓存有效期
+    ttl: Duration,
+    /// 计算缓存键的随机密钥
+    hash_key: Zeroizing<[u8; 32]>,
+    /// 缓存内容
+    state: Mutex<KeyCacheState>,
+}
+
+impl fmt::Debug for KeyCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyCache")
+            .field("capacity", &self.capacity)
+            .field("ttl", &self.ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+impl KeyCache {
+    /// 创建新的派生密钥缓存
+    pub fn new(capacity: usize, ttl: Duration) -> Result<Self> {
+        let mut hash_key = Zeroizing::new([0u8; 32]);
+        getrandom::getrandom(hash_key.as_mut())
+            .map_err(|e| anyhow::anyhow!("生成密钥缓存随机密钥失败: {:?}", e))?;
+        Ok(Self {
+            capacity,
+            ttl,
+            hash_key,
+            state: Mutex::new(KeyCacheState::default()),
+        })
+    }
+
+    /// 计算缓存键
+    fn cache_key(&self, profile: &[u8], salt: &[u8], password: &str) -> CacheKey {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(self.hash_key.as_ref())
+            .expect("HMAC接受任意长度密钥");
+        // 各字段带长度前缀，避免拼接歧义
+        for part in [profile, salt, password.as_bytes()] {
+            mac.update(&(part.len() as u64).to_be_bytes());
+            mac.update(part);
+        }
+        mac.finalize().into_bytes().into()
+    }
+
+    /// 当前缓存的条目数
+    #[cfg(test)]
+    pub fn entry_count(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+
+    /// 获取缓存的派生密钥，未命中时调用derive派生并写入缓存
+    pub fn get_or_derive<F>(&self, profile: &[u8], salt: &[u8], password: &str, derive: F) -> Result<Zeroizing<Vec<u8>>>
+    where
+        F: FnOnce() -> Result<Zeroizing<Vec<u8>>>,
+    {
+        let cache_key = self.cache_key(profile, salt, password);
+
+        {
+            let mut state = self.state.lock().unwrap();
+            let expired = match state.entries.get(&cache_key) {
+                Some(cached) if cached.inserted_at.elapsed() < self.ttl => {
+                    let key = cached.key.clone();
+                    // 命中后移到队尾
+                    state.order.retain(|k| *k != cache_key);
+                    state.order.push_back(cache_key);
+                    return Ok(key);
+                },
+                Some(_) => true,
+                None => false,
+            };
+            if expired {
+                state.entries.remove(&cache_key);
+                state.order.retain(|k| *k != cache_key);
+            }
+        }
+
+        // 派生密钥时不持有锁
+        let key = derive()?;
+
+        let mut state = self.state.lock().unwrap();
+        if !state.entries.contains_key(&cache_key) {
+            // 超出容量时淘汰最久未使用的条目，密钥在移除时清零
+            while state.entries.len() >= self.capacity {
+                match state.order.pop_front() {
+                    Some(oldest) => {
+                        state.entries.remove(&oldest);
+                    },
+                    None => break,
+                }
+            }
+            state.order.push_back(cache_key);
+        }
+        state.entries.insert(cache_key, CachedKey {
+            key: key.clone(),
+            inserted_at: Instant::now(),
+        });
+
+        Ok(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    /// 统计派生次数的派生函数
+    fn counting_derive<'a>(calls: &'a Cell<usize>, key: &'a [u8]) -> impl FnOnce() -> Result<Zeroizing<Vec<u8>>> + 'a {
+        move || {
+            calls.set(calls.get() + 1);
+            Ok(Zeroizing::new(key.to_vec()))
+        }
+    }
+
+    #[test]
+    fn repeated_password_hits_cache() {
+        let cache = KeyCache::new(8, Duration::from_secs(60)).unwrap();
+        let calls = Cell::new(0);
+
+        let first = cache.get_or_derive(b"hkdf", b"salt", "password", counting_derive(&calls, b"key-1")).unwrap();
+        let second = cache.get_or_derive(b"hkdf", b"salt", "password", counting_derive(&calls, b"key-2")).unwrap();
+        assert_eq!((first.as_slice(), second.as_slice()), (b"key-1".as_slice(), b"key-1".as_slice()));
+        assert_eq!(calls.get(), 1);
+
+        // 盐值或派生参数不同时不能复用
+        cache.get_or_derive(b"hkdf", b"other", "password", counting_derive(&calls, b"key-3")).unwrap();
+        cache.get_or_derive(b"pbkdf2", b"salt", "password", counting_derive(&calls, b"key-4")).unwrap();
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn expired_entry_is_derived_again() {
+        let cache = KeyCache::new(8, Duration::ZERO).unwrap();
+        let calls = Cell::new(0);
+
+        cache.get_or_derive(b"hkdf", b"salt", "password", counting_derive(&calls, b"key")).unwrap();
+        cache.get_or_derive(b"hkdf", b"salt", "password", counting_derive(&calls, b"key")).unwrap();
+        assert_eq!(calls.get(), 2);
+        assert_eq!(cache.state.lock().unwrap().entries.len(), 1);
+    }
+
+    #[test]
+    fn least_recently_used_entry_is_evicted() {
+        let cache = KeyCache::new(2, Duration::from_secs(60)).unwrap();
+        let calls = Cell::new(0);
+
+        cache.get_or_derive(b"hkdf", b"salt", "a", counting_derive(&calls, b"key-a")).unwrap();
+        cache.get_or_derive(b"hkdf", b"salt", "b", counting_derive(&calls, b"key-b")).unwrap();
+        // 访问a后b成为最久未使用的条目
+        cache.get_or_derive(b"hkdf", b"salt", "a", counting_derive(&calls, b"key-a")).unwrap();
+        cache.get_or_derive(b"hkdf", b"salt", "c", counting_derive(&calls, b"key-c")).unwrap();
+        assert_eq!(calls.get(), 3);
+
+        {
+            let state = cache.state.lock().unwrap();
+            assert_eq!(state.entries.len(), 2);
+            assert_eq!(state.order.len(), 2);
+            assert!(state.entries.values().all(|cached| cached.key.as_slice() != b"key-b"));
+        }
+        cache.get_or_derive(b"hkdf", b"salt", "b", counting_derive(&calls, b"key-b")).unwrap();
+        assert_eq!(calls.get(), 4);
+    }
+
+    #[test]
+    fn cached_keys_are_zeroized_on_drop() {
+        fn assert_zeroize_on_drop<T: zeroize::ZeroizeOnDrop>() {}
+        // 淘汰和过期移除条目时丢弃CachedKey，密钥随之清零
+        assert_zeroize_on_drop::<Zeroizing<Vec<u8>>>();
+    }
+}
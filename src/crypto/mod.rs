@@ -1,79 +1,510 @@
 use anyhow::Result;
 use base64::{Engine as _, engine::general_purpose};
-use aes_gcm::{Aes256Gcm, Key, Nonce};
-use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::Aes256Gcm;
+use aes_gcm::aead::{self, Aead, KeyInit, Payload};
+use cbc::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit, block_padding::Pkcs7};
+use chacha20poly1305::ChaCha20Poly1305;
 use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use argon2::Argon2;
+use pbkdf2::pbkdf2_hmac;
 use sha2::Sha256;
+use std::collections::HashMap;
 use std::convert::TryInto;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+use zeroize::Zeroizing;
+
+use crate::config::{Argon2AllocFailurePolicy, Base64Variant, EncryptionConfig, PaddingScheme};
+use crate::monitoring;
+
+mod cache_cipher;
+mod envelope;
+mod key_cache;
+mod rng;
+mod stream;
+
+pub use cache_cipher::CacheCipher;
+use envelope::{EnvelopeHeader, KdfParams};
+use key_cache::KeyCache;
+pub use rng::{OsRngSource, RngSource};
+pub use stream::{MAX_CHUNK_SIZE as MAX_STREAM_CHUNK_SIZE, StreamDecryptor, StreamEncryptor};
+pub use envelope::{MAX_ARGON2_ITERATIONS, MAX_ARGON2_MEMORY, MAX_ARGON2_PARALLELISM, MAX_PBKDF2_ITERATIONS};
+#[cfg(any(test, feature = "test-rng"))]
+pub use rng::SeededRngSource;
+
+/// nonce长度
+const NONCE_LEN: usize = 12;
+
+/// AES-256-CBC的IV长度
+const CBC_IV_LEN: usize = 16;
+/// AES分组长度
+const CBC_BLOCK_LEN: usize = 16;
+/// HMAC-SHA256认证标签长度
+const CBC_TAG_LEN: usize = 32;
+/// 派生AES-256-CBC加密密钥的HKDF info
+const CBC_ENC_KEY_INFO: &[u8] = b"aes-256-cbc-hmac encryption key";
+/// 派生HMAC-SHA256密钥的HKDF info
+const CBC_MAC_KEY_INFO: &[u8] = b"aes-256-cbc-hmac mac key";
+
+/// 分配指定块数的Argon2工作内存，无法分配时返回None
+type BlockAllocator = fn(usize) -> Option<Vec<argon2::Block>>;
+
+/// 按块数分配Argon2工作内存，分配失败时返回None而不是终止进程
+fn allocate_blocks(count: usize) -> Option<Vec<argon2::Block>> {
+    let mut blocks = Vec::new();
+    blocks.try_reserve_exact(count).ok()?;
+    blocks.resize(count, argon2::Block::default());
+    Some(blocks)
+}
+
+/// 加密相关错误
+#[derive(Debug, thiserror::Error)]
+pub enum CryptoError {
+    /// 密文信封格式无效
+    #[error("无效的密文信封: {0}")]
+    InvalidEnvelope(String),
+    /// 认证标签校验失败，通常是密码错误或密文被篡改
+    #[error("{0}解密失败: 密码错误或密文已被篡改")]
+    AuthenticationFailed(String),
+    /// 解密后明文与信封中记录的校验和不一致
+    #[error("明文校验和不匹配，数据可能已损坏")]
+    ChecksumMismatch,
+    /// 密文的加密算法与当前解密算法不一致
+    #[error("加密算法不匹配: 当前使用 {expected}，密文由 {actual} 加密")]
+    AlgorithmMismatch { expected: String, actual: String },
+    /// 解码或解压后的数据超过允许的最大字节数
+    #[error("数据过大: 解码后超过 {limit} 字节上限")]
+    TooLarge { limit: usize },
+    /// 请求的资源类型与信封中记录的资源类型不一致
+    #[error("资源类型不匹配: 密文属于 {expected}，请求为 {actual}")]
+    ResourceTypeMismatch { expected: String, actual: String },
+    /// 密文记录的盐值标识没有配置对应的资源类型盐值
+    #[error("未配置盐值标识 {0} 对应的资源类型盐值")]
+    UnknownSaltId(String),
+    /// 无法分配Argon2id所需的工作内存
+    #[error("服务内存不足: 无法分配Argon2所需的 {memory} KiB 内存")]
+    InsufficientMemory { memory: u32 },
+    /// 请求的加密算法未启用或不支持，或当前配置不支持请求的操作
+    #[error("{0}")]
+    Unsupported(String),
+    /// 密文不是有效的Base64
+    #[error("密文不是有效的Base64: {0}")]
+    InvalidBase64(base64::DecodeError),
+    /// 解密后的明文不是有效的UTF-8
+    #[error("解密后的明文不是有效的UTF-8")]
+    InvalidUtf8,
+}
 
 /// 加密工具结构体
 #[derive(Debug, Clone)]
 pub struct EncryptionUtils {
+    /// 默认加密算法名称
     algorithm: String,
+    /// 启用的加密算法名称
+    enabled_algorithms: Vec<String>,
+    /// 按资源类型指定的加密算法名称
+    resource_type_algorithms: HashMap<String, String>,
     key_length: u32,
-    #[allow(dead_code)]
+    /// 密钥派生算法ID
+    kdf: u8,
+    /// PBKDF2迭代次数
     iterations: u32,
+    /// 解密时接受的PBKDF2最大迭代次数
+    max_iterations: u32,
+    /// Argon2id参数，加密时记录在信封头部
+    argon2: KdfParams,
+    /// 解密时接受的Argon2id参数上限
+    argon2_max: KdfParams,
+    /// Argon2id无法分配内存时的处理方式
+    argon2_alloc_failure: Argon2AllocFailurePolicy,
+    /// 备用Argon2id参数，处理方式为fallback时用于加密
+    argon2_fallback: KdfParams,
+    /// 分配Argon2id工作内存，测试中替换为模拟分配失败的实现
+    block_allocator: BlockAllocator,
     salt: Vec<u8>,
+    /// 按资源类型配置的盐值，与每次加密的随机盐值一起参与密钥派生
+    resource_type_salts: HashMap<String, Vec<u8>>,
+    /// 是否在信封中附带明文校验和
+    plaintext_checksum: bool,
+    /// 是否在加密前压缩明文
+    compression: bool,
+    /// 启用压缩的最小明文字节数
+    compression_min_size: usize,
+    /// 明文填充方案
+    padding: PaddingScheme,
+    /// 启用填充的资源类型，为空时对所有资源类型生效
+    padding_resource_types: Vec<String>,
+    /// 是否在信封中记录资源类型，并在解密时校验
+    resource_type_guard: bool,
+    /// 是否将资源类型作为附加认证数据，关闭时生成版本1信封
+    bind_resource_type: bool,
+    /// 派生密钥缓存，未启用时为None
+    key_cache: Option<Arc<KeyCache>>,
+    /// 密钥派生耗时告警阈值，未启用时为None
+    kdf_slow_threshold: Option<Duration>,
+    /// 解密时依次尝试的密文Base64变体
+    base64_variants: Vec<Base64Variant>,
+    /// 解码和解压后允许的最大字节数
+    max_decoded_size: usize,
+    /// 生成盐值和nonce的随机数来源
+    rng: Arc<dyn RngSource>,
+    /// 流式加密的分块大小（字节）
+    stream_chunk_size: usize,
 }
 
 impl EncryptionUtils {
     /// 创建新的加密工具实例
-    pub fn new(algorithm: String, key_length: u32, iterations: u32, salt: String) -> Self {
+    pub fn new(config: &EncryptionConfig) -> Self {
+        let key_cache = if config.key_cache_size > 0 {
+            let cache = KeyCache::new(config.key_cache_size, Duration::from_secs(config.key_cache_ttl))
+                .expect("无法创建派生密钥缓存");
+            Some(Arc::new(cache))
+        } else {
+            None
+        };
+
         Self {
-            algorithm,
-            key_length,
-            iterations,
-            salt: salt.into_bytes(),
+            algorithm: config.algorithm.clone(),
+            enabled_algorithms: config.enabled_algorithms.clone(),
+            resource_type_algorithms: config.resource_type_algorithms.clone(),
+            key_length: config.key_length,
+            kdf: envelope::kdf_id(&config.key_derivation).unwrap_or(envelope::KDF_HKDF_SHA256),
+            iterations: config.iterations,
+            max_iterations: config.max_iterations,
+            argon2: KdfParams {
+                memory: config.argon2_memory,
+                iterations: config.argon2_iterations,
+                parallelism: config.argon2_parallelism,
+            },
+            argon2_max: KdfParams {
+                memory: config.argon2_max_memory,
+                iterations: config.argon2_max_iterations,
+                parallelism: config.argon2_max_parallelism,
+            },
+            argon2_alloc_failure: config.argon2_alloc_failure,
+            argon2_fallback: KdfParams {
+                memory: config.argon2_fallback_memory,
+                iterations: config.argon2_fallback_iterations,
+                parallelism: config.argon2_fallback_parallelism,
+            },
+            block_allocator: allocate_blocks,
+            salt: config.salt.clone().into_bytes(),
+            resource_type_salts: config.resource_type_salts.iter()
+                .map(|(resource_type, salt)| (resource_type.clone(), salt.clone().into_bytes()))
+                .collect(),
+            plaintext_checksum: config.plaintext_checksum,
+            compression: config.compression,
+            compression_min_size: config.compression_min_size,
+            padding: config.padding.clone(),
+            padding_resource_types: config.padding_resource_types.clone(),
+            resource_type_guard: config.resource_type_guard,
+            bind_resource_type: config.bind_resource_type,
+            key_cache,
+            kdf_slow_threshold: (config.kdf_slow_threshold_ms > 0)
+                .then(|| Duration::from_millis(config.kdf_slow_threshold_ms)),
+            base64_variants: config.base64_variants.clone(),
+            max_decoded_size: config.max_decoded_size,
+            rng: Arc::new(OsRngSource),
+            stream_chunk_size: config.stream_chunk_size,
         }
     }
 
-    /// 生成加密密钥
-    pub fn generate_key(&self, password: &str) -> Result<Vec<u8>> {
-        // 使用HKDF从密码和盐生成密钥
-        let hkdf = Hkdf::<Sha256>::new(Some(&self.salt), password.as_bytes());
-        let mut key = vec![0u8; self.key_length.try_into()?];
-        hkdf.expand(b"encryption", &mut key)
-            .map_err(|e| anyhow::anyhow!("HKDF密钥生成失败: {:?}", e))?;
+    /// 替换随机数来源，用于测试中生成可复现的密文
+    #[cfg(any(test, feature = "test-rng"))]
+    pub fn with_rng(mut self, rng: Arc<dyn RngSource>) -> Self {
+        self.rng = rng;
+        self
+    }
+
+    /// 替换Argon2工作内存的分配方式，用于测试内存分配失败
+    #[cfg(test)]
+    fn with_block_allocator(mut self, allocator: BlockAllocator) -> Self {
+        self.block_allocator = allocator;
+        self
+    }
+
+    /// 使用配置的密钥派生算法和指定盐值生成加密密钥
+    ///
+    /// 指定了资源类型盐值时，将其追加在盐值之后一起参与派生
+    pub fn generate_key(&self, password: &str, salt: &[u8], type_salt: Option<&[u8]>) -> Result<Zeroizing<Vec<u8>>> {
+        let params = (self.kdf == envelope::KDF_ARGON2ID).then_some(&self.argon2);
+        self.derive_key(self.kdf, params, password, &combine_salt(salt, type_salt))
+    }
+
+    /// 使用指定的密钥派生算法和盐值生成加密密钥，启用缓存时优先从缓存获取
+    ///
+    /// params为信封记录的密钥派生参数，HKDF和未记录参数的旧密文为None
+    fn derive_key(&self, kdf: u8, params: Option<&KdfParams>, password: &str, salt: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+        match self.key_cache {
+            Some(ref cache) => {
+                // 派生参数作为缓存键的一部分，参数不同的密钥互不命中
+                let mut profile = vec![kdf];
+                profile.extend_from_slice(&self.key_length.to_be_bytes());
+                profile.extend_from_slice(&self.iterations.to_be_bytes());
+                if let Some(params) = params {
+                    profile.extend_from_slice(&params.memory.to_be_bytes());
+                    profile.extend_from_slice(&params.iterations.to_be_bytes());
+                    profile.extend_from_slice(&params.parallelism.to_be_bytes());
+                }
+                cache.get_or_derive(&profile, salt, password, || self.derive_key_uncached(kdf, params, password, salt))
+            },
+            None => self.derive_key_uncached(kdf, params, password, salt),
+        }
+    }
+
+    /// 不经过缓存直接派生密钥，并记录派生耗时
+    fn derive_key_uncached(&self, kdf: u8, params: Option<&KdfParams>, password: &str, salt: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+        let started = Instant::now();
+        let key = self.run_kdf(kdf, params, password, salt)?;
+        let elapsed = started.elapsed();
+
+        let elapsed_ms = elapsed.as_millis() as u64;
+        let kdf_name = envelope::kdf_name(kdf);
+        metrics::histogram!(monitoring::KDF_DURATION_SECONDS, "kdf" => kdf_name).record(elapsed.as_secs_f64());
+        debug!(kdf = kdf_name, iterations = self.iterations, elapsed_ms, "密钥派生完成");
+        if let Some(threshold) = self.kdf_slow_threshold
+            && elapsed > threshold
+        {
+            warn!(
+                kdf = kdf_name,
+                iterations = self.iterations,
+                elapsed_ms,
+                threshold_ms = threshold.as_millis() as u64,
+                "密钥派生耗时超过阈值，请检查派生参数配置"
+            );
+        }
+
+        Ok(key)
+    }
+
+    /// 执行密钥派生算法
+    fn run_kdf(&self, kdf: u8, params: Option<&KdfParams>, password: &str, salt: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+        let mut key = Zeroizing::new(vec![0u8; self.key_length.try_into()?]);
+        match kdf {
+            envelope::KDF_HKDF_SHA256 => {
+                // 使用HKDF从密码和盐生成密钥
+                let hkdf = Hkdf::<Sha256>::new(Some(salt), password.as_bytes());
+                hkdf.expand(b"encryption", &mut key)
+                    .map_err(|e| anyhow::anyhow!("HKDF密钥生成失败: {:?}", e))?;
+            },
+            envelope::KDF_PBKDF2_SHA256 => {
+                // 使用PBKDF2生成密钥，优先使用信封记录的迭代次数，未记录的旧密文使用配置的迭代次数
+                let iterations = params.map_or(self.iterations, |params| params.iterations);
+                pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, iterations, &mut key);
+            },
+            envelope::KDF_ARGON2ID => {
+                // 使用信封记录的参数执行Argon2id，参数上限已在解析头部时检查
+                let params = params
+                    .ok_or_else(|| CryptoError::InvalidEnvelope("缺少Argon2参数".to_string()))?;
+                let memory = params.memory;
+                let params = argon2::Params::new(params.memory, params.iterations, params.parallelism, Some(key.len()))
+                    .map_err(|e| CryptoError::InvalidEnvelope(format!("无效的Argon2参数: {}", e)))?;
+                // 自行分配工作内存，分配失败时返回容量错误，而不是像库内分配那样终止进程
+                let Some(mut blocks) = (self.block_allocator)(params.block_count()) else {
+                    metrics::counter!(monitoring::ARGON2_ALLOC_FAILURES_TOTAL).increment(1);
+                    return Err(CryptoError::InsufficientMemory { memory }.into());
+                };
+                Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+                    .hash_password_into_with_memory(password.as_bytes(), salt, &mut key, &mut blocks)
+                    .map_err(|e| anyhow::anyhow!("Argon2密钥生成失败: {}", e))?;
+            },
+            _ => return Err(CryptoError::InvalidEnvelope(format!("未知的密钥派生算法ID: {}", kdf)).into()),
+        }
         Ok(key)
     }
 
-    /// 加密数据
-    pub async fn encrypt(&self, data: &str, password: &str) -> Result<String> {
-        match self.algorithm.as_str() {
-            "aes-256-gcm" => self.encrypt_aes_256_gcm(data, password),
-            _ => anyhow::bail!("不支持的加密算法: {}", self.algorithm),
+    /// 获取资源类型对应的填充方案
+    fn padding_for(&self, resource_type: &str) -> &PaddingScheme {
+        if self.padding_resource_types.is_empty() || self.padding_resource_types.iter().any(|t| t == resource_type) {
+            &self.padding
+        } else {
+            &PaddingScheme::None
         }
     }
 
-    /// 解密数据
-    pub async fn decrypt(&self, encrypted_data: &str, password: &str) -> Result<String> {
-        match self.algorithm.as_str() {
-            "aes-256-gcm" => self.decrypt_aes_256_gcm(encrypted_data, password),
-            _ => anyhow::bail!("不支持的加密算法: {}", self.algorithm),
+    /// 选择加密算法：请求指定的算法优先，其次是资源类型指定的算法，最后是默认算法
+    fn algorithm_for<'a>(&'a self, resource_type: &str, requested: Option<&'a str>) -> Result<&'a str> {
+        let algorithm = requested
+            .or_else(|| self.resource_type_algorithms.get(resource_type).map(String::as_str))
+            .unwrap_or(&self.algorithm);
+        if !self.enabled_algorithms.iter().any(|a| a == algorithm) {
+            return Err(CryptoError::Unsupported(format!("加密算法 {} 未启用", algorithm)).into());
+        }
+        Ok(algorithm)
+    }
+
+    /// 加密字符串数据，algorithm为None时按资源类型或默认配置选择算法
+    pub async fn encrypt(&self, data: &str, password: &str, resource_type: &str, algorithm: Option<&str>) -> Result<String> {
+        self.encrypt_bytes(data.as_bytes(), password, resource_type, algorithm).await
+    }
+
+    /// 解密为字符串数据，明文必须是有效的UTF-8
+    pub async fn decrypt(&self, encrypted_data: &str, password: &str, resource_type: &str) -> Result<String> {
+        let plaintext = self.decrypt_bytes(encrypted_data, password, resource_type).await?;
+        let plaintext = String::from_utf8(plaintext).map_err(|_| CryptoError::InvalidUtf8)?;
+        Ok(plaintext)
+    }
+
+    /// 加密二进制数据，algorithm为None时按资源类型或默认配置选择算法
+    ///
+    /// 密钥派生和加密都是CPU密集操作，在阻塞线程池中执行，避免大量加密请求占满异步运行时的工作线程，
+    /// 导致健康检查等I/O任务得不到调度
+    pub async fn encrypt_bytes(&self, data: &[u8], password: &str, resource_type: &str, algorithm: Option<&str>) -> Result<String> {
+        let utils = self.clone();
+        let data = Zeroizing::new(data.to_vec());
+        let password = Zeroizing::new(password.to_string());
+        let (resource_type, algorithm) = (resource_type.to_string(), algorithm.map(str::to_string));
+        tokio::task::spawn_blocking(move || utils.encrypt_bytes_blocking(&data, &password, &resource_type, algorithm.as_deref())).await?
+    }
+
+    /// 在当前线程加密二进制数据
+    fn encrypt_bytes_blocking(&self, data: &[u8], password: &str, resource_type: &str, algorithm: Option<&str>) -> Result<String> {
+        let algorithm = self.algorithm_for(resource_type, algorithm)?;
+        match envelope::algorithm_id(algorithm) {
+            Some(envelope::ALGORITHM_AES_256_GCM) => self.encrypt_aes_256_gcm(data, password, resource_type),
+            Some(envelope::ALGORITHM_CHACHA20_POLY1305) => self.encrypt_chacha20_poly1305(data, password, resource_type),
+            Some(envelope::ALGORITHM_AES_256_CBC_HMAC) => self.encrypt_aes_256_cbc_hmac(data, password, resource_type),
+            _ => Err(CryptoError::Unsupported(format!("不支持的加密算法: {}", algorithm)).into()),
+        }
+    }
+
+    /// 解密为二进制数据，不做UTF-8校验
+    ///
+    /// 按信封头部记录的算法ID选择解密算法，与当前配置的加密算法无关；无头部的旧版密文按AES-256-GCM处理。
+    /// 与加密一样在阻塞线程池中执行。
+    pub async fn decrypt_bytes(&self, encrypted_data: &str, password: &str, resource_type: &str) -> Result<Vec<u8>> {
+        let utils = self.clone();
+        let encrypted_data = encrypted_data.to_string();
+        let password = Zeroizing::new(password.to_string());
+        let resource_type = resource_type.to_string();
+        tokio::task::spawn_blocking(move || utils.decrypt_bytes_blocking(&encrypted_data, &password, &resource_type)).await?
+    }
+
+    /// 在当前线程解密为二进制数据
+    fn decrypt_bytes_blocking(&self, encrypted_data: &str, password: &str, resource_type: &str) -> Result<Vec<u8>> {
+        // Base64解码，兼容不同客户端的编码方式
+        let combined = self.decode_base64(encrypted_data)?;
+
+        let algorithm = match EnvelopeHeader::parse(&combined)? {
+            Some((header, _)) => header.algorithm,
+            None => envelope::ALGORITHM_AES_256_GCM,
+        };
+        match algorithm {
+            envelope::ALGORITHM_AES_256_GCM => self.decrypt_aes_256_gcm(&combined, password, resource_type),
+            envelope::ALGORITHM_CHACHA20_POLY1305 => self.decrypt_chacha20_poly1305(&combined, password, resource_type),
+            envelope::ALGORITHM_AES_256_CBC_HMAC => self.decrypt_aes_256_cbc_hmac(&combined, password, resource_type),
+            _ => Err(CryptoError::InvalidEnvelope(format!("未知的算法ID: {}", algorithm)).into()),
+        }
+    }
+
+    /// 创建流式加密器，algorithm为None时按资源类型或默认配置选择算法，只支持AEAD算法
+    ///
+    /// 密钥派生在阻塞线程池中执行
+    pub async fn stream_encryptor(&self, password: &str, resource_type: &str, algorithm: Option<&str>) -> Result<StreamEncryptor> {
+        // 流式头部没有密钥派生参数段
+        if self.kdf == envelope::KDF_ARGON2ID {
+            return Err(CryptoError::Unsupported("流式加密不支持Argon2id密钥派生".to_string()).into());
+        }
+        let algorithm = self.algorithm_for(resource_type, algorithm)?;
+        let algorithm_id = envelope::algorithm_id(algorithm)
+            .ok_or_else(|| CryptoError::Unsupported(format!("不支持的加密算法: {}", algorithm)))?;
+        let utils = self.clone();
+        let password = Zeroizing::new(password.to_string());
+        let resource_type = resource_type.to_string();
+        tokio::task::spawn_blocking(move || StreamEncryptor::new(&utils, algorithm_id, &password, &resource_type)).await?
+    }
+
+    /// 创建流式解密器，按流式密文头部记录的算法解密
+    pub fn stream_decryptor(&self, password: &str, resource_type: &str) -> StreamDecryptor {
+        StreamDecryptor::new(self, password, resource_type)
+    }
+
+    /// 按配置顺序依次尝试各Base64变体解码，全部失败时返回第一个变体的错误
+    fn decode_base64(&self, encoded: &str) -> Result<Vec<u8>> {
+        // 按编码长度估算解码后的大小，超过上限时不解码
+        self.check_decoded_size(encoded.len().div_ceil(4) * 3)?;
+
+        let mut first_error = None;
+        for variant in &self.base64_variants {
+            let result = match variant {
+                Base64Variant::Standard => general_purpose::STANDARD.decode(encoded),
+                Base64Variant::UrlSafe => general_purpose::URL_SAFE.decode(encoded),
+                Base64Variant::StandardNoPad => general_purpose::STANDARD_NO_PAD.decode(encoded),
+                Base64Variant::UrlSafeNoPad => general_purpose::URL_SAFE_NO_PAD.decode(encoded),
+            };
+            match result {
+                Ok(decoded) => return Ok(decoded),
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                },
+            }
+        }
+        match first_error {
+            Some(e) => Err(CryptoError::InvalidBase64(e).into()),
+            None => anyhow::bail!("未配置密文Base64变体"),
         }
     }
 
     /// 使用AES-256-GCM加密数据
-    fn encrypt_aes_256_gcm(&self, data: &str, password: &str) -> Result<String> {
-        // 生成密钥
-        let key = self.generate_key(password)?;
-        let key = Key::<Aes256Gcm>::from_slice(&key);
+    fn encrypt_aes_256_gcm(&self, data: &[u8], password: &str, resource_type: &str) -> Result<String> {
+        self.seal::<Aes256Gcm>(envelope::ALGORITHM_AES_256_GCM, data, password, resource_type)
+    }
 
-        // 创建加密器
-        let cipher = Aes256Gcm::new(key);
+    /// 使用AES-256-GCM解密数据
+    fn decrypt_aes_256_gcm(&self, combined: &[u8], password: &str, resource_type: &str) -> Result<Vec<u8>> {
+        self.open::<Aes256Gcm>(envelope::ALGORITHM_AES_256_GCM, combined, password, resource_type)
+    }
+
+    /// 使用ChaCha20-Poly1305加密数据，适用于没有AES硬件加速的设备
+    fn encrypt_chacha20_poly1305(&self, data: &[u8], password: &str, resource_type: &str) -> Result<String> {
+        self.seal::<ChaCha20Poly1305>(envelope::ALGORITHM_CHACHA20_POLY1305, data, password, resource_type)
+    }
+
+    /// 使用ChaCha20-Poly1305解密数据
+    fn decrypt_chacha20_poly1305(&self, combined: &[u8], password: &str, resource_type: &str) -> Result<Vec<u8>> {
+        self.open::<ChaCha20Poly1305>(envelope::ALGORITHM_CHACHA20_POLY1305, combined, password, resource_type)
+    }
+
+    /// 检查解码或解压后的大小是否超过上限，应在分配内存之前调用
+    fn check_decoded_size(&self, size: usize) -> Result<()> {
+        if size > self.max_decoded_size {
+            return Err(CryptoError::TooLarge { limit: self.max_decoded_size }.into());
+        }
+        Ok(())
+    }
+
+    /// 使用指定AEAD算法加密数据并封装为信封
+    fn seal<C>(&self, algorithm: u8, data: &[u8], password: &str, resource_type: &str) -> Result<String>
+    where
+        C: Aead + KeyInit,
+    {
+        let name = envelope::algorithm_name(algorithm);
+
+        // 生成密钥并创建加密器
+        let (mut header, key) = self.new_envelope(algorithm, password, resource_type)?;
+        let cipher = C::new_from_slice(&key)
+            .map_err(|_| anyhow::anyhow!("{}需要32字节密钥，当前密钥长度为{}字节", name, key.len()))?;
 
         // 生成随机nonce
-        let mut nonce_bytes = [0u8; 12];
-        getrandom::getrandom(&mut nonce_bytes)
-            .map_err(|e| anyhow::anyhow!("生成随机nonce失败: {:?}", e))?;
-        let nonce = Nonce::from_slice(&nonce_bytes);
+        let mut nonce_bytes = [0u8; NONCE_LEN];
+        self.rng.fill(&mut nonce_bytes)
+            .map_err(|e| anyhow::anyhow!("生成随机nonce失败: {}", e))?;
+        let nonce = aead::Nonce::<C>::from_slice(&nonce_bytes);
+
+        // 构建明文时可能设置压缩标志位，需在序列化头部之前完成
+        let payload = self.build_payload(&mut header, data, resource_type)?;
+        let header_bytes = header.to_bytes();
+        let aad = envelope::associated_data(&header_bytes, header.version, resource_type);
 
-        // 加密数据
-        let ciphertext = cipher.encrypt(nonce, data.as_bytes())
-            .map_err(|e| anyhow::anyhow!("AES-GCM加密失败: {:?}", e))?;
+        // 加密数据，头部和资源类型作为附加认证数据
+        let ciphertext = cipher.encrypt(nonce, Payload { msg: &payload, aad: &aad })
+            .map_err(|e| anyhow::anyhow!("{}加密失败: {:?}", name, e))?;
 
-        // 组合nonce和密文
-        let mut combined = Vec::with_capacity(nonce_bytes.len() + ciphertext.len());
+        // 组合头部、nonce和密文
+        let mut combined = Vec::with_capacity(header_bytes.len() + nonce_bytes.len() + ciphertext.len());
+        combined.extend_from_slice(&header_bytes);
         combined.extend_from_slice(&nonce_bytes);
         combined.extend_from_slice(&ciphertext);
 
@@ -82,26 +513,958 @@ impl EncryptionUtils {
         Ok(encrypted)
     }
 
-    /// 使用AES-256-GCM解密数据
-    fn decrypt_aes_256_gcm(&self, encrypted_data: &str, password: &str) -> Result<String> {
-        // Base64解码
-        let combined = general_purpose::STANDARD.decode(encrypted_data)?;
+    /// 解析信封并使用指定AEAD算法解密数据
+    fn open<C>(&self, algorithm: u8, combined: &[u8], password: &str, resource_type: &str) -> Result<Vec<u8>>
+    where
+        C: Aead + KeyInit,
+    {
+        let name = envelope::algorithm_name(algorithm);
+        let (header, aad, body) = self.parse_envelope(algorithm, combined, resource_type)?;
 
         // 分离nonce和密文
-        let (nonce_bytes, ciphertext) = combined.split_at(12);
-        let nonce = Nonce::from_slice(nonce_bytes);
-
-        // 生成密钥
-        let key = self.generate_key(password)?;
-        let key = Key::<Aes256Gcm>::from_slice(&key);
+        if body.len() < NONCE_LEN {
+            return Err(CryptoError::InvalidEnvelope("密文长度不足".to_string()).into());
+        }
+        let (nonce_bytes, ciphertext) = body.split_at(NONCE_LEN);
+        let nonce = aead::Nonce::<C>::from_slice(nonce_bytes);
 
-        // 创建解密器
-        let cipher = Aes256Gcm::new(key);
+        let key = self.envelope_key(header.as_ref(), password)?;
+        let cipher = C::new_from_slice(&key)
+            .map_err(|_| anyhow::anyhow!("{}需要32字节密钥，当前密钥长度为{}字节", name, key.len()))?;
 
         // 解密数据
-        let plaintext = cipher.decrypt(nonce, ciphertext)
-            .map_err(|e| anyhow::anyhow!("AES-GCM解密失败: {:?}", e))?;
-        let plaintext = String::from_utf8(plaintext)?;
+        let plaintext = cipher.decrypt(nonce, Payload { msg: ciphertext, aad: &aad })
+            .map_err(|_| CryptoError::AuthenticationFailed(name.to_string()))?;
+
+        self.finish_payload(header.as_ref(), plaintext)
+    }
+
+    /// 使用AES-256-CBC加密并以HMAC-SHA256认证（先加密后MAC），用于与旧版Java服务互通
+    fn encrypt_aes_256_cbc_hmac(&self, data: &[u8], password: &str, resource_type: &str) -> Result<String> {
+        let (mut header, key) = self.new_envelope(envelope::ALGORITHM_AES_256_CBC_HMAC, password, resource_type)?;
+        let (enc_key, mac_key) = cbc_hmac_keys(&key)?;
+
+        // 生成随机IV
+        let mut iv = [0u8; CBC_IV_LEN];
+        self.rng.fill(&mut iv)
+            .map_err(|e| anyhow::anyhow!("生成随机IV失败: {}", e))?;
+
+        let payload = self.build_payload(&mut header, data, resource_type)?;
+        let header_bytes = header.to_bytes();
+        let aad = envelope::associated_data(&header_bytes, header.version, resource_type);
+
+        // PKCS7填充后加密
+        let ciphertext = cbc::Encryptor::<aes::Aes256>::new_from_slices(enc_key.as_ref(), &iv)
+            .map_err(|e| anyhow::anyhow!("AES-256-CBC初始化失败: {:?}", e))?
+            .encrypt_padded_vec_mut::<Pkcs7>(&payload);
+
+        // 对附加认证数据、IV和密文计算HMAC
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(mac_key.as_ref())
+            .expect("HMAC接受任意长度密钥");
+        mac.update(&aad);
+        mac.update(&iv);
+        mac.update(&ciphertext);
+
+        // 组合头部、IV、密文和认证标签
+        let mut combined = Vec::with_capacity(header_bytes.len() + iv.len() + ciphertext.len() + CBC_TAG_LEN);
+        combined.extend_from_slice(&header_bytes);
+        combined.extend_from_slice(&iv);
+        combined.extend_from_slice(&ciphertext);
+        combined.extend_from_slice(&mac.finalize().into_bytes());
+
+        Ok(general_purpose::STANDARD.encode(combined))
+    }
+
+    /// 校验HMAC后使用AES-256-CBC解密数据
+    fn decrypt_aes_256_cbc_hmac(&self, combined: &[u8], password: &str, resource_type: &str) -> Result<Vec<u8>> {
+        let algorithm = envelope::ALGORITHM_AES_256_CBC_HMAC;
+        let name = envelope::algorithm_name(algorithm);
+        let (header, aad, body) = self.parse_envelope(algorithm, combined, resource_type)?;
+
+        // 至少包含IV、一个分组和认证标签
+        if body.len() < CBC_IV_LEN + CBC_BLOCK_LEN + CBC_TAG_LEN || !(body.len() - CBC_IV_LEN - CBC_TAG_LEN).is_multiple_of(CBC_BLOCK_LEN) {
+            return Err(CryptoError::InvalidEnvelope("密文长度不足或不是完整分组".to_string()).into());
+        }
+        let (body, tag) = body.split_at(body.len() - CBC_TAG_LEN);
+        let (iv, ciphertext) = body.split_at(CBC_IV_LEN);
+
+        let key = self.envelope_key(header.as_ref(), password)?;
+        let (enc_key, mac_key) = cbc_hmac_keys(&key)?;
+
+        // 先以常量时间校验认证标签，校验通过后才解密
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(mac_key.as_ref())
+            .expect("HMAC接受任意长度密钥");
+        mac.update(&aad);
+        mac.update(iv);
+        mac.update(ciphertext);
+        mac.verify_slice(tag)
+            .map_err(|_| CryptoError::AuthenticationFailed(name.to_string()))?;
+
+        let plaintext = cbc::Decryptor::<aes::Aes256>::new_from_slices(enc_key.as_ref(), iv)
+            .map_err(|e| anyhow::anyhow!("AES-256-CBC初始化失败: {:?}", e))?
+            .decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+            .map_err(|_| CryptoError::InvalidEnvelope("无效的PKCS7填充".to_string()))?;
+
+        self.finish_payload(header.as_ref(), plaintext)
+    }
+
+    /// 生成新的信封头部和对应的密钥
+    fn new_envelope(&self, algorithm: u8, password: &str, resource_type: &str) -> Result<(EnvelopeHeader, Zeroizing<Vec<u8>>)> {
+        // 每次加密生成新的随机盐值，相同密码不会派生出相同的密钥
+        let mut salt = [0u8; envelope::SALT_LEN];
+        self.rng.fill(&mut salt)
+            .map_err(|e| anyhow::anyhow!("生成随机盐值失败: {}", e))?;
+        // 配置了资源类型盐值时一起参与派生，并在头部记录盐值标识
+        let type_salt = self.resource_type_salts.get(resource_type).map(Vec::as_slice);
+        // 启用派生密钥缓存时，PBKDF2和Argon2id以固定盐值派生主密钥，相同密码和资源类型的加密可以命中缓存，
+        // 再按随机盐值扩展出每条密文各自的密钥
+        let master = self.uses_master_key();
+        let kdf_salt = if master { combine_salt(&self.salt, type_salt) } else { combine_salt(&salt, type_salt) };
+        let (mut key, argon2) = self.generate_envelope_key(password, &kdf_salt)?;
+        if master {
+            key = self.expand_master_key(&key, &salt)?;
+        }
+
+        // 构建信封头部
+        let mut flags = 0;
+        if master {
+            flags |= envelope::FLAG_MASTER_KEY;
+        }
+        if self.plaintext_checksum {
+            flags |= envelope::FLAG_CHECKSUM;
+        }
+        if *self.padding_for(resource_type) != PaddingScheme::None {
+            flags |= envelope::FLAG_PADDED;
+        }
+        let mut header = EnvelopeHeader::new(algorithm, self.kdf, flags).with_salt(salt);
+        if let Some(params) = argon2 {
+            header = header.with_kdf_params(params);
+        }
+        if !self.bind_resource_type {
+            header.version = envelope::VERSION_HEADER_AAD;
+        }
+        if self.resource_type_guard {
+            header = header.with_resource_type(resource_type)?;
+        }
+        if type_salt.is_some() {
+            header = header.with_salt_id(resource_type)?;
+        }
+        Ok((header, key))
+    }
+
+    /// 生成新密文使用的密钥，同时返回需要记录在头部的密钥派生参数，HKDF为None
+    fn generate_envelope_key(&self, password: &str, salt: &[u8]) -> Result<(Zeroizing<Vec<u8>>, Option<KdfParams>)> {
+        match self.kdf {
+            envelope::KDF_ARGON2ID => self.generate_argon2_key(password, salt),
+            envelope::KDF_PBKDF2_SHA256 => {
+                let params = KdfParams::pbkdf2(self.iterations);
+                Ok((self.derive_key(self.kdf, Some(&params), password, salt)?, Some(params)))
+            },
+            kdf => Ok((self.derive_key(kdf, None, password, salt)?, None)),
+        }
+    }
+
+    /// 新密文是否使用主密钥：启用派生密钥缓存且密钥派生算法为PBKDF2或Argon2id时使用，HKDF本身开销很小，不需要缓存
+    fn uses_master_key(&self) -> bool {
+        self.key_cache.is_some() && self.kdf != envelope::KDF_HKDF_SHA256
+    }
+
+    /// 以HKDF-SHA256按随机盐值从主密钥扩展出单条密文的密钥
+    fn expand_master_key(&self, master: &[u8], salt: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+        let mut key = Zeroizing::new(vec![0u8; self.key_length.try_into()?]);
+        Hkdf::<Sha256>::new(Some(salt), master)
+            .expand(b"encryption", &mut key)
+            .map_err(|e| anyhow::anyhow!("HKDF密钥生成失败: {:?}", e))?;
+        Ok(key)
+    }
+
+    /// 按配置的Argon2id参数生成密钥，返回实际使用的参数
+    ///
+    /// 无法分配内存且处理方式为fallback时改用备用参数，头部记录备用参数，解密时按头部参数派生
+    fn generate_argon2_key(&self, password: &str, salt: &[u8]) -> Result<(Zeroizing<Vec<u8>>, Option<KdfParams>)> {
+        match self.derive_key(self.kdf, Some(&self.argon2), password, salt) {
+            Ok(key) => Ok((key, Some(self.argon2))),
+            Err(e) if self.argon2_alloc_failure == Argon2AllocFailurePolicy::Fallback
+                && matches!(e.downcast_ref::<CryptoError>(), Some(CryptoError::InsufficientMemory { .. })) =>
+            {
+                warn!(
+                    memory = self.argon2.memory,
+                    fallback_memory = self.argon2_fallback.memory,
+                    "Argon2无法分配所需内存，改用备用参数加密"
+                );
+                metrics::counter!(monitoring::ARGON2_FALLBACKS_TOTAL).increment(1);
+                let key = self.derive_key(self.kdf, Some(&self.argon2_fallback), password, salt)?;
+                Ok((key, Some(self.argon2_fallback)))
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    /// 按信封头部的标志位构建待加密的明文：按需压缩、附带校验和并填充
+    ///
+    /// 启用压缩且明文不小于最小字节数时压缩，压缩后变小才使用并设置压缩标志位
+    fn build_payload(&self, header: &mut EnvelopeHeader, data: &[u8], resource_type: &str) -> Result<Vec<u8>> {
+        let compressed = if self.compression && data.len() >= self.compression_min_size {
+            Some(envelope::compress(data)?).filter(|compressed| compressed.len() < data.len())
+        } else {
+            None
+        };
+        let mut payload = match compressed {
+            Some(compressed) => {
+                header.flags |= envelope::FLAG_COMPRESSED;
+                compressed
+            },
+            None => data.to_vec(),
+        };
+        // 按需在明文末尾附带校验和，校验和按压缩前的明文计算，随明文一起加密
+        if header.has_flag(envelope::FLAG_CHECKSUM) {
+            payload.extend_from_slice(&envelope::checksum(data));
+        }
+        // 按需填充，隐藏明文长度
+        envelope::pad(&mut payload, self.padding_for(resource_type));
+        Ok(payload)
+    }
+
+    /// 解析信封头部并检查算法和资源类型，返回头部、附加认证数据和剩余数据
+    fn parse_envelope<'a>(&self, algorithm: u8, combined: &'a [u8], resource_type: &str) -> Result<(Option<EnvelopeHeader>, Vec<u8>, &'a [u8])> {
+        // 解析信封头部，无头部的数据按旧版AES-256-GCM格式处理
+        let (header, body) = match EnvelopeHeader::parse(combined)? {
+            Some((header, body)) => (Some(header), body),
+            None => (None, combined),
+        };
+        // 旧版无头部密文没有附加认证数据
+        let aad = match header {
+            Some(ref header) => envelope::associated_data(&combined[..combined.len() - body.len()], header.version, resource_type),
+            None => Vec::new(),
+        };
+        let envelope_algorithm = header.as_ref()
+            .map(|h| h.algorithm)
+            .unwrap_or(envelope::ALGORITHM_AES_256_GCM);
+        if envelope_algorithm != algorithm {
+            return Err(CryptoError::AlgorithmMismatch {
+                expected: envelope::algorithm_name(algorithm).to_string(),
+                actual: envelope::algorithm_name(envelope_algorithm).to_string(),
+            }.into());
+        }
+
+        // 启用资源类型校验时，拒绝跨资源类型复用密文
+        if self.resource_type_guard
+            && let Some(expected) = header.as_ref().and_then(|h| h.resource_type.as_ref())
+            && expected != resource_type
+        {
+            return Err(CryptoError::ResourceTypeMismatch {
+                expected: expected.clone(),
+                actual: resource_type.to_string(),
+            }.into());
+        }
+
+        Ok((header, aad, body))
+    }
+
+    /// 按信封记录的密钥派生算法和盐值生成密钥，旧版密文使用HKDF和配置的盐值
+    ///
+    /// 头部记录了盐值标识时，按标识取对应的资源类型盐值一起参与派生
+    fn envelope_key(&self, header: Option<&EnvelopeHeader>, password: &str) -> Result<Zeroizing<Vec<u8>>> {
+        let kdf = header.map(|h| h.kdf).unwrap_or(envelope::KDF_HKDF_SHA256);
+        let salt = header
+            .and_then(|h| h.salt.as_ref())
+            .map(|salt| salt.as_slice())
+            .unwrap_or(&self.salt);
+        let type_salt = match header.and_then(|h| h.salt_id.as_ref()) {
+            Some(salt_id) => Some(self.resource_type_salts.get(salt_id)
+                .ok_or_else(|| CryptoError::UnknownSaltId(salt_id.clone()))?
+                .as_slice()),
+            None => None,
+        };
+        let params = header.and_then(|h| h.kdf_params.as_ref());
+        self.check_kdf_params(kdf, params)?;
+
+        // 使用主密钥的密文以配置的盐值派生主密钥，再按头部的随机盐值扩展
+        if let Some(header) = header
+            && header.has_flag(envelope::FLAG_MASTER_KEY)
+        {
+            let random_salt = header.salt.as_ref()
+                .ok_or_else(|| CryptoError::InvalidEnvelope("使用主密钥的密文缺少随机盐值".to_string()))?;
+            let master = self.derive_key(kdf, params, password, &combine_salt(&self.salt, type_salt))?;
+            return self.expand_master_key(&master, random_salt);
+        }
+        self.derive_key(kdf, params, password, &combine_salt(salt, type_salt))
+    }
+
+    /// 检查密文记录的密钥派生参数不超过配置允许的上限，在派生密钥之前拒绝按密文指定的高开销参数
+    fn check_kdf_params(&self, kdf: u8, params: Option<&KdfParams>) -> Result<()> {
+        if kdf == envelope::KDF_PBKDF2_SHA256
+            && let Some(params) = params
+            && params.iterations > self.max_iterations
+        {
+            return Err(CryptoError::InvalidEnvelope(format!(
+                "PBKDF2迭代次数 {} 超过允许的上限 {}", params.iterations, self.max_iterations
+            )).into());
+        }
+        if kdf == envelope::KDF_ARGON2ID
+            && let Some(params) = params
+            && (params.memory > self.argon2_max.memory
+                || params.iterations > self.argon2_max.iterations
+                || params.parallelism > self.argon2_max.parallelism)
+        {
+            return Err(CryptoError::InvalidEnvelope(format!(
+                "Argon2参数超过允许的上限: 内存 {} KiB，迭代 {} 次，并行度 {}",
+                params.memory, params.iterations, params.parallelism
+            )).into());
+        }
+        Ok(())
+    }
+
+    /// 按信封头部的标志位去除填充、解压并校验明文校验和
+    fn finish_payload(&self, header: Option<&EnvelopeHeader>, mut plaintext: Vec<u8>) -> Result<Vec<u8>> {
+        // 去除填充
+        if let Some(header) = header
+            && header.has_flag(envelope::FLAG_PADDED)
+        {
+            envelope::unpad(&mut plaintext)?;
+        }
+
+        // 分离明文校验和
+        let expected = match header {
+            Some(header) if header.has_flag(envelope::FLAG_CHECKSUM) => {
+                if plaintext.len() < envelope::CHECKSUM_LEN {
+                    return Err(CryptoError::InvalidEnvelope("缺少明文校验和".to_string()).into());
+                }
+                Some(plaintext.split_off(plaintext.len() - envelope::CHECKSUM_LEN))
+            },
+            _ => None,
+        };
+
+        // 解压，解压后的大小受解码上限约束
+        if let Some(header) = header
+            && header.has_flag(envelope::FLAG_COMPRESSED)
+        {
+            plaintext = envelope::decompress(&plaintext, self.max_decoded_size)?;
+        }
+
+        // 校验明文校验和
+        if let Some(expected) = expected
+            && envelope::checksum(&plaintext)[..] != expected[..]
+        {
+            return Err(CryptoError::ChecksumMismatch.into());
+        }
+
         Ok(plaintext)
     }
 }
+
+/// 拼接随机盐值和资源类型盐值，未指定资源类型盐值时原样返回
+fn combine_salt(salt: &[u8], type_salt: Option<&[u8]>) -> Vec<u8> {
+    let mut combined = salt.to_vec();
+    if let Some(type_salt) = type_salt {
+        combined.extend_from_slice(type_salt);
+    }
+    combined
+}
+
+/// 由派生密钥扩展出的子密钥，移除时自动清零
+type SubKey = Zeroizing<[u8; 32]>;
+
+/// 从派生密钥以HKDF-SHA256分别扩展出AES-256-CBC加密密钥和HMAC-SHA256密钥
+fn cbc_hmac_keys(key: &[u8]) -> Result<(SubKey, SubKey)> {
+    let hkdf = Hkdf::<Sha256>::new(None, key);
+    let mut enc_key = Zeroizing::new([0u8; 32]);
+    let mut mac_key = Zeroizing::new([0u8; 32]);
+    hkdf.expand(CBC_ENC_KEY_INFO, enc_key.as_mut())
+        .and_then(|_| hkdf.expand(CBC_MAC_KEY_INFO, mac_key.as_mut()))
+        .map_err(|e| anyhow::anyhow!("HKDF密钥生成失败: {:?}", e))?;
+    Ok((enc_key, mac_key))
+}
+
+/// 计算内容寻址的资源ID
+///
+/// 使用带密钥的HMAC-SHA256对资源类型、密码和明文计算盲索引，相同输入得到相同ID，没有密钥无法由ID反推明文。
+/// 密码参与计算：不同密码加密的相同明文得到不同ID，复用的已有记录一定能用调用方的密码解密，
+/// 调用方也无法借此得知其他人保存过相同的明文
+pub fn content_id(key: &[u8], resource_type: &str, password: &str, data: &[u8]) -> String {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key)
+        .expect("HMAC接受任意长度密钥");
+    // 各字段带长度前缀，避免拼接歧义
+    for part in [resource_type.as_bytes(), password.as_bytes(), data] {
+        mac.update(&(part.len() as u64).to_be_bytes());
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::service::ServiceError;
+
+    /// 模拟内存紧张时允许分配的最大块数，每块1 KiB，与备用参数的内存开销相同
+    const AVAILABLE_BLOCKS: usize = 1024;
+
+    fn argon2_utils() -> EncryptionUtils {
+        argon2_utils_with_policy("error")
+    }
+
+    fn argon2_utils_with_policy(policy: &str) -> EncryptionUtils {
+        utils_with(&[
+            ("ENCRYPTION_KEY_DERIVATION", "argon2id"),
+            ("ENCRYPTION_ARGON2_MEMORY", "4096"),
+            ("ENCRYPTION_ARGON2_ITERATIONS", "1"),
+            ("ENCRYPTION_ARGON2_ALLOC_FAILURE", policy),
+            ("ENCRYPTION_ARGON2_FALLBACK_MEMORY", "1024"),
+            ("ENCRYPTION_ARGON2_FALLBACK_ITERATIONS", "1"),
+        ])
+    }
+
+    /// 按附加的加密配置创建工具，配置需通过验证
+    fn utils_with(vars: &[(&str, &str)]) -> EncryptionUtils {
+        let mut vars = vars.to_vec();
+        vars.push(("CRUD_API_WRITE_INSTANCE_URL", "http://localhost"));
+        let config = AppConfig::from_vars(&vars).unwrap();
+        config.validate().unwrap();
+        EncryptionUtils::new(&config.encryption)
+    }
+
+    /// 模拟内存紧张：只能分配不超过AVAILABLE_BLOCKS块内存
+    fn constrained_allocator(count: usize) -> Option<Vec<argon2::Block>> {
+        (count <= AVAILABLE_BLOCKS).then(|| allocate_blocks(count)).flatten()
+    }
+
+    fn recorded_params(encrypted: &str) -> KdfParams {
+        let bytes = general_purpose::STANDARD.decode(encrypted).unwrap();
+        let (header, _) = EnvelopeHeader::parse(&bytes).unwrap().unwrap();
+        header.kdf_params.unwrap()
+    }
+
+    #[tokio::test]
+    async fn allocation_failure_returns_capacity_error() {
+        let utils = argon2_utils().with_block_allocator(constrained_allocator);
+        let e = utils.encrypt("secret", "password", "user", None).await.unwrap_err();
+        assert!(matches!(e.downcast_ref::<CryptoError>(), Some(CryptoError::InsufficientMemory { memory: 4096 })));
+        assert_eq!(ServiceError::from_crypto(e).status_code(), axum::http::StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn decrypt_reports_allocation_failure() {
+        let encrypted = argon2_utils().encrypt("secret", "password", "user", None).await.unwrap();
+        let utils = argon2_utils().with_block_allocator(constrained_allocator);
+        let e = utils.decrypt(&encrypted, "password", "user").await.unwrap_err();
+        assert!(matches!(e.downcast_ref::<CryptoError>(), Some(CryptoError::InsufficientMemory { memory: 4096 })));
+        assert_eq!(argon2_utils().decrypt(&encrypted, "password", "user").await.unwrap(), "secret");
+    }
+
+    #[tokio::test]
+    async fn allocation_failure_falls_back_to_lighter_profile() {
+        let utils = argon2_utils_with_policy("fallback").with_block_allocator(constrained_allocator);
+        let encrypted = utils.encrypt("secret", "password", "user", None).await.unwrap();
+        assert_eq!(recorded_params(&encrypted), KdfParams { memory: 1024, iterations: 1, parallelism: 1 });
+
+        // 头部记录了备用参数，内存充足的实例同样可以解密
+        let decrypted = argon2_utils().decrypt(&encrypted, "password", "user").await.unwrap();
+        assert_eq!(decrypted, "secret");
+    }
+
+    #[tokio::test]
+    async fn fallback_uses_configured_profile_when_memory_is_available() {
+        let encrypted = argon2_utils_with_policy("fallback").encrypt("secret", "password", "user", None).await.unwrap();
+        assert_eq!(recorded_params(&encrypted).memory, 4096);
+    }
+
+    #[tokio::test]
+    async fn decrypt_cannot_fall_back_from_recorded_params() {
+        let encrypted = argon2_utils().encrypt("secret", "password", "user", None).await.unwrap();
+        let utils = argon2_utils_with_policy("fallback").with_block_allocator(constrained_allocator);
+        let e = utils.decrypt(&encrypted, "password", "user").await.unwrap_err();
+        assert!(matches!(e.downcast_ref::<CryptoError>(), Some(CryptoError::InsufficientMemory { memory: 4096 })));
+    }
+
+    #[tokio::test]
+    async fn argon2_params_above_configured_ceiling_are_rejected_before_allocation() {
+        let encrypted = argon2_utils().encrypt("secret", "password", "user", None).await.unwrap();
+
+        // 默认上限为本服务配置的参数，头部要求更多内存时返回400，不分配工作内存
+        let lighter = [
+            ("ENCRYPTION_KEY_DERIVATION", "argon2id"),
+            ("ENCRYPTION_ARGON2_MEMORY", "2048"),
+            ("ENCRYPTION_ARGON2_ITERATIONS", "1"),
+        ];
+        let utils = utils_with(&lighter).with_block_allocator(|_| panic!("超过上限的参数不应分配内存"));
+        let e = utils.decrypt(&encrypted, "password", "user").await.unwrap_err();
+        assert!(matches!(e.downcast_ref::<CryptoError>(), Some(CryptoError::InvalidEnvelope(_))));
+        assert_eq!(ServiceError::from_crypto(e).status_code(), axum::http::StatusCode::BAD_REQUEST);
+
+        // 单独放宽上限后可以解密参数更高的已有数据
+        let mut vars = lighter.to_vec();
+        vars.push(("ENCRYPTION_ARGON2_MAX_MEMORY", "4096"));
+        assert_eq!(utils_with(&vars).decrypt(&encrypted, "password", "user").await.unwrap(), "secret");
+    }
+
+    #[test]
+    fn argon2_ceiling_cannot_be_below_encryption_params() {
+        let config = AppConfig::from_vars(&[
+            ("CRUD_API_WRITE_INSTANCE_URL", "http://localhost"),
+            ("ENCRYPTION_KEY_DERIVATION", "argon2id"),
+            ("ENCRYPTION_ARGON2_MEMORY", "4096"),
+            ("ENCRYPTION_ARGON2_MAX_MEMORY", "2048"),
+        ]).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn key_cache_hits_on_repeated_encryption() {
+        let vars = [("ENCRYPTION_KEY_DERIVATION", "pbkdf2"), ("ENCRYPTION_ITERATIONS", "1000")];
+        let mut cached_vars = vars.to_vec();
+        cached_vars.push(("KEY_CACHE_SIZE", "8"));
+        let utils = utils_with(&cached_vars);
+        let cache = utils.key_cache.clone().unwrap();
+
+        let first = utils.encrypt("first", "password", "user", None).await.unwrap();
+        let second = utils.encrypt("second", "password", "user", None).await.unwrap();
+        // 第二次加密命中第一次派生的主密钥，每条密文仍使用各自的随机盐值
+        assert_eq!(cache.entry_count(), 1);
+        let header = |encrypted: &str| {
+            let bytes = general_purpose::STANDARD.decode(encrypted).unwrap();
+            EnvelopeHeader::parse(&bytes).unwrap().unwrap().0
+        };
+        assert!(header(&first).has_flag(envelope::FLAG_MASTER_KEY));
+        assert_ne!(header(&first).salt, header(&second).salt);
+
+        // 其他密码派生新的主密钥
+        utils.encrypt("third", "other", "user", None).await.unwrap();
+        assert_eq!(cache.entry_count(), 2);
+
+        // 未启用缓存的实例同样可以解密，且自身加密时不使用主密钥
+        let uncached = utils_with(&vars);
+        assert_eq!(uncached.decrypt(&first, "password", "user").await.unwrap(), "first");
+        assert_eq!(uncached.decrypt(&second, "password", "user").await.unwrap(), "second");
+        assert!(uncached.decrypt(&first, "other", "user").await.is_err());
+        let plain = uncached.encrypt("plain", "password", "user", None).await.unwrap();
+        assert!(!header(&plain).has_flag(envelope::FLAG_MASTER_KEY));
+    }
+
+    fn pbkdf2_utils(iterations: &str) -> EncryptionUtils {
+        utils_with(&[("ENCRYPTION_KEY_DERIVATION", "pbkdf2"), ("ENCRYPTION_ITERATIONS", iterations)])
+    }
+
+    #[tokio::test]
+    async fn pbkdf2_iterations_recorded_in_envelope() {
+        let encrypted = pbkdf2_utils("1000").encrypt("secret", "password", "user", None).await.unwrap();
+        assert_eq!(recorded_params(&encrypted), KdfParams::pbkdf2(1000));
+
+        // 修改迭代次数配置后，按头部记录的迭代次数解密已有数据
+        let decrypted = pbkdf2_utils("2000").decrypt(&encrypted, "password", "user").await.unwrap();
+        assert_eq!(decrypted, "secret");
+    }
+
+    #[tokio::test]
+    async fn pbkdf2_iterations_above_configured_ceiling_are_rejected() {
+        let encrypted = pbkdf2_utils("5000").encrypt("secret", "password", "user", None).await.unwrap();
+
+        // 默认上限为配置迭代次数的2倍，按头部次数派生之前拒绝
+        let e = pbkdf2_utils("1000").decrypt(&encrypted, "password", "user").await.unwrap_err();
+        assert!(matches!(e.downcast_ref::<CryptoError>(), Some(CryptoError::InvalidEnvelope(_))));
+        assert_eq!(ServiceError::from_crypto(e).status_code(), axum::http::StatusCode::BAD_REQUEST);
+
+        // 调低迭代次数时可以单独放宽上限
+        let utils = utils_with(&[
+            ("ENCRYPTION_KEY_DERIVATION", "pbkdf2"),
+            ("ENCRYPTION_ITERATIONS", "1000"),
+            ("ENCRYPTION_MAX_ITERATIONS", "5000"),
+        ]);
+        assert_eq!(utils.decrypt(&encrypted, "password", "user").await.unwrap(), "secret");
+    }
+
+    #[test]
+    fn max_iterations_cannot_be_below_iterations() {
+        let config = AppConfig::from_vars(&[
+            ("CRUD_API_WRITE_INSTANCE_URL", "http://localhost"),
+            ("ENCRYPTION_KEY_DERIVATION", "pbkdf2"),
+            ("ENCRYPTION_ITERATIONS", "1000"),
+            ("ENCRYPTION_MAX_ITERATIONS", "999"),
+        ]).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn fallback_profile_must_be_lighter() {
+        let config = AppConfig::from_vars(&[
+            ("CRUD_API_WRITE_INSTANCE_URL", "http://localhost"),
+            ("ENCRYPTION_KEY_DERIVATION", "argon2id"),
+            ("ENCRYPTION_ARGON2_MEMORY", "4096"),
+            ("ENCRYPTION_ARGON2_ALLOC_FAILURE", "fallback"),
+            ("ENCRYPTION_ARGON2_FALLBACK_MEMORY", "4096"),
+        ]).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    /// 以AES-256-GCM加密原样的明文载荷，不经过build_payload，用于构造校验和错误的信封
+    fn seal_raw_payload(utils: &EncryptionUtils, payload: &[u8]) -> String {
+        let (header, key) = utils.new_envelope(envelope::ALGORITHM_AES_256_GCM, "password", "user").unwrap();
+        let cipher = Aes256Gcm::new_from_slice(&key).unwrap();
+        let nonce_bytes = [7u8; NONCE_LEN];
+        let header_bytes = header.to_bytes();
+        let aad = envelope::associated_data(&header_bytes, header.version, "user");
+        let ciphertext = cipher.encrypt(aead::Nonce::<Aes256Gcm>::from_slice(&nonce_bytes), Payload { msg: payload, aad: &aad }).unwrap();
+        general_purpose::STANDARD.encode([header_bytes, nonce_bytes.to_vec(), ciphertext].concat())
+    }
+
+    #[tokio::test]
+    async fn plaintext_checksum_verified_after_decrypt() {
+        let utils = utils_with(&[("ENCRYPTION_PLAINTEXT_CHECKSUM", "true")]);
+        let encrypted = utils.encrypt("secret", "password", "user", None).await.unwrap();
+
+        let bytes = general_purpose::STANDARD.decode(&encrypted).unwrap();
+        let (header, _) = EnvelopeHeader::parse(&bytes).unwrap().unwrap();
+        assert!(header.has_flag(envelope::FLAG_CHECKSUM));
+        assert_eq!(utils.decrypt(&encrypted, "password", "user").await.unwrap(), "secret");
+    }
+
+    #[tokio::test]
+    async fn wrong_plaintext_checksum_is_distinct_error() {
+        let utils = utils_with(&[("ENCRYPTION_PLAINTEXT_CHECKSUM", "true")]);
+        let payload = [b"secret".as_slice(), &envelope::checksum(b"other")].concat();
+        let encrypted = seal_raw_payload(&utils, &payload);
+
+        // 认证标签有效，只有校验和不匹配
+        let e = utils.decrypt(&encrypted, "password", "user").await.unwrap_err();
+        assert!(matches!(e.downcast_ref::<CryptoError>(), Some(CryptoError::ChecksumMismatch)));
+    }
+
+    #[tokio::test]
+    async fn padding_hides_length_of_short_plaintexts() {
+        let utils = utils_with(&[("ENCRYPTION_PADDING", "power_of_two")]);
+        // 加上填充标记后都填充到16字节
+        let short = utils.encrypt("abcdefghi", "password", "user", None).await.unwrap();
+        let longer = utils.encrypt("abcdefghijklmn", "password", "user", None).await.unwrap();
+        assert_eq!(short.len(), longer.len());
+
+        assert_eq!(utils.decrypt(&short, "password", "user").await.unwrap(), "abcdefghi");
+        assert_eq!(utils.decrypt(&longer, "password", "user").await.unwrap(), "abcdefghijklmn");
+    }
+
+    #[tokio::test]
+    async fn padding_applies_only_to_configured_resource_types() {
+        let utils = utils_with(&[("ENCRYPTION_PADDING", "64"), ("ENCRYPTION_PADDING_RESOURCE_TYPES", "user")]);
+        let padded = [utils.encrypt("a", "password", "user", None).await.unwrap(), utils.encrypt("abcdef", "password", "user", None).await.unwrap()];
+        let unpadded = [utils.encrypt("a", "password", "order", None).await.unwrap(), utils.encrypt("abcdef", "password", "order", None).await.unwrap()];
+        assert_eq!(padded[0].len(), padded[1].len());
+        assert_ne!(unpadded[0].len(), unpadded[1].len());
+        assert_eq!(utils.decrypt(&padded[1], "password", "user").await.unwrap(), "abcdef");
+    }
+
+    #[tokio::test]
+    async fn resource_type_guard_accepts_matching_type() {
+        let utils = utils_with(&[("ENCRYPTION_RESOURCE_TYPE_GUARD", "true")]);
+        let encrypted = utils.encrypt("secret", "password", "user", None).await.unwrap();
+        assert_eq!(utils.decrypt(&encrypted, "password", "user").await.unwrap(), "secret");
+    }
+
+    #[tokio::test]
+    async fn resource_type_guard_rejects_other_type() {
+        // 不绑定附加认证数据时，只有资源类型校验能拒绝跨资源类型复用
+        let guarded = utils_with(&[("ENCRYPTION_RESOURCE_TYPE_GUARD", "true"), ("ENCRYPTION_BIND_RESOURCE_TYPE", "false")]);
+        let encrypted = guarded.encrypt("secret", "password", "user", None).await.unwrap();
+
+        let e = guarded.decrypt(&encrypted, "password", "order").await.unwrap_err();
+        assert!(matches!(
+            e.downcast_ref::<CryptoError>(),
+            Some(CryptoError::ResourceTypeMismatch { expected, actual }) if expected == "user" && actual == "order"
+        ));
+
+        let unguarded = utils_with(&[("ENCRYPTION_BIND_RESOURCE_TYPE", "false")]);
+        assert_eq!(unguarded.decrypt(&encrypted, "password", "order").await.unwrap(), "secret");
+    }
+
+    #[tokio::test]
+    async fn every_encryption_draws_fresh_nonce() {
+        let utils = utils_with(&[]);
+        let mut nonces = std::collections::HashSet::new();
+        for _ in 0..64 {
+            let encrypted = utils.encrypt("secret", "password", "user", None).await.unwrap();
+            let bytes = general_purpose::STANDARD.decode(&encrypted).unwrap();
+            let (_, body) = EnvelopeHeader::parse(&bytes).unwrap().unwrap();
+            assert!(nonces.insert(body[..NONCE_LEN].to_vec()));
+            assert_eq!(utils.decrypt(&encrypted, "password", "user").await.unwrap(), "secret");
+        }
+    }
+
+    #[tokio::test]
+    async fn new_envelopes_carry_random_salt() {
+        let utils = utils_with(&[]);
+        let salts: Vec<_> = [
+            utils.encrypt("secret", "password", "user", None).await.unwrap(),
+            utils.encrypt("secret", "password", "user", None).await.unwrap(),
+        ].iter().map(|encrypted| {
+            let bytes = general_purpose::STANDARD.decode(encrypted).unwrap();
+            EnvelopeHeader::parse(&bytes).unwrap().unwrap().0.salt.unwrap()
+        }).collect();
+        assert_ne!(salts[0], salts[1]);
+    }
+
+    #[tokio::test]
+    async fn legacy_ciphertext_without_header_uses_configured_salt() {
+        let utils = utils_with(&[]);
+        // 旧版格式：nonce | 密文，HKDF按配置的盐值派生，没有附加认证数据
+        let key = utils.derive_key(envelope::KDF_HKDF_SHA256, None, "password", &utils.salt).unwrap();
+        let cipher = Aes256Gcm::new_from_slice(&key).unwrap();
+        let nonce_bytes = [3u8; NONCE_LEN];
+        let ciphertext = cipher.encrypt(aead::Nonce::<Aes256Gcm>::from_slice(&nonce_bytes), b"legacy secret".as_slice()).unwrap();
+        let legacy = general_purpose::STANDARD.encode([nonce_bytes.as_slice(), &ciphertext].concat());
+
+        assert_eq!(utils.decrypt(&legacy, "password", "user").await.unwrap(), "legacy secret");
+        let current = utils.encrypt("new secret", "password", "user", None).await.unwrap();
+        assert_eq!(utils.decrypt(&current, "password", "user").await.unwrap(), "new secret");
+    }
+
+    /// 执行一次PBKDF2密钥派生，返回期间输出的日志
+    fn derive_with_logs(threshold_ms: &str, iterations: u32) -> String {
+        let utils = utils_with(&[("KDF_SLOW_THRESHOLD_MS", threshold_ms)]);
+        let logs = monitoring::LogCapture::default();
+        let _guard = logs.install();
+        utils.derive_key(envelope::KDF_PBKDF2_SHA256, Some(&KdfParams::pbkdf2(iterations)), "password", b"salt").unwrap();
+        logs.contents()
+    }
+
+    #[test]
+    fn slow_key_derivation_logs_warning() {
+        let logs = derive_with_logs("1", 50_000);
+        assert!(logs.contains("密钥派生耗时超过阈值"));
+        assert!(logs.contains("kdf=\"PBKDF2-SHA256\""));
+    }
+
+    #[test]
+    fn fast_or_unmonitored_key_derivation_does_not_warn() {
+        assert!(!derive_with_logs("60000", 1).contains("密钥派生耗时超过阈值"));
+        assert!(!derive_with_logs("0", 50_000).contains("密钥派生耗时超过阈值"));
+    }
+
+    fn recorded_algorithm(encrypted: &str) -> u8 {
+        let bytes = general_purpose::STANDARD.decode(encrypted).unwrap();
+        let (header, _) = EnvelopeHeader::parse(&bytes).unwrap().unwrap();
+        header.algorithm
+    }
+
+    #[tokio::test]
+    async fn enabled_algorithms_coexist_in_one_process() {
+        let utils = utils_with(&[
+            ("ENCRYPTION_ALGORITHM", "aes-256-gcm"),
+            ("ENCRYPTION_ENABLED_ALGORITHMS", "aes-256-gcm,chacha20-poly1305"),
+            ("ENCRYPTION_RESOURCE_TYPE_ALGORITHMS", "payment:chacha20-poly1305"),
+        ]);
+
+        let by_default = utils.encrypt("default", "password", "user", None).await.unwrap();
+        let by_resource_type = utils.encrypt("resource type", "password", "payment", None).await.unwrap();
+        let by_request = utils.encrypt("request", "password", "user", Some("chacha20-poly1305")).await.unwrap();
+        assert_eq!(recorded_algorithm(&by_default), envelope::ALGORITHM_AES_256_GCM);
+        assert_eq!(recorded_algorithm(&by_resource_type), envelope::ALGORITHM_CHACHA20_POLY1305);
+        assert_eq!(recorded_algorithm(&by_request), envelope::ALGORITHM_CHACHA20_POLY1305);
+
+        // 解密按头部记录的算法分派
+        assert_eq!(utils.decrypt(&by_default, "password", "user").await.unwrap(), "default");
+        assert_eq!(utils.decrypt(&by_resource_type, "password", "payment").await.unwrap(), "resource type");
+        assert_eq!(utils.decrypt(&by_request, "password", "user").await.unwrap(), "request");
+
+        assert!(utils.encrypt("data", "password", "user", Some("aes-256-cbc-hmac")).await.is_err());
+    }
+
+    #[test]
+    fn algorithm_settings_must_be_enabled() {
+        let validate = |vars: &[(&str, &str)]| {
+            let mut vars = vars.to_vec();
+            vars.push(("CRUD_API_WRITE_INSTANCE_URL", "http://localhost"));
+            AppConfig::from_vars(&vars).unwrap().validate()
+        };
+        assert!(validate(&[("ENCRYPTION_ENABLED_ALGORITHMS", "chacha20-poly1305")]).is_err());
+        assert!(validate(&[
+            ("ENCRYPTION_ENABLED_ALGORITHMS", "aes-256-gcm"),
+            ("ENCRYPTION_RESOURCE_TYPE_ALGORITHMS", "payment:chacha20-poly1305"),
+        ]).is_err());
+        assert!(validate(&[("ENCRYPTION_ENABLED_ALGORITHMS", "aes-256-gcm,rot13")]).is_err());
+    }
+
+    /// 生成标准编码中同时含有 +/ 字符和填充的密文，使各Base64变体的编码互不相同
+    async fn ciphertext_with_distinct_encodings(utils: &EncryptionUtils) -> Vec<u8> {
+        loop {
+            let encrypted = utils.encrypt("base64 interop", "password", "user", None).await.unwrap();
+            if encrypted.ends_with('=') && encrypted.contains(['+', '/']) {
+                return general_purpose::STANDARD.decode(encrypted).unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn decrypts_all_base64_variants() {
+        let utils = utils_with(&[]);
+        let raw = ciphertext_with_distinct_encodings(&utils).await;
+        for encoded in [
+            general_purpose::STANDARD.encode(&raw),
+            general_purpose::URL_SAFE.encode(&raw),
+            general_purpose::STANDARD_NO_PAD.encode(&raw),
+            general_purpose::URL_SAFE_NO_PAD.encode(&raw),
+        ] {
+            assert_eq!(utils.decrypt(&encoded, "password", "user").await.unwrap(), "base64 interop");
+        }
+    }
+
+    #[tokio::test]
+    async fn only_configured_base64_variants_are_tried() {
+        let utils = utils_with(&[("DECRYPT_BASE64_VARIANTS", "standard")]);
+        let raw = ciphertext_with_distinct_encodings(&utils).await;
+        assert!(utils.decrypt(&general_purpose::STANDARD.encode(&raw), "password", "user").await.is_ok());
+        assert!(utils.decrypt(&general_purpose::URL_SAFE.encode(&raw), "password", "user").await.is_err());
+        assert!(utils.decrypt(&general_purpose::STANDARD_NO_PAD.encode(&raw), "password", "user").await.is_err());
+    }
+
+    /// 总是失败的随机数来源，模拟服务端故障
+    #[derive(Debug)]
+    struct FailingRng;
+
+    impl RngSource for FailingRng {
+        fn fill(&self, _dest: &mut [u8]) -> Result<()> {
+            anyhow::bail!("随机数来源不可用")
+        }
+    }
+
+    #[tokio::test]
+    async fn client_caused_errors_are_bad_requests() {
+        let utils = utils_with(&[]);
+        let status = |e: anyhow::Error| ServiceError::from_crypto(e).status_code();
+
+        let e = utils.decrypt("not base64!", "password", "user").await.unwrap_err();
+        assert_eq!(status(e), axum::http::StatusCode::BAD_REQUEST);
+        let e = utils.encrypt("secret", "password", "user", Some("chacha20-poly1305")).await.unwrap_err();
+        assert_eq!(status(e), axum::http::StatusCode::BAD_REQUEST);
+        let binary = utils.encrypt_bytes(&[0xff, 0xfe], "password", "user", None).await.unwrap();
+        let e = utils.decrypt(&binary, "password", "user").await.unwrap_err();
+        assert_eq!(status(e), axum::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn server_faults_are_internal_errors() {
+        let utils = utils_with(&[]).with_rng(Arc::new(FailingRng));
+        let e = utils.encrypt("secret", "password", "user", None).await.unwrap_err();
+        assert!(matches!(ServiceError::from_crypto(e), ServiceError::Internal(_)));
+    }
+
+    fn seeded_utils(seed: u64) -> EncryptionUtils {
+        utils_with(&[]).with_rng(Arc::new(SeededRngSource::new(seed)))
+    }
+
+    #[tokio::test]
+    async fn seeded_rng_gives_exact_envelope_layout() {
+        let encrypted = seeded_utils(42).encrypt("layout", "password", "user", None).await.unwrap();
+        // 相同种子得到完全相同的密文，不同种子则不同
+        assert_eq!(seeded_utils(42).encrypt("layout", "password", "user", None).await.unwrap(), encrypted);
+        assert_ne!(seeded_utils(43).encrypt("layout", "password", "user", None).await.unwrap(), encrypted);
+
+        // 随机字节依次用作盐值和nonce
+        let reference = SeededRngSource::new(42);
+        let mut salt = [0u8; envelope::SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        reference.fill(&mut salt).unwrap();
+        reference.fill(&mut nonce).unwrap();
+
+        // 布局：头部 | nonce | 密文 | 16字节认证标签
+        let bytes = general_purpose::STANDARD.decode(&encrypted).unwrap();
+        let (header, body) = EnvelopeHeader::parse(&bytes).unwrap().unwrap();
+        assert_eq!(&bytes[..bytes.len() - body.len()], header.to_bytes().as_slice());
+        assert_eq!(header.algorithm, envelope::ALGORITHM_AES_256_GCM);
+        assert_eq!(header.salt, Some(salt));
+        assert_eq!(&body[..NONCE_LEN], &nonce);
+        assert!(body.len() >= NONCE_LEN + "layout".len() + 16);
+
+        assert_eq!(seeded_utils(7).decrypt(&encrypted, "password", "user").await.unwrap(), "layout");
+    }
+
+    fn is_too_large(e: &anyhow::Error, expected: usize) -> bool {
+        matches!(e.downcast_ref::<CryptoError>(), Some(CryptoError::TooLarge { limit }) if *limit == expected)
+    }
+
+    /// 解析密文中的信封头部
+    fn header_of(encrypted: &str) -> EnvelopeHeader {
+        let bytes = general_purpose::STANDARD.decode(encrypted).unwrap();
+        EnvelopeHeader::parse(&bytes).unwrap().unwrap().0
+    }
+
+    #[tokio::test]
+    async fn compressible_plaintext_yields_smaller_ciphertext() {
+        let document = serde_json::json!({ "rows": vec![serde_json::json!({ "name": "encryption-service", "status": "active" }); 200] }).to_string();
+        let plain = utils_with(&[]);
+        let compressing = utils_with(&[("ENCRYPTION_COMPRESSION", "true")]);
+
+        let uncompressed = plain.encrypt(&document, "password", "user", None).await.unwrap();
+        let compressed = compressing.encrypt(&document, "password", "user", None).await.unwrap();
+        assert!(compressed.len() * 4 < uncompressed.len(), "{} >= {}", compressed.len(), uncompressed.len());
+        assert!(header_of(&compressed).has_flag(envelope::FLAG_COMPRESSED));
+        assert!(!header_of(&uncompressed).has_flag(envelope::FLAG_COMPRESSED));
+        assert_eq!(compressing.decrypt(&compressed, "password", "user").await.unwrap(), document);
+
+        // 未压缩的旧数据在开启压缩后仍可解密，压缩数据在关闭压缩后同样可解密
+        assert_eq!(compressing.decrypt(&uncompressed, "password", "user").await.unwrap(), document);
+        assert_eq!(plain.decrypt(&compressed, "password", "user").await.unwrap(), document);
+    }
+
+    #[tokio::test]
+    async fn plaintext_below_min_size_is_not_compressed() {
+        let utils = utils_with(&[("ENCRYPTION_COMPRESSION", "true"), ("ENCRYPTION_COMPRESSION_MIN_SIZE", "256")]);
+        let small = utils.encrypt(&"a".repeat(255), "password", "user", None).await.unwrap();
+        assert!(!header_of(&small).has_flag(envelope::FLAG_COMPRESSED));
+        assert_eq!(utils.decrypt(&small, "password", "user").await.unwrap(), "a".repeat(255));
+
+        let large = utils.encrypt(&"a".repeat(256), "password", "user", None).await.unwrap();
+        assert!(header_of(&large).has_flag(envelope::FLAG_COMPRESSED));
+        assert!(large.len() < small.len());
+    }
+
+    #[tokio::test]
+    async fn decompression_beyond_ceiling_is_rejected() {
+        // 1MB的零字节压缩后只有约1KB，编码后的密文远小于上限
+        let bomb = "\0".repeat(1024 * 1024);
+        let sealer = utils_with(&[("ENCRYPTION_COMPRESSION", "true")]);
+        let encrypted = sealer.encrypt(&bomb, "password", "user", None).await.unwrap();
+        assert!(encrypted.len() < 64 * 1024);
+
+        let limited = utils_with(&[("ENCRYPTION_COMPRESSION", "true"), ("DECRYPT_MAX_DECODED_BYTES", "65536")]);
+        let e = limited.decrypt(&encrypted, "password", "user").await.unwrap_err();
+        assert!(is_too_large(&e, 65536), "{:?}", e);
+
+        assert_eq!(sealer.decrypt(&encrypted, "password", "user").await.unwrap().len(), bomb.len());
+    }
+
+    #[tokio::test]
+    async fn oversized_base64_is_rejected_before_decoding() {
+        let utils = utils_with(&[("DECRYPT_MAX_DECODED_BYTES", "1024")]);
+        let e = utils.decrypt(&"A".repeat(4096), "password", "user").await.unwrap_err();
+        assert!(is_too_large(&e, 1024), "{:?}", e);
+    }
+
+    #[tokio::test]
+    async fn ciphertext_is_bound_to_its_resource_type() {
+        for algorithm in ["aes-256-gcm", "chacha20-poly1305", "aes-256-cbc-hmac"] {
+            let utils = utils_with(&[("ENCRYPTION_ALGORITHM", algorithm)]);
+            let encrypted = utils.encrypt("salary", "password", "invoice", None).await.unwrap();
+            assert_eq!(utils.decrypt(&encrypted, "password", "invoice").await.unwrap(), "salary");
+            // 以其他资源类型解密时附加认证数据不一致，认证失败
+            assert!(utils.decrypt(&encrypted, "password", "payslip").await.is_err(), "{}", algorithm);
+        }
+    }
+
+    #[tokio::test]
+    async fn version_one_envelope_without_resource_type_aad_still_decrypts() {
+        let utils = utils_with(&[]);
+        let (mut header, key) = utils.new_envelope(envelope::ALGORITHM_AES_256_GCM, "password", "invoice").unwrap();
+        header.version = envelope::VERSION_HEADER_AAD;
+        let payload = utils.build_payload(&mut header, b"v1 secret", "invoice").unwrap();
+        let header_bytes = header.to_bytes();
+        // 版本1只以头部作为附加认证数据
+        let aad = envelope::associated_data(&header_bytes, header.version, "invoice");
+        assert_eq!(aad, header_bytes);
+        let cipher = Aes256Gcm::new_from_slice(&key).unwrap();
+        let nonce_bytes = [5u8; NONCE_LEN];
+        let ciphertext = cipher.encrypt(aead::Nonce::<Aes256Gcm>::from_slice(&nonce_bytes), Payload { msg: &payload, aad: &aad }).unwrap();
+        let v1 = general_purpose::STANDARD.encode([header_bytes, nonce_bytes.to_vec(), ciphertext].concat());
+
+        assert_eq!(utils.decrypt(&v1, "password", "invoice").await.unwrap(), "v1 secret");
+        assert_eq!(utils.decrypt(&v1, "password", "payslip").await.unwrap(), "v1 secret");
+    }
+}
@@ -0,0 +1,44 @@
+use std::fmt::Debug;
+
+use anyhow::Result;
+
+/// 随机数来源，用于生成盐值和nonce
+pub trait RngSource: Debug + Send + Sync {
+    /// 用随机字节填满dest
+    fn fill(&self, dest: &mut [u8]) -> Result<()>;
+}
+
+/// 操作系统随机数来源，生产环境唯一使用的实现
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsRngSource;
+
+impl RngSource for OsRngSource {
+    fn fill(&self, dest: &mut [u8]) -> Result<()> {
+        getrandom::getrandom(dest).map_err(|e| anyhow::anyhow!("生成随机数失败: {:?}", e))
+    }
+}
+
+/// 按种子生成确定性字节序列的随机数来源，仅用于测试，使相同输入得到相同密文
+///
+/// 只在测试或启用 `test-rng` 特性时编译，生产构建中不存在
+#[cfg(any(test, feature = "test-rng"))]
+#[derive(Debug)]
+pub struct SeededRngSource(std::sync::Mutex<rand::rngs::StdRng>);
+
+#[cfg(any(test, feature = "test-rng"))]
+impl SeededRngSource {
+    /// 使用指定种子创建
+    pub fn new(seed: u64) -> Self {
+        use rand::SeedableRng;
+        Self(std::sync::Mutex::new(rand::rngs::StdRng::seed_from_u64(seed)))
+    }
+}
+
+#[cfg(any(test, feature = "test-rng"))]
+impl RngSource for SeededRngSource {
+    fn fill(&self, dest: &mut [u8]) -> Result<()> {
+        use rand::RngCore;
+        self.0.lock().unwrap().fill_bytes(dest);
+        Ok(())
+    }
+}
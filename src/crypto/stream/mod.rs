@@ -0,0 +1,448 @@
+use aes_gcm::Aes256Gcm;
+use aes_gcm::aead::{self, Aead, KeyInit, Payload};
+use anyhow::Result;
+use chacha20poly1305::ChaCha20Poly1305;
+use zeroize::Zeroizing;
+
+use super::envelope::{self, KdfParams};
+use super::{CryptoError, EncryptionUtils};
+
+/// 流式密文魔数
+pub const MAGIC: &[u8; 3] = b"ESS";
+/// 流式密文版本1：头部没有迭代次数段，PBKDF2按配置的迭代次数派生
+pub const VERSION_NO_ITERATIONS: u8 = 1;
+/// 当前流式密文版本：头部记录PBKDF2迭代次数
+pub const VERSION: u8 = 2;
+/// nonce前缀长度，nonce为 前缀(7) | 分块序号(4) | 最后一块标志(1)
+pub const NONCE_PREFIX_LEN: usize = 7;
+/// 流式密文头部长度
+pub const HEADER_LEN: usize = MAGIC.len() + 3 + 4 + 4 + envelope::SALT_LEN + NONCE_PREFIX_LEN;
+/// 版本1流式密文头部长度
+const HEADER_LEN_NO_ITERATIONS: usize = HEADER_LEN - 4;
+/// 分块头部长度：标志(1) | 密文长度(4)
+pub const FRAME_HEADER_LEN: usize = 5;
+/// 认证标签长度
+pub const TAG_LEN: usize = 16;
+/// 允许的最大分块大小，解密时拒绝声明了更大分块的密文，避免按攻击者指定的大小分配内存
+pub const MAX_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+/// 分块标志：中间块
+const FRAME_MIDDLE: u8 = 0x00;
+/// 分块标志：最后一块
+const FRAME_LAST: u8 = 0x01;
+
+/// 流式加密支持的AEAD算法
+enum StreamCipher {
+    Aes256Gcm(Box<Aes256Gcm>),
+    ChaCha20Poly1305(Box<ChaCha20Poly1305>),
+}
+
+impl StreamCipher {
+    /// 按算法ID创建加密器，只支持AEAD算法
+    fn new(algorithm: u8, key: &[u8]) -> Result<Self> {
+        let name = envelope::algorithm_name(algorithm);
+        let invalid_key = |_| anyhow::anyhow!("{}需要32字节密钥，当前密钥长度为{}字节", name, key.len());
+        match algorithm {
+            envelope::ALGORITHM_AES_256_GCM => Ok(Self::Aes256Gcm(Box::new(Aes256Gcm::new_from_slice(key).map_err(invalid_key)?))),
+            envelope::ALGORITHM_CHACHA20_POLY1305 => Ok(Self::ChaCha20Poly1305(Box::new(ChaCha20Poly1305::new_from_slice(key).map_err(invalid_key)?))),
+            _ => Err(CryptoError::InvalidEnvelope(format!("{}不支持流式加密", name)).into()),
+        }
+    }
+
+    /// 加密一个分块
+    fn seal(&self, nonce: &[u8], aad: &[u8], msg: &[u8]) -> aead::Result<Vec<u8>> {
+        match self {
+            Self::Aes256Gcm(cipher) => cipher.encrypt(aead::Nonce::<Aes256Gcm>::from_slice(nonce), Payload { msg, aad }),
+            Self::ChaCha20Poly1305(cipher) => cipher.encrypt(aead::Nonce::<ChaCha20Poly1305>::from_slice(nonce), Payload { msg, aad }),
+        }
+    }
+
+    /// 解密一个分块
+    fn open(&self, nonce: &[u8], aad: &[u8], msg: &[u8]) -> aead::Result<Vec<u8>> {
+        match self {
+            Self::Aes256Gcm(cipher) => cipher.decrypt(aead::Nonce::<Aes256Gcm>::from_slice(nonce), Payload { msg, aad }),
+            Self::ChaCha20Poly1305(cipher) => cipher.decrypt(aead::Nonce::<ChaCha20Poly1305>::from_slice(nonce), Payload { msg, aad }),
+        }
+    }
+}
+
+/// 计算附加认证数据：头部 | 资源类型长度(8，大端) | 资源类型，与版本2信封相同
+fn associated_data(header: &[u8], resource_type: &str) -> Vec<u8> {
+    let mut aad = header.to_vec();
+    aad.extend_from_slice(&(resource_type.len() as u64).to_be_bytes());
+    aad.extend_from_slice(resource_type.as_bytes());
+    aad
+}
+
+/// 由nonce前缀、分块序号和最后一块标志组成分块nonce
+fn frame_nonce(prefix: &[u8; NONCE_PREFIX_LEN], counter: u32, flag: u8) -> [u8; 12] {
+    let mut nonce = [0u8; 12];
+    nonce[..NONCE_PREFIX_LEN].copy_from_slice(prefix);
+    nonce[NONCE_PREFIX_LEN..11].copy_from_slice(&counter.to_be_bytes());
+    nonce[11] = flag;
+    nonce
+}
+
+/// 流式加密器：按固定大小分块加密，不需要一次性持有全部明文
+///
+/// 输出布局见README“流式加解密”一节：头部之后依次为各分块，每个分块带标志和长度前缀。
+pub struct StreamEncryptor {
+    cipher: StreamCipher,
+    /// 附加认证数据：头部和资源类型
+    aad: Vec<u8>,
+    nonce_prefix: [u8; NONCE_PREFIX_LEN],
+    /// 下一个分块的序号
+    counter: u32,
+    chunk_size: usize,
+    /// 尚未凑满一个分块的明文
+    buffer: Zeroizing<Vec<u8>>,
+    /// 尚未输出的头部
+    header: Option<Vec<u8>>,
+    finished: bool,
+}
+
+impl StreamEncryptor {
+    /// 创建流式加密器
+    pub(super) fn new(utils: &EncryptionUtils, algorithm: u8, password: &str, resource_type: &str) -> Result<Self> {
+        let mut salt = [0u8; envelope::SALT_LEN];
+        let mut nonce_prefix = [0u8; NONCE_PREFIX_LEN];
+        utils.rng.fill(&mut salt)
+            .and_then(|_| utils.rng.fill(&mut nonce_prefix))
+            .map_err(|e| anyhow::anyhow!("生成随机数失败: {}", e))?;
+
+        // 流式头部没有盐值标识段，不使用资源类型盐值
+        let key = utils.generate_key(password, &salt, None)?;
+        let cipher = StreamCipher::new(algorithm, &key)?;
+
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&[VERSION, algorithm, utils.kdf]);
+        header.extend_from_slice(&(utils.stream_chunk_size as u32).to_be_bytes());
+        // 记录PBKDF2迭代次数，修改配置后已有流式密文仍按加密时的次数解密；HKDF记录为0
+        let iterations = if utils.kdf == envelope::KDF_PBKDF2_SHA256 { utils.iterations } else { 0 };
+        header.extend_from_slice(&iterations.to_be_bytes());
+        header.extend_from_slice(&salt);
+        header.extend_from_slice(&nonce_prefix);
+
+        Ok(Self {
+            cipher,
+            aad: associated_data(&header, resource_type),
+            nonce_prefix,
+            counter: 0,
+            chunk_size: utils.stream_chunk_size,
+            buffer: Zeroizing::new(Vec::new()),
+            header: Some(header),
+            finished: false,
+        })
+    }
+
+    /// 写入明文，返回加密器和可以输出的密文（首次调用时包含头部）
+    ///
+    /// 只有确认后面还有数据时才输出完整分块，最后一块在finish时输出。分块加密在阻塞线程池中执行。
+    pub async fn update(mut self, data: impl AsRef<[u8]> + Send + 'static) -> Result<(Self, Vec<u8>)> {
+        tokio::task::spawn_blocking(move || {
+            let output = self.update_blocking(data.as_ref())?;
+            Ok((self, output))
+        }).await?
+    }
+
+    /// 结束加密，输出剩余明文组成的最后一块（可能为空），在阻塞线程池中执行
+    pub async fn finish(mut self) -> Result<Vec<u8>> {
+        tokio::task::spawn_blocking(move || self.finish_blocking()).await?
+    }
+
+    /// 在当前线程加密已凑满的分块
+    fn update_blocking(&mut self, data: &[u8]) -> Result<Vec<u8>> {
+        if self.finished {
+            anyhow::bail!("流式加密已结束");
+        }
+        let mut output = self.header.take().unwrap_or_default();
+        self.buffer.extend_from_slice(data);
+        if self.buffer.len() > self.chunk_size {
+            let full_chunks = (self.buffer.len() - 1) / self.chunk_size;
+            let consumed = full_chunks * self.chunk_size;
+            for chunk in self.buffer[..consumed].chunks(self.chunk_size) {
+                let frame = Self::seal_frame(&self.cipher, &self.aad, &self.nonce_prefix, self.counter, FRAME_MIDDLE, chunk)?;
+                output.extend_from_slice(&frame);
+                self.counter = self.counter.checked_add(1)
+                    .ok_or_else(|| anyhow::anyhow!("流式加密分块数超过上限"))?;
+            }
+            self.buffer.drain(..consumed);
+        }
+        Ok(output)
+    }
+
+    /// 在当前线程加密最后一块
+    fn finish_blocking(&mut self) -> Result<Vec<u8>> {
+        if self.finished {
+            anyhow::bail!("流式加密已结束");
+        }
+        self.finished = true;
+        let mut output = self.header.take().unwrap_or_default();
+        let frame = Self::seal_frame(&self.cipher, &self.aad, &self.nonce_prefix, self.counter, FRAME_LAST, &self.buffer)?;
+        output.extend_from_slice(&frame);
+        self.buffer.clear();
+        Ok(output)
+    }
+
+    /// 加密一个分块并加上标志和长度前缀
+    fn seal_frame(cipher: &StreamCipher, aad: &[u8], prefix: &[u8; NONCE_PREFIX_LEN], counter: u32, flag: u8, chunk: &[u8]) -> Result<Vec<u8>> {
+        let nonce = frame_nonce(prefix, counter, flag);
+        let ciphertext = cipher.seal(&nonce, aad, chunk)
+            .map_err(|e| anyhow::anyhow!("分块加密失败: {:?}", e))?;
+        let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + ciphertext.len());
+        frame.push(flag);
+        frame.extend_from_slice(&(ciphertext.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&ciphertext);
+        Ok(frame)
+    }
+}
+
+/// 解析头部后得到的解密状态
+struct DecryptState {
+    cipher: StreamCipher,
+    aad: Vec<u8>,
+    nonce_prefix: [u8; NONCE_PREFIX_LEN],
+    chunk_size: usize,
+}
+
+/// 流式解密器：逐块校验并解密，只输出已通过认证的分块
+pub struct StreamDecryptor {
+    utils: EncryptionUtils,
+    password: Zeroizing<String>,
+    resource_type: String,
+    /// 头部解析前为None
+    state: Option<DecryptState>,
+    /// 尚未组成完整头部或分块的密文
+    buffer: Vec<u8>,
+    /// 下一个分块的序号
+    counter: u32,
+    /// 是否已解密最后一块
+    done: bool,
+}
+
+impl StreamDecryptor {
+    /// 创建流式解密器
+    pub(super) fn new(utils: &EncryptionUtils, password: &str, resource_type: &str) -> Self {
+        Self {
+            utils: utils.clone(),
+            password: Zeroizing::new(password.to_string()),
+            resource_type: resource_type.to_string(),
+            state: None,
+            buffer: Vec::new(),
+            counter: 0,
+            done: false,
+        }
+    }
+
+    /// 是否已解密最后一块
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// 写入密文，返回解密器和已通过认证的明文
+    ///
+    /// 首次凑满头部时派生密钥，密钥派生和分块解密都在阻塞线程池中执行
+    pub async fn update(mut self, data: impl AsRef<[u8]> + Send + 'static) -> Result<(Self, Vec<u8>)> {
+        tokio::task::spawn_blocking(move || {
+            let output = self.update_blocking(data.as_ref())?;
+            Ok((self, output))
+        }).await?
+    }
+
+    /// 在当前线程解析头部并解密已完整的分块
+    fn update_blocking(&mut self, data: &[u8]) -> Result<Vec<u8>> {
+        self.buffer.extend_from_slice(data);
+        let mut output = Vec::new();
+        let mut offset = 0;
+
+        if self.state.is_none() {
+            let Some((state, header_len)) = self.parse_header()? else {
+                return Ok(output);
+            };
+            self.state = Some(state);
+            offset = header_len;
+        }
+        let state = self.state.as_ref().expect("头部已解析");
+
+        while !self.done && self.buffer.len() - offset >= FRAME_HEADER_LEN {
+            let frame = &self.buffer[offset..];
+            let flag = frame[0];
+            let len = u32::from_be_bytes([frame[1], frame[2], frame[3], frame[4]]) as usize;
+            // 中间块必须是完整分块，最后一块不能超过分块大小
+            let valid_len = match flag {
+                FRAME_MIDDLE => len == state.chunk_size + TAG_LEN,
+                FRAME_LAST => (TAG_LEN..=state.chunk_size + TAG_LEN).contains(&len),
+                _ => return Err(CryptoError::InvalidEnvelope(format!("未知的分块标志: {:#04x}", flag)).into()),
+            };
+            if !valid_len {
+                return Err(CryptoError::InvalidEnvelope(format!("第{}块长度无效: {}", self.counter, len)).into());
+            }
+            if frame.len() < FRAME_HEADER_LEN + len {
+                break;
+            }
+
+            let nonce = frame_nonce(&state.nonce_prefix, self.counter, flag);
+            let plaintext = state.cipher.open(&nonce, &state.aad, &frame[FRAME_HEADER_LEN..FRAME_HEADER_LEN + len])
+                .map_err(|_| CryptoError::AuthenticationFailed(format!("第{}块", self.counter)))?;
+            output.extend_from_slice(&plaintext);
+            offset += FRAME_HEADER_LEN + len;
+
+            if flag == FRAME_LAST {
+                self.done = true;
+            } else {
+                self.counter = self.counter.checked_add(1)
+                    .ok_or_else(|| CryptoError::InvalidEnvelope("流式密文分块数超过上限".to_string()))?;
+            }
+        }
+        self.buffer.drain(..offset);
+
+        if self.done && !self.buffer.is_empty() {
+            return Err(CryptoError::InvalidEnvelope("最后一块之后还有多余数据".to_string()).into());
+        }
+        Ok(output)
+    }
+
+    /// 结束解密，缺少最后一块时视为密文被截断
+    pub fn finish(&self) -> Result<()> {
+        if !self.done {
+            return Err(CryptoError::InvalidEnvelope("流式密文不完整，可能已被截断".to_string()).into());
+        }
+        Ok(())
+    }
+
+    /// 解析头部并派生密钥，返回解密状态和头部长度；数据不足一个完整头部时返回None
+    fn parse_header(&self) -> Result<Option<(DecryptState, usize)>> {
+        if self.buffer.len() <= MAGIC.len() {
+            return Ok(None);
+        }
+        if !self.buffer.starts_with(MAGIC) {
+            return Err(CryptoError::InvalidEnvelope("不是流式密文".to_string()).into());
+        }
+        let version = self.buffer[MAGIC.len()];
+        let header_len = match version {
+            VERSION_NO_ITERATIONS => HEADER_LEN_NO_ITERATIONS,
+            VERSION => HEADER_LEN,
+            _ => return Err(CryptoError::InvalidEnvelope(format!("不支持的流式密文版本: {}", version)).into()),
+        };
+        if self.buffer.len() < header_len {
+            return Ok(None);
+        }
+        let header = &self.buffer[..header_len];
+        let (algorithm, kdf) = (header[4], header[5]);
+        let chunk_size = u32::from_be_bytes([header[6], header[7], header[8], header[9]]) as usize;
+        if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+            return Err(CryptoError::InvalidEnvelope(format!("无效的分块大小: {}", chunk_size)).into());
+        }
+        let mut rest = &header[10..];
+        // 版本2记录了PBKDF2迭代次数，版本1和HKDF按配置派生
+        let mut params = None;
+        if version == VERSION {
+            let iterations = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]);
+            if kdf == envelope::KDF_PBKDF2_SHA256 {
+                let recorded = KdfParams::pbkdf2(iterations);
+                recorded.check(kdf)?;
+                params = Some(recorded);
+            }
+            rest = &rest[4..];
+        }
+        let (salt, prefix) = rest.split_at(envelope::SALT_LEN);
+        let mut nonce_prefix = [0u8; NONCE_PREFIX_LEN];
+        nonce_prefix.copy_from_slice(prefix);
+
+        self.utils.check_kdf_params(kdf, params.as_ref())?;
+        let key = self.utils.derive_key(kdf, params.as_ref(), &self.password, salt)?;
+        let state = DecryptState {
+            cipher: StreamCipher::new(algorithm, &key)?,
+            aad: associated_data(header, &self.resource_type),
+            nonce_prefix,
+            chunk_size,
+        };
+        Ok(Some((state, header_len)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::AppConfig;
+    use crate::crypto::CryptoError;
+    use crate::crypto::EncryptionUtils;
+
+    fn stream_utils() -> EncryptionUtils {
+        stream_utils_with(&[])
+    }
+
+    fn stream_utils_with(vars: &[(&str, &str)]) -> EncryptionUtils {
+        let mut vars = vars.to_vec();
+        vars.extend([
+            ("CRUD_API_WRITE_INSTANCE_URL", "http://localhost"),
+            ("ENCRYPTION_ITERATIONS", "1000"),
+            ("STREAM_CHUNK_SIZE", "16"),
+        ]);
+        let config = AppConfig::from_vars(&vars).unwrap();
+        config.validate().unwrap();
+        EncryptionUtils::new(&config.encryption)
+    }
+
+    async fn encrypt_all(utils: &EncryptionUtils, plaintext: &[u8]) -> Vec<u8> {
+        let encryptor = utils.stream_encryptor("password", "users", None).await.unwrap();
+        let (encryptor, mut ciphertext) = encryptor.update(plaintext.to_vec()).await.unwrap();
+        ciphertext.extend_from_slice(&encryptor.finish().await.unwrap());
+        ciphertext
+    }
+
+    #[tokio::test]
+    async fn round_trip_across_chunks_on_blocking_pool() {
+        let utils = stream_utils();
+        let plaintext: Vec<u8> = (0..100u8).collect();
+
+        let mut encryptor = utils.stream_encryptor("password", "users", None).await.unwrap();
+        let mut ciphertext = Vec::new();
+        for part in plaintext.chunks(7) {
+            let (next, output) = encryptor.update(part.to_vec()).await.unwrap();
+            ciphertext.extend_from_slice(&output);
+            encryptor = next;
+        }
+        ciphertext.extend_from_slice(&encryptor.finish().await.unwrap());
+
+        // 逐字节写入，头部和分块跨越多次update
+        let mut decryptor = utils.stream_decryptor("password", "users");
+        let mut decrypted = Vec::new();
+        for byte in ciphertext {
+            let (next, output) = decryptor.update([byte]).await.unwrap();
+            decrypted.extend_from_slice(&output);
+            decryptor = next;
+        }
+        decryptor.finish().unwrap();
+        assert_eq!(decrypted, plaintext);
+    }
+
+    #[tokio::test]
+    async fn wrong_password_fails_authentication() {
+        let utils = stream_utils();
+        let encryptor = utils.stream_encryptor("password", "users", None).await.unwrap();
+        let (encryptor, mut ciphertext) = encryptor.update(b"secret".to_vec()).await.unwrap();
+        ciphertext.extend_from_slice(&encryptor.finish().await.unwrap());
+
+        let decryptor = utils.stream_decryptor("other", "users");
+        assert!(decryptor.update(ciphertext).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn pbkdf2_iterations_recorded_in_stream_header() {
+        let ciphertext = encrypt_all(&stream_utils_with(&[("ENCRYPTION_KEY_DERIVATION", "pbkdf2")]), b"secret").await;
+        assert_eq!(ciphertext[3], super::VERSION);
+        assert_eq!(&ciphertext[10..14], &1000u32.to_be_bytes());
+
+        // 修改迭代次数配置后，按头部记录的迭代次数解密已有流式密文
+        let utils = stream_utils_with(&[("ENCRYPTION_KEY_DERIVATION", "pbkdf2"), ("ENCRYPTION_ITERATIONS", "600")]);
+        let (decryptor, decrypted) = utils.stream_decryptor("password", "users").update(ciphertext.clone()).await.unwrap();
+        decryptor.finish().unwrap();
+        assert_eq!(decrypted, b"secret");
+
+        // 超过配置上限的迭代次数在派生密钥之前拒绝
+        let utils = stream_utils_with(&[("ENCRYPTION_KEY_DERIVATION", "pbkdf2"), ("ENCRYPTION_ITERATIONS", "400")]);
+        let e = utils.stream_decryptor("password", "users").update(ciphertext).await.err().unwrap();
+        assert!(matches!(e.downcast_ref::<CryptoError>(), Some(CryptoError::InvalidEnvelope(_))));
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+/// 缓存键，由进程内随机密钥对资源和密码做HMAC得到，不可逆
+type CacheKey = [u8; 32];
+
+/// 解密结果的来源
+#[derive(Debug, Clone, Copy)]
+pub enum DecryptSource<'a> {
+    /// 按资源ID从CRUD API获取的数据
+    Resource(&'a str),
+    /// 请求中直接携带的密文
+    Ciphertext(&'a str),
+}
+
+/// 缓存的明文，移除时自动清零
+struct CachedPlaintext {
+    data: Zeroizing<String>,
+    /// 按资源ID缓存时为“资源类型/资源ID”，用于资源更新后失效
+    resource: Option<String>,
+    inserted_at: Instant,
+}
+
+/// 缓存内容
+#[derive(Default)]
+struct DecryptCacheState {
+    entries: HashMap<CacheKey, CachedPlaintext>,
+    /// 访问顺序，队首为最久未使用
+    order: VecDeque<CacheKey>,
+}
+
+/// 解密结果缓存（有界LRU + TTL）
+///
+/// 缓存键包含密码的HMAC，不同密码的条目互不命中；不保存密码，也无法由缓存键反推出密码。
+pub struct DecryptCache {
+    /// 最大缓存条目数
+    capacity: usize,
+    /// 缓存有效期
+    ttl: Duration,
+    /// 计算缓存键的随机密钥
+    hash_key: Zeroizing<[u8; 32]>,
+    /// 缓存内容
+    state: Mutex<DecryptCacheState>,
+}
+
+impl fmt::Debug for DecryptCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DecryptCache")
+            .field("capacity", &self.capacity)
+            .field("ttl", &self.ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DecryptCache {
+    /// 创建新的解密结果缓存
+    pub fn new(capacity: usize, ttl: Duration) -> Result<Self> {
+        let mut hash_key = Zeroizing::new([0u8; 32]);
+        getrandom::getrandom(hash_key.as_mut())
+            .map_err(|e| anyhow::anyhow!("生成解密缓存随机密钥失败: {:?}", e))?;
+        Ok(Self {
+            capacity,
+            ttl,
+            hash_key,
+            state: Mutex::new(DecryptCacheState::default()),
+        })
+    }
+
+    /// 计算缓存键
+    fn cache_key(&self, resource_type: &str, source: DecryptSource, password: &str) -> CacheKey {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(self.hash_key.as_ref())
+            .expect("HMAC接受任意长度密钥");
+        let (kind, value) = match source {
+            DecryptSource::Resource(id) => (b"resource".as_slice(), id),
+            DecryptSource::Ciphertext(data) => (b"ciphertext".as_slice(), data),
+        };
+        // 各字段带长度前缀，避免拼接歧义
+        for part in [kind, resource_type.as_bytes(), value.as_bytes(), password.as_bytes()] {
+            mac.update(&(part.len() as u64).to_be_bytes());
+            mac.update(part);
+        }
+        mac.finalize().into_bytes().into()
+    }
+
+    /// 获取缓存的明文，过期条目在访问时移除
+    pub fn get(&self, resource_type: &str, source: DecryptSource, password: &str) -> Option<String> {
+        let cache_key = self.cache_key(resource_type, source, password);
+        let mut state = self.state.lock().unwrap();
+        match state.entries.get(&cache_key) {
+            Some(cached) if cached.inserted_at.elapsed() < self.ttl => {
+                let data = cached.data.to_string();
+                // 命中后移到队尾
+                state.order.retain(|k| *k != cache_key);
+                state.order.push_back(cache_key);
+                Some(data)
+            },
+            Some(_) => {
+                state.entries.remove(&cache_key);
+                state.order.retain(|k| *k != cache_key);
+                None
+            },
+            None => None,
+        }
+    }
+
+    /// 写入解密结果，超出容量时淘汰最久未使用的条目
+    pub fn insert(&self, resource_type: &str, source: DecryptSource, password: &str, data: &str) {
+        let cache_key = self.cache_key(resource_type, source, password);
+        let resource = match source {
+            DecryptSource::Resource(id) => Some(format!("{}/{}", resource_type, id)),
+            DecryptSource::Ciphertext(_) => None,
+        };
+
+        let mut state = self.state.lock().unwrap();
+        if !state.entries.contains_key(&cache_key) {
+            while state.entries.len() >= self.capacity {
+                match state.order.pop_front() {
+                    Some(oldest) => {
+                        state.entries.remove(&oldest);
+                    },
+                    None => break,
+                }
+            }
+            state.order.push_back(cache_key);
+        }
+        state.entries.insert(cache_key, CachedPlaintext {
+            data: Zeroizing::new(data.to_string()),
+            resource,
+            inserted_at: Instant::now(),
+        });
+    }
+
+    /// 移除指定资源的所有缓存条目，资源被修改后调用，避免旧密码仍能命中
+    pub fn invalidate_resource(&self, resource_type: &str, resource_id: &str) {
+        let resource = format!("{}/{}", resource_type, resource_id);
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        state.entries.retain(|_, cached| cached.resource.as_deref() != Some(resource.as_str()));
+        let entries = &state.entries;
+        state.order.retain(|k| entries.contains_key(k));
+    }
+}
@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+use reqwest::Client;
+
+use crate::config::AppConfig;
+
+/// 创建共享的HTTP客户端，调度器、加密服务、Test实例管理器和提醒共用同一个连接池
+///
+/// Client内部使用Arc，克隆后共享连接池和TLS、代理设置；
+/// 发往CRUD API实例的请求按实例配置单独设置超时时间，客户端超时只对其他请求生效
+pub fn build(config: &AppConfig) -> Client {
+    let pool = &config.http_client;
+    Client::builder()
+        .timeout(Duration::from_millis(config.crud_api.timeout))
+        .pool_max_idle_per_host(pool.pool_max_idle_per_host)
+        .pool_idle_timeout((pool.pool_idle_timeout > 0).then(|| Duration::from_secs(pool.pool_idle_timeout)))
+        .tcp_keepalive((pool.tcp_keepalive > 0).then(|| Duration::from_secs(pool.tcp_keepalive)))
+        .build()
+        .expect("无法创建HTTP客户端")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::net::SocketAddr;
+    use std::sync::{Arc, Mutex};
+
+    use axum::Router;
+    use axum::extract::{ConnectInfo, State};
+    use axum::routing::get;
+
+    use super::*;
+
+    type Peers = Arc<Mutex<HashSet<SocketAddr>>>;
+
+    async fn record_peer(State(peers): State<Peers>, ConnectInfo(peer): ConnectInfo<SocketAddr>) -> &'static str {
+        peers.lock().unwrap().insert(peer);
+        "ok"
+    }
+
+    /// 启动记录客户端连接地址的服务，每个TCP连接对应一个不同的地址
+    async fn peer_recorder() -> (String, Peers) {
+        let peers = Peers::default();
+        let app = Router::new().route("/", get(record_peer)).with_state(peers.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
+        });
+        (url, peers)
+    }
+
+    /// 用客户端的多个克隆依次发送请求，返回使用的连接数
+    async fn connections_used(vars: &[(&str, &str)]) -> usize {
+        let mut vars = vars.to_vec();
+        vars.push(("CRUD_API_WRITE_INSTANCE_URL", "http://localhost"));
+        let client = build(&AppConfig::from_vars(&vars).unwrap());
+        let (url, peers) = peer_recorder().await;
+        for _ in 0..3 {
+            client.clone().get(&url).send().await.unwrap().text().await.unwrap();
+        }
+        peers.lock().unwrap().len()
+    }
+
+    #[tokio::test]
+    async fn clones_share_one_connection_pool() {
+        assert_eq!(connections_used(&[]).await, 1);
+        // 不保留空闲连接时每个请求都建立新连接
+        assert_eq!(connections_used(&[("HTTP_POOL_MAX_IDLE_PER_HOST", "0")]).await, 3);
+    }
+}
@@ -2,19 +2,32 @@ use std::net::SocketAddr;
 use std::sync::Arc;
 
 use axum::{serve};
-use tracing::info;
+use axum_server::Handle;
+use axum_server::tls_rustls::RustlsConfig;
+use tokio::sync::watch;
+use tracing::{info, warn};
 use dotenvy::dotenv;
 
 use crate::service::EncryptionService;
-use crate::api::create_router;
-use crate::config::AppConfig;
+use crate::api::{create_router, in_flight_requests};
+use crate::config::{AppConfig, AuthMode};
 
 mod config;
 mod crypto;
 mod service;
 mod api;
+mod auth;
 mod scheduler;
 mod cache;
+mod audit;
+mod decrypt_cache;
+mod monitoring;
+mod rate_limit;
+mod notifier;
+mod request_id;
+mod retry;
+mod http_client;
+mod single_flight;
 mod test_instance;
 mod test_config;
 
@@ -29,29 +42,51 @@ async fn main() {
     // 测试配置加载
     test_config::test_config_loading();
     
-    // 加载配置
-    let config = AppConfig::from_env().expect("无法加载配置");
+    // 加载配置，设置了CONFIG_FILE时从配置文件加载，环境变量覆盖文件中的值
+    let config = match std::env::var("CONFIG_FILE") {
+        Ok(path) if !path.is_empty() => AppConfig::from_file(std::path::Path::new(&path)),
+        _ => AppConfig::from_env(),
+    }.expect("无法加载配置");
     config.validate().expect("配置验证失败");
+    if config.auth.mode == AuthMode::None {
+        warn!("认证方式为none，加解密接口不校验调用方身份");
+    }
     
     info!("服务配置: {:?}", config);
     
+    // 安装指标记录器
+    monitoring::install().expect("无法安装指标记录器");
+
     // 创建服务实例
     let config_arc = Arc::new(config.clone());
     let encryption_service = EncryptionService::new(config_arc.clone());
     let encryption_service = Arc::new(encryption_service);
     
+    // 后台任务停止信号
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let mut background_tasks = Vec::new();
+
     // 启动调度器健康检查
-    encryption_service.get_scheduler().start_health_check().await;
+    background_tasks.push(encryption_service.get_scheduler().start_health_check(shutdown_rx.clone()).await);
     
     // 启动Test实例管理器定期检查
-    encryption_service.get_test_instance_manager().start_periodic_check().await;
+    background_tasks.push(encryption_service.get_test_instance_manager().start_periodic_check(shutdown_rx.clone()).await);
     
+    // 启动Test实例健康检查
+    background_tasks.extend(encryption_service.get_test_instance_manager().start_health_check(shutdown_rx.clone()).await);
+
     // 启动缓存管理器定期清理任务
-    encryption_service.get_cache_manager().start_cleanup_task().await;
+    background_tasks.push(encryption_service.get_cache_manager().start_cleanup_task(shutdown_rx.clone()).await);
+
+    // 启动缓存重放任务
+    background_tasks.extend(encryption_service.start_replay_task(shutdown_rx.clone()).await);
+
+    // 启动后重放遗留的缓存
+    background_tasks.extend(encryption_service.start_startup_replay(shutdown_rx).await);
     
     // 构建路由
     let app = create_router(
-        encryption_service
+        encryption_service.clone()
     );
     
     // 配置服务器地址
@@ -66,13 +101,82 @@ async fn main() {
           config.service.role);
     
     // 启动服务器
-    let listener = tokio::net::TcpListener::bind(&addr)
-        .await
-        .expect("无法绑定地址");
-    
-    info!("加密服务正在运行，监听地址: {}", listener.local_addr().unwrap());
-    
-    serve(listener, app)
-        .await
-        .expect("服务器启动失败");
+    if config.server.https {
+        // 证书路径已在配置验证时检查
+        let cert_path = config.server.tls_cert_path.as_deref().unwrap_or_default();
+        let key_path = config.server.tls_key_path.as_deref().unwrap_or_default();
+        let tls_config = RustlsConfig::from_pem_file(cert_path, key_path)
+            .await
+            .unwrap_or_else(|e| panic!("无法加载TLS证书 {} 或私钥 {}: {}", cert_path, key_path, e));
+
+        // 收到停止信号后不再接受新连接，等待正在处理的请求完成
+        let handle = Handle::new();
+        let shutdown_handle = handle.clone();
+        tokio::spawn(async move {
+            shutdown_signal().await;
+            info!("收到停止信号，等待 {} 个正在处理的请求完成", in_flight_requests());
+            shutdown_handle.graceful_shutdown(None);
+        });
+
+        info!("加密服务正在运行（HTTPS），监听地址: {}", addr);
+
+        axum_server::bind_rustls(addr, tls_config)
+            .handle(handle)
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+            .await
+            .expect("服务器启动失败");
+    } else {
+        let listener = tokio::net::TcpListener::bind(&addr)
+            .await
+            .expect("无法绑定地址");
+
+        info!("加密服务正在运行，监听地址: {}", listener.local_addr().unwrap());
+
+        // 收到停止信号后不再接受新连接，等待正在处理的请求完成
+        serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+            .with_graceful_shutdown(async {
+                shutdown_signal().await;
+                info!("收到停止信号，等待 {} 个正在处理的请求完成", in_flight_requests());
+            })
+            .await
+            .expect("服务器启动失败");
+    }
+
+    // 停止后台任务
+    let _ = shutdown_tx.send(true);
+    for task in background_tasks {
+        if let Err(e) = task.await {
+            warn!("后台任务异常退出: {:?}", e);
+        }
+    }
+
+    // 确保缓存写入已落盘
+    if let Err(e) = encryption_service.get_cache_manager().flush() {
+        warn!("同步缓存文件失败: {:?}", e);
+    }
+
+    info!("加密服务已停止");
+}
+
+/// 等待Ctrl+C或SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c().await.expect("无法监听Ctrl+C信号");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("无法监听SIGTERM信号")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
 }
@@ -0,0 +1,92 @@
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use anyhow::Result;
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+
+/// 请求总数，标签 operation：encrypt、decrypt
+pub const REQUESTS_TOTAL: &str = "encryption_requests_total";
+/// 加解密失败次数，标签 operation
+pub const CRYPTO_FAILURES_TOTAL: &str = "encryption_crypto_failures_total";
+/// CRUD API不可用时的降级次数，标签 operation
+pub const CRUD_FALLBACKS_TOTAL: &str = "encryption_crud_fallbacks_total";
+/// 解密结果缓存命中次数
+pub const DECRYPT_CACHE_HITS_TOTAL: &str = "encryption_decrypt_cache_hits_total";
+/// 缓存写入次数，标签 data_type：encrypt、decrypt
+pub const CACHE_WRITES_TOTAL: &str = "encryption_cache_writes_total";
+/// 加解密耗时（秒），标签 operation
+pub const CRYPTO_DURATION_SECONDS: &str = "encryption_crypto_duration_seconds";
+/// 密钥派生耗时（秒），标签 kdf，命中派生密钥缓存时不计入
+pub const KDF_DURATION_SECONDS: &str = "encryption_kdf_duration_seconds";
+/// CRUD API单次请求耗时（秒）
+pub const CRUD_REQUEST_DURATION_SECONDS: &str = "encryption_crud_request_duration_seconds";
+/// Argon2无法分配工作内存的次数
+pub const ARGON2_ALLOC_FAILURES_TOTAL: &str = "encryption_argon2_alloc_failures_total";
+/// Argon2内存分配失败后改用备用参数加密的次数
+pub const ARGON2_FALLBACKS_TOTAL: &str = "encryption_argon2_fallbacks_total";
+/// 健康的CRUD API实例数
+pub const HEALTHY_INSTANCES: &str = "encryption_healthy_instances";
+
+/// 耗时直方图的分桶（秒）
+const DURATION_BUCKETS: &[f64] = &[0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Prometheus导出句柄，安装后用于渲染指标
+static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+/// 安装全局指标记录器，只需在启动时调用一次
+pub fn install() -> Result<()> {
+    let handle = PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Suffix("_duration_seconds".to_string()), DURATION_BUCKETS)?
+        .install_recorder()?;
+    HANDLE.set(handle).map_err(|_| anyhow::anyhow!("指标记录器已安装"))?;
+    Ok(())
+}
+
+/// 以Prometheus文本格式渲染当前指标，未安装记录器时返回空字符串
+pub fn render() -> String {
+    HANDLE.get().map(|handle| handle.render()).unwrap_or_default()
+}
+
+/// 记录一次加解密的耗时，失败时同时计入失败次数
+pub fn record_crypto<T>(operation: &'static str, started: Instant, result: &Result<T>) {
+    metrics::histogram!(CRYPTO_DURATION_SECONDS, "operation" => operation).record(started.elapsed().as_secs_f64());
+    if result.is_err() {
+        metrics::counter!(CRYPTO_FAILURES_TOTAL, "operation" => operation).increment(1);
+    }
+}
+
+/// 收集日志输出，仅用于测试
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub struct LogCapture(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+#[cfg(test)]
+impl LogCapture {
+    /// 在当前线程安装收集WARN及以上级别日志的订阅者，返回的guard销毁前有效
+    pub fn install(&self) -> tracing::subscriber::DefaultGuard {
+        let writer = self.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .with_max_level(tracing::Level::WARN)
+            .finish();
+        tracing::subscriber::set_default(subscriber)
+    }
+
+    /// 已收集的日志
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.lock().unwrap()).into_owned()
+    }
+}
+
+#[cfg(test)]
+impl std::io::Write for LogCapture {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
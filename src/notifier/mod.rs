@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use futures::future::BoxFuture;
+use reqwest::Client;
+
+use crate::config::{NotifierType, ReminderConfig};
+
+/// 提醒发送目标
+pub trait Notifier: Debug + Send + Sync {
+    /// 目标名称，用于日志和限流通道
+    fn name(&self) -> &'static str;
+
+    /// 发送一条文本提醒
+    fn send<'a>(&'a self, message: &'a str) -> BoxFuture<'a, Result<()>>;
+}
+
+/// 按配置创建提醒发送目标，未配置Webhook URL时返回None
+pub fn from_config(config: &ReminderConfig, http_client: Client) -> Option<Arc<dyn Notifier>> {
+    let webhook_url = config.webhook_url.clone()?;
+    let notifier: Arc<dyn Notifier> = match config.notifier_type {
+        NotifierType::Wechat => Arc::new(WechatNotifier { http_client, webhook_url }),
+        NotifierType::Slack => Arc::new(SlackNotifier { http_client, webhook_url }),
+        NotifierType::Generic => Arc::new(GenericJsonNotifier { http_client, webhook_url }),
+    };
+    Some(notifier)
+}
+
+/// 以JSON请求体POST到Webhook，非2xx响应视为失败
+async fn post_json(http_client: &Client, webhook_url: &str, payload: serde_json::Value) -> Result<()> {
+    http_client
+        .post(webhook_url)
+        .json(&payload)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// 企业微信群机器人
+#[derive(Debug)]
+pub struct WechatNotifier {
+    http_client: Client,
+    webhook_url: String,
+}
+
+impl Notifier for WechatNotifier {
+    fn name(&self) -> &'static str {
+        "wechat"
+    }
+
+    fn send<'a>(&'a self, message: &'a str) -> BoxFuture<'a, Result<()>> {
+        let payload = serde_json::json!({
+            "msgtype": "text",
+            "text": {
+                "content": message,
+            }
+        });
+        Box::pin(post_json(&self.http_client, &self.webhook_url, payload))
+    }
+}
+
+/// Slack Incoming Webhook
+#[derive(Debug)]
+pub struct SlackNotifier {
+    http_client: Client,
+    webhook_url: String,
+}
+
+impl Notifier for SlackNotifier {
+    fn name(&self) -> &'static str {
+        "slack"
+    }
+
+    fn send<'a>(&'a self, message: &'a str) -> BoxFuture<'a, Result<()>> {
+        let payload = serde_json::json!({ "text": message });
+        Box::pin(post_json(&self.http_client, &self.webhook_url, payload))
+    }
+}
+
+/// 通用JSON Webhook，请求体为 {"message": "..."}
+#[derive(Debug)]
+pub struct GenericJsonNotifier {
+    http_client: Client,
+    webhook_url: String,
+}
+
+impl Notifier for GenericJsonNotifier {
+    fn name(&self) -> &'static str {
+        "generic"
+    }
+
+    fn send<'a>(&'a self, message: &'a str) -> BoxFuture<'a, Result<()>> {
+        let payload = serde_json::json!({ "message": message });
+        Box::pin(post_json(&self.http_client, &self.webhook_url, payload))
+    }
+}
+
+/// 限流判定结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum RateDecision {
+    /// 允许发送，suppressed为上一窗口被合并未发送的提醒数
+    Send { suppressed: u32 },
+    /// 超出限制，不发送
+    Suppress,
+}
+
+/// 单个通道的限流窗口
+#[derive(Debug)]
+struct Window {
+    /// 窗口开始时间
+    started: Instant,
+    /// 本窗口已发送数
+    sent: u32,
+    /// 累计未发送数，在下一次发送时汇总
+    suppressed: u32,
+}
+
+/// 提醒限流器，按通道（目标和级别）限制每个时间窗口内发送的提醒数
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    /// 每个窗口最多发送的提醒数
+    max_per_window: u32,
+    /// 窗口长度
+    window: Duration,
+    /// 各通道的窗口状态
+    windows: Arc<Mutex<HashMap<String, Window>>>,
+}
+
+impl RateLimiter {
+    /// 创建限流器，max_per_window为0时不限流
+    pub fn new(max_per_window: u32, window: Duration) -> Self {
+        Self {
+            max_per_window,
+            window,
+            windows: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// 判断指定通道的提醒是否可以发送
+    pub fn check(&self, key: &str) -> RateDecision {
+        self.check_at(key, Instant::now())
+    }
+
+    /// 按指定时间判断，便于按时间推演
+    fn check_at(&self, key: &str, now: Instant) -> RateDecision {
+        if self.max_per_window == 0 {
+            return RateDecision::Send { suppressed: 0 };
+        }
+
+        let mut windows = self.windows.lock().unwrap();
+        let window = windows.entry(key.to_string()).or_insert(Window {
+            started: now,
+            sent: 0,
+            suppressed: 0,
+        });
+
+        // 窗口结束后重新计数，未发送的提醒数保留到下一次发送
+        if now.duration_since(window.started) >= self.window {
+            window.started = now;
+            window.sent = 0;
+        }
+
+        if window.sent < self.max_per_window {
+            window.sent += 1;
+            let suppressed = std::mem::take(&mut window.suppressed);
+            RateDecision::Send { suppressed }
+        } else {
+            window.suppressed += 1;
+            RateDecision::Suppress
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::extract::State;
+    use axum::http::StatusCode;
+    use axum::routing::post;
+    use axum::{Json, Router};
+
+    use super::*;
+    use crate::config::AppConfig;
+
+    /// 模拟Webhook收到的请求体
+    type Received = Arc<Mutex<Vec<serde_json::Value>>>;
+
+    /// 启动记录请求体的模拟Webhook，/fail 返回500
+    async fn mock_webhook() -> (String, Received) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let received = Received::default();
+        let app = Router::new()
+            .route("/hook", post(|State(received): State<Received>, Json(body): Json<serde_json::Value>| async move {
+                received.lock().unwrap().push(body);
+            }))
+            .route("/fail", post(|| async { StatusCode::INTERNAL_SERVER_ERROR }))
+            .with_state(received.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, received)
+    }
+
+    fn notifier(vars: &[(&str, &str)]) -> Option<Arc<dyn Notifier>> {
+        let mut vars = vars.to_vec();
+        vars.push(("CRUD_API_WRITE_INSTANCE_URL", "http://127.0.0.1:1"));
+        from_config(&AppConfig::from_vars(&vars).unwrap().reminder, Client::new())
+    }
+
+    #[tokio::test]
+    async fn each_notifier_sends_its_payload_shape() {
+        let (base, received) = mock_webhook().await;
+        let webhook_url = format!("{}/hook", base);
+        for notifier_type in ["wechat", "slack", "generic"] {
+            let notifier = notifier(&[("NOTIFIER_TYPE", notifier_type), ("NOTIFIER_WEBHOOK_URL", &webhook_url)]).unwrap();
+            assert_eq!(notifier.name(), notifier_type);
+            notifier.send("hello").await.unwrap();
+        }
+        assert_eq!(*received.lock().unwrap(), [
+            serde_json::json!({ "msgtype": "text", "text": { "content": "hello" } }),
+            serde_json::json!({ "text": "hello" }),
+            serde_json::json!({ "message": "hello" }),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn wechat_is_the_backward_compatible_default() {
+        let (base, received) = mock_webhook().await;
+        let webhook_url = format!("{}/hook", base);
+        let notifier = notifier(&[("WECHAT_WEBHOOK_URL", &webhook_url)]).unwrap();
+        assert_eq!(notifier.name(), "wechat");
+        notifier.send("hello").await.unwrap();
+        assert_eq!(received.lock().unwrap()[0]["msgtype"], "text");
+
+        assert!(self::notifier(&[]).is_none());
+    }
+
+    #[tokio::test]
+    async fn webhook_error_status_fails_send() {
+        let (base, _) = mock_webhook().await;
+        let webhook_url = format!("{}/fail", base);
+        let notifier = notifier(&[("NOTIFIER_TYPE", "slack"), ("NOTIFIER_WEBHOOK_URL", &webhook_url)]).unwrap();
+        assert!(notifier.send("hello").await.is_err());
+    }
+
+    #[test]
+    fn caps_each_channel_and_summarizes_suppressed() {
+        let limiter = RateLimiter::new(3, Duration::from_secs(60));
+        let start = Instant::now();
+
+        let decisions: Vec<_> = (0..10).map(|_| limiter.check_at("wechat:warning", start)).collect();
+        assert_eq!(decisions.iter().filter(|d| matches!(d, RateDecision::Send { .. })).count(), 3);
+        // 其他通道不受影响
+        assert_eq!(limiter.check_at("slack:warning", start), RateDecision::Send { suppressed: 0 });
+
+        // 下一个窗口的第一条提醒汇总上一窗口未发送的数量
+        let next_window = start + Duration::from_secs(60);
+        assert_eq!(limiter.check_at("wechat:warning", next_window), RateDecision::Send { suppressed: 7 });
+        assert_eq!(limiter.check_at("wechat:warning", next_window), RateDecision::Send { suppressed: 0 });
+    }
+
+    #[test]
+    fn zero_limit_never_suppresses() {
+        let limiter = RateLimiter::new(0, Duration::from_secs(60));
+        assert!((0..100).all(|_| limiter.check("wechat:warning") == RateDecision::Send { suppressed: 0 }));
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 超过该客户端数时清理已回满的令牌桶
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// 单个客户端的令牌桶
+#[derive(Debug)]
+struct Bucket {
+    /// 当前令牌数
+    tokens: f64,
+    /// 上次补充令牌的时间
+    updated: Instant,
+}
+
+/// 按客户端限流的令牌桶限流器
+#[derive(Debug)]
+pub struct ClientRateLimiter {
+    /// 每秒补充的令牌数
+    rate: f64,
+    /// 令牌桶容量，即允许的突发请求数
+    burst: f64,
+    /// 各客户端的令牌桶
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl ClientRateLimiter {
+    /// 创建限流器
+    pub fn new(rate: f64, burst: u32) -> Self {
+        Self {
+            rate,
+            burst: f64::from(burst.max(1)),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 从客户端的令牌桶中取出cost个令牌，令牌不足时不扣减并返回需要等待的时间
+    ///
+    /// cost超过桶容量时按桶容量计算，即需要令牌桶全满才能通过
+    pub fn acquire(&self, key: &str, cost: u32) -> Result<(), Duration> {
+        self.acquire_at(key, cost, Instant::now())
+    }
+
+    /// 按指定时间取令牌
+    fn acquire_at(&self, key: &str, cost: u32, now: Instant) -> Result<(), Duration> {
+        let cost = f64::from(cost).min(self.burst);
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.len() > PRUNE_THRESHOLD {
+            let (rate, burst) = (self.rate, self.burst);
+            buckets.retain(|_, bucket| bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * rate < burst);
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+
+        // 按经过的时间补充令牌
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.updated = now;
+
+        if bucket.tokens >= cost {
+            bucket.tokens -= cost;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((cost - bucket.tokens) / self.rate))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn burst_exhaustion_reports_wait_and_refills() {
+        let limiter = ClientRateLimiter::new(2.0, 3);
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.acquire_at("client", 1, start).is_ok());
+        }
+        // 每秒补充2个令牌，缺1个令牌需要等待0.5秒
+        assert_eq!(limiter.acquire_at("client", 1, start), Err(Duration::from_millis(500)));
+        assert_eq!(limiter.acquire_at("client", 1, start + Duration::from_millis(250)), Err(Duration::from_millis(250)));
+
+        assert!(limiter.acquire_at("client", 1, start + Duration::from_millis(500)).is_ok());
+        assert_eq!(limiter.acquire_at("client", 1, start + Duration::from_millis(500)), Err(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn clients_have_independent_buckets() {
+        let limiter = ClientRateLimiter::new(1.0, 1);
+        let now = Instant::now();
+
+        assert!(limiter.acquire_at("a", 1, now).is_ok());
+        assert!(limiter.acquire_at("a", 1, now).is_err());
+        assert!(limiter.acquire_at("b", 1, now).is_ok());
+    }
+
+    #[test]
+    fn cost_above_burst_needs_full_bucket() {
+        let limiter = ClientRateLimiter::new(1.0, 5);
+        let now = Instant::now();
+
+        assert!(limiter.acquire_at("client", 2, now).is_ok());
+        assert_eq!(limiter.acquire_at("client", 100, now), Err(Duration::from_secs(2)));
+        assert!(limiter.acquire_at("client", 100, now + Duration::from_secs(2)).is_ok());
+    }
+}
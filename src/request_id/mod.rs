@@ -0,0 +1,36 @@
+use std::future::Future;
+
+use reqwest::RequestBuilder;
+
+/// 请求ID请求头
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+tokio::task_local! {
+    /// 当前请求的请求ID
+    static REQUEST_ID: String;
+}
+
+/// 在指定请求ID的上下文中执行
+pub async fn scope<F: Future>(request_id: String, f: F) -> F::Output {
+    REQUEST_ID.scope(request_id, f).await
+}
+
+/// 获取当前上下文的请求ID
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// 生成新的请求ID
+pub fn generate() -> String {
+    let mut bytes = [0u8; 8];
+    getrandom::getrandom(&mut bytes).expect("无法生成请求ID");
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 为出站请求附加当前上下文的请求ID
+pub fn propagate(builder: RequestBuilder) -> RequestBuilder {
+    match current() {
+        Some(request_id) => builder.header(REQUEST_ID_HEADER, request_id),
+        None => builder,
+    }
+}
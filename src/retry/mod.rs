@@ -0,0 +1,110 @@
+use std::time::{Duration, Instant};
+
+use reqwest::{RequestBuilder, Response};
+use tracing::warn;
+
+use crate::monitoring;
+
+/// 首次重试前的等待时间，之后每次翻倍
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+
+/// 单次重试的最长等待时间
+const MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+/// 发送请求，连接失败和5xx响应时按指数退避重试，最多重试retries次
+///
+/// 4xx响应说明请求本身有误，不重试
+pub async fn send_with_retry<F>(build_request: F, retries: u32) -> reqwest::Result<Response>
+where
+    F: Fn() -> RequestBuilder,
+{
+    let mut attempt = 0;
+    loop {
+        let started = Instant::now();
+        let result = build_request()
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        metrics::histogram!(monitoring::CRUD_REQUEST_DURATION_SECONDS).record(started.elapsed().as_secs_f64());
+
+        match result {
+            Ok(response) => return Ok(response),
+            Err(e) if attempt < retries && is_retryable(&e) => {
+                let backoff = INITIAL_BACKOFF
+                    .saturating_mul(2u32.saturating_pow(attempt))
+                    .min(MAX_BACKOFF);
+                attempt += 1;
+                warn!("CRUD API请求失败，{}ms后进行第{}次重试: {}", backoff.as_millis(), attempt, e);
+                tokio::time::sleep(backoff).await;
+            },
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// 判断错误是否值得重试，即是否由实例本身不可用导致
+pub fn is_retryable(e: &reqwest::Error) -> bool {
+    match e.status() {
+        Some(status) => status.is_server_error(),
+        None => e.is_connect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use axum::Router;
+    use axum::extract::State;
+    use axum::http::StatusCode;
+    use axum::routing::get;
+
+    use super::*;
+
+    /// 启动模拟实例：前failures次请求返回status，之后返回200，返回地址和收到的请求数
+    async fn flaky_server(failures: usize, status: StatusCode) -> (String, Arc<AtomicUsize>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/resource", listener.local_addr().unwrap());
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let app = Router::new()
+            .route("/resource", get(move |State(attempts): State<Arc<AtomicUsize>>| async move {
+                if attempts.fetch_add(1, Ordering::SeqCst) < failures { status } else { StatusCode::OK }
+            }))
+            .with_state(attempts.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, attempts)
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors_until_success() {
+        let (url, attempts) = flaky_server(2, StatusCode::SERVICE_UNAVAILABLE).await;
+        let client = reqwest::Client::new();
+
+        let started = Instant::now();
+        let response = send_with_retry(|| client.get(&url), 3).await.unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+        // 两次退避：100ms + 200ms
+        assert!(started.elapsed() >= Duration::from_millis(300));
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_configured_retries() {
+        let (url, attempts) = flaky_server(2, StatusCode::BAD_GATEWAY).await;
+        let client = reqwest::Client::new();
+
+        let e = send_with_retry(|| client.get(&url), 1).await.unwrap_err();
+        assert_eq!(e.status().map(|status| status.as_u16()), Some(502));
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        let (url, attempts) = flaky_server(2, StatusCode::BAD_REQUEST).await;
+        let client = reqwest::Client::new();
+
+        assert!(send_with_retry(|| client.get(&url), 3).await.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+}
@@ -1,15 +1,23 @@
 use std::sync::{Arc, RwLock};
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, watch};
+use tokio::task::JoinHandle;
 use tokio::time::interval;
-use tracing::{info, error};
+use tracing::{debug, info, warn, error};
 use anyhow::Result;
-use reqwest::Client;
-use serde::Deserialize;
+use futures::future::join_all;
+use rand::Rng;
+use reqwest::{Client, Response};
+use serde::Serialize;
 
-use crate::config::{AppConfig, SchedulerStrategy, CrudApiInstance};
+use crate::config::{AppConfig, SchedulerStrategy, SelectionPolicy, CrudApiInstance};
+use crate::request_id;
+use crate::monitoring;
+use crate::retry;
 
 /// 实例健康状态
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum InstanceHealthStatus {
     /// 健康
     Healthy,
@@ -19,10 +27,142 @@ pub enum InstanceHealthStatus {
     Unknown,
 }
 
-/// 健康检查响应
-#[derive(Debug, Deserialize)]
-struct HealthCheckResponse {
-    status: String,
+/// 选择实例失败的原因
+#[derive(Debug, thiserror::Error)]
+pub enum SelectError {
+    /// 没有可用的写实例，但读实例可用，处于只读降级状态，写请求应走缓存路径
+    #[error("没有健康的写实例可用，仅读实例可用（只读降级）")]
+    WriteUnavailableReadOnly,
+    /// 没有可处理该类请求的实例
+    #[error("没有健康的{0}实例可用")]
+    NoHealthyInstance(&'static str),
+}
+
+/// 熔断器状态
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// 正常放行请求
+    Closed,
+    /// 已熔断，跳过该实例
+    Open,
+    /// 熔断冷却结束，放行一次探测请求
+    HalfOpen,
+}
+
+/// 单个实例的熔断器
+#[derive(Debug, Clone)]
+struct CircuitBreaker {
+    state: BreakerState,
+    /// 连续失败次数
+    consecutive_failures: u32,
+    /// 连续健康检查成功次数
+    consecutive_successes: u32,
+    /// 熔断或探测开始的时间
+    since: Instant,
+    /// 半开状态下是否已有探测请求在进行
+    probe_in_flight: bool,
+}
+
+impl CircuitBreaker {
+    fn new() -> Self {
+        Self {
+            state: BreakerState::Closed,
+            consecutive_failures: 0,
+            consecutive_successes: 0,
+            since: Instant::now(),
+            probe_in_flight: false,
+        }
+    }
+
+    /// 当前是否可以向该实例发送请求
+    fn is_available(&self, open_duration: Duration) -> bool {
+        match self.state {
+            BreakerState::Closed => true,
+            BreakerState::Open => self.since.elapsed() >= open_duration,
+            // 探测请求未返回结果且超过冷却时间时，允许重新探测
+            BreakerState::HalfOpen => !self.probe_in_flight || self.since.elapsed() >= open_duration,
+        }
+    }
+
+    /// 实例被选中时更新状态，冷却结束的熔断器转为半开并占用探测名额
+    fn on_selected(&mut self) {
+        if self.state != BreakerState::Closed {
+            self.state = BreakerState::HalfOpen;
+            self.since = Instant::now();
+            self.probe_in_flight = true;
+        }
+    }
+
+    /// 记录一次成功请求
+    fn record_success(&mut self) {
+        self.state = BreakerState::Closed;
+        self.consecutive_failures = 0;
+        self.consecutive_successes = 0;
+        self.probe_in_flight = false;
+    }
+
+    /// 记录一次健康检查成功，连续成功达到阈值时关闭熔断器，返回熔断器是否因此关闭
+    fn record_health_success(&mut self, threshold: u32) -> bool {
+        self.consecutive_failures = 0;
+        if self.state == BreakerState::Closed {
+            return false;
+        }
+
+        self.consecutive_successes = self.consecutive_successes.saturating_add(1);
+        if self.consecutive_successes >= threshold {
+            self.record_success();
+            return true;
+        }
+        false
+    }
+
+    /// 记录一次失败请求或健康检查失败，返回熔断器是否因此打开
+    fn record_failure(&mut self, threshold: u32) -> bool {
+        self.consecutive_successes = 0;
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        let should_open = match self.state {
+            BreakerState::HalfOpen => true,
+            BreakerState::Closed => threshold > 0 && self.consecutive_failures >= threshold,
+            BreakerState::Open => false,
+        };
+        if should_open {
+            self.state = BreakerState::Open;
+            self.since = Instant::now();
+            self.probe_in_flight = false;
+        }
+        should_open
+    }
+}
+
+/// 实例运行状态
+#[derive(Debug, Clone)]
+struct InstanceState {
+    instance: CrudApiInstance,
+    status: InstanceHealthStatus,
+    breaker: CircuitBreaker,
+    /// 连续健康检查成功次数
+    probe_successes: u32,
+    /// 连续健康检查失败次数
+    probe_failures: u32,
+    /// 读请求平滑加权轮询的当前权重
+    read_weight: i64,
+    /// 写请求平滑加权轮询的当前权重，与读请求分别计数，mixed实例的读请求不影响写请求的轮询
+    write_weight: i64,
+    /// 上次被选中的时间，从未被选中时为None
+    last_used: Option<Instant>,
+}
+
+/// 实例状态信息
+#[derive(Debug, Clone, Serialize)]
+pub struct InstanceStatus {
+    pub id: String,
+    pub url: String,
+    pub status: InstanceHealthStatus,
+    /// 熔断器状态
+    pub breaker: BreakerState,
+    /// 连续失败次数
+    pub consecutive_failures: u32,
 }
 
 /// 调度器结构体
@@ -32,147 +172,617 @@ pub struct CrudApiScheduler {
     config: Arc<AppConfig>,
     /// HTTP客户端
     http_client: Client,
-    /// 实例健康状态
-    instance_health: Arc<RwLock<Vec<(CrudApiInstance, InstanceHealthStatus)>>>,
-    /// 负载均衡计数器
-    load_balance_counter: Arc<RwLock<usize>>,
+    /// 实例健康状态及熔断器
+    instance_health: Arc<RwLock<Vec<InstanceState>>>,
+    /// 上一轮健康检查完成的时间，持有锁期间执行检查，保证同一时间只有一轮检查
+    last_sweep: Arc<Mutex<Option<Instant>>>,
+}
+
+impl InstanceState {
+    /// 读或写请求的平滑加权轮询当前权重
+    fn current_weight(&mut self, is_write_operation: bool) -> &mut i64 {
+        if is_write_operation {
+            &mut self.write_weight
+        } else {
+            &mut self.read_weight
+        }
+    }
+}
+
+/// 平滑加权轮询：每个候选实例的当前权重加上自身权重，选出当前权重最大的实例，再减去候选权重总和
+///
+/// 读写请求使用各自的当前权重
+fn select_round_robin(health_status: &mut [InstanceState], usable: &[usize], is_write_operation: bool) -> usize {
+    let mut total_weight = 0i64;
+    let mut selected = usable[0];
+    let mut selected_weight = i64::MIN;
+    for &index in usable {
+        let state = &mut health_status[index];
+        let weight = i64::from(state.instance.weight);
+        let current_weight = state.current_weight(is_write_operation);
+        *current_weight += weight;
+        total_weight += weight;
+        if *current_weight > selected_weight {
+            selected = index;
+            selected_weight = *current_weight;
+        }
+    }
+    *health_status[selected].current_weight(is_write_operation) -= total_weight;
+    selected
+}
+
+/// 按权重随机选择，权重总和为0时等概率选择
+fn select_random(health_status: &[InstanceState], usable: &[usize]) -> usize {
+    let mut rng = rand::thread_rng();
+    let total_weight: u64 = usable.iter().map(|&index| u64::from(health_status[index].instance.weight)).sum();
+    if total_weight == 0 {
+        return usable[rng.gen_range(0..usable.len())];
+    }
+
+    let mut point = rng.gen_range(0..total_weight);
+    for &index in usable {
+        let weight = u64::from(health_status[index].instance.weight);
+        if point < weight {
+            return index;
+        }
+        point -= weight;
+    }
+    usable[usable.len() - 1]
 }
 
 impl CrudApiScheduler {
     /// 创建新的调度器实例
-    pub fn new(config: Arc<AppConfig>) -> Self {
-        let http_client = Client::builder()
-            .timeout(Duration::from_millis(config.crud_api.timeout))
-            .build()
-            .expect("无法创建HTTP客户端");
-
+    pub fn new(config: Arc<AppConfig>, http_client: Client) -> Self {
         // 初始化实例健康状态
         let instance_health = config.crud_api.instances.iter()
-            .map(|instance| (instance.clone(), InstanceHealthStatus::Unknown))
+            .map(|instance| InstanceState {
+                instance: instance.clone(),
+                status: InstanceHealthStatus::Unknown,
+                breaker: CircuitBreaker::new(),
+                probe_successes: 0,
+                probe_failures: 0,
+                read_weight: 0,
+                write_weight: 0,
+                last_used: None,
+            })
             .collect();
 
-        let scheduler = Self {
+        Self {
             config,
             http_client,
             instance_health: Arc::new(RwLock::new(instance_health)),
-            load_balance_counter: Arc::new(RwLock::new(0)),
-        };
-
-        scheduler
+            last_sweep: Arc::new(Mutex::new(None)),
+        }
     }
 
     /// 启动健康检查
-    pub async fn start_health_check(&self) {
+    pub async fn start_health_check(&self, mut shutdown: watch::Receiver<bool>) -> JoinHandle<()> {
         let scheduler = self.clone();
         tokio::spawn(async move {
             let mut interval = interval(Duration::from_secs(scheduler.config.crud_api.health_check_interval));
             loop {
-                interval.tick().await;
+                tokio::select! {
+                    _ = interval.tick() => {},
+                    _ = shutdown.changed() => break,
+                }
                 if let Err(e) = scheduler.perform_health_check().await {
                     error!("健康检查失败: {:?}", e);
                 }
             }
-        });
+            info!("健康检查任务已停止");
+        })
+    }
+
+    /// 执行一轮健康检查，已有检查在进行时等待其完成后再执行，供定期检查使用
+    pub async fn perform_health_check(&self) -> Result<()> {
+        let mut last_sweep = self.last_sweep.lock().await;
+        self.sweep().await?;
+        *last_sweep = Some(Instant::now());
+        Ok(())
+    }
+
+    /// 按需执行健康检查，返回是否实际执行了检查
+    ///
+    /// 等待期间已有一轮检查完成，或距上一轮检查不足CRUD_API_HEALTH_CHECK_MIN_INTERVAL时不再探测，
+    /// 沿用上一轮的结果，避免频繁触发时反复请求各实例的健康检查接口
+    pub async fn request_health_check(&self) -> Result<bool> {
+        let requested = Instant::now();
+        let mut last_sweep = self.last_sweep.lock().await;
+        let min_interval = Duration::from_secs(self.config.crud_api.health_check_min_interval);
+        if let Some(last) = *last_sweep
+            && (last >= requested || last.elapsed() < min_interval)
+        {
+            debug!(elapsed_ms = last.elapsed().as_millis() as u64, "距上一轮健康检查不足最小间隔，沿用上一轮结果");
+            return Ok(false);
+        }
+        self.sweep().await?;
+        *last_sweep = Some(Instant::now());
+        Ok(true)
     }
 
-    /// 执行健康检查
-    async fn perform_health_check(&self) -> Result<()> {
+    /// 探测所有实例并更新健康状态，调用方需持有last_sweep锁
+    async fn sweep(&self) -> Result<()> {
         // 1. 首先获取所有实例的副本，避免在await期间持有锁
         let instances: Vec<CrudApiInstance> = {
             let health_status = self.instance_health.read().unwrap();
-            health_status.iter().map(|(instance, _)| instance.clone()).collect()
+            health_status.iter().map(|state| state.instance.clone()).collect()
         };
         
-        // 2. 检查每个实例的健康状态，不持有锁
-        let mut new_health_status = Vec::with_capacity(instances.len());
-        for instance in instances {
-            let health_url = format!("{}/health", instance.url);
-            
-            let status = match self.http_client.get(&health_url).send().await {
-                Ok(response) => {
-                    if response.status().is_success() {
-                        match response.json::<HealthCheckResponse>().await {
-                            Ok(health_response) => {
-                                if health_response.status == "ok" {
-                                    InstanceHealthStatus::Healthy
-                                } else {
-                                    InstanceHealthStatus::Unhealthy
-                                }
-                            },
-                            Err(_) => InstanceHealthStatus::Unhealthy,
-                        }
-                    } else {
-                        InstanceHealthStatus::Unhealthy
-                    }
-                },
-                Err(_) => InstanceHealthStatus::Unhealthy,
-            };
-            
-            new_health_status.push((instance, status));
-        }
+        // 按配置为本轮健康检查生成请求ID
+        let sweep_request_id = self.config.request_id.health_check
+            .then(|| format!("health-{}", request_id::generate()));
+
+        // 2. 并发检查所有实例的健康状态，不持有锁
+        let checks = instances.into_iter().map(|instance| async {
+            let status = self.check_instance(&instance, sweep_request_id.as_deref()).await;
+            (instance, status)
+        });
+        let new_health_status = join_all(checks).await;
         
-        // 3. 更新健康状态，只在更新时持有锁
+        // 3. 更新健康状态和熔断器，只在更新时持有锁
+        let failure_threshold = self.config.crud_api.breaker_failure_threshold;
+        let success_threshold = self.config.crud_api.breaker_success_threshold;
+        let unhealthy_threshold = self.config.crud_api.unhealthy_threshold.max(1);
+        let healthy_threshold = self.config.crud_api.healthy_threshold.max(1);
         let mut health_status = self.instance_health.write().unwrap();
-        for i in 0..health_status.len() {
-            let (ref instance, ref new_status) = new_health_status[i];
-            let current_status = &mut health_status[i].1;
-            
-            if *current_status != *new_status {
-                info!("CRUD API实例 {:?} 健康状态变化: {:?} -> {:?}", instance.id, current_status, new_status);
-                *current_status = new_status.clone();
+        for (state, (instance, new_status)) in health_status.iter_mut().zip(new_health_status) {
+            // 连续探测结果达到阈值才切换状态，避免偶发失败导致实例反复上下线；首次检查直接确定状态
+            let status = if new_status == InstanceHealthStatus::Healthy {
+                state.probe_failures = 0;
+                state.probe_successes = state.probe_successes.saturating_add(1);
+                if state.status == InstanceHealthStatus::Unknown || state.probe_successes >= healthy_threshold {
+                    InstanceHealthStatus::Healthy
+                } else {
+                    state.status.clone()
+                }
+            } else {
+                state.probe_successes = 0;
+                state.probe_failures = state.probe_failures.saturating_add(1);
+                if state.status == InstanceHealthStatus::Unknown || state.probe_failures >= unhealthy_threshold {
+                    new_status.clone()
+                } else {
+                    state.status.clone()
+                }
+            };
+            if state.status != status {
+                info!("CRUD API实例 {:?} 健康状态变化: {:?} -> {:?}", instance.id, state.status, status);
             }
+
+            // 健康检查结果与请求结果共同决定熔断状态
+            if new_status == InstanceHealthStatus::Healthy {
+                if state.breaker.record_health_success(success_threshold) {
+                    info!("CRUD API实例 {} 连续 {} 次健康检查成功，熔断恢复", instance.id, success_threshold);
+                }
+            } else if state.breaker.record_failure(failure_threshold) {
+                warn!("CRUD API实例 {} 连续失败 {} 次，已熔断", instance.id, state.breaker.consecutive_failures);
+            }
+            state.status = status;
         }
+
+        let healthy_count = health_status.iter()
+            .filter(|state| state.status == InstanceHealthStatus::Healthy)
+            .count();
+        metrics::gauge!(monitoring::HEALTHY_INSTANCES).set(healthy_count as f64);
         
         Ok(())
     }
 
+    /// 检查单个实例的健康状态
+    async fn check_instance(&self, instance: &CrudApiInstance, sweep_request_id: Option<&str>) -> InstanceHealthStatus {
+        let health_url = format!("{}{}", instance.url, instance.health_path);
+        let expect = &instance.health_expect;
+
+        let mut request = instance.health_check_headers()
+            .apply(self.http_client.get(&health_url).timeout(instance.request_timeout()));
+        if let Some(id) = sweep_request_id {
+            request = request.header(request_id::REQUEST_ID_HEADER, id);
+        }
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(_) => return InstanceHealthStatus::Unhealthy,
+        };
+
+        // 配置了期望状态码时必须一致，否则要求2xx
+        let status_ok = match expect.status {
+            Some(status) => response.status().as_u16() == status,
+            None => response.status().is_success(),
+        };
+        if !status_ok {
+            return InstanceHealthStatus::Unhealthy;
+        }
+
+        // 未配置期望字段时不检查响应体
+        let Some(ref field) = expect.field else {
+            return InstanceHealthStatus::Healthy;
+        };
+        let max_bytes = self.config.crud_api.health_response_max_bytes;
+        let body = match read_body_limited(response, max_bytes).await {
+            Ok(Some(body)) => body,
+            Ok(None) => {
+                warn!("CRUD API实例 {} 健康检查响应超过 {} 字节，视为不健康", instance.id, max_bytes);
+                return InstanceHealthStatus::Unhealthy;
+            },
+            Err(_) => return InstanceHealthStatus::Unhealthy,
+        };
+        match serde_json::from_slice::<serde_json::Value>(&body) {
+            Ok(body) if field.matches(&body) => InstanceHealthStatus::Healthy,
+            _ => InstanceHealthStatus::Unhealthy,
+        }
+    }
+
+    /// 熔断后放行探测请求前的冷却时间
+    fn breaker_open_duration(&self) -> Duration {
+        Duration::from_secs(self.config.crud_api.breaker_open_duration)
+    }
+
+    /// 实例是否健康、类型匹配且未被熔断
+    fn is_usable(&self, state: &InstanceState, instance_type: &str) -> bool {
+        state.status == InstanceHealthStatus::Healthy &&
+        (state.instance.instance_type == instance_type || state.instance.instance_type == "mixed") &&
+        state.breaker.is_available(self.breaker_open_duration())
+    }
+
     /// 获取健康的实例列表
     fn get_healthy_instances(&self, instance_type: &str) -> Vec<CrudApiInstance> {
         let health_status = self.instance_health.read().unwrap();
         
         health_status.iter()
-            .filter(|(instance, status)| {
-                *status == InstanceHealthStatus::Healthy && 
-                (instance.instance_type == instance_type || instance.instance_type == "mixed")
-            })
-            .map(|(instance, _)| instance.clone())
+            .filter(|state| self.is_usable(state, instance_type))
+            .map(|state| state.instance.clone())
             .collect()
     }
 
+    /// 是否存在可处理该类请求的健康实例，读请求同样考虑降级使用的写实例
+    pub fn has_healthy_instance(&self, is_write_operation: bool) -> bool {
+        if is_write_operation {
+            !self.get_healthy_instances("write").is_empty()
+        } else {
+            !self.get_healthy_instances("read").is_empty() || !self.get_healthy_instances("write").is_empty()
+        }
+    }
+
     /// 根据请求类型选择实例
+    ///
+    /// 降级顺序：
+    /// - 写请求：write和mixed实例；都不可用时不会改用只读实例，读实例可用时返回
+    ///   [`SelectError::WriteUnavailableReadOnly`]，由调用方改走缓存路径
+    /// - 读请求：read和mixed实例；都不可用时降级到write实例，写实例总能读到最新数据
     pub fn select_instance(&self, is_write_operation: bool) -> Result<CrudApiInstance> {
         // 统一调度逻辑：所有模式都使用相同的逻辑
         let instance_type = if is_write_operation { "write" } else { "read" };
-        let healthy_instances = self.get_healthy_instances(instance_type);
-        
-        if healthy_instances.is_empty() {
-            return Err(anyhow::anyhow!("没有健康的{}实例可用", instance_type));
+
+        // 选择和占用半开探测名额需要在同一把写锁内完成
+        let mut health_status = self.instance_health.write().unwrap();
+        let usable_of = |instance_type: &str| -> Vec<usize> {
+            health_status.iter()
+                .enumerate()
+                .filter(|(_, state)| self.is_usable(state, instance_type))
+                .map(|(index, _)| index)
+                .collect()
+        };
+        let mut usable = usable_of(instance_type);
+
+        if usable.is_empty() {
+            if is_write_operation {
+                if !usable_of("read").is_empty() {
+                    return Err(SelectError::WriteUnavailableReadOnly.into());
+                }
+                return Err(SelectError::NoHealthyInstance("write").into());
+            }
+            usable = usable_of("write");
+            if usable.is_empty() {
+                return Err(SelectError::NoHealthyInstance("read").into());
+            }
+            warn!("没有健康的读实例可用，读请求降级到写实例");
         }
         
         // 检查是否为单实例模式
         let is_single_mode = self.config.crud_api.strategy == SchedulerStrategy::Single;
         
-        if is_single_mode {
+        let selected = if is_single_mode {
             // 单实例模式：直接返回第一个健康实例
-            Ok(healthy_instances.first().cloned().unwrap())
+            usable[0]
         } else {
-            // 读写分离或负载均衡模式：使用轮询负载均衡
-            let mut counter = self.load_balance_counter.write().unwrap();
-            let index = *counter % healthy_instances.len();
-            *counter = *counter + 1;
-            
-            Ok(healthy_instances[index].clone())
+            // 读写分离或负载均衡模式：按配置的选择策略
+            match self.config.crud_api.selection_policy {
+                SelectionPolicy::RoundRobin => select_round_robin(&mut health_status, &usable, is_write_operation),
+                SelectionPolicy::Random => select_random(&health_status, &usable),
+                // 从未被选中的实例优先
+                SelectionPolicy::LeastRecentlyUsed => usable.iter()
+                    .copied()
+                    .min_by_key(|&index| health_status[index].last_used)
+                    .unwrap_or(usable[0]),
+            }
+        };
+
+        let state = &mut health_status[selected];
+        state.last_used = Some(Instant::now());
+        state.breaker.on_selected();
+        Ok(state.instance.clone())
+    }
+
+    /// 记录对实例请求的结果，用于熔断判断
+    ///
+    /// 只有连接失败和5xx响应计为失败，4xx说明实例可用
+    pub fn record_request_result(&self, instance_id: &str, result: &reqwest::Result<Response>) {
+        let failed = matches!(result, Err(e) if retry::is_retryable(e));
+        let threshold = self.config.crud_api.breaker_failure_threshold;
+
+        let mut health_status = self.instance_health.write().unwrap();
+        let Some(state) = health_status.iter_mut().find(|state| state.instance.id == instance_id) else {
+            return;
+        };
+
+        if !failed {
+            if state.breaker.state != BreakerState::Closed {
+                info!("CRUD API实例 {} 探测成功，熔断恢复", instance_id);
+            }
+            state.breaker.record_success();
+        } else if state.breaker.record_failure(threshold) {
+            warn!("CRUD API实例 {} 连续失败 {} 次，已熔断", instance_id, state.breaker.consecutive_failures);
         }
     }
 
     /// 获取所有实例状态
-    pub fn get_all_instance_status(&self) -> Vec<(String, String, InstanceHealthStatus)> {
+    pub fn get_all_instance_status(&self) -> Vec<InstanceStatus> {
         let health_status = self.instance_health.read().unwrap();
         
         health_status.iter()
-            .map(|(instance, status)| {
-                (instance.id.clone(), instance.url.clone(), status.clone())
+            .map(|state| InstanceStatus {
+                id: state.instance.id.clone(),
+                url: state.instance.url.clone(),
+                status: state.status.clone(),
+                breaker: state.breaker.state,
+                consecutive_failures: state.breaker.consecutive_failures,
             })
             .collect()
     }
 }
+
+/// 读取响应体，超过max_bytes时停止读取并返回None
+async fn read_body_limited(mut response: Response, max_bytes: usize) -> reqwest::Result<Option<Vec<u8>>> {
+    if response.content_length().is_some_and(|len| len > max_bytes as u64) {
+        return Ok(None);
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > max_bytes {
+            return Ok(None);
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(Some(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use axum::Router;
+    use axum::extract::State;
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+    use axum::routing::get;
+
+    use super::*;
+
+    /// 启动健康检查结果可切换的模拟实例，返回地址和健康开关
+    async fn switchable_instance() -> (String, Arc<AtomicBool>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let healthy = Arc::new(AtomicBool::new(true));
+        let app = Router::new()
+            .route("/health", get(|State(healthy): State<Arc<AtomicBool>>| async move {
+                if healthy.load(Ordering::SeqCst) {
+                    axum::Json(serde_json::json!({ "status": "ok" })).into_response()
+                } else {
+                    StatusCode::SERVICE_UNAVAILABLE.into_response()
+                }
+            }))
+            .with_state(healthy.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, healthy)
+    }
+
+    fn scheduler(vars: &[(&str, &str)]) -> CrudApiScheduler {
+        let config = AppConfig::from_vars(vars).unwrap();
+        CrudApiScheduler::new(Arc::new(config), Client::new())
+    }
+
+    fn breaker_states(scheduler: &CrudApiScheduler) -> Vec<BreakerState> {
+        scheduler
+            .get_all_instance_status()
+            .iter()
+            .map(|status| status.breaker)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn health_checks_drive_circuit_breaker() {
+        let (url, healthy) = switchable_instance().await;
+        let scheduler = scheduler(&[
+            ("CRUD_API_BACKEND_TYPE", "single"),
+            ("CRUD_API_WRITE_INSTANCE_URL", &url),
+            ("CIRCUIT_BREAKER_FAILURE_THRESHOLD", "2"),
+            ("CIRCUIT_BREAKER_SUCCESS_THRESHOLD", "2"),
+            ("CIRCUIT_BREAKER_OPEN_DURATION", "3600"),
+            ("CRUD_API_UNHEALTHY_THRESHOLD", "1"),
+            ("CRUD_API_HEALTHY_THRESHOLD", "1"),
+        ]);
+        scheduler.perform_health_check().await.unwrap();
+        assert!(scheduler.select_instance(true).is_ok());
+
+        // 连续两次健康检查失败后熔断
+        healthy.store(false, Ordering::SeqCst);
+        scheduler.perform_health_check().await.unwrap();
+        assert!(breaker_states(&scheduler).iter().all(|state| *state == BreakerState::Closed));
+        scheduler.perform_health_check().await.unwrap();
+        assert!(breaker_states(&scheduler).iter().all(|state| *state == BreakerState::Open));
+        assert!(scheduler.select_instance(true).is_err());
+
+        // 健康状态恢复后，熔断器仍需连续成功达到阈值才关闭
+        healthy.store(true, Ordering::SeqCst);
+        scheduler.perform_health_check().await.unwrap();
+        assert!(scheduler
+            .get_all_instance_status()
+            .iter()
+            .all(|status| status.status == InstanceHealthStatus::Healthy));
+        assert!(scheduler.select_instance(true).is_err());
+        scheduler.perform_health_check().await.unwrap();
+        assert!(breaker_states(&scheduler).iter().all(|state| *state == BreakerState::Closed));
+        assert!(scheduler.select_instance(true).is_ok());
+    }
+
+    /// 启动健康检查接口响应缓慢的模拟实例
+    async fn slow_instance() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let app = Router::new().route("/health", get(|| async {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            axum::Json(serde_json::json!({ "status": "ok" }))
+        }));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    #[tokio::test]
+    async fn slow_instances_are_checked_concurrently() {
+        const INSTANCES: usize = 6;
+        let url = slow_instance().await;
+        let ids: Vec<_> = (0..INSTANCES).map(|i| format!("crud-{}", i)).collect();
+        let mut vars = vec![
+            ("CRUD_API_BACKEND_TYPE".to_string(), "load_balance".to_string()),
+            ("CRUD_API_WRITE_INSTANCE_URL".to_string(), url.clone()),
+        ];
+        for (i, id) in ids.iter().enumerate() {
+            vars.push((format!("CRUD_API_INSTANCE_{}_ID", i), id.clone()));
+            vars.push((format!("CRUD_API_INSTANCE_{}_URL", i), url.clone()));
+            vars.push((format!("CRUD_API_INSTANCE_{}_TIMEOUT", i), "500".to_string()));
+        }
+        let vars: Vec<_> = vars.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        let scheduler = scheduler(&vars);
+
+        // 逐个检查需要 INSTANCES 个超时窗口，并发检查约一个窗口即可完成
+        let started = Instant::now();
+        scheduler.perform_health_check().await.unwrap();
+        let elapsed = started.elapsed();
+        assert!(elapsed < Duration::from_millis(1500), "健康检查耗时 {:?}", elapsed);
+
+        let statuses = scheduler.get_all_instance_status();
+        assert_eq!(statuses.len(), INSTANCES);
+        assert!(statuses.iter().all(|status| status.status == InstanceHealthStatus::Unhealthy));
+    }
+
+    /// 生成约1MB、内容本身健康的健康检查响应体
+    fn large_health_body() -> String {
+        format!(r#"{{"status":"ok","padding":"{}"}}"#, "x".repeat(1024 * 1024))
+    }
+
+    /// 启动返回超大健康检查响应的模拟实例，chunked 为真时不带Content-Length分块返回
+    async fn oversized_instance(chunked: bool) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let app = Router::new().route("/health", get(move || async move {
+            if chunked {
+                let chunks: Vec<_> = large_health_body().into_bytes()
+                    .chunks(8192)
+                    .map(|chunk| Ok::<_, std::io::Error>(chunk.to_vec()))
+                    .collect();
+                axum::body::Body::from_stream(futures::stream::iter(chunks)).into_response()
+            } else {
+                large_health_body().into_response()
+            }
+        }));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    async fn assert_oversized_is_unhealthy(chunked: bool) {
+        let url = oversized_instance(chunked).await;
+        let scheduler = scheduler(&[
+            ("CRUD_API_BACKEND_TYPE", "single"),
+            ("CRUD_API_WRITE_INSTANCE_URL", &url),
+            ("HEALTH_RESPONSE_MAX_BYTES", "1024"),
+        ]);
+        let logs = crate::monitoring::LogCapture::default();
+        let _guard = logs.install();
+        scheduler.perform_health_check().await.unwrap();
+
+        assert!(scheduler
+            .get_all_instance_status()
+            .iter()
+            .all(|status| status.status == InstanceHealthStatus::Unhealthy));
+        assert!(logs.contents().contains("健康检查响应超过 1024 字节"));
+    }
+
+    #[tokio::test]
+    async fn oversized_health_response_is_unhealthy() {
+        assert_oversized_is_unhealthy(false).await;
+    }
+
+    #[tokio::test]
+    async fn oversized_chunked_health_response_is_capped() {
+        assert_oversized_is_unhealthy(true).await;
+    }
+
+    /// 负载均衡模式下指向同一健康实例的多个实例，按选择策略创建调度器并完成首次健康检查
+    async fn load_balance_scheduler(policy: &str, instances: usize) -> CrudApiScheduler {
+        let (url, _) = switchable_instance().await;
+        let mut vars = vec![
+            ("CRUD_API_BACKEND_TYPE".to_string(), "load_balance".to_string()),
+            ("CRUD_API_WRITE_INSTANCE_URL".to_string(), url.clone()),
+            ("CRUD_API_SELECTION_POLICY".to_string(), policy.to_string()),
+        ];
+        for i in 0..instances {
+            vars.push((format!("CRUD_API_INSTANCE_{}_ID", i), format!("crud-{}", i)));
+            vars.push((format!("CRUD_API_INSTANCE_{}_URL", i), url.clone()));
+        }
+        let vars: Vec<_> = vars.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        let scheduler = scheduler(&vars);
+        scheduler.perform_health_check().await.unwrap();
+        scheduler
+    }
+
+    /// 连续选择若干次，返回依次选中的实例ID
+    fn selections(scheduler: &CrudApiScheduler, count: usize) -> Vec<String> {
+        (0..count).map(|_| scheduler.select_instance(true).unwrap().id).collect()
+    }
+
+    fn counts(selected: &[String]) -> std::collections::HashMap<&str, usize> {
+        let mut counts = std::collections::HashMap::new();
+        for id in selected {
+            *counts.entry(id.as_str()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    #[tokio::test]
+    async fn round_robin_cycles_through_instances() {
+        let scheduler = load_balance_scheduler("round_robin", 3).await;
+        let selected = selections(&scheduler, 300);
+        assert!(counts(&selected).values().all(|&count| count == 100));
+        assert!(selected.chunks(3).all(|cycle| cycle == ["crud-0", "crud-1", "crud-2"]));
+    }
+
+    #[tokio::test]
+    async fn random_spreads_across_instances() {
+        let scheduler = load_balance_scheduler("random", 3).await;
+        let selected = selections(&scheduler, 3000);
+        let counts = counts(&selected);
+        assert_eq!(counts.len(), 3);
+        // 期望每个实例约1000次，留足随机波动的余量
+        assert!(counts.values().all(|&count| (800..=1200).contains(&count)), "{:?}", counts);
+        // 随机选择不应与轮询顺序完全一致
+        assert!(selected.chunks(3).any(|cycle| cycle != ["crud-0", "crud-1", "crud-2"]));
+    }
+
+    #[tokio::test]
+    async fn least_recently_used_picks_the_stalest_instance() {
+        let scheduler = load_balance_scheduler("least_recently_used", 3).await;
+        let selected = selections(&scheduler, 300);
+        assert!(counts(&selected).values().all(|&count| count == 100));
+        // 任意连续三次选择覆盖全部实例
+        assert!(selected.windows(3).all(|window| window[0] != window[1] && window[1] != window[2] && window[0] != window[2]));
+    }
+}
@@ -0,0 +1,71 @@
+use axum::http::StatusCode;
+
+use crate::crypto::CryptoError;
+
+/// 服务错误，区分客户端错误和基础设施错误
+#[derive(Debug, thiserror::Error)]
+pub enum ServiceError {
+    /// 密码错误或密文被篡改，认证标签校验失败
+    #[error("密码错误或密文已被篡改")]
+    BadPassword,
+    /// 当前服务角色不允许执行该操作
+    #[error("{0}")]
+    RoleNotAllowed(String),
+    /// 请求参数无效，如资源ID格式不符合要求
+    #[error("{0}")]
+    InvalidRequest(String),
+    /// 请求的资源在CRUD API中不存在
+    #[error("{0}")]
+    NotFound(String),
+    /// CRUD API不可用或返回了无效响应
+    #[error("CRUD API不可用: {0}")]
+    UpstreamUnavailable(anyhow::Error),
+    /// 请求数据无法加解密，如密文格式无效、算法未启用，只包含客户端导致的加解密错误
+    #[error("{0}")]
+    Crypto(anyhow::Error),
+    /// 配置无效
+    #[error("配置无效: {0}")]
+    Config(anyhow::Error),
+    /// 批量请求的条目数超过上限
+    #[error("批量请求条目数超过上限: 最多 {limit} 项，提交了 {count} 项")]
+    BatchTooLarge { limit: usize, count: usize },
+    /// 严格模式批量处理中某一项失败
+    #[error("第{index}项处理失败: {source}")]
+    BatchItem { index: usize, source: Box<ServiceError> },
+    /// 其他内部错误
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl ServiceError {
+    /// 将加解密错误分类，认证标签校验失败视为密码错误
+    ///
+    /// 只有CryptoError是请求数据导致的错误；随机数生成失败、密钥长度配置错误、阻塞任务异常等其他错误属于服务端故障
+    pub fn from_crypto(e: anyhow::Error) -> Self {
+        match e.downcast_ref::<CryptoError>() {
+            Some(CryptoError::AuthenticationFailed(_)) => ServiceError::BadPassword,
+            Some(_) => ServiceError::Crypto(e),
+            None => ServiceError::Internal(e),
+        }
+    }
+
+    /// 对应的HTTP状态码
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ServiceError::Crypto(e) if matches!(e.downcast_ref::<CryptoError>(), Some(CryptoError::TooLarge { .. })) => {
+                StatusCode::PAYLOAD_TOO_LARGE
+            },
+            ServiceError::Crypto(e) if matches!(e.downcast_ref::<CryptoError>(), Some(CryptoError::InsufficientMemory { .. })) => {
+                StatusCode::SERVICE_UNAVAILABLE
+            },
+            ServiceError::BadPassword | ServiceError::InvalidRequest(_) | ServiceError::BatchTooLarge { .. } | ServiceError::Crypto(_) => {
+                StatusCode::BAD_REQUEST
+            },
+            ServiceError::RoleNotAllowed(_) => StatusCode::FORBIDDEN,
+            ServiceError::NotFound(_) => StatusCode::NOT_FOUND,
+            ServiceError::UpstreamUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ServiceError::BatchItem { source, .. } => source.status_code(),
+            ServiceError::Config(_) | ServiceError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
@@ -1,53 +1,192 @@
+use std::collections::HashSet;
+use std::future::Future;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use anyhow::Result;
+use chrono::{DateTime, Timelike, Utc};
+use futures::stream::{self, StreamExt, TryStreamExt};
 use serde::{Deserialize, Serialize};
-use reqwest::Client;
-use tracing::{warn, error};
-use crate::config::AppConfig;
-use crate::crypto::EncryptionUtils;
-use crate::scheduler::CrudApiScheduler;
-use crate::cache::{CacheManager, CacheDataType, EncryptCacheData, DecryptCacheData};
-use crate::test_instance::TestInstanceManager;
+use sha2::{Digest, Sha256};
+use axum::http::StatusCode;
+use regex::Regex;
+use reqwest::{Client, Method, RequestBuilder};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use utoipa::ToSchema;
+use tracing::{debug, info, warn, error, instrument};
+use crate::config::{AppConfig, CrudApiInstance};
+use crate::crypto::{self, EncryptionUtils, StreamDecryptor, StreamEncryptor};
+use crate::scheduler::{CrudApiScheduler, SelectError};
+use crate::decrypt_cache::{DecryptCache, DecryptSource};
+use crate::cache::{CacheManager, CacheDataType, CacheEntry, EncryptCacheData, DecryptCacheData};
+use crate::test_instance::{TestInstanceConfig, TestInstanceManager};
+use crate::audit::AuditLog;
+use crate::monitoring;
+use crate::request_id;
+use crate::retry;
+use crate::http_client;
+use crate::single_flight::SingleFlight;
+
+mod error;
+
+pub use error::ServiceError;
 
 /// 加密请求结构体
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
 pub struct EncryptRequest {
     pub data: String,
     pub password: String,
     pub resource_type: String,
+    /// 指定加密算法，必须是已启用的算法，未指定时按资源类型或默认配置选择
+    #[serde(default)]
+    pub algorithm: Option<String>,
+    /// 只加密并返回密文，不写入CRUD API和缓存，用于调试客户端集成
+    #[serde(default)]
+    pub dry_run: Option<bool>,
+}
+
+/// 更新资源请求结构体，资源类型和资源ID由请求路径指定
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct UpdateRequest {
+    pub data: String,
+    pub password: String,
+    /// 指定加密算法，必须是已启用的算法，未指定时按资源类型或默认配置选择
+    #[serde(default)]
+    pub algorithm: Option<String>,
 }
 
 /// 解密请求结构体
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
 pub struct DecryptRequest {
-    pub encrypted_data: String,
+    /// 请求中直接提供的密文；同时指定resource_id时只在CRUD API不可用时用于降级解密
+    #[serde(default)]
+    pub encrypted_data: Option<String>,
     pub password: String,
     pub resource_type: String,
     pub resource_id: Option<String>,
 }
 
+/// 字段重新加密请求结构体
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct RewrapRequest {
+    pub resource_type: String,
+    pub resource_id: String,
+    /// 当前密码
+    pub password: String,
+    /// 需要重新加密的字段，字段值必须是密文字符串
+    pub fields: Vec<String>,
+    /// 新密码，未指定时沿用当前密码
+    #[serde(default)]
+    pub new_password: Option<String>,
+    /// 指定加密算法，未指定时按资源类型或默认配置选择
+    #[serde(default)]
+    pub algorithm: Option<String>,
+}
+
+/// 字段重新加密响应结构体
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct RewrapResponse {
+    pub resource_id: String,
+    /// 已重新加密的字段
+    pub fields: Vec<String>,
+}
+
+/// 密钥轮换请求结构体
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct RotateRequest {
+    pub resource_type: String,
+    /// 当前密码
+    pub old_password: String,
+    /// 新密码
+    pub new_password: String,
+    pub encrypted_data: String,
+    /// 指定加密算法，未指定时按资源类型或默认配置选择
+    #[serde(default)]
+    pub algorithm: Option<String>,
+}
+
+/// 密钥轮换响应结构体
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct RotateResponse {
+    /// 用新密码加密的密文
+    pub encrypted_data: String,
+}
+
+/// 删除资源响应结构体
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct DeleteResponse {
+    pub resource_type: String,
+    pub resource_id: String,
+}
+
 /// 加密响应结构体
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
 pub struct EncryptResponse {
     pub encrypted_data: String,
     pub resource_id: Option<String>,
+    /// 是否已写入CRUD API；为true但resource_id为空时，CRUD API没有返回资源ID，资源无法按ID读取
+    pub persisted: bool,
+    /// 已持久化资源的地址，用于Location响应头
+    #[serde(skip)]
+    pub location: Option<String>,
 }
 
 /// 解密响应结构体
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
 pub struct DecryptResponse {
     pub data: String,
     pub resource_id: Option<String>,
 }
 
+/// 密文校验响应结构体，不包含明文
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct VerifyResponse {
+    pub valid: bool,
+    pub resource_id: Option<String>,
+}
+
+/// 批量操作单项结果
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct BatchItemResult<T> {
+    /// 条目在请求中的下标
+    pub index: usize,
+    pub success: bool,
+    pub data: Option<T>,
+    /// 失败原因，成功时为None
+    pub error: Option<String>,
+    /// 条目处理结果对应的HTTP状态码
+    pub status: u16,
+}
+
+impl<T> BatchItemResult<T> {
+    /// 根据单项处理结果创建，失败时附带状态码和返回给客户端的错误信息
+    fn from_result(index: usize, result: Result<T, (StatusCode, String)>) -> Self {
+        match result {
+            Ok(data) => Self { index, success: true, data: Some(data), error: None, status: StatusCode::OK.as_u16() },
+            Err((status, e)) => Self { index, success: false, data: None, error: Some(e), status: status.as_u16() },
+        }
+    }
+}
+
 /// 通用响应结构体
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
 pub struct GenericResponse<T> {
     pub success: bool,
     pub message: String,
     pub data: Option<T>,
 }
 
+/// 从CRUD API获取加密数据的结果，并发的相同请求共享同一结果
+#[derive(Debug, Clone)]
+enum FetchOutcome {
+    /// 获取到加密数据
+    Found(String),
+    /// CRUD API不可用，使用请求中的加密数据
+    Unavailable,
+    /// CRUD API返回了无效响应
+    Invalid(String),
+}
+
 /// 加密服务结构体
 #[derive(Debug, Clone)]
 pub struct EncryptionService {
@@ -57,9 +196,24 @@ pub struct EncryptionService {
     scheduler: CrudApiScheduler,
     cache_manager: CacheManager,
     test_instance_manager: TestInstanceManager,
+    /// 缓存重放锁
+    replay_lock: Arc<tokio::sync::Mutex<()>>,
+    /// 资源ID必须匹配的格式
+    resource_id_pattern: Option<Regex>,
+    /// 正在进行的资源获取请求，未启用合并时为None
+    decrypt_fetches: Option<Arc<SingleFlight<FetchOutcome>>>,
+    /// 解密结果缓存，未启用时为None
+    decrypt_cache: Option<Arc<DecryptCache>>,
+    /// 审计日志，未配置时为None
+    audit_log: Option<Arc<AuditLog>>,
 }
 
 impl EncryptionService {
+    /// 获取配置
+    pub fn get_config(&self) -> &AppConfig {
+        &self.config
+    }
+
     /// 获取服务ID
     pub fn get_service_id(&self) -> String {
         self.config.service.id.clone()
@@ -70,6 +224,86 @@ impl EncryptionService {
         self.config.service.role.clone()
     }
     
+    /// 获取有效服务角色
+    ///
+    /// 开启自动降级时，mixed角色在写实例全部不可用而读实例可用时收窄为decrypt，反之收窄为encrypt
+    pub fn get_effective_role(&self) -> String {
+        let role = &self.config.service.role;
+        if !self.config.service.auto_downgrade || role != "mixed" {
+            return role.clone();
+        }
+
+        let write_available = self.scheduler.has_healthy_instance(true);
+        let read_available = self.scheduler.has_healthy_instance(false);
+        match (write_available, read_available) {
+            (false, true) => "decrypt".to_string(),
+            (true, false) => "encrypt".to_string(),
+            // 读写均可用或均不可用时保持原角色，由原有的故障转移逻辑处理
+            _ => role.clone(),
+        }
+    }
+
+    /// 判断指定时间是否处于维护窗口内
+    pub fn is_in_maintenance_at(&self, now: DateTime<Utc>) -> bool {
+        let minute_of_day = now.hour() * 60 + now.minute();
+        self.config.service.maintenance_schedule.iter().any(|window| window.contains(minute_of_day))
+    }
+
+    /// 当前是否处于维护窗口内
+    pub fn is_in_maintenance(&self) -> bool {
+        self.is_in_maintenance_at(Utc::now())
+    }
+
+    /// 检查服务角色是否允许执行加密或解密操作
+    fn check_role(&self, is_encrypt: bool) -> Result<(), ServiceError> {
+        let (allowed, operation) = if is_encrypt { ("encrypt", "加密") } else { ("decrypt", "解密") };
+        self.check_role_for(allowed, operation)
+    }
+
+    /// 检查服务角色是否允许执行需要allowed角色的操作，operation用于错误信息
+    ///
+    /// 需要encrypt角色的操作都会写入CRUD API，维护窗口内一律拒绝
+    fn check_role_for(&self, allowed: &str, operation: &str) -> Result<(), ServiceError> {
+        let is_encrypt = allowed == "encrypt";
+        let role = &self.config.service.role;
+        if role != allowed && role != "mixed" {
+            return Err(ServiceError::RoleNotAllowed(format!("当前服务角色不允许执行{}操作", operation)));
+        }
+
+        // 降级由实例不可用导致，恢复后自动解除
+        let effective_role = self.get_effective_role();
+        if effective_role != allowed && effective_role != "mixed" {
+            let unavailable = if is_encrypt { "写" } else { "读" };
+            return Err(ServiceError::UpstreamUnavailable(anyhow::anyhow!(
+                "服务角色已降级为{}（没有健康的{}实例），暂不允许执行{}操作", effective_role, unavailable, operation
+            )));
+        }
+
+        // 维护窗口内服务只读
+        if is_encrypt && self.is_in_maintenance() {
+            return Err(ServiceError::RoleNotAllowed(format!("服务处于维护窗口内，暂不允许执行{}操作", operation)));
+        }
+
+        Ok(())
+    }
+
+    /// 生成返回给客户端的错误信息
+    ///
+    /// 完整错误总是记录到日志；客户端错误直接返回错误信息，服务端错误在未开启详细错误时只返回通用信息和请求ID，便于按请求ID查询日志
+    pub fn client_error(&self, e: &ServiceError) -> String {
+        let request_id = request_id::current();
+        error!("请求处理失败 (请求ID: {}): {:?}", request_id.as_deref().unwrap_or("-"), e);
+
+        if self.config.server.verbose_errors || e.status_code().is_client_error() {
+            e.to_string()
+        } else {
+            match request_id {
+                Some(id) => format!("内部错误，请求ID: {}", id),
+                None => "内部错误".to_string(),
+            }
+        }
+    }
+
     /// 获取调度器
     pub fn get_scheduler(&self) -> &CrudApiScheduler {
         &self.scheduler
@@ -89,26 +323,45 @@ impl EncryptionService {
 impl EncryptionService {
     /// 创建新的加密服务实例
     pub fn new(config: Arc<AppConfig>) -> Self {
-        let crypto_utils = EncryptionUtils::new(
-            config.encryption.algorithm.clone(),
-            config.encryption.key_length,
-            config.encryption.iterations,
-            config.encryption.salt.clone(),
-        );
+        let crypto_utils = EncryptionUtils::new(&config.encryption);
 
-        let http_client = Client::builder()
-            .timeout(std::time::Duration::from_millis(config.crud_api.timeout))
-            .build()
-            .expect("无法创建HTTP客户端");
+        // 调度器和Test实例管理器共用同一个HTTP客户端
+        let http_client = http_client::build(&config);
 
         // 创建并初始化调度器
-        let scheduler = CrudApiScheduler::new(config.clone());
+        let scheduler = CrudApiScheduler::new(config.clone(), http_client.clone());
 
         // 创建缓存管理器
-        let cache_manager = CacheManager::new();
+        let cache_manager = CacheManager::new(
+            config.service.id.clone(),
+            &config.cache,
+            config.encryption.cache_encryption_key.as_deref(),
+            config.failover.cache_secondary_dir.clone(),
+            config.failover.prioritize_encrypt,
+        );
 
         // 创建Test实例管理器
-        let test_instance_manager = TestInstanceManager::new(config.clone(), cache_manager.clone());
+        let test_instance_manager = TestInstanceManager::new(config.clone(), http_client.clone());
+
+        // 资源ID格式已在配置验证时检查
+        let resource_id_pattern = config.resource_id.pattern.as_deref()
+            .map(|pattern| Regex::new(&format!("^(?:{})$", pattern)).expect("无效的资源ID格式"));
+
+        // 合并并发的相同资源获取请求
+        let decrypt_fetches = config.crud_api.single_flight.then(|| Arc::new(SingleFlight::new()));
+
+        // 解密结果缓存
+        let decrypt_cache = (config.cache.decrypt_cache_size > 0).then(|| {
+            let cache = DecryptCache::new(config.cache.decrypt_cache_size, Duration::from_secs(config.cache.decrypt_cache_ttl))
+                .expect("无法创建解密结果缓存");
+            Arc::new(cache)
+        });
+
+        // 审计日志，目录是否可写已在配置验证时检查
+        let audit_log = config.audit.log_path.as_deref().map(|path| {
+            let log = AuditLog::open(path, config.service.id.clone()).expect("无法打开审计日志");
+            Arc::new(log)
+        });
 
         Self {
             config,
@@ -117,26 +370,62 @@ impl EncryptionService {
             scheduler,
             cache_manager,
             test_instance_manager,
+            replay_lock: Arc::new(tokio::sync::Mutex::new(())),
+            resource_id_pattern,
+            decrypt_fetches,
+            decrypt_cache,
+            audit_log,
+        }
+    }
+
+    /// 记录审计日志，未配置审计日志时跳过
+    fn audit(&self, operation: &str, resource_type: &str, resource_id: Option<&str>, ciphertext: Option<&str>, success: bool) {
+        if let Some(ref audit_log) = self.audit_log {
+            audit_log.record(operation, resource_type, resource_id, ciphertext, success);
         }
     }
 
     /// 加密数据并保存到CRUD API
-    pub async fn encrypt(&self, request: EncryptRequest) -> Result<EncryptResponse> {
+    #[instrument(name = "encrypt", skip_all, fields(resource_type = %request.resource_type))]
+    pub async fn encrypt(&self, request: EncryptRequest) -> Result<EncryptResponse, ServiceError> {
+        let resource_type = request.resource_type.clone();
+        let result = self.encrypt_inner(request).await;
+        let response = result.as_ref().ok();
+        self.audit("encrypt", &resource_type, response.and_then(|r| r.resource_id.as_deref()), response.map(|r| r.encrypted_data.as_str()), result.is_ok());
+        result
+    }
+
+    /// 加密数据并保存到CRUD API，审计日志由调用方记录
+    async fn encrypt_inner(&self, request: EncryptRequest) -> Result<EncryptResponse, ServiceError> {
+        metrics::counter!(monitoring::REQUESTS_TOTAL, "operation" => "encrypt").increment(1);
+
         // 检查服务角色是否允许加密
-        if self.config.service.role != "encrypt" && self.config.service.role != "mixed" {
-            anyhow::bail!("当前服务角色不允许执行加密操作");
-        }
+        self.check_role(true)?;
+        resource_type_segment(&request.resource_type)?;
 
         // 执行加密
-        let encrypted_data = self.crypto_utils.encrypt(&request.data, &request.password).await?;
+        let started = Instant::now();
+        let result = self.crypto_utils.encrypt(&request.data, &request.password, &request.resource_type, request.algorithm.as_deref()).await;
+        monitoring::record_crypto("encrypt", started, &result);
+        let encrypted_data = result.map_err(ServiceError::from_crypto)?;
+
+        // 试运行只返回密文，不写入CRUD API和缓存
+        if request.dry_run.unwrap_or(false) {
+            debug!("试运行加密，跳过CRUD API和缓存写入");
+            return Ok(EncryptResponse {
+                encrypted_data,
+                resource_id: None,
+                persisted: false,
+                location: None,
+            });
+        }
+
+        // 内容寻址模式下由资源类型、密码和明文生成资源ID
+        let content_id = self.config.encryption.content_id_key.as_ref()
+            .map(|key| crypto::content_id(key.as_bytes(), &request.resource_type, &request.password, request.data.as_bytes()));
 
         // 准备保存到CRUD API的数据
-        let crud_data = serde_json::json!({
-            "encrypted_data": encrypted_data,
-            "resource_type": request.resource_type,
-            "created_at": chrono::Utc::now().to_rfc3339(),
-            "updated_at": chrono::Utc::now().to_rfc3339(),
-        });
+        let crud_data = crud_record(&request.resource_type, &encrypted_data, content_id.as_deref(), Utc::now());
 
         // 创建缓存数据
         let encrypt_cache_data = EncryptCacheData {
@@ -144,38 +433,68 @@ impl EncryptionService {
             password: request.password.clone(),
             resource_type: request.resource_type.clone(),
             encrypted_data: encrypted_data.clone(),
+            persisted: false,
+            resource_id: None,
+            content_id: content_id.clone(),
         };
 
         // 尝试调用CRUD API
         match self.scheduler.select_instance(true) {
             Ok(instance) => {
+                // 内容寻址模式下相同内容已存在时直接复用已有记录
+                if let Some(ref id) = content_id {
+                    match self.find_existing(&instance, &request.resource_type, id).await {
+                        Ok(Some(existing)) => {
+                            info!("资源 {}/{} 已存在，复用已有记录", request.resource_type, id);
+                            return Ok(EncryptResponse {
+                                encrypted_data: existing,
+                                resource_id: Some(id.clone()),
+                                persisted: true,
+                                location: self.location_for(&instance.url, &request.resource_type, id),
+                            });
+                        },
+                        Ok(None) => {},
+                        Err(e) => warn!("查询已有记录失败，继续写入: {:?}", e),
+                    }
+                }
+
                 // 调用CRUD API保存数据
-                let crud_url = format!("{}/{}", instance.url, request.resource_type);
-                match self.http_client
-                    .post(&crud_url)
-                    .json(&crud_data)
-                    .send()
-                    .await
-                    .and_then(|resp| resp.error_for_status())
-                {
+                match self.post_record(&instance, &request.resource_type, &crud_data, &encrypted_data).await {
                     Ok(response) => {
-                        // CRUD API调用成功，缓存数据
-                        if let Err(e) = self.cache_manager.write_cache(CacheDataType::Encrypt(encrypt_cache_data)) {
+                        // CRUD API调用成功，缓存数据并标记为已写入，不再重放
+                        let persisted = EncryptCacheData { persisted: true, ..encrypt_cache_data };
+                        if let Err(e) = self.cache_manager.write_cache(CacheDataType::Encrypt(persisted)) {
                             warn!("缓存数据失败: {:?}", e);
                         }
 
-                        let crud_response: GenericResponse<serde_json::Value> = response.json().await?;
-                        let resource_id = crud_response.data
-                            .and_then(|data| data.get("id").and_then(|id| id.as_str().map(|s| s.to_string())));
+                        let crud_response: GenericResponse<serde_json::Value> = response.json().await
+                            .map_err(|e| ServiceError::UpstreamUnavailable(e.into()))?;
+                        let resource_id = match crud_response.data.as_ref().and_then(response_id) {
+                            Some(id) => Some(id),
+                            None => {
+                                // 内容寻址模式下仍可按内容ID读取，否则调用方无法再取回数据
+                                if content_id.is_none() {
+                                    warn!("CRUD API保存成功但响应中没有资源ID，资源无法按ID读取，响应结构: {}", response_shape(crud_response.data.as_ref()));
+                                }
+                                content_id
+                            },
+                        };
+
+                        // 按模板生成资源地址
+                        let location = resource_id.as_deref()
+                            .and_then(|id| self.location_for(&instance.url, &request.resource_type, id));
 
                         Ok(EncryptResponse {
                             encrypted_data,
                             resource_id,
+                            persisted: true,
+                            location,
                         })
                     },
                     Err(e) => {
                         // CRUD API调用失败，缓存数据并处理容错
                         error!("调用CRUD API失败: {:?}", e);
+                        metrics::counter!(monitoring::CRUD_FALLBACKS_TOTAL, "operation" => "encrypt").increment(1);
                         if let Err(cache_err) = self.cache_manager.write_cache(CacheDataType::Encrypt(encrypt_cache_data)) {
                             warn!("缓存数据失败: {:?}", cache_err);
                         }
@@ -185,84 +504,109 @@ impl EncryptionService {
                         Ok(EncryptResponse {
                             encrypted_data,
                             resource_id: None,
+                            persisted: false,
+                            location: None,
                         })
                     },
                 }
             },
             Err(e) => {
                 // 没有健康的CRUD API实例，缓存数据并处理容错
-                error!("没有健康的CRUD API实例: {:?}", e);
+                let read_only = matches!(e.downcast_ref::<SelectError>(), Some(SelectError::WriteUnavailableReadOnly));
+                if read_only {
+                    warn!("写实例不可用，处于只读降级状态，数据写入缓存等待重放");
+                } else {
+                    error!("没有健康的CRUD API实例: {:?}", e);
+                }
+                metrics::counter!(monitoring::CRUD_FALLBACKS_TOTAL, "operation" => "encrypt").increment(1);
                 if let Err(cache_err) = self.cache_manager.write_cache(CacheDataType::Encrypt(encrypt_cache_data)) {
                     warn!("缓存数据失败: {:?}", cache_err);
                 }
 
-                // 创建Test实例并导入缓存数据
-                if let Err(ti_err) = self.test_instance_manager.create_test_instance().await {
-                    error!("创建Test实例失败: {:?}", ti_err);
-                } else if let Err(import_err) = self.test_instance_manager.import_cache_data().await {
-                    error!("导入缓存数据失败: {:?}", import_err);
+                // 只读降级时读实例仍在服务，只缓存等待写实例恢复后重放，不创建Test实例
+                // 仅关键资源类型创建Test实例并导入缓存数据，其他资源类型只缓存
+                if read_only {
+                    info!("只读降级状态下不创建Test实例，数据已缓存");
+                } else if !self.test_instance_manager.is_provision_eligible(&request.resource_type) {
+                    info!("资源类型 {} 不触发Test实例创建，数据已缓存", request.resource_type);
+                } else {
+                    match self.test_instance_manager.create_test_instance().await {
+                        Ok(test_instance) => {
+                            if let Err(import_err) = self.import_cache_to_test_instance(&test_instance).await {
+                                error!("导入缓存数据失败: {:?}", import_err);
+                            }
+                        },
+                        Err(ti_err) => error!("创建Test实例失败: {:?}", ti_err),
+                    }
                 }
 
                 // 返回加密后的数据，不依赖CRUD API
                 Ok(EncryptResponse {
                     encrypted_data,
                     resource_id: None,
+                    persisted: false,
+                    location: None,
                 })
             },
         }
     }
 
     /// 从CRUD API获取数据并解密
-    pub async fn decrypt(&self, request: DecryptRequest) -> Result<DecryptResponse> {
+    #[instrument(name = "decrypt", skip_all, fields(resource_type = %request.resource_type, resource_id = ?request.resource_id))]
+    pub async fn decrypt(&self, request: DecryptRequest) -> Result<DecryptResponse, ServiceError> {
+        let resource_type = request.resource_type.clone();
+        let resource_id = request.resource_id.clone();
+        let mut ciphertext = None;
+        let result = self.decrypt_inner(request, &mut ciphertext).await;
+        self.audit("decrypt", &resource_type, resource_id.as_deref(), ciphertext.as_deref(), result.is_ok());
+        result
+    }
+
+    /// 从CRUD API获取数据并解密，实际解密的密文写入ciphertext供审计日志使用
+    async fn decrypt_inner(&self, request: DecryptRequest, ciphertext: &mut Option<String>) -> Result<DecryptResponse, ServiceError> {
+        metrics::counter!(monitoring::REQUESTS_TOTAL, "operation" => "decrypt").increment(1);
+
         // 检查服务角色是否允许解密
-        if self.config.service.role != "decrypt" && self.config.service.role != "mixed" {
-            anyhow::bail!("当前服务角色不允许执行解密操作");
-        }
+        self.check_role(false)?;
 
         // 克隆resource_id用于返回
         let resource_id = request.resource_id.clone();
-        
-        let encrypted_data = match &request.resource_id {
-            Some(resource_id) => {
-                // 尝试从CRUD API获取加密数据
-                match self.scheduler.select_instance(false) {
-                    Ok(instance) => {
-                        // 从CRUD API获取加密数据
-                        let crud_url = format!("{}/{}/{}?select=encrypted_data", 
-                                            instance.url, 
-                                            request.resource_type, 
-                                            resource_id);
-                        match self.http_client
-                            .get(&crud_url)
-                            .send()
-                            .await
-                            .and_then(|resp| resp.error_for_status())
-                        {
-                            Ok(response) => {
-                                let crud_response: GenericResponse<serde_json::Value> = response.json().await?;
-                                crud_response.data
-                                    .and_then(|data| data.get("encrypted_data").and_then(|ed| ed.as_str().map(|s| s.to_string())))
-                                    .ok_or_else(|| anyhow::anyhow!("无法获取加密数据"))?
-                            },
-                            Err(e) => {
-                                // CRUD API调用失败，使用请求中的encrypted_data
-                                error!("从CRUD API获取加密数据失败: {:?}", e);
-                                request.encrypted_data.clone()
-                            },
-                        }
-                    },
-                    Err(e) => {
-                        // 没有健康的CRUD API实例，使用请求中的encrypted_data
-                        error!("没有健康的CRUD API实例: {:?}", e);
-                        request.encrypted_data.clone()
-                    },
-                }
-            },
-            None => request.encrypted_data.clone(),
+
+        // 命中解密缓存时跳过CRUD API请求和解密
+        let source = match (&request.resource_id, &request.encrypted_data) {
+            (Some(id), _) => DecryptSource::Resource(id),
+            (None, Some(encrypted_data)) => DecryptSource::Ciphertext(encrypted_data),
+            (None, None) => return Err(missing_ciphertext()),
         };
+        if let Some(ref cache) = self.decrypt_cache
+            && let Some(data) = cache.get(&request.resource_type, source, &request.password)
+        {
+            metrics::counter!(monitoring::DECRYPT_CACHE_HITS_TOTAL).increment(1);
+            debug!("命中解密缓存");
+            if let DecryptSource::Ciphertext(encrypted_data) = source {
+                *ciphertext = Some(encrypted_data.to_string());
+            }
+            return Ok(DecryptResponse {
+                data,
+                resource_id,
+            });
+        }
+
+        // 获取加密数据
+        let (encrypted_data, from_crud) = self.fetch_encrypted_data(&request).await?;
+        *ciphertext = Some(encrypted_data.clone());
 
         // 执行解密
-        let data = self.crypto_utils.decrypt(&encrypted_data, &request.password).await?;
+        let started = Instant::now();
+        let result = self.crypto_utils.decrypt(&encrypted_data, &request.password, &request.resource_type).await;
+        monitoring::record_crypto("decrypt", started, &result);
+        let data = result.map_err(ServiceError::from_crypto)?;
+
+        // 只有从CRUD API获取的数据按资源ID缓存，降级使用请求中的密文时按密文缓存
+        if let Some(ref cache) = self.decrypt_cache {
+            let source = if from_crud { source } else { DecryptSource::Ciphertext(&encrypted_data) };
+            cache.insert(&request.resource_type, source, &request.password, &data);
+        }
 
         // 创建缓存数据
         let decrypt_cache_data = DecryptCacheData {
@@ -284,54 +628,1760 @@ impl EncryptionService {
         })
     }
 
-    /// 批量加密数据
-    pub async fn batch_encrypt(&self, requests: Vec<EncryptRequest>) -> Result<Vec<EncryptResponse>> {
+    /// 获取待解密的数据：指定resource_id时优先从CRUD API获取，CRUD API不可用时使用请求中的encrypted_data
+    ///
+    /// 只指定resource_id且CRUD API不可用时返回UpstreamUnavailable；返回的布尔值表示数据是否来自CRUD API
+    async fn fetch_encrypted_data(&self, request: &DecryptRequest) -> Result<(String, bool), ServiceError> {
+        let resource_id = match (&request.resource_id, &request.encrypted_data) {
+            (Some(resource_id), _) => {
+                resource_type_segment(&request.resource_type)?;
+                self.resource_id_segment(resource_id)?
+            },
+            (None, Some(encrypted_data)) => return Ok((encrypted_data.clone(), false)),
+            (None, None) => return Err(missing_ciphertext()),
+        };
+
+        // 并发的相同资源请求合并为一次CRUD API调用
+        let outcome = match self.decrypt_fetches {
+            Some(ref fetches) => {
+                let key = format!("{}/{}", request.resource_type, resource_id);
+                fetches.run(&key, || self.fetch_from_crud(&request.resource_type, &resource_id)).await
+            },
+            None => self.fetch_from_crud(&request.resource_type, &resource_id).await,
+        };
+
+        match outcome {
+            FetchOutcome::Found(encrypted_data) => Ok((encrypted_data, true)),
+            FetchOutcome::Invalid(message) => Err(ServiceError::UpstreamUnavailable(anyhow::anyhow!(message))),
+            FetchOutcome::Unavailable => match request.encrypted_data {
+                // CRUD API不可用，使用请求中的encrypted_data
+                Some(ref encrypted_data) => {
+                    metrics::counter!(monitoring::CRUD_FALLBACKS_TOTAL, "operation" => "decrypt").increment(1);
+                    warn!("CRUD API不可用，使用请求中的加密数据解密");
+                    Ok((encrypted_data.clone(), false))
+                },
+                None => Err(ServiceError::UpstreamUnavailable(anyhow::anyhow!(
+                    "无法从CRUD API获取资源 {}/{}，请求中没有可用于降级解密的encrypted_data", request.resource_type, resource_id
+                ))),
+            },
+        }
+    }
+
+    /// 从CRUD API获取指定资源的加密数据
+    async fn fetch_from_crud(&self, resource_type: &str, resource_id: &str) -> FetchOutcome {
+        let instance = match self.scheduler.select_instance(false) {
+            Ok(instance) => instance,
+            Err(e) => {
+                error!("没有健康的CRUD API实例: {:?}", e);
+                return FetchOutcome::Unavailable;
+            },
+        };
+
+        let crud_url = format!("{}/{}/{}?select=encrypted_data", 
+                            instance.url, 
+                            resource_type, 
+                            resource_id);
+        let result = retry::send_with_retry(
+            || self.crud_request(&instance, Method::GET, &crud_url),
+            instance.retries,
+        ).await;
+        self.scheduler.record_request_result(&instance.id, &result);
+        let response = match result {
+            Ok(response) => response,
+            Err(e) => {
+                error!("从CRUD API获取加密数据失败: {:?}", e);
+                return FetchOutcome::Unavailable;
+            },
+        };
+
+        match response.json::<GenericResponse<serde_json::Value>>().await {
+            Ok(crud_response) => crud_response.data
+                .and_then(|data| data.get("encrypted_data").and_then(|ed| ed.as_str().map(|s| s.to_string())))
+                .map(FetchOutcome::Found)
+                .unwrap_or_else(|| FetchOutcome::Invalid("无法获取加密数据".to_string())),
+            Err(e) => FetchOutcome::Invalid(e.to_string()),
+        }
+    }
+
+    /// 重新加密CRUD API中一条记录的指定字段并写回
+    ///
+    /// 记录在服务端完成解密和重新加密，明文不经过网络；未指定的字段原样写回
+    pub async fn rewrap(&self, request: RewrapRequest) -> Result<RewrapResponse, ServiceError> {
+        let resource_type = request.resource_type.clone();
+        let resource_id = request.resource_id.clone();
+        let result = self.rewrap_inner(request).await;
+        self.audit("rewrap", &resource_type, Some(&resource_id), None, result.is_ok());
+        result
+    }
+
+    /// 重新加密CRUD API中一条记录的指定字段并写回，审计日志由调用方记录
+    async fn rewrap_inner(&self, request: RewrapRequest) -> Result<RewrapResponse, ServiceError> {
+        metrics::counter!(monitoring::REQUESTS_TOTAL, "operation" => "rewrap").increment(1);
+
+        // 同时需要解密和加密权限
+        self.check_role(false)?;
+        self.check_role(true)?;
+
+        if request.fields.is_empty() {
+            return Err(ServiceError::InvalidRequest("未指定需要重新加密的字段".to_string()));
+        }
+
+        resource_type_segment(&request.resource_type)?;
+        let resource_id = self.resource_id_segment(&request.resource_id)?;
+
+        // 读写使用同一个写实例，避免读到尚未同步的旧数据
+        let instance = self.scheduler.select_instance(true)
+            .map_err(ServiceError::UpstreamUnavailable)?;
+        let mut record = self.fetch_record(&instance, &request.resource_type, &resource_id).await
+            .map_err(ServiceError::UpstreamUnavailable)?;
+
+        let new_password = request.new_password.as_deref().unwrap_or(&request.password);
+        for field in &request.fields {
+            let encrypted_data = record.get(field)
+                .and_then(|value| value.as_str())
+                .ok_or_else(|| ServiceError::InvalidRequest(format!("字段 {} 不存在或不是字符串", field)))?;
+
+            let started = Instant::now();
+            let data = self.crypto_utils.decrypt(encrypted_data, &request.password, &request.resource_type).await
+                .map_err(ServiceError::from_crypto)?;
+            let result = self.crypto_utils.encrypt(&data, new_password, &request.resource_type, request.algorithm.as_deref()).await;
+            monitoring::record_crypto("rewrap", started, &result);
+            record[field] = serde_json::Value::String(result.map_err(ServiceError::from_crypto)?);
+        }
+        record["updated_at"] = serde_json::Value::String(Utc::now().to_rfc3339());
+
+        let crud_url = format!("{}/{}/{}", instance.url, request.resource_type, resource_id);
+        let result = retry::send_with_retry(
+            || self.crud_request(&instance, Method::PUT, &crud_url).json(&record),
+            instance.retries,
+        ).await;
+        self.scheduler.record_request_result(&instance.id, &result);
+        result.map_err(|e| ServiceError::UpstreamUnavailable(e.into()))?;
+
+        // 重新加密后旧密码不应再命中解密缓存
+        if let Some(ref cache) = self.decrypt_cache {
+            cache.invalidate_resource(&request.resource_type, &request.resource_id);
+        }
+
+        info!("资源 {}/{} 已重新加密字段: {}", request.resource_type, request.resource_id, request.fields.join(", "));
+        Ok(RewrapResponse {
+            resource_id: request.resource_id,
+            fields: request.fields,
+        })
+    }
+
+    /// 用旧密码解密密文并用新密码重新加密，明文不离开服务端
+    pub async fn rotate(&self, request: RotateRequest) -> Result<RotateResponse, ServiceError> {
+        let resource_type = request.resource_type.clone();
+        let encrypted_data = request.encrypted_data.clone();
+        let result = self.rotate_inner(request).await;
+        self.audit("rotate", &resource_type, None, Some(&encrypted_data), result.is_ok());
+        result
+    }
+
+    /// 用旧密码解密密文并用新密码重新加密，审计日志由调用方记录
+    async fn rotate_inner(&self, request: RotateRequest) -> Result<RotateResponse, ServiceError> {
+        metrics::counter!(monitoring::REQUESTS_TOTAL, "operation" => "rotate").increment(1);
+
+        // 同时需要解密和加密权限
+        self.check_role(false)?;
+        self.check_role(true)?;
+
+        let started = Instant::now();
+        let data = self.crypto_utils.decrypt(&request.encrypted_data, &request.old_password, &request.resource_type).await
+            .map_err(ServiceError::from_crypto)?;
+        let result = self.crypto_utils.encrypt(&data, &request.new_password, &request.resource_type, request.algorithm.as_deref()).await;
+        monitoring::record_crypto("rotate", started, &result);
+
+        Ok(RotateResponse {
+            encrypted_data: result.map_err(ServiceError::from_crypto)?,
+        })
+    }
+
+    /// 用新数据重新加密已有资源并写回CRUD API，资源ID保持不变
+    ///
+    /// CRUD API不可用时与加密一样写入缓存，写实例恢复后重放更新
+    #[instrument(name = "update", skip_all, fields(resource_type = %resource_type, resource_id = %resource_id))]
+    pub async fn update(&self, resource_type: &str, resource_id: &str, request: UpdateRequest) -> Result<EncryptResponse, ServiceError> {
+        let result = self.update_inner(resource_type, resource_id, request).await;
+        self.audit("update", resource_type, Some(resource_id), result.as_ref().ok().map(|r| r.encrypted_data.as_str()), result.is_ok());
+        result
+    }
+
+    /// 用新数据重新加密已有资源并写回CRUD API，审计日志由调用方记录
+    async fn update_inner(&self, resource_type: &str, resource_id: &str, request: UpdateRequest) -> Result<EncryptResponse, ServiceError> {
+        metrics::counter!(monitoring::REQUESTS_TOTAL, "operation" => "update").increment(1);
+
         // 检查服务角色是否允许加密
-        if self.config.service.role != "encrypt" && self.config.service.role != "mixed" {
-            anyhow::bail!("当前服务角色不允许执行加密操作");
+        self.check_role(true)?;
+
+        resource_type_segment(resource_type)?;
+        let id_segment = self.resource_id_segment(resource_id)?;
+
+        let started = Instant::now();
+        let result = self.crypto_utils.encrypt(&request.data, &request.password, resource_type, request.algorithm.as_deref()).await;
+        monitoring::record_crypto("update", started, &result);
+        let encrypted_data = result.map_err(ServiceError::from_crypto)?;
+
+        let update_cache_data = EncryptCacheData {
+            data: request.data,
+            password: request.password,
+            resource_type: resource_type.to_string(),
+            encrypted_data: encrypted_data.clone(),
+            persisted: false,
+            resource_id: Some(resource_id.to_string()),
+            content_id: None,
+        };
+
+        // 旧密文已被替换，不应再命中解密缓存
+        if let Some(ref cache) = self.decrypt_cache {
+            cache.invalidate_resource(resource_type, resource_id);
+        }
+
+        let result = match self.scheduler.select_instance(true) {
+            Ok(instance) => {
+                let crud_data = update_record(resource_type, &encrypted_data, Utc::now());
+                match self.put_record(&instance, resource_type, &id_segment, &crud_data).await {
+                    Ok(_) => Ok(()),
+                    Err(e) if e.status() == Some(reqwest::StatusCode::NOT_FOUND) => {
+                        return Err(ServiceError::NotFound(format!("资源 {}/{} 不存在", resource_type, resource_id)));
+                    },
+                    Err(e) => Err(e.into()),
+                }
+            },
+            Err(e) => Err(e),
+        };
+
+        let persisted = result.is_ok();
+        match result {
+            Ok(()) => {
+                // 写入成功，缓存数据并标记为已写入，不再重放
+                let persisted = EncryptCacheData { persisted: true, ..update_cache_data };
+                if let Err(e) = self.cache_manager.write_cache(CacheDataType::Encrypt(persisted)) {
+                    warn!("缓存数据失败: {:?}", e);
+                }
+                info!("资源 {}/{} 已更新", resource_type, resource_id);
+            },
+            Err(e) => {
+                // 只缓存等待写实例恢复后重放，Test实例中没有需要更新的原记录
+                error!("更新CRUD API记录失败，数据写入缓存等待重放: {:?}", e);
+                metrics::counter!(monitoring::CRUD_FALLBACKS_TOTAL, "operation" => "update").increment(1);
+                if let Err(cache_err) = self.cache_manager.write_cache(CacheDataType::Encrypt(update_cache_data)) {
+                    warn!("缓存数据失败: {:?}", cache_err);
+                }
+            },
+        }
+
+        Ok(EncryptResponse {
+            encrypted_data,
+            resource_id: Some(resource_id.to_string()),
+            persisted,
+            location: None,
+        })
+    }
+
+    /// 删除CRUD API中的一条记录
+    ///
+    /// 删除属于写操作，只有encrypt和mixed角色可以执行；记录不存在时返回NotFound
+    pub async fn delete(&self, resource_type: &str, resource_id: &str) -> Result<DeleteResponse, ServiceError> {
+        metrics::counter!(monitoring::REQUESTS_TOTAL, "operation" => "delete").increment(1);
+
+        // 删除需要写入权限，使用单独的错误信息
+        self.check_role_for("encrypt", "删除")?;
+
+        resource_type_segment(resource_type)?;
+        let id_segment = self.resource_id_segment(resource_id)?;
+        let instance = self.scheduler.select_instance(true)
+            .map_err(ServiceError::UpstreamUnavailable)?;
+
+        let crud_url = format!("{}/{}/{}", instance.url, resource_type, id_segment);
+        let result = retry::send_with_retry(
+            || self.crud_request(&instance, Method::DELETE, &crud_url),
+            instance.retries,
+        ).await;
+        self.scheduler.record_request_result(&instance.id, &result);
+        match result {
+            Ok(_) => {},
+            Err(e) if e.status() == Some(reqwest::StatusCode::NOT_FOUND) => {
+                return Err(ServiceError::NotFound(format!("资源 {}/{} 不存在", resource_type, resource_id)));
+            },
+            Err(e) => return Err(ServiceError::UpstreamUnavailable(e.into())),
         }
 
-        let mut responses = Vec::with_capacity(requests.len());
-        for request in requests {
-            let response = self.encrypt(request).await?;
-            responses.push(response);
+        // 记录已删除，不应再命中解密缓存
+        if let Some(ref cache) = self.decrypt_cache {
+            cache.invalidate_resource(resource_type, resource_id);
         }
 
-        Ok(responses)
+        info!("资源 {}/{} 已删除", resource_type, resource_id);
+        Ok(DeleteResponse {
+            resource_type: resource_type.to_string(),
+            resource_id: resource_id.to_string(),
+        })
     }
 
-    /// 批量解密数据
-    pub async fn batch_decrypt(&self, requests: Vec<DecryptRequest>) -> Result<Vec<DecryptResponse>> {
+    /// 创建流式加密器，流式加解密不读写CRUD API和缓存
+    pub async fn stream_encryptor(&self, password: &str, resource_type: &str, algorithm: Option<&str>) -> Result<StreamEncryptor, ServiceError> {
+        metrics::counter!(monitoring::REQUESTS_TOTAL, "operation" => "encrypt_stream").increment(1);
+
+        // 检查服务角色是否允许加密
+        self.check_role(true)?;
+
+        self.crypto_utils.stream_encryptor(password, resource_type, algorithm).await
+            .map_err(ServiceError::from_crypto)
+    }
+
+    /// 创建流式解密器
+    pub fn stream_decryptor(&self, password: &str, resource_type: &str) -> Result<StreamDecryptor, ServiceError> {
+        metrics::counter!(monitoring::REQUESTS_TOTAL, "operation" => "decrypt_stream").increment(1);
+
         // 检查服务角色是否允许解密
-        if self.config.service.role != "decrypt" && self.config.service.role != "mixed" {
-            anyhow::bail!("当前服务角色不允许执行解密操作");
+        self.check_role(false)?;
+
+        Ok(self.crypto_utils.stream_decryptor(password, resource_type))
+    }
+
+    /// 获取CRUD API中的完整记录
+    async fn fetch_record(&self, instance: &CrudApiInstance, resource_type: &str, id: &str) -> Result<serde_json::Value> {
+        resource_type_segment(resource_type)?;
+        let crud_url = format!("{}/{}/{}", instance.url, resource_type, id);
+        let result = retry::send_with_retry(
+            || self.crud_request(instance, Method::GET, &crud_url),
+            instance.retries,
+        ).await;
+        self.scheduler.record_request_result(&instance.id, &result);
+
+        let crud_response: GenericResponse<serde_json::Value> = result?.json().await?;
+        crud_response.data
+            .filter(|data| data.is_object())
+            .ok_or_else(|| anyhow::anyhow!("资源 {}/{} 不存在或格式无效", resource_type, id))
+    }
+
+    /// 向CRUD API写入一条记录，携带由密文生成的幂等键，重放时后端可据此去重
+    async fn post_record(
+        &self,
+        instance: &CrudApiInstance,
+        resource_type: &str,
+        crud_data: &serde_json::Value,
+        encrypted_data: &str,
+    ) -> reqwest::Result<reqwest::Response> {
+        let crud_url = format!("{}/{}", instance.url, resource_type);
+        let idempotency_key = idempotency_key(encrypted_data);
+        let result = retry::send_with_retry(
+            || self.crud_request(instance, Method::POST, &crud_url)
+                .header(IDEMPOTENCY_KEY_HEADER, &idempotency_key)
+                .json(crud_data),
+            instance.retries,
+        ).await;
+        self.scheduler.record_request_result(&instance.id, &result);
+        result
+    }
+
+    /// 更新CRUD API中的一条记录
+    async fn put_record(
+        &self,
+        instance: &CrudApiInstance,
+        resource_type: &str,
+        id_segment: &str,
+        crud_data: &serde_json::Value,
+    ) -> reqwest::Result<reqwest::Response> {
+        let crud_url = format!("{}/{}/{}", instance.url, resource_type, id_segment);
+        let result = retry::send_with_retry(
+            || self.crud_request(instance, Method::PUT, &crud_url).json(crud_data),
+            instance.retries,
+        ).await;
+        self.scheduler.record_request_result(&instance.id, &result);
+        result
+    }
+
+    /// 将一条缓存的加密数据写入指定实例：记录了资源ID时更新该资源，否则按幂等键新建
+    ///
+    /// 资源类型或资源ID无效时返回错误，调用方跳过该条目；请求结果原样返回
+    async fn replay_entry(
+        &self,
+        instance: &CrudApiInstance,
+        entry: &CacheEntry,
+        data: &EncryptCacheData,
+    ) -> Result<reqwest::Result<reqwest::Response>, ServiceError> {
+        resource_type_segment(&data.resource_type)?;
+        let changed_at = DateTime::from_timestamp(entry.timestamp as i64, 0).unwrap_or_else(Utc::now);
+        let result = match data.resource_id {
+            // 更新已有资源
+            Some(ref resource_id) => {
+                let id_segment = self.resource_id_segment(resource_id)?;
+                let crud_data = update_record(&data.resource_type, &data.encrypted_data, changed_at);
+                self.put_record(instance, &data.resource_type, &id_segment, &crud_data).await
+            },
+            None => {
+                let crud_data = crud_record(&data.resource_type, &data.encrypted_data, data.content_id.as_deref(), changed_at);
+                self.post_record(instance, &data.resource_type, &crud_data, &data.encrypted_data).await
+            },
+        };
+        Ok(result)
+    }
+
+    /// 将缓存中未写入CRUD API的加密数据导入Test实例，返回本次导入的条数
+    ///
+    /// 与重放使用相同的请求和幂等键；已导入当前实例的条目不再重复导入。
+    /// 导入后条目仍保留在缓存中，写实例恢复后照常重放
+    pub async fn import_cache_to_test_instance(&self, test_instance: &TestInstanceConfig) -> Result<usize> {
+        let instance = self.test_instance_manager.crud_instance(test_instance)?;
+
+        let mut imported = 0;
+        for entry in self.cache_manager.read_all_cache()? {
+            let CacheDataType::Encrypt(ref data) = entry.data_type else {
+                continue;
+            };
+            if data.persisted || test_instance.imported_entries.contains(&entry.id) {
+                continue;
+            }
+
+            match self.replay_entry(&instance, &entry, data).await {
+                Ok(Ok(_)) => {},
+                // 被拒绝的条目跳过，不影响其他条目导入
+                Ok(Err(e)) if is_rejected(&e) => {
+                    warn!("Test实例 {} 拒绝了缓存条目 {}: {}", test_instance.id, entry.id, e);
+                    continue;
+                },
+                Ok(Err(e)) => anyhow::bail!("导入缓存数据到Test实例 {} 失败: {}", test_instance.id, e),
+                Err(e) => {
+                    warn!("缓存条目 {} 的资源类型或资源ID无效，跳过导入: {}", entry.id, e);
+                    continue;
+                },
+            }
+            self.test_instance_manager.mark_imported(test_instance, &entry.id);
+            imported += 1;
+        }
+
+        info!("已将 {} 条缓存数据导入Test实例 {}", imported, test_instance.id);
+        Ok(imported)
+    }
+
+    /// 将缓存中未写入CRUD API的加密数据重放到健康的写实例，成功后从缓存中删除，返回重放条数
+    pub async fn replay_cache(&self, limit: Option<usize>) -> Result<usize> {
+        if !self.scheduler.has_healthy_instance(true) {
+            return Ok(0);
         }
 
-        let mut responses = Vec::with_capacity(requests.len());
-        for request in requests {
-            let response = self.decrypt(request).await?;
-            responses.push(response);
+        // 同一时间只允许一轮重放，避免同一条目被重复写入
+        let Ok(_guard) = self.replay_lock.try_lock() else {
+            return Ok(0);
+        };
+
+        let mut replayed = HashSet::new();
+        let mut rejected = HashSet::new();
+        for entry in self.cache_manager.read_all_cache()? {
+            let CacheDataType::Encrypt(ref data) = entry.data_type else {
+                continue;
+            };
+            if data.persisted {
+                continue;
+            }
+            if limit.is_some_and(|limit| replayed.len() >= limit) {
+                break;
+            }
+
+            // 写实例再次不可用时停止本轮重放，等待下次执行
+            let Ok(instance) = self.scheduler.select_instance(true) else {
+                break;
+            };
+            let result = match self.replay_entry(&instance, &entry, data).await {
+                Ok(result) => result,
+                Err(e) => {
+                    warn!("缓存条目 {} 的资源类型或资源ID无效，跳过重放: {}", entry.id, e);
+                    continue;
+                },
+            };
+
+            match result {
+                Ok(_) => {
+                    replayed.insert(entry.id);
+                },
+                // 条目本身被拒绝（如幂等键冲突返回409），重试也不会成功，移出重放队列后继续重放后续条目
+                Err(e) if is_rejected(&e) => {
+                    error!("CRUD API拒绝了缓存条目 {}，不再重放: {}", entry.id, e);
+                    rejected.insert(entry.id);
+                },
+                // 写实例不可用或请求超时时停止本轮重放，等待下次执行
+                Err(e) => {
+                    warn!("重放缓存数据失败，等待下次重放: {:?}", e);
+                    break;
+                },
+            }
         }
 
-        Ok(responses)
+        // 已写入CRUD API和被拒绝的条目标记为已处理（文件后端移入processed目录），不再重放
+        let replayed_total = self.cache_manager.mark_processed(&replayed)?;
+        let rejected_total = self.cache_manager.mark_processed(&rejected)?;
+        if rejected_total > 0 {
+            warn!("{} 条缓存数据被CRUD API拒绝，已移出重放队列", rejected_total);
+        }
+
+        if replayed_total > 0 {
+            info!("已将 {} 条缓存数据重放到CRUD API", replayed_total);
+        }
+        Ok(replayed_total)
     }
 
-    /// 服务健康检查
-    pub async fn health_check(&self) -> Result<()> {
-        // 检查配置是否有效
-        self.config.validate()?;
-        
-        // 执行调度器健康检查
-        let instance_status = self.scheduler.get_all_instance_status();
-        
-        // 检查是否有健康的实例
-        let has_healthy_instance = instance_status.iter()
-            .any(|(_, _, status)| *status == crate::scheduler::InstanceHealthStatus::Healthy);
-        
-        if !has_healthy_instance {
-            anyhow::bail!("没有健康的CRUD API实例可用");
-        }
-        
-        Ok(())
+    /// 启动缓存重放任务
+    pub async fn start_replay_task(&self, mut shutdown: watch::Receiver<bool>) -> Option<JoinHandle<()>> {
+        let interval_secs = self.config.failover.replay_interval;
+        if interval_secs == 0 {
+            return None;
+        }
+
+        let service = self.clone();
+        let handle = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(interval_secs));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {},
+                    _ = shutdown.changed() => break,
+                }
+                if let Err(e) = service.replay_cache(None).await {
+                    error!("重放缓存数据失败: {:?}", e);
+                }
+            }
+            info!("缓存重放任务已停止");
+        });
+        Some(handle)
+    }
+
+    /// 启动重放任务：完成首次健康检查并等到写实例可用后，重放一次启动前遗留的缓存
+    ///
+    /// 与定期重放共用重放锁和已处理标记，同一条目不会重复写入
+    pub async fn start_startup_replay(&self, mut shutdown: watch::Receiver<bool>) -> Option<JoinHandle<()>> {
+        if !self.config.failover.startup_replay {
+            return None;
+        }
+
+        let service = self.clone();
+        let handle = tokio::spawn(async move {
+            let retry_interval = std::time::Duration::from_secs(service.config.crud_api.health_check_interval.max(1));
+            loop {
+                if let Err(e) = service.scheduler.request_health_check().await {
+                    error!("健康检查失败: {:?}", e);
+                }
+                if service.scheduler.has_healthy_instance(true) {
+                    break;
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(retry_interval) => {},
+                    _ = shutdown.changed() => return,
+                }
+            }
+
+            let limit = Some(service.config.failover.startup_replay_limit).filter(|limit| *limit > 0);
+            match service.replay_cache(limit).await {
+                Ok(replayed) => info!("启动重放完成，共重放 {} 条缓存数据", replayed),
+                Err(e) => error!("启动重放缓存数据失败: {:?}", e),
+            }
+        });
+        Some(handle)
+    }
+
+    /// 按Location模板生成资源地址，未配置模板时返回None
+    fn location_for(&self, crud_base: &str, resource_type: &str, id: &str) -> Option<String> {
+        self.config.server.location_template.as_ref().map(|template| template
+            .replace("{crud_base}", crud_base)
+            .replace("{resource_type}", resource_type)
+            .replace("{id}", id))
+    }
+
+    /// 查询CRUD API中指定ID的记录，存在时返回其加密数据，不存在时返回None
+    async fn find_existing(&self, instance: &CrudApiInstance, resource_type: &str, id: &str) -> Result<Option<String>> {
+        resource_type_segment(resource_type)?;
+        let crud_url = format!("{}/{}/{}?select=encrypted_data", instance.url, resource_type, id);
+        let result = retry::send_with_retry(
+            || self.crud_request(instance, Method::GET, &crud_url),
+            instance.retries,
+        ).await;
+        self.scheduler.record_request_result(&instance.id, &result);
+
+        let response = match result {
+            Ok(response) => response,
+            Err(e) if e.status() == Some(reqwest::StatusCode::NOT_FOUND) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let crud_response: GenericResponse<serde_json::Value> = response.json().await?;
+        Ok(crud_response.data
+            .and_then(|data| data.get("encrypted_data").and_then(|ed| ed.as_str().map(|s| s.to_string()))))
+    }
+
+    /// 校验资源ID格式，返回可以拼接到CRUD API请求地址中的资源ID
+    ///
+    /// 只由点组成的资源ID（如 . 和 ..）会被URL解析为路径中的点段而跳出资源集合，无论格式和编码配置如何都拒绝；
+    /// 配置了格式时资源ID必须完整匹配；包含URL不安全字符时按配置进行百分号编码或拒绝
+    fn resource_id_segment(&self, resource_id: &str) -> Result<String, ServiceError> {
+        if resource_id.is_empty() {
+            return Err(ServiceError::InvalidRequest("资源ID不能为空".to_string()));
+        }
+        if is_dot_segment(resource_id) {
+            return Err(ServiceError::InvalidRequest(format!("资源ID {} 不能只由点组成", resource_id)));
+        }
+
+        if let Some(ref pattern) = self.resource_id_pattern
+            && !pattern.is_match(resource_id)
+        {
+            return Err(ServiceError::InvalidRequest(format!("资源ID {} 不符合格式要求", resource_id)));
+        }
+
+        if resource_id.bytes().all(is_url_safe) {
+            return Ok(resource_id.to_string());
+        }
+        if self.config.resource_id.encode_unsafe {
+            Ok(percent_encode(resource_id))
+        } else {
+            Err(ServiceError::InvalidRequest(format!("资源ID {} 包含URL不安全字符", resource_id)))
+        }
+    }
+
+    /// 创建发往CRUD API实例的请求，附加实例的认证请求头和超时时间，并按配置附加X-Request-Id
+    fn crud_request(&self, instance: &CrudApiInstance, method: Method, url: &str) -> RequestBuilder {
+        let builder = self.http_client.request(method, url).timeout(instance.request_timeout());
+        self.with_request_id(instance.headers.apply(builder))
+    }
+
+    /// 按配置为CRUD API请求附加当前请求的X-Request-Id
+    fn with_request_id(&self, builder: RequestBuilder) -> RequestBuilder {
+        if self.config.request_id.propagate {
+            request_id::propagate(builder)
+        } else {
+            builder
+        }
+    }
+
+    /// 批量加密数据
+    pub async fn batch_encrypt(&self, requests: Vec<EncryptRequest>) -> Result<Vec<BatchItemResult<EncryptResponse>>, ServiceError> {
+        self.check_batch_size(requests.len())?;
+
+        // 检查服务角色是否允许加密
+        if let Err(e) = self.check_role(true) {
+            return self.reject_batch(requests.len(), e);
+        }
+
+        let results = self.run_batch(requests, |request| self.encrypt(request)).await?;
+        Ok(results.into_iter().enumerate()
+            .map(|(index, result)| self.batch_item(index, result))
+            .collect())
+    }
+
+    /// 批量解密数据
+    pub async fn batch_decrypt(&self, requests: Vec<DecryptRequest>) -> Result<Vec<BatchItemResult<DecryptResponse>>, ServiceError> {
+        self.check_batch_size(requests.len())?;
+
+        // 检查服务角色是否允许解密
+        if let Err(e) = self.check_role(false) {
+            return self.reject_batch(requests.len(), e);
+        }
+
+        let results = self.run_batch(requests, |request| self.decrypt(request)).await?;
+        Ok(results.into_iter().enumerate()
+            .map(|(index, result)| self.batch_item(index, result))
+            .collect())
+    }
+
+    /// 检查批量请求的条目数，超过上限时整批拒绝
+    fn check_batch_size(&self, count: usize) -> Result<(), ServiceError> {
+        let limit = self.config.batch.max_size;
+        if count > limit {
+            return Err(ServiceError::BatchTooLarge { limit, count });
+        }
+        Ok(())
+    }
+
+    /// 将单项处理结果转换为批量结果
+    fn batch_item<T>(&self, index: usize, result: Result<T, ServiceError>) -> BatchItemResult<T> {
+        BatchItemResult::from_result(index, result.map_err(|e| (e.status_code(), self.client_error(&e))))
+    }
+
+    /// 整批无法处理时的结果：开启汇总状态码时每一项都记录同一原因，否则整批返回错误
+    fn reject_batch<T>(&self, len: usize, e: ServiceError) -> Result<Vec<BatchItemResult<T>>, ServiceError> {
+        if !self.config.batch.aggregate_status {
+            return Err(e);
+        }
+        let (status, message) = (e.status_code(), self.client_error(&e));
+        Ok((0..len)
+            .map(|index| BatchItemResult::from_result(index, Err((status, message.clone()))))
+            .collect())
+    }
+
+    /// 批量校验密文是否可以解密，只返回每一项的校验结果，不返回明文也不写入缓存
+    pub async fn batch_verify(&self, requests: Vec<DecryptRequest>) -> Result<Vec<VerifyResponse>, ServiceError> {
+        self.check_batch_size(requests.len())?;
+
+        // 检查服务角色是否允许解密
+        self.check_role(false)?;
+
+        self.run_batch(requests, |request| async move {
+            Ok::<_, ServiceError>(self.verify_item(request).await)
+        }).await?.into_iter().collect()
+    }
+
+    /// 校验密码能否解密密文，只返回校验结果，不返回明文也不写入缓存
+    ///
+    /// 密码错误、密文被篡改或无法获取密文时都返回valid为false而不是错误，调用方无法通过状态码区分失败原因
+    pub async fn verify(&self, request: DecryptRequest) -> Result<VerifyResponse, ServiceError> {
+        // 检查服务角色是否允许解密
+        self.check_role(false)?;
+
+        Ok(self.verify_item(request).await)
+    }
+
+    /// 获取密文并解密校验认证标签，解密出的明文立即丢弃
+    async fn verify_item(&self, request: DecryptRequest) -> VerifyResponse {
+        let valid = match self.fetch_encrypted_data(&request).await {
+            Ok((encrypted_data, _)) => self.crypto_utils
+                .decrypt(&encrypted_data, &request.password, &request.resource_type)
+                .await
+                .is_ok(),
+            Err(e) => {
+                warn!("获取待校验数据失败: {:?}", e);
+                false
+            },
+        };
+        VerifyResponse {
+            valid,
+            resource_id: request.resource_id,
+        }
+    }
+
+    /// 以有限并发处理批量条目，返回每一项的处理结果并保持输入顺序
+    ///
+    /// 严格模式下任一条目失败时整批失败
+    async fn run_batch<T, R, F, Fut>(&self, items: Vec<T>, handler: F) -> Result<Vec<Result<R, ServiceError>>, ServiceError>
+    where
+        F: Fn(T) -> Fut,
+        Fut: Future<Output = Result<R, ServiceError>>,
+    {
+        let pending = stream::iter(items.into_iter().enumerate())
+            .map(|(index, item)| {
+                let future = handler(item);
+                async move { (index, future.await) }
+            })
+            .buffer_unordered(self.config.batch.concurrency);
+
+        let mut results: Vec<(usize, Result<R, ServiceError>)> = if self.config.batch.strict {
+            pending
+                .map(|(index, result)| match result {
+                    Ok(response) => Ok((index, Ok(response))),
+                    Err(e) => Err(ServiceError::BatchItem { index, source: Box::new(e) }),
+                })
+                .try_collect()
+                .await?
+        } else {
+            pending.collect().await
+        };
+
+        // 按输入顺序还原结果
+        results.sort_unstable_by_key(|(index, _)| *index);
+        Ok(results.into_iter().map(|(_, result)| result).collect())
+    }
+
+    /// 就绪检查，返回各子系统的状态
+    ///
+    /// CRUD API全部不可用时加密请求降级写入本地缓存，解密请求降级使用请求中的密文，
+    /// 因此只要配置有效且CRUD API或本地缓存之一可用即视为就绪
+    pub fn readiness(&self) -> ReadinessReport {
+        let config = match self.config.validate() {
+            Ok(()) => SubsystemState::ok(),
+            Err(e) => SubsystemState::error(format!("{:#}", e)),
+        };
+
+        let instance_status = self.scheduler.get_all_instance_status();
+        let healthy_instances = instance_status.iter()
+            .filter(|instance| instance.status == crate::scheduler::InstanceHealthStatus::Healthy)
+            .count();
+        let crud_api = if healthy_instances > 0 {
+            SubsystemState::ok()
+        } else {
+            SubsystemState::error("没有健康的CRUD API实例可用".to_string())
+        };
+
+        // 内存缓存后端总是可用
+        let cache = match self.config.cache.backend {
+            crate::config::CacheBackendType::Memory => SubsystemState::ok(),
+            crate::config::CacheBackendType::File => match crate::config::check_dir_writable(&self.config.cache.cache_dir) {
+                Ok(()) => SubsystemState::ok(),
+                Err(e) => SubsystemState::error(format!("缓存目录不可写: {}", e)),
+            },
+        };
+
+        let ready = config.is_ok() && (crud_api.is_ok() || cache.is_ok());
+        ReadinessReport {
+            ready,
+            // CRUD API不可用但可以降级时标记为降级运行
+            degraded: ready && !crud_api.is_ok(),
+            healthy_instances,
+            total_instances: instance_status.len(),
+            config,
+            crud_api,
+            cache,
+        }
+    }
+}
+
+impl ReadinessReport {
+    /// 按 /health 的规则判断：配置无效或没有健康的CRUD API实例时返回错误
+    pub fn health_result(&self) -> Result<(), ServiceError> {
+        if let Some(ref message) = self.config.error {
+            return Err(ServiceError::Config(anyhow::anyhow!(message.clone())));
+        }
+        if let Some(ref message) = self.crud_api.error {
+            return Err(ServiceError::UpstreamUnavailable(anyhow::anyhow!(message.clone())));
+        }
+        Ok(())
+    }
+}
+
+/// 子系统状态
+#[derive(Debug, Clone, Serialize)]
+pub struct SubsystemState {
+    /// ok 或 error
+    pub status: &'static str,
+    /// 不可用的原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl SubsystemState {
+    fn ok() -> Self {
+        Self { status: "ok", error: None }
+    }
+
+    fn error(message: String) -> Self {
+        Self { status: "error", error: Some(message) }
+    }
+
+    /// 子系统是否可用
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// 就绪检查结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadinessReport {
+    /// 是否可以处理请求
+    pub ready: bool,
+    /// 是否处于降级运行（CRUD API不可用，使用本地缓存）
+    pub degraded: bool,
+    /// 健康的CRUD API实例数
+    pub healthy_instances: usize,
+    /// CRUD API实例总数
+    pub total_instances: usize,
+    /// 配置
+    pub config: SubsystemState,
+    /// CRUD API
+    pub crud_api: SubsystemState,
+    /// 本地缓存
+    pub cache: SubsystemState,
+}
+
+/// 幂等键请求头
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// 构造保存到CRUD API的记录
+fn crud_record(resource_type: &str, encrypted_data: &str, content_id: Option<&str>, created_at: DateTime<Utc>) -> serde_json::Value {
+    let mut crud_data = serde_json::json!({
+        "encrypted_data": encrypted_data,
+        "resource_type": resource_type,
+        "created_at": created_at.to_rfc3339(),
+        "updated_at": Utc::now().to_rfc3339(),
+    });
+    if let Some(id) = content_id {
+        crud_data["id"] = serde_json::Value::String(id.to_string());
+    }
+    crud_data
+}
+
+/// 解密请求既没有resource_id也没有encrypted_data
+fn missing_ciphertext() -> ServiceError {
+    ServiceError::InvalidRequest("必须提供encrypted_data或resource_id".to_string())
+}
+
+/// 生成更新CRUD API记录的数据，只包含新密文和更新时间
+fn update_record(resource_type: &str, encrypted_data: &str, updated_at: DateTime<Utc>) -> serde_json::Value {
+    serde_json::json!({
+        "encrypted_data": encrypted_data,
+        "resource_type": resource_type,
+        "updated_at": updated_at.to_rfc3339(),
+    })
+}
+
+/// 从CRUD API响应数据中取出资源ID，字符串和数字ID均可
+fn response_id(data: &serde_json::Value) -> Option<String> {
+    match data.get("id")? {
+        serde_json::Value::String(id) => Some(id.clone()),
+        serde_json::Value::Number(id) => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+/// 描述响应数据的结构，只包含字段名和类型，不包含字段值
+fn response_shape(data: Option<&serde_json::Value>) -> String {
+    match data {
+        Some(serde_json::Value::Object(map)) => format!("{{{}}}", map.keys().cloned().collect::<Vec<_>>().join(", ")),
+        Some(serde_json::Value::Array(items)) => format!("数组（{} 项）", items.len()),
+        Some(serde_json::Value::String(_)) => "字符串".to_string(),
+        Some(serde_json::Value::Number(_)) => "数字".to_string(),
+        Some(serde_json::Value::Bool(_)) => "布尔值".to_string(),
+        Some(serde_json::Value::Null) | None => "null".to_string(),
+    }
+}
+
+/// CRUD API是否拒绝了请求本身：返回了4xx等非5xx错误状态，重试也不会成功
+///
+/// 连接失败、超时和5xx说明实例暂时不可用，不属于拒绝
+fn is_rejected(e: &reqwest::Error) -> bool {
+    e.status().is_some() && !retry::is_retryable(e)
+}
+
+/// 校验资源类型，返回可以拼接到CRUD API请求地址中的资源类型
+///
+/// 资源类型作为URL路径中的集合名，只允许ASCII字母、数字、- 和 _，不进行编码，
+/// 路径参数中已解码的 / 和 .. 不能借此跳出资源集合
+fn resource_type_segment(resource_type: &str) -> Result<&str, ServiceError> {
+    if resource_type.is_empty() {
+        return Err(ServiceError::InvalidRequest("资源类型不能为空".to_string()));
+    }
+    if !resource_type.bytes().all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_')) {
+        return Err(ServiceError::InvalidRequest(format!("资源类型 {} 只能包含字母、数字、- 和 _", resource_type)));
+    }
+    Ok(resource_type)
+}
+
+/// 是否只由点组成，拼接到URL路径中会被当作 . 或 .. 点段规范化
+fn is_dot_segment(segment: &str) -> bool {
+    segment.bytes().all(|byte| byte == b'.')
+}
+
+/// 是否为URL中无需编码的字符（RFC 3986 非保留字符）
+fn is_url_safe(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~')
+}
+
+/// 对URL不安全字符进行百分号编码
+fn percent_encode(value: &str) -> String {
+    value.bytes()
+        .map(|byte| if is_url_safe(byte) { (byte as char).to_string() } else { format!("%{:02X}", byte) })
+        .collect()
+}
+
+/// 由密文生成幂等键，每次加密的密文都不同，同一密文的重复写入可以被后端识别
+fn idempotency_key(encrypted_data: &str) -> String {
+    Sha256::digest(encrypted_data.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use axum::extract::{Path, State};
+    use axum::http::HeaderMap;
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+
+    use super::*;
+
+    /// 模拟Test实例收到的写入请求：资源类型、幂等键和请求体
+    type Received = Arc<Mutex<Vec<(String, Option<String>, serde_json::Value)>>>;
+
+    async fn provision(State((base, _)): State<(String, Received)>) -> Json<serde_json::Value> {
+        Json(serde_json::json!({ "data": { "id": "test-1", "url": format!("{}/instance", base) } }))
+    }
+
+    async fn create_record(
+        State((_, received)): State<(String, Received)>,
+        Path(resource_type): Path<String>,
+        headers: HeaderMap,
+        Json(body): Json<serde_json::Value>,
+    ) -> (StatusCode, Json<serde_json::Value>) {
+        let idempotency_key = headers.get(IDEMPOTENCY_KEY_HEADER).and_then(|value| value.to_str().ok()).map(str::to_string);
+        // 密文为conflict的条目模拟幂等键冲突
+        let conflict = body["encrypted_data"] == "conflict";
+        received.lock().unwrap().push((resource_type, idempotency_key, body));
+        if conflict {
+            return (StatusCode::CONFLICT, Json(serde_json::json!({ "success": false, "message": "幂等键冲突" })));
+        }
+        (StatusCode::OK, Json(serde_json::json!({ "success": true, "data": { "id": "1" } })))
+    }
+
+    /// 启动模拟的Test实例创建接口和Test实例，返回创建接口地址和Test实例收到的写入请求
+    async fn mock_provisioner() -> (String, Received) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let received = Received::default();
+        let app = Router::new()
+            .route("/provision", post(provision))
+            .route("/instance/:resource_type", post(create_record))
+            .with_state((base.clone(), received.clone()));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("{}/provision", base), received)
+    }
+
+    fn service(provision_url: &str) -> EncryptionService {
+        service_with(provision_url, &[])
+    }
+
+    /// CRUD API不可达、使用内存缓存的服务，附加额外配置
+    fn service_with(provision_url: &str, vars: &[(&str, &str)]) -> EncryptionService {
+        let mut vars = vars.to_vec();
+        vars.push(("CRUD_API_WRITE_INSTANCE_URL", "http://127.0.0.1:1"));
+        vars.push(("TEST_INSTANCE_PROVISION_URL", provision_url));
+        vars.push(("CACHE_BACKEND", "memory"));
+        EncryptionService::new(Arc::new(AppConfig::from_vars(&vars).unwrap()))
+    }
+
+    /// 模拟CRUD API实例：读取接口放慢响应使并发请求重叠，并统计收到的读取请求数
+    async fn mock_crud(encrypted_data: String) -> (String, Arc<AtomicUsize>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let app = Router::new()
+            .route("/health", get(|| async { Json(serde_json::json!({ "status": "ok" })) }))
+            .route("/:resource_type/:resource_id", get(|State((encrypted_data, fetches)): State<(String, Arc<AtomicUsize>)>| async move {
+                fetches.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                Json(serde_json::json!({ "success": true, "message": "ok", "data": { "encrypted_data": encrypted_data } }))
+            }))
+            .with_state((encrypted_data, fetches.clone()));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, fetches)
+    }
+
+    fn cache_encrypt(service: &EncryptionService, encrypted_data: &str, persisted: bool) {
+        let data = EncryptCacheData {
+            data: "plaintext".to_string(),
+            password: String::new(),
+            resource_type: "user".to_string(),
+            encrypted_data: encrypted_data.to_string(),
+            persisted,
+            resource_id: None,
+            content_id: None,
+        };
+        service.cache_manager.write_cache(CacheDataType::Encrypt(data)).unwrap();
+    }
+
+    #[tokio::test]
+    async fn imports_pending_entries_into_provisioned_instance() {
+        let (provision_url, received) = mock_provisioner().await;
+        let service = service(&provision_url);
+        cache_encrypt(&service, "pending-1", false);
+        cache_encrypt(&service, "already-persisted", true);
+        cache_encrypt(&service, "pending-2", false);
+
+        let test_instance = service.test_instance_manager.create_test_instance().await.unwrap();
+        assert_eq!(service.import_cache_to_test_instance(&test_instance).await.unwrap(), 2);
+
+        let requests = received.lock().unwrap().clone();
+        let written: Vec<_> = requests.iter().map(|(_, _, body)| body["encrypted_data"].as_str().unwrap()).collect();
+        assert_eq!(written, ["pending-1", "pending-2"]);
+        for (resource_type, idempotency_key, body) in &requests {
+            assert_eq!(resource_type, "user");
+            assert_eq!(idempotency_key.as_deref(), Some(super::idempotency_key(body["encrypted_data"].as_str().unwrap()).as_str()));
+        }
+
+        // 已导入的条目不再重复导入，新缓存的条目照常导入
+        cache_encrypt(&service, "pending-3", false);
+        let test_instance = service.test_instance_manager.create_test_instance().await.unwrap();
+        assert_eq!(service.import_cache_to_test_instance(&test_instance).await.unwrap(), 1);
+        assert_eq!(received.lock().unwrap().len(), 3);
+    }
+
+    /// 启动接收写入请求的模拟CRUD API写实例，返回地址和收到的写入请求
+    async fn mock_write_instance() -> (String, Received) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let received = Received::default();
+        let app = Router::new()
+            .route("/health", get(|| async { Json(serde_json::json!({ "status": "ok" })) }))
+            .route("/:resource_type", post(create_record))
+            .with_state((url.clone(), received.clone()));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, received)
+    }
+
+    #[tokio::test]
+    async fn replays_cached_encrypts_once_write_instance_recovers() {
+        let (crud_url, received) = mock_write_instance().await;
+        let service = service_with("http://127.0.0.1:1/provision", &[("CRUD_API_WRITE_INSTANCE_URL", &crud_url)]);
+        cache_encrypt(&service, "pending-1", false);
+        cache_encrypt(&service, "already-persisted", true);
+        cache_encrypt(&service, "pending-2", false);
+
+        // 写实例健康检查通过前不重放
+        assert_eq!(service.replay_cache(None).await.unwrap(), 0);
+        assert!(received.lock().unwrap().is_empty());
+
+        service.scheduler.perform_health_check().await.unwrap();
+        assert_eq!(service.replay_cache(None).await.unwrap(), 2);
+        let requests = received.lock().unwrap().clone();
+        let written: Vec<_> = requests.iter().map(|(_, _, body)| body["encrypted_data"].as_str().unwrap()).collect();
+        assert_eq!(written, ["pending-1", "pending-2"]);
+        for (resource_type, idempotency_key, body) in &requests {
+            assert_eq!(resource_type, "user");
+            assert_eq!(idempotency_key.as_deref(), Some(super::idempotency_key(body["encrypted_data"].as_str().unwrap()).as_str()));
+        }
+
+        // 重放成功的条目从缓存移除，再次重放不会重复写入
+        assert!(service.cache_manager.read_all_cache().unwrap().iter().all(|entry| match entry.data_type {
+            CacheDataType::Encrypt(ref data) => data.persisted,
+            _ => true,
+        }));
+        assert_eq!(service.replay_cache(None).await.unwrap(), 0);
+        assert_eq!(received.lock().unwrap().len(), 2);
+    }
+
+    /// 已写入CRUD API的密文
+    fn written(received: &Received) -> Vec<String> {
+        received.lock().unwrap().iter().map(|(_, _, body)| body["encrypted_data"].as_str().unwrap().to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn rejected_entry_does_not_stall_replay() {
+        let (crud_url, received) = mock_write_instance().await;
+        let service = service_with("http://127.0.0.1:1/provision", &[("CRUD_API_WRITE_INSTANCE_URL", &crud_url)]);
+        cache_encrypt(&service, "conflict", false);
+        cache_encrypt(&service, "pending-1", false);
+        service.scheduler.perform_health_check().await.unwrap();
+
+        assert_eq!(service.replay_cache(None).await.unwrap(), 1);
+        assert_eq!(written(&received), ["conflict", "pending-1"]);
+
+        // 被拒绝的条目已移出重放队列，之后缓存的条目照常重放
+        cache_encrypt(&service, "pending-2", false);
+        assert_eq!(service.replay_cache(None).await.unwrap(), 1);
+        assert_eq!(written(&received), ["conflict", "pending-1", "pending-2"]);
+    }
+
+    #[tokio::test]
+    async fn rejected_entry_does_not_abort_import() {
+        let (provision_url, received) = mock_provisioner().await;
+        let service = service(&provision_url);
+        cache_encrypt(&service, "conflict", false);
+        cache_encrypt(&service, "pending-1", false);
+
+        let test_instance = service.test_instance_manager.create_test_instance().await.unwrap();
+        assert_eq!(service.import_cache_to_test_instance(&test_instance).await.unwrap(), 1);
+        assert_eq!(written(&received), ["conflict", "pending-1"]);
+    }
+
+    #[tokio::test]
+    async fn import_fails_when_instance_unreachable() {
+        let (provision_url, _) = mock_provisioner().await;
+        let service = service(&provision_url);
+        cache_encrypt(&service, "pending-1", false);
+
+        let mut test_instance = service.test_instance_manager.create_test_instance().await.unwrap();
+        test_instance.url = "http://127.0.0.1:1".to_string();
+        assert!(service.import_cache_to_test_instance(&test_instance).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn provisioning_failure_is_surfaced() {
+        let service = service("http://127.0.0.1:1/provision");
+        assert!(service.test_instance_manager.create_test_instance().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn concurrent_decrypts_of_same_id_share_one_fetch() {
+        let probe = service("http://127.0.0.1:1/provision");
+        let encrypted_data = probe.crypto_utils.encrypt("plaintext", "password", "user", None).await.unwrap();
+        let (crud_url, fetches) = mock_crud(encrypted_data).await;
+        let config = AppConfig::from_vars(&[
+            ("CRUD_API_WRITE_INSTANCE_URL", &crud_url),
+            ("CRUD_API_SINGLE_FLIGHT", "true"),
+            ("CACHE_BACKEND", "memory"),
+        ]).unwrap();
+        let service = Arc::new(EncryptionService::new(Arc::new(config)));
+        service.scheduler.perform_health_check().await.unwrap();
+
+        let decrypts = (0..20).map(|_| {
+            let service = service.clone();
+            tokio::spawn(async move {
+                service.decrypt(DecryptRequest {
+                    encrypted_data: None,
+                    password: "password".to_string(),
+                    resource_type: "user".to_string(),
+                    resource_id: Some("42".to_string()),
+                }).await
+            })
+        }).collect::<Vec<_>>();
+        for decrypt in decrypts {
+            assert_eq!(decrypt.await.unwrap().unwrap().data, "plaintext");
+        }
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    }
+
+    fn verify_request(encrypted_data: &str, password: &str) -> DecryptRequest {
+        DecryptRequest {
+            encrypted_data: Some(encrypted_data.to_string()),
+            password: password.to_string(),
+            resource_type: "user".to_string(),
+            resource_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn batch_verify_reports_per_item_verdicts() {
+        let service = service("http://127.0.0.1:1/provision");
+        let valid = service.crypto_utils.encrypt("plaintext", "password", "user", None).await.unwrap();
+        // 修改密文中间的一个字符，认证标签校验失败
+        let mut tampered = valid.clone().into_bytes();
+        let middle = tampered.len() / 2;
+        tampered[middle] = if tampered[middle] == b'A' { b'B' } else { b'A' };
+        let tampered = String::from_utf8(tampered).unwrap();
+
+        let verdicts = service.batch_verify(vec![
+            verify_request(&valid, "password"),
+            verify_request(&tampered, "password"),
+            verify_request(&valid, "wrong-password"),
+            verify_request("not base64!", "password"),
+        ]).await.unwrap();
+        let verdicts: Vec<bool> = verdicts.iter().map(|verdict| verdict.valid).collect();
+        assert_eq!(verdicts, [true, false, false, false]);
+    }
+
+    fn encrypt_request(resource_type: &str) -> EncryptRequest {
+        EncryptRequest {
+            data: "plaintext".to_string(),
+            password: "password".to_string(),
+            resource_type: resource_type.to_string(),
+            algorithm: None,
+            dry_run: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn dry_run_skips_crud_api_and_cache() {
+        let dir = std::env::temp_dir().join(format!("encryption-service-dry-run-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let (crud_url, received) = mock_write_instance().await;
+        let vars = [("CRUD_API_WRITE_INSTANCE_URL", crud_url.as_str()), ("CACHE_BACKEND", "file"), ("CACHE_DIR", dir.to_str().unwrap())];
+
+        // 写实例不可用时普通加密会写入缓存，试运行同样跳过
+        let service = service_with("http://127.0.0.1:1/provision", &vars);
+        let response = service.encrypt(EncryptRequest { dry_run: Some(true), ..encrypt_request("user") }).await.unwrap();
+        assert_eq!((response.resource_id, response.persisted), (None, false));
+        assert_eq!(service.crypto_utils.decrypt(&response.encrypted_data, "password", "user").await.unwrap(), "plaintext");
+
+        service.scheduler.perform_health_check().await.unwrap();
+        service.encrypt(EncryptRequest { dry_run: Some(true), ..encrypt_request("user") }).await.unwrap();
+        service.cache_manager.flush().unwrap();
+        assert!(received.lock().unwrap().is_empty());
+        let cache_files = std::fs::read_dir(&dir).map(|entries| entries.count()).unwrap_or(0);
+        assert_eq!(cache_files, 0);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn idless_crud_response_is_persisted_but_not_addressable() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let crud_url = format!("http://{}", listener.local_addr().unwrap());
+        let app = Router::new()
+            .route("/health", get(|| async { Json(serde_json::json!({ "status": "ok" })) }))
+            .route("/:resource_type", post(|| async {
+                Json(serde_json::json!({ "success": true, "message": "ok", "data": { "created": true, "rev": 3 } }))
+            }));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let service = service_with("http://127.0.0.1:1/provision", &[("CRUD_API_WRITE_INSTANCE_URL", &crud_url)]);
+        service.scheduler.perform_health_check().await.unwrap();
+
+        let logs = monitoring::LogCapture::default();
+        let response = {
+            let _guard = logs.install();
+            service.encrypt(encrypt_request("user")).await.unwrap()
+        };
+        assert_eq!((response.resource_id, response.persisted), (None, true));
+        assert!(response.location.is_none());
+        let logs = logs.contents();
+        assert!(logs.contains("CRUD API保存成功但响应中没有资源ID"), "{}", logs);
+        assert!(logs.contains("{created, rev}"), "{}", logs);
+
+        // 已写入CRUD API，不再进入重放
+        let entries = service.cache_manager.read_all_cache().unwrap();
+        assert!(matches!(entries[..], [CacheEntry { data_type: CacheDataType::Encrypt(EncryptCacheData { persisted: true, .. }), .. }]));
+    }
+
+    #[tokio::test]
+    async fn only_eligible_resource_types_provision_test_instance() {
+        let (provision_url, received) = mock_provisioner().await;
+        let service = service_with(&provision_url, &[("TEST_INSTANCE_RESOURCE_TYPES", "payment")]);
+
+        // 非关键资源类型只缓存，不创建Test实例
+        let response = service.encrypt(encrypt_request("user")).await.unwrap();
+        assert!(!response.persisted);
+        assert_eq!(service.cache_manager.read_all_cache().unwrap().len(), 1);
+        assert!(received.lock().unwrap().is_empty());
+
+        // 关键资源类型创建Test实例并导入全部待重放的缓存数据
+        service.encrypt(encrypt_request("payment")).await.unwrap();
+        let mut imported: Vec<_> = received.lock().unwrap().iter().map(|(resource_type, _, _)| resource_type.clone()).collect();
+        imported.sort();
+        assert_eq!(imported, ["payment", "user"]);
+    }
+
+    #[tokio::test]
+    async fn write_outage_degrades_role_to_decrypt() {
+        let probe = service("http://127.0.0.1:1/provision");
+        let encrypted_data = probe.crypto_utils.encrypt("plaintext", "password", "user", None).await.unwrap();
+        let (crud_url, _) = mock_crud(encrypted_data).await;
+        let service = service_with("http://127.0.0.1:1/provision", &[
+            ("CRUD_API_READ_INSTANCE_URL", &crud_url),
+            ("SERVICE_ROLE_AUTO_DOWNGRADE", "true"),
+        ]);
+        service.scheduler.perform_health_check().await.unwrap();
+        assert_eq!(service.get_effective_role(), "decrypt");
+
+        let e = service.encrypt(encrypt_request("user")).await.unwrap_err();
+        assert!(matches!(e, ServiceError::UpstreamUnavailable(_)));
+        assert!(e.to_string().contains("服务角色已降级为decrypt"));
+
+        let response = service.decrypt(DecryptRequest {
+            encrypted_data: None,
+            password: "password".to_string(),
+            resource_type: "user".to_string(),
+            resource_id: Some("42".to_string()),
+        }).await.unwrap();
+        assert_eq!(response.data, "plaintext");
+    }
+
+    /// 模拟CRUD API的资源存储：按ID保存写入的记录
+    type Records = Arc<Mutex<std::collections::HashMap<String, serde_json::Value>>>;
+
+    async fn store_record(
+        State(records): State<Records>,
+        Json(body): Json<serde_json::Value>,
+    ) -> Json<serde_json::Value> {
+        let id = body["id"].as_str().unwrap().to_string();
+        records.lock().unwrap().insert(id.clone(), body);
+        Json(serde_json::json!({ "success": true, "message": "ok", "data": { "id": id } }))
+    }
+
+    async fn load_record(
+        State(records): State<Records>,
+        Path((_, id)): Path<(String, String)>,
+    ) -> axum::response::Response {
+        use axum::response::IntoResponse;
+        match records.lock().unwrap().get(&id) {
+            Some(record) => Json(serde_json::json!({ "success": true, "message": "ok", "data": record })).into_response(),
+            None => axum::http::StatusCode::NOT_FOUND.into_response(),
+        }
+    }
+
+    async fn replace_record(
+        State(records): State<Records>,
+        Path((_, id)): Path<(String, String)>,
+        Json(body): Json<serde_json::Value>,
+    ) -> Json<serde_json::Value> {
+        records.lock().unwrap().insert(id, body);
+        Json(serde_json::json!({ "success": true, "message": "ok", "data": null }))
+    }
+
+    /// 启动按ID存取记录的模拟CRUD API实例，返回地址和已保存的记录
+    async fn mock_crud_store() -> (String, Records) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let records = Records::default();
+        let app = Router::new()
+            .route("/health", get(|| async { Json(serde_json::json!({ "status": "ok" })) }))
+            .route("/:resource_type", post(store_record))
+            .route("/:resource_type/:resource_id", get(load_record).put(replace_record))
+            .with_state(records.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, records)
+    }
+
+    #[tokio::test]
+    async fn content_addressed_ids_deduplicate_records() {
+        let (crud_url, records) = mock_crud_store().await;
+        let service = service_with("http://127.0.0.1:1/provision", &[
+            ("CRUD_API_WRITE_INSTANCE_URL", &crud_url),
+            ("CONTENT_ID_KEY", "content-id-key-0123456789"),
+        ]);
+        service.scheduler.perform_health_check().await.unwrap();
+        let request = |data: &str| EncryptRequest { data: data.to_string(), ..encrypt_request("user") };
+
+        let first = service.encrypt(request("plaintext")).await.unwrap();
+        let second = service.encrypt(request("plaintext")).await.unwrap();
+        assert!(first.resource_id.is_some());
+        assert_eq!(second.resource_id, first.resource_id);
+        // 相同内容复用已保存的记录，而不是重新加密写入
+        assert_eq!(second.encrypted_data, first.encrypted_data);
+        assert_eq!(records.lock().unwrap().len(), 1);
+
+        let other = service.encrypt(request("other plaintext")).await.unwrap();
+        assert_ne!(other.resource_id, first.resource_id);
+        assert_eq!(records.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn content_addressed_reuse_is_scoped_to_password() {
+        let (crud_url, records) = mock_crud_store().await;
+        let service = service_with("http://127.0.0.1:1/provision", &[
+            ("CRUD_API_WRITE_INSTANCE_URL", &crud_url),
+            ("CONTENT_ID_KEY", "content-id-key-0123456789"),
+        ]);
+        service.scheduler.perform_health_check().await.unwrap();
+        let request = |password: &str| EncryptRequest { password: password.to_string(), ..encrypt_request("user") };
+
+        // 其他调用方用不同密码加密相同明文时不复用已有记录，得到的密文可以用自己的密码解密
+        let alice = service.encrypt(request("alice-password")).await.unwrap();
+        let bob = service.encrypt(request("bob-password")).await.unwrap();
+        assert_ne!(bob.resource_id, alice.resource_id);
+        assert_ne!(bob.encrypted_data, alice.encrypted_data);
+        assert_eq!(service.crypto_utils.decrypt(&bob.encrypted_data, "bob-password", "user").await.unwrap(), "plaintext");
+        assert_eq!(records.lock().unwrap().len(), 2);
+        assert_eq!(service.encrypt(request("bob-password")).await.unwrap().resource_id, bob.resource_id);
+
+        // 缓存条目记录写入时的内容ID，重放时不依赖缓存中的密码和明文
+        let entries = service.cache_manager.read_all_cache().unwrap();
+        let content_ids: Vec<_> = entries.iter()
+            .filter_map(|entry| match entry.data_type {
+                CacheDataType::Encrypt(ref data) => data.content_id.clone(),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(content_ids, [alice.resource_id.unwrap(), bob.resource_id.unwrap()]);
+    }
+
+    /// 在请求ID上下文中生成服务端错误的客户端信息，返回信息和期间输出的日志
+    async fn upstream_error_message(verbose: &str) -> (String, String) {
+        let service = service_with("http://127.0.0.1:1/provision", &[("VERBOSE_ERRORS", verbose)]);
+        let logs = monitoring::LogCapture::default();
+        let _guard = logs.install();
+        let e = ServiceError::UpstreamUnavailable(anyhow::anyhow!("连接 10.0.0.7:5432 被拒绝"));
+        let message = request_id::scope("req-509".to_string(), async { service.client_error(&e) }).await;
+        (message, logs.contents())
+    }
+
+    #[tokio::test]
+    async fn production_errors_are_generic_but_logged_in_full() {
+        let (message, logs) = upstream_error_message("false").await;
+        assert_eq!(message, "内部错误，请求ID: req-509");
+        assert!(logs.contains("req-509") && logs.contains("10.0.0.7:5432"));
+    }
+
+    #[tokio::test]
+    async fn verbose_errors_return_detail() {
+        let (message, _) = upstream_error_message("true").await;
+        assert!(message.contains("10.0.0.7:5432"));
+    }
+
+    #[test]
+    fn client_errors_keep_their_message_in_production() {
+        let service = service_with("http://127.0.0.1:1/provision", &[("VERBOSE_ERRORS", "false")]);
+        let e = ServiceError::InvalidRequest("未指定需要重新加密的字段".to_string());
+        assert!(e.status_code().is_client_error());
+        assert_eq!(service.client_error(&e), e.to_string());
+    }
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        use chrono::TimeZone;
+        Utc.with_ymd_and_hms(2026, 1, 1, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn maintenance_follows_schedule_boundaries() {
+        let service = service_with("http://127.0.0.1:1/provision", &[("MAINTENANCE_SCHEDULE", "02:00-03:30, 23:00-01:00")]);
+
+        // 开始时间进入维护，结束时间退出维护
+        assert!(!service.is_in_maintenance_at(at(1, 59)));
+        assert!(service.is_in_maintenance_at(at(2, 0)));
+        assert!(service.is_in_maintenance_at(at(3, 29)));
+        assert!(!service.is_in_maintenance_at(at(3, 30)));
+
+        // 跨越午夜的窗口
+        assert!(!service.is_in_maintenance_at(at(22, 59)));
+        assert!(service.is_in_maintenance_at(at(23, 0)));
+        assert!(service.is_in_maintenance_at(at(0, 30)));
+        assert!(!service.is_in_maintenance_at(at(1, 0)));
+    }
+
+    #[tokio::test]
+    async fn maintenance_rejects_encrypt_but_allows_decrypt() {
+        let service = service_with("http://127.0.0.1:1/provision", &[("MAINTENANCE_SCHEDULE", "00:00-23:59, 23:59-00:00")]);
+        assert!(service.is_in_maintenance());
+        assert!(service.encrypt(encrypt_request("user")).await.is_err());
+
+        let encrypted_data = service.crypto_utils.encrypt("plaintext", "password", "user", None).await.unwrap();
+        let response = service.decrypt(verify_request(&encrypted_data, "password")).await.unwrap();
+        assert_eq!(response.data, "plaintext");
+
+        // 删除同样属于写操作，错误信息指明删除操作
+        let e = service.delete("user", "42").await.unwrap_err();
+        assert!(e.to_string().contains("暂不允许执行删除操作"), "{}", e);
+    }
+
+    /// 模拟CRUD API收到的请求：路径、Authorization和X-Health-Token请求头
+    type SeenAuth = Arc<Mutex<Vec<(String, Option<String>, Option<String>)>>>;
+
+    fn record_auth(seen: &SeenAuth, path: &str, headers: &HeaderMap) {
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok()).map(str::to_string);
+        seen.lock().unwrap().push((path.to_string(), header("authorization"), header("x-health-token")));
+    }
+
+    /// 启动记录认证请求头的模拟CRUD API实例
+    async fn mock_crud_auth() -> (String, SeenAuth) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let seen = SeenAuth::default();
+        let app = Router::new()
+            .route("/health", get(|State(seen): State<SeenAuth>, headers: HeaderMap| async move {
+                record_auth(&seen, "/health", &headers);
+                Json(serde_json::json!({ "status": "ok" }))
+            }))
+            .route("/:resource_type", post(|State(seen): State<SeenAuth>, Path(resource_type): Path<String>, headers: HeaderMap| async move {
+                record_auth(&seen, &format!("/{}", resource_type), &headers);
+                Json(serde_json::json!({ "success": true, "message": "ok", "data": { "id": "1" } }))
+            }))
+            .with_state(seen.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, seen)
+    }
+
+    #[tokio::test]
+    async fn health_probe_and_data_call_use_separate_auth_headers() {
+        let (crud_url, seen) = mock_crud_auth().await;
+        let service = service_with("http://127.0.0.1:1/provision", &[
+            ("CRUD_API_WRITE_INSTANCE_URL", &crud_url),
+            ("CRUD_API_WRITE_INSTANCE_HEADERS", "Authorization: Bearer data-token"),
+            ("CRUD_API_WRITE_INSTANCE_HEALTH_HEADERS", "X-Health-Token: health-token"),
+        ]);
+        service.scheduler.perform_health_check().await.unwrap();
+        assert!(service.encrypt(encrypt_request("user")).await.unwrap().persisted);
+
+        let seen = seen.lock().unwrap().clone();
+        let find = |path: &str| seen.iter().find(|(p, _, _)| p == path).cloned().unwrap();
+        assert_eq!(find("/health"), ("/health".to_string(), None, Some("health-token".to_string())));
+        assert_eq!(find("/user"), ("/user".to_string(), Some("Bearer data-token".to_string()), None));
+    }
+
+    #[tokio::test]
+    async fn health_probe_falls_back_to_data_auth_headers() {
+        let (crud_url, seen) = mock_crud_auth().await;
+        let service = service_with("http://127.0.0.1:1/provision", &[
+            ("CRUD_API_WRITE_INSTANCE_URL", &crud_url),
+            ("CRUD_API_WRITE_INSTANCE_HEADERS", "Authorization: Bearer data-token"),
+        ]);
+        service.scheduler.perform_health_check().await.unwrap();
+
+        let (_, authorization, _) = seen.lock().unwrap()[0].clone();
+        assert_eq!(authorization.as_deref(), Some("Bearer data-token"));
+    }
+
+    #[tokio::test]
+    async fn background_tasks_stop_on_shutdown() {
+        let service = service_with("http://127.0.0.1:1/provision", &[
+            ("CACHE_REPLAY_INTERVAL", "1"),
+            ("TEST_INSTANCE_HEALTH_INTERVAL", "1"),
+        ]);
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let mut tasks = vec![
+            service.scheduler.start_health_check(shutdown_rx.clone()).await,
+            service.test_instance_manager.start_periodic_check(shutdown_rx.clone()).await,
+            service.cache_manager.start_cleanup_task(shutdown_rx.clone()).await,
+        ];
+        tasks.extend(service.test_instance_manager.start_health_check(shutdown_rx.clone()).await);
+        tasks.extend(service.start_replay_task(shutdown_rx).await);
+        assert_eq!(tasks.len(), 5);
+
+        shutdown_tx.send(true).unwrap();
+        for task in tasks {
+            tokio::time::timeout(Duration::from_secs(2), task).await.expect("后台任务未在停止信号后退出").unwrap();
+        }
+        service.cache_manager.flush().unwrap();
+    }
+
+    #[tokio::test]
+    async fn startup_replays_backlog_left_in_cache_dir() {
+        let dir = std::env::temp_dir().join(format!("encryption-service-startup-replay-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let cache_vars = [("CACHE_BACKEND", "file"), ("CACHE_DIR", dir.to_str().unwrap())];
+
+        // 上一次运行期间CRUD API不可用，加密数据留在缓存目录中
+        let crashed = service_with("http://127.0.0.1:1/provision", &cache_vars);
+        cache_encrypt(&crashed, "backlog-1", false);
+        cache_encrypt(&crashed, "backlog-2", false);
+        drop(crashed);
+
+        // 重启后写实例健康，启动重放写入全部遗留数据
+        let (crud_url, received) = mock_write_instance().await;
+        let mut vars = cache_vars.to_vec();
+        vars.push(("CRUD_API_WRITE_INSTANCE_URL", &crud_url));
+        let restarted = service_with("http://127.0.0.1:1/provision", &vars);
+        let (_shutdown_tx, shutdown_rx) = watch::channel(false);
+        let replay = restarted.start_startup_replay(shutdown_rx.clone()).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(5), replay).await.unwrap().unwrap();
+        let written: Vec<_> = received.lock().unwrap().iter()
+            .map(|(_, _, body)| body["encrypted_data"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(written, ["backlog-1", "backlog-2"]);
+
+        // 已重放的条目带有处理标记，再次启动不会重复写入
+        let restarted_again = service_with("http://127.0.0.1:1/provision", &vars);
+        let replay = restarted_again.start_startup_replay(shutdown_rx).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(5), replay).await.unwrap().unwrap();
+        assert_eq!(received.lock().unwrap().len(), 2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn rewrap_reencrypts_only_targeted_fields() {
+        let (crud_url, records) = mock_crud_store().await;
+        let service = service_with("http://127.0.0.1:1/provision", &[("CRUD_API_WRITE_INSTANCE_URL", &crud_url)]);
+        service.scheduler.perform_health_check().await.unwrap();
+
+        let email = service.crypto_utils.encrypt("user@example.com", "old-password", "user", None).await.unwrap();
+        let phone = service.crypto_utils.encrypt("13800000000", "old-password", "user", None).await.unwrap();
+        let document = serde_json::json!({
+            "id": "doc-1",
+            "email": email,
+            "phone": phone,
+            "name": "plain name",
+            "profile": { "city": "Shanghai", "tags": ["a", "b"] },
+        });
+        records.lock().unwrap().insert("doc-1".to_string(), document.clone());
+
+        let response = service.rewrap(RewrapRequest {
+            resource_type: "user".to_string(),
+            resource_id: "doc-1".to_string(),
+            password: "old-password".to_string(),
+            fields: vec!["email".to_string()],
+            new_password: Some("new-password".to_string()),
+            algorithm: None,
+        }).await.unwrap();
+        assert_eq!(response.fields, ["email"]);
+
+        let written = records.lock().unwrap()["doc-1"].clone();
+        // 目标字段换用新密码加密，其余字段原样写回
+        let rewrapped = written["email"].as_str().unwrap();
+        assert_ne!(rewrapped, email);
+        assert_eq!(service.crypto_utils.decrypt(rewrapped, "new-password", "user").await.unwrap(), "user@example.com");
+        assert!(service.crypto_utils.decrypt(rewrapped, "old-password", "user").await.is_err());
+        for field in ["id", "phone", "name", "profile"] {
+            assert_eq!(written[field], document[field], "字段 {} 被修改", field);
+        }
+        assert!(written["updated_at"].is_string());
+    }
+
+    /// 启动记录读取路径的模拟CRUD API实例，所有读取都返回同一密文
+    async fn mock_crud_paths(encrypted_data: String) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let paths = Arc::new(Mutex::new(Vec::new()));
+        let app = Router::new()
+            .route("/health", get(|| async { Json(serde_json::json!({ "status": "ok" })) }))
+            .route("/:resource_type/:resource_id", get(
+                |State((encrypted_data, paths)): State<(String, Arc<Mutex<Vec<String>>>)>, uri: axum::http::Uri| async move {
+                    paths.lock().unwrap().push(uri.path().to_string());
+                    Json(serde_json::json!({ "success": true, "message": "ok", "data": { "encrypted_data": encrypted_data } }))
+                },
+            ))
+            .with_state((encrypted_data, paths.clone()));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, paths)
+    }
+
+    fn fetch_request(resource_id: &str) -> DecryptRequest {
+        DecryptRequest {
+            encrypted_data: None,
+            password: "password".to_string(),
+            resource_type: "user".to_string(),
+            resource_id: Some(resource_id.to_string()),
+        }
+    }
+
+    /// 按资源ID配置启动读取服务，返回服务和CRUD API收到的读取路径
+    async fn resource_id_service(vars: &[(&str, &str)]) -> (EncryptionService, Arc<Mutex<Vec<String>>>) {
+        let probe = service("http://127.0.0.1:1/provision");
+        let encrypted_data = probe.crypto_utils.encrypt("plaintext", "password", "user", None).await.unwrap();
+        let (crud_url, paths) = mock_crud_paths(encrypted_data).await;
+        let mut vars = vars.to_vec();
+        vars.push(("CRUD_API_WRITE_INSTANCE_URL", &crud_url));
+        let service = service_with("http://127.0.0.1:1/provision", &vars);
+        service.scheduler.perform_health_check().await.unwrap();
+        (service, paths)
+    }
+
+    #[tokio::test]
+    async fn resource_id_with_slash_rejected_by_default() {
+        let (service, paths) = resource_id_service(&[]).await;
+
+        let e = service.decrypt(fetch_request("42/../admin")).await.unwrap_err();
+        assert_eq!(e.status_code(), StatusCode::BAD_REQUEST);
+        assert!(paths.lock().unwrap().is_empty());
+
+        assert_eq!(service.decrypt(fetch_request("user-42_v1.0~x")).await.unwrap().data, "plaintext");
+        assert_eq!(*paths.lock().unwrap(), ["/user/user-42_v1.0~x"]);
+    }
+
+    #[tokio::test]
+    async fn resource_id_with_slash_encoded_when_enabled() {
+        let (service, paths) = resource_id_service(&[("RESOURCE_ID_ENCODE", "true")]).await;
+        assert_eq!(service.decrypt(fetch_request("a/b?c")).await.unwrap().data, "plaintext");
+        assert_eq!(*paths.lock().unwrap(), ["/user/a%2Fb%3Fc"]);
+    }
+
+    #[tokio::test]
+    async fn resource_id_must_match_configured_pattern() {
+        let (service, paths) = resource_id_service(&[("RESOURCE_ID_PATTERN", "[0-9]+")]).await;
+        assert_eq!(service.decrypt(fetch_request("abc")).await.unwrap_err().status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(service.decrypt(fetch_request("42")).await.unwrap().data, "plaintext");
+        assert_eq!(*paths.lock().unwrap(), ["/user/42"]);
+    }
+
+    #[tokio::test]
+    async fn dot_segment_resource_ids_rejected() {
+        for vars in [&[][..], &[("RESOURCE_ID_ENCODE", "true"), ("RESOURCE_ID_PATTERN", ".*")][..]] {
+            let (service, paths) = resource_id_service(vars).await;
+            for resource_id in [".", ".."] {
+                let e = service.decrypt(fetch_request(resource_id)).await.unwrap_err();
+                assert_eq!(e.status_code(), StatusCode::BAD_REQUEST, "{}", resource_id);
+            }
+            assert!(paths.lock().unwrap().is_empty());
+        }
+
+        // 已编码的点段按字面值处理：不编码时拒绝，开启编码时百分号本身被编码，不会被解析为 ..
+        let (service, paths) = resource_id_service(&[]).await;
+        assert_eq!(service.decrypt(fetch_request("%2E%2E")).await.unwrap_err().status_code(), StatusCode::BAD_REQUEST);
+        assert!(paths.lock().unwrap().is_empty());
+        let (service, paths) = resource_id_service(&[("RESOURCE_ID_ENCODE", "true")]).await;
+        assert_eq!(service.decrypt(fetch_request("%2E%2E")).await.unwrap().data, "plaintext");
+        assert_eq!(*paths.lock().unwrap(), ["/user/%252E%252E"]);
+    }
+
+    #[tokio::test]
+    async fn resource_type_cannot_escape_collection() {
+        // 记录所有到达CRUD API的请求路径
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let crud_url = format!("http://{}", listener.local_addr().unwrap());
+        let paths = Arc::new(Mutex::new(Vec::new()));
+        let app = Router::new()
+            .route("/health", get(|| async { Json(serde_json::json!({ "status": "ok" })) }))
+            .fallback(|State(paths): State<Arc<Mutex<Vec<String>>>>, uri: axum::http::Uri| async move {
+                paths.lock().unwrap().push(uri.path().to_string());
+                Json(serde_json::json!({ "success": true, "message": "ok", "data": { "id": "1" } }))
+            })
+            .with_state(paths.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let service = service_with("http://127.0.0.1:1/provision", &[("CRUD_API_WRITE_INSTANCE_URL", &crud_url)]);
+        service.scheduler.perform_health_check().await.unwrap();
+
+        // 路径参数已解码，a%2F..%2Fadmin 到达服务时为 a/../admin
+        for resource_type in ["a/../admin", "..", ".", "user.v2", "", "a?b"] {
+            let e = service.delete(resource_type, "42").await.unwrap_err();
+            assert_eq!(e.status_code(), StatusCode::BAD_REQUEST, "{}", resource_type);
+            let e = service.encrypt(encrypt_request(resource_type)).await.unwrap_err();
+            assert_eq!(e.status_code(), StatusCode::BAD_REQUEST, "{}", resource_type);
+            let request = DecryptRequest { resource_type: resource_type.to_string(), ..fetch_request("42") };
+            assert_eq!(service.decrypt(request).await.unwrap_err().status_code(), StatusCode::BAD_REQUEST);
+        }
+        assert!(paths.lock().unwrap().is_empty());
+
+        service.delete("user_v2-archive", "42").await.unwrap();
+        assert_eq!(*paths.lock().unwrap(), ["/user_v2-archive/42"]);
     }
 }
@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+
+use tokio::sync::watch;
+
+/// 合并并发的相同请求：同一个键同时只执行一次，其他调用方等待并共享结果
+#[derive(Debug)]
+pub struct SingleFlight<T> {
+    /// 正在执行的请求，完成后移除
+    calls: Mutex<HashMap<String, watch::Receiver<Option<T>>>>,
+}
+
+impl<T: Clone> SingleFlight<T> {
+    /// 创建实例
+    pub fn new() -> Self {
+        Self {
+            calls: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 执行键对应的请求，已有相同键的请求在执行时等待其结果
+    ///
+    /// 执行请求的调用方被取消时，等待中的调用方各自重新执行
+    pub async fn run<F, Fut>(&self, key: &str, f: F) -> T
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        let sender = {
+            let mut calls = self.calls.lock().unwrap();
+            match calls.get(key) {
+                Some(receiver) => Err(receiver.clone()),
+                None => {
+                    let (sender, receiver) = watch::channel(None);
+                    calls.insert(key.to_string(), receiver);
+                    Ok(sender)
+                },
+            }
+        };
+
+        match sender {
+            Ok(sender) => {
+                // 完成或被取消时都移除记录
+                let _guard = CallGuard { flight: self, key };
+                let result = f().await;
+                let _ = sender.send(Some(result.clone()));
+                result
+            },
+            Err(mut receiver) => {
+                let shared = receiver.wait_for(|result| result.is_some()).await
+                    .ok()
+                    .and_then(|result| result.clone());
+                match shared {
+                    Some(result) => result,
+                    None => f().await,
+                }
+            },
+        }
+    }
+}
+
+impl<T: Clone> Default for SingleFlight<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 请求结束时移除正在执行的记录
+struct CallGuard<'a, T> {
+    flight: &'a SingleFlight<T>,
+    key: &'a str,
+}
+
+impl<T> Drop for CallGuard<'_, T> {
+    fn drop(&mut self) {
+        self.flight.calls.lock().unwrap().remove(self.key);
+    }
+}
@@ -1,15 +1,25 @@
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
 use tokio::time::interval;
 use tracing::{info, warn, error};
 use anyhow::Result;
 use reqwest::Client;
+use serde::Serialize;
 
-use crate::config::AppConfig;
-use crate::cache::CacheManager;
+use std::collections::HashSet;
+
+use crate::config::{AppConfig, CrudApiInstance, UnhealthyAction};
+use crate::scheduler::InstanceHealthStatus;
+use crate::notifier::{self, Notifier, RateDecision, RateLimiter};
+
+/// Test实例过期提醒的级别，与发送目标名称组成限流通道
+const EXPIRED_REMINDER_LEVEL: &str = "warning";
 
 /// Test实例状态
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum TestInstanceState {
     /// 未创建
     #[allow(dead_code)]
@@ -21,13 +31,11 @@ pub enum TestInstanceState {
 }
 
 /// Test实例配置
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct TestInstanceConfig {
     /// 实例ID
-    #[allow(dead_code)]
     pub id: String,
     /// 实例URL
-    #[allow(dead_code)]
     pub url: String,
     /// 数据库前缀
     #[allow(dead_code)]
@@ -39,42 +47,53 @@ pub struct TestInstanceConfig {
     pub expired_at: u64,
     /// 状态
     pub state: TestInstanceState,
+    /// 健康状态，创建后尚未检查时为Unknown
+    pub health: InstanceHealthStatus,
+    /// 连续健康检查失败次数
+    pub consecutive_failures: u32,
+    /// 上次健康检查时间（秒）
+    pub last_checked_at: Option<u64>,
+    /// 上次发送过期提醒的时间（秒）
+    pub last_reminded_at: Option<u64>,
+    /// 已导入该实例的缓存条目ID
+    #[serde(skip)]
+    pub imported_entries: HashSet<String>,
 }
 
 /// Test实例管理器
 #[derive(Debug, Clone)]
 pub struct TestInstanceManager {
     /// 配置
-    #[allow(dead_code)]
     config: Arc<AppConfig>,
     /// HTTP客户端
     http_client: Client,
-    /// 缓存管理器
-    cache_manager: CacheManager,
     /// Test实例配置
     test_instance: Arc<RwLock<Option<TestInstanceConfig>>>,
-    /// 企业微信群机器人URL
-    wechat_webhook_url: String,
+    /// 提醒发送目标，未配置Webhook URL时为None
+    notifier: Option<Arc<dyn Notifier>>,
+    /// 提醒限流器
+    reminder_limiter: RateLimiter,
+    /// Test实例创建锁，避免并发创建多个实例
+    provision_lock: Arc<tokio::sync::Mutex<()>>,
 }
 
 impl TestInstanceManager {
     /// 创建新的Test实例管理器
-    pub fn new(config: Arc<AppConfig>, cache_manager: CacheManager) -> Self {
-        let http_client = Client::builder()
-            .timeout(Duration::from_millis(config.crud_api.timeout))
-            .build()
-            .expect("无法创建HTTP客户端");
+    pub fn new(config: Arc<AppConfig>, http_client: Client) -> Self {
+        let notifier = notifier::from_config(&config.reminder, http_client.clone());
 
-        // 默认企业微信群机器人URL
-        let wechat_webhook_url = std::env::var("WECHAT_WEBHOOK_URL")
-            .unwrap_or_default();
+        let reminder_limiter = RateLimiter::new(
+            config.reminder.rate_limit,
+            Duration::from_secs(config.reminder.rate_window),
+        );
 
         Self {
             config,
             http_client,
-            cache_manager,
             test_instance: Arc::new(RwLock::new(None)),
-            wechat_webhook_url,
+            notifier,
+            reminder_limiter,
+            provision_lock: Arc::new(tokio::sync::Mutex::new(())),
         }
     }
 
@@ -86,137 +105,555 @@ impl TestInstanceManager {
             .as_secs()
     }
 
-    /// 创建Test实例
+    /// 获取当前Test实例，未创建时返回None
+    pub fn get_test_instance(&self) -> Option<TestInstanceConfig> {
+        self.test_instance.read().unwrap().clone()
+    }
+
+    /// 资源类型是否允许触发Test实例创建
+    pub fn is_provision_eligible(&self, resource_type: &str) -> bool {
+        let eligible_types = &self.config.failover.provision_resource_types;
+        eligible_types.is_empty() || eligible_types.iter().any(|t| t == resource_type)
+    }
+
+    /// 创建Test实例，通过配置的创建接口申请新实例
     pub async fn create_test_instance(&self) -> Result<TestInstanceConfig> {
-        let mut test_instance = self.test_instance.write().unwrap();
+        // 同一时间只创建一个实例，等待中的调用方复用已创建的实例
+        let _guard = self.provision_lock.lock().await;
 
         // 如果Test实例已存在且未过期，直接返回
-        if let Some(ref instance) = *test_instance {
-            if instance.state == TestInstanceState::Created && self.get_current_timestamp() < instance.expired_at {
-                return Ok(instance.clone());
-            }
+        if let Some(instance) = self.get_test_instance()
+            && instance.state == TestInstanceState::Created
+            && self.get_current_timestamp() < instance.expired_at
+        {
+            return Ok(instance);
         }
 
-        // TODO: 实现Test实例创建逻辑
-        // 目前使用模拟数据
+        let provision_url = self.config.failover.test_instance_provision_url.as_deref()
+            .ok_or_else(|| anyhow::anyhow!("未配置TEST_INSTANCE_PROVISION_URL，无法创建Test实例"))?;
+
+        let request = serde_json::json!({
+            "service_id": self.config.service.id,
+            "ttl": self.config.failover.test_instance_ttl,
+        });
+        let body: serde_json::Value = self.http_client
+            .post(provision_url)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| anyhow::anyhow!("请求Test实例创建接口失败: {}", e))?
+            .error_for_status()
+            .map_err(|e| anyhow::anyhow!("Test实例创建接口返回错误: {}", e))?
+            .json()
+            .await
+            .map_err(|e| anyhow::anyhow!("Test实例创建接口返回了无效响应: {}", e))?;
+
+        // 兼容直接返回实例和包装在data字段中的响应
+        let provisioned = body.get("data").filter(|data| data.is_object()).unwrap_or(&body);
+        let field = |name: &str| provisioned.get(name)
+            .and_then(|value| value.as_str())
+            .filter(|value| !value.is_empty())
+            .map(|value| value.to_string());
+        let id = field("id").ok_or_else(|| anyhow::anyhow!("Test实例创建接口未返回实例ID"))?;
+        let url = field("url").ok_or_else(|| anyhow::anyhow!("Test实例创建接口未返回实例URL"))?;
+
         let created_at = self.get_current_timestamp();
-        let expired_at = created_at + 172800; // 48小时后过期
-        
         let test_instance_config = TestInstanceConfig {
-            id: String::from("test-instance-01"),
-            url: format!("http://localhost:8001"),
-            db_prefix: String::from("test_"),
+            id,
+            url: url.trim_end_matches('/').to_string(),
+            db_prefix: field("db_prefix").unwrap_or_else(|| String::from("test_")),
             created_at,
-            expired_at,
+            expired_at: created_at + self.config.failover.test_instance_ttl,
             state: TestInstanceState::Created,
+            health: InstanceHealthStatus::Unknown,
+            consecutive_failures: 0,
+            last_checked_at: None,
+            last_reminded_at: None,
+            imported_entries: HashSet::new(),
         };
 
         // 保存Test实例配置
-        *test_instance = Some(test_instance_config.clone());
+        *self.test_instance.write().unwrap() = Some(test_instance_config.clone());
 
         info!("已创建Test实例: {:?}", test_instance_config);
         Ok(test_instance_config)
     }
 
-    /// 导入缓存数据到Test实例
-    pub async fn import_cache_data(&self) -> Result<()> {
-        // 检查Test实例是否存在
-        let has_created_instance = {
-            let test_instance_opt = self.test_instance.read().unwrap();
-            test_instance_opt.as_ref()
-                .map(|instance| instance.state == TestInstanceState::Created)
-                .unwrap_or(false)
-        };
-        
-        let _test_instance = if has_created_instance {
-            // Test实例已存在，获取实例
-            let test_instance_opt = self.test_instance.read().unwrap();
-            test_instance_opt.clone().unwrap()
-        } else {
-            // Test实例不存在，创建Test实例
-            self.create_test_instance().await?
-        };
-
-        // 读取所有缓存数据
-        let cache_entries = self.cache_manager.read_all_cache()?;
-        info!("准备导入 {} 条缓存数据到Test实例", cache_entries.len());
+    /// 访问Test实例使用的CRUD API实例配置，超时、重试和认证请求头与写实例一致
+    pub fn crud_instance(&self, instance: &TestInstanceConfig) -> Result<CrudApiInstance> {
+        let template = self.config.crud_api.instances.iter()
+            .find(|candidate| candidate.instance_type != "read")
+            .ok_or_else(|| anyhow::anyhow!("没有可用于访问Test实例的写实例配置"))?;
+        Ok(CrudApiInstance {
+            id: instance.id.clone(),
+            url: instance.url.clone(),
+            instance_type: "write".to_string(),
+            ..template.clone()
+        })
+    }
 
-        // TODO: 实现缓存数据导入逻辑
-        // 目前只记录日志
-        for entry in cache_entries {
-            info!("准备导入缓存数据: {:?}", entry);
-            // 这里应该实现具体的数据导入逻辑
+    /// 记录缓存条目已导入Test实例，实例已被替换时忽略
+    pub fn mark_imported(&self, instance: &TestInstanceConfig, entry_id: &str) {
+        let mut test_instance = self.test_instance.write().unwrap();
+        if let Some(ref mut current) = *test_instance
+            && current.id == instance.id
+            && current.created_at == instance.created_at
+        {
+            current.imported_entries.insert(entry_id.to_string());
         }
+    }
 
-        info!("缓存数据导入完成");
-        Ok(())
+    /// 按模板生成Test实例过期提醒内容
+    fn reminder_content(&self, instance: &TestInstanceConfig) -> String {
+        let age_hours = self.get_current_timestamp().saturating_sub(instance.created_at) / 3600;
+        self.config.reminder.template
+            .replace("{id}", &instance.id)
+            .replace("{url}", &instance.url)
+            .replace("{age_hours}", &age_hours.to_string())
+            .replace("{created_at}", &format_timestamp(instance.created_at))
+            .replace("{expired_at}", &format_timestamp(instance.expired_at))
     }
 
-    /// 发送企业微信提醒
-    pub async fn send_wechat_reminder(&self) -> Result<()> {
-        if self.wechat_webhook_url.is_empty() {
-            warn!("企业微信机器人URL未配置，无法发送提醒");
+    /// 发送Test实例过期提醒
+    pub async fn send_reminder(&self, instance: &TestInstanceConfig) -> Result<()> {
+        let Some(ref notifier) = self.notifier else {
+            warn!("提醒Webhook URL未配置，无法发送提醒");
             return Ok(());
-        }
+        };
 
-        let message = serde_json::json!({
-            "msgtype": "text",
-            "text": {
-                "content": "Test实例已存在超过48小时，请及时处理",
-            }
-        });
+        // 超出频率限制的提醒不发送，数量合并到下一条提醒中
+        let channel = format!("{}:{}", notifier.name(), EXPIRED_REMINDER_LEVEL);
+        let suppressed = match self.reminder_limiter.check(&channel) {
+            RateDecision::Send { suppressed } => suppressed,
+            RateDecision::Suppress => {
+                warn!("{}提醒超出频率限制，本条提醒未发送", notifier.name());
+                return Ok(());
+            },
+        };
 
-        let _response = self.http_client
-            .post(&self.wechat_webhook_url)
-            .json(&message)
-            .send()
-            .await?
-            .error_for_status()?;
+        let mut content = self.reminder_content(instance);
+        if suppressed > 0 {
+            content.push_str(&format!("\n（另有 {} 条提醒因频率限制未发送）", suppressed));
+        }
+
+        notifier.send(&content).await?;
 
-        info!("已发送企业微信提醒");
+        info!("已发送{}提醒", notifier.name());
         Ok(())
     }
 
     /// 启动定期检查
-    pub async fn start_periodic_check(&self) {
+    pub async fn start_periodic_check(&self, mut shutdown: watch::Receiver<bool>) -> JoinHandle<()> {
         let test_instance_manager = self.clone();
         tokio::spawn(async move {
-            let mut interval = interval(Duration::from_secs(3600)); // 每小时检查一次
+            // 每小时检查一次，重复提醒间隔更短时按提醒间隔检查
+            let repeat_interval = test_instance_manager.config.reminder.repeat_interval;
+            let check_interval = if repeat_interval > 0 { repeat_interval.min(3600) } else { 3600 };
+            let mut interval = interval(Duration::from_secs(check_interval));
             loop {
-                interval.tick().await;
+                tokio::select! {
+                    _ = interval.tick() => {},
+                    _ = shutdown.changed() => break,
+                }
                 if let Err(e) = test_instance_manager.periodic_check().await {
                     error!("定期检查失败: {:?}", e);
                 }
             }
-        });
+            info!("Test实例定期检查任务已停止");
+        })
     }
 
-    /// 定期检查Test实例
-    async fn periodic_check(&self) -> Result<()> {
-        let current_timestamp = self.get_current_timestamp();
-        
-        // 检查Test实例是否存在
-        let test_instance = self.test_instance.read().unwrap().clone();
-        if let Some(instance) = test_instance {
-            // 检查Test实例是否过期
-            if current_timestamp > instance.expired_at && instance.state != TestInstanceState::Expired {
-                // 更新Test实例状态
-                {  // 使用块确保锁在await前释放
-                    let mut test_instance_write = self.test_instance.write().unwrap();
-                    if let Some(ref mut instance_write) = *test_instance_write {
-                        instance_write.state = TestInstanceState::Expired;
-                        info!("Test实例已过期: {:?}", instance_write);
-                    }
-                    // 锁会在这里自动释放
+    /// 启动Test实例健康检查，未配置检查间隔时返回None
+    pub async fn start_health_check(&self, mut shutdown: watch::Receiver<bool>) -> Option<JoinHandle<()>> {
+        let health_interval = self.config.failover.test_instance_health_interval;
+        if health_interval == 0 {
+            return None;
+        }
+
+        let test_instance_manager = self.clone();
+        Some(tokio::spawn(async move {
+            let mut interval = interval(Duration::from_secs(health_interval));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {},
+                    _ = shutdown.changed() => break,
                 }
+                if let Err(e) = test_instance_manager.check_health().await {
+                    error!("Test实例健康检查失败: {:?}", e);
+                }
+            }
+            info!("Test实例健康检查任务已停止");
+        }))
+    }
+
+    /// 检查未过期的Test实例是否健康，连续失败达到阈值时按配置销毁或重新创建
+    pub async fn check_health(&self) -> Result<()> {
+        let Some(instance) = self.get_test_instance() else {
+            return Ok(());
+        };
+        if instance.state != TestInstanceState::Created {
+            return Ok(());
+        }
 
-                // 发送企业微信提醒
-                if let Err(e) = self.send_wechat_reminder().await {
-                    warn!("发送企业微信提醒失败: {:?}", e);
+        let health_url = format!("{}/health", instance.url);
+        let healthy = matches!(
+            self.http_client.get(&health_url).send().await,
+            Ok(response) if response.status().is_success()
+        );
+
+        let threshold = self.config.failover.test_instance_unhealthy_threshold.max(1);
+        let became_unhealthy = {
+            let mut test_instance = self.test_instance.write().unwrap();
+            let Some(ref mut current) = *test_instance else {
+                return Ok(());
+            };
+            // 检查期间实例已被替换时忽略本次结果
+            if current.id != instance.id || current.created_at != instance.created_at {
+                return Ok(());
+            }
+
+            current.last_checked_at = Some(self.get_current_timestamp());
+            if healthy {
+                if current.health == InstanceHealthStatus::Unhealthy {
+                    info!("Test实例 {} 已恢复健康", current.id);
+                }
+                current.health = InstanceHealthStatus::Healthy;
+                current.consecutive_failures = 0;
+                false
+            } else {
+                current.consecutive_failures += 1;
+                let reached = current.consecutive_failures >= threshold && current.health != InstanceHealthStatus::Unhealthy;
+                if reached {
+                    current.health = InstanceHealthStatus::Unhealthy;
                 }
+                warn!("Test实例 {} 健康检查失败，连续失败 {} 次", current.id, current.consecutive_failures);
+                reached
+            }
+        };
+
+        if became_unhealthy {
+            match self.config.failover.test_instance_unhealthy_action {
+                UnhealthyAction::None => warn!("Test实例 {} 不健康", instance.id),
+                UnhealthyAction::Teardown => {
+                    warn!("Test实例 {} 不健康，销毁实例", instance.id);
+                    self.teardown();
+                },
+                UnhealthyAction::Reprovision => {
+                    warn!("Test实例 {} 不健康，重新创建实例", instance.id);
+                    self.teardown();
+                    self.create_test_instance().await?;
+                },
             }
         }
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// 销毁当前Test实例
+    fn teardown(&self) {
+        if let Some(instance) = self.test_instance.write().unwrap().take() {
+            info!("已销毁Test实例: {}", instance.id);
+        }
+    }
+
+    /// 定期检查Test实例，过期后发送提醒，并按配置的间隔重复提醒
+    async fn periodic_check(&self) -> Result<()> {
+        let current_timestamp = self.get_current_timestamp();
+        let repeat_interval = self.config.reminder.repeat_interval;
+
+        // 检查Test实例是否过期以及是否需要提醒，使用块确保锁在await前释放
+        let reminder = {
+            let mut test_instance = self.test_instance.write().unwrap();
+            match *test_instance {
+                Some(ref mut instance) if current_timestamp > instance.expired_at => {
+                    if instance.state != TestInstanceState::Expired {
+                        instance.state = TestInstanceState::Expired;
+                        info!("Test实例已过期: {:?}", instance);
+                    }
+                    let due = match instance.last_reminded_at {
+                        None => true,
+                        Some(last) => repeat_interval > 0 && current_timestamp.saturating_sub(last) >= repeat_interval,
+                    };
+                    if due {
+                        instance.last_reminded_at = Some(current_timestamp);
+                    }
+                    due.then(|| instance.clone())
+                },
+                _ => None,
+            }
+        };
+
+        // 发送过期提醒
+        if let Some(instance) = reminder
+            && let Err(e) = self.send_reminder(&instance).await
+        {
+            warn!("发送提醒失败: {:?}", e);
+        }
+
+        Ok(())
+    }
+}
+
+/// 将秒级时间戳格式化为RFC 3339时间
+fn format_timestamp(timestamp: u64) -> String {
+    chrono::DateTime::from_timestamp(timestamp as i64, 0)
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    use axum::extract::State;
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+
+    use super::*;
+
+    /// 模拟Webhook收到的提醒内容
+    type Messages = Arc<Mutex<Vec<String>>>;
+
+    /// 启动记录提醒内容的通用JSON Webhook，返回地址和收到的提醒
+    async fn mock_webhook() -> (String, Messages) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/webhook", listener.local_addr().unwrap());
+        let messages = Messages::default();
+        let app = Router::new()
+            .route("/webhook", post(|State(messages): State<Messages>, Json(body): Json<serde_json::Value>| async move {
+                messages.lock().unwrap().push(body["message"].as_str().unwrap_or_default().to_string());
+            }))
+            .with_state(messages.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, messages)
+    }
+
+    fn expired_instance() -> TestInstanceConfig {
+        TestInstanceConfig {
+            id: "test-1".to_string(),
+            url: "http://127.0.0.1:1".to_string(),
+            db_prefix: "test_".to_string(),
+            created_at: 0,
+            expired_at: 0,
+            state: TestInstanceState::Expired,
+            health: InstanceHealthStatus::Unknown,
+            consecutive_failures: 0,
+            last_checked_at: None,
+            last_reminded_at: None,
+            imported_entries: HashSet::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn rapid_reminders_are_rate_limited_and_summarized() {
+        let (webhook_url, messages) = mock_webhook().await;
+        let config = AppConfig::from_vars(&[
+            ("CRUD_API_WRITE_INSTANCE_URL", "http://127.0.0.1:1"),
+            ("NOTIFIER_TYPE", "generic"),
+            ("NOTIFIER_WEBHOOK_URL", &webhook_url),
+            ("REMINDER_RATE_LIMIT", "3"),
+            ("REMINDER_RATE_WINDOW", "1"),
+        ]).unwrap();
+        let manager = TestInstanceManager::new(Arc::new(config), Client::new());
+        let instance = expired_instance();
+
+        for _ in 0..10 {
+            manager.send_reminder(&instance).await.unwrap();
+        }
+        assert_eq!(messages.lock().unwrap().len(), 3);
+        assert!(messages.lock().unwrap().iter().all(|message| !message.contains("频率限制")));
+
+        // 窗口结束后的提醒附带被合并的提醒数
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        manager.send_reminder(&instance).await.unwrap();
+        let messages = messages.lock().unwrap();
+        assert_eq!(messages.len(), 4);
+        assert!(messages[3].contains("另有 7 条提醒因频率限制未发送"));
+    }
+
+    /// 模拟Test实例创建接口和Test实例：每次创建返回新的实例ID，实例健康状态可切换
+    #[derive(Clone)]
+    struct MockProvisioner {
+        base: String,
+        provisioned: Arc<AtomicUsize>,
+        healthy: Arc<AtomicBool>,
+    }
+
+    async fn mock_provisioner() -> MockProvisioner {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mock = MockProvisioner {
+            base: format!("http://{}", listener.local_addr().unwrap()),
+            provisioned: Arc::new(AtomicUsize::new(0)),
+            healthy: Arc::new(AtomicBool::new(true)),
+        };
+        let app = Router::new()
+            .route("/provision", post(|State(mock): State<MockProvisioner>| async move {
+                let n = mock.provisioned.fetch_add(1, Ordering::SeqCst) + 1;
+                Json(serde_json::json!({ "data": { "id": format!("test-{}", n), "url": format!("{}/instance", mock.base) } }))
+            }))
+            .route("/instance/health", get(|State(mock): State<MockProvisioner>| async move {
+                if mock.healthy.load(Ordering::SeqCst) { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE }.into_response()
+            }))
+            .with_state(mock.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        mock
+    }
+
+    fn manager(mock: &MockProvisioner, vars: &[(&str, &str)]) -> TestInstanceManager {
+        let provision_url = format!("{}/provision", mock.base);
+        let mut vars = vars.to_vec();
+        vars.push(("CRUD_API_WRITE_INSTANCE_URL", "http://127.0.0.1:1"));
+        vars.push(("TEST_INSTANCE_PROVISION_URL", &provision_url));
+        TestInstanceManager::new(Arc::new(AppConfig::from_vars(&vars).unwrap()), Client::new())
+    }
+
+    /// 创建Test实例并在实例变为不健康后检查指定次数
+    async fn provision_then_fail(mock: &MockProvisioner, manager: &TestInstanceManager, checks: usize) {
+        manager.create_test_instance().await.unwrap();
+        manager.check_health().await.unwrap();
+        assert_eq!(manager.get_test_instance().unwrap().health, InstanceHealthStatus::Healthy);
+
+        mock.healthy.store(false, Ordering::SeqCst);
+        for _ in 0..checks {
+            manager.check_health().await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn unhealthy_test_instance_is_reported() {
+        let mock = mock_provisioner().await;
+        let manager = manager(&mock, &[("TEST_INSTANCE_UNHEALTHY_THRESHOLD", "2")]);
+        provision_then_fail(&mock, &manager, 1).await;
+        // 未达到阈值前保持健康状态
+        let instance = manager.get_test_instance().unwrap();
+        assert_eq!((instance.health, instance.consecutive_failures), (InstanceHealthStatus::Healthy, 1));
+
+        manager.check_health().await.unwrap();
+        let instance = manager.get_test_instance().unwrap();
+        assert_eq!((instance.health, instance.consecutive_failures), (InstanceHealthStatus::Unhealthy, 2));
+    }
+
+    #[tokio::test]
+    async fn unhealthy_test_instance_is_torn_down() {
+        let mock = mock_provisioner().await;
+        let manager = manager(&mock, &[("TEST_INSTANCE_UNHEALTHY_THRESHOLD", "2"), ("TEST_INSTANCE_UNHEALTHY_ACTION", "teardown")]);
+        provision_then_fail(&mock, &manager, 2).await;
+        assert!(manager.get_test_instance().is_none());
+    }
+
+    #[tokio::test]
+    async fn unhealthy_test_instance_is_reprovisioned() {
+        let mock = mock_provisioner().await;
+        let manager = manager(&mock, &[("TEST_INSTANCE_UNHEALTHY_THRESHOLD", "1"), ("TEST_INSTANCE_UNHEALTHY_ACTION", "reprovision")]);
+        provision_then_fail(&mock, &manager, 1).await;
+
+        let instance = manager.get_test_instance().unwrap();
+        assert_eq!(instance.id, "test-2");
+        assert_eq!(instance.health, InstanceHealthStatus::Unknown);
+        assert_eq!(mock.provisioned.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn provisions_instance_with_configured_lifetime() {
+        let mock = mock_provisioner().await;
+        let manager = manager(&mock, &[("TEST_INSTANCE_TTL", "3600")]);
+
+        let instance = manager.create_test_instance().await.unwrap();
+        assert_eq!(instance.id, "test-1");
+        assert_eq!(instance.url, format!("{}/instance", mock.base));
+        assert_eq!(instance.expired_at - instance.created_at, 3600);
+        assert_eq!(manager.get_test_instance().unwrap().id, "test-1");
+
+        // 未过期的实例直接复用，不重复创建
+        manager.create_test_instance().await.unwrap();
+        assert_eq!(mock.provisioned.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn provisioning_errors_are_surfaced() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let app = Router::new()
+            .route("/provision", post(|| async { StatusCode::INTERNAL_SERVER_ERROR }))
+            .route("/incomplete", post(|| async { Json(serde_json::json!({ "data": { "id": "test-1" } })) }));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        for (path, expected) in [("/provision", "Test实例创建接口返回错误"), ("/incomplete", "Test实例创建接口未返回实例URL")] {
+            let provision_url = format!("{}{}", base, path);
+            let config = AppConfig::from_vars(&[
+                ("CRUD_API_WRITE_INSTANCE_URL", "http://127.0.0.1:1"),
+                ("TEST_INSTANCE_PROVISION_URL", &provision_url),
+            ]).unwrap();
+            let manager = TestInstanceManager::new(Arc::new(config), Client::new());
+            let e = manager.create_test_instance().await.unwrap_err();
+            assert!(e.to_string().contains(expected), "{}", e);
+            assert!(manager.get_test_instance().is_none());
+        }
+    }
+
+    /// 使用通用JSON Webhook发送提醒的管理器，当前Test实例已创建50小时、过期2小时
+    async fn expired_manager(vars: &[(&str, &str)]) -> (TestInstanceManager, Messages) {
+        let (webhook_url, messages) = mock_webhook().await;
+        let mut vars = vars.to_vec();
+        vars.push(("CRUD_API_WRITE_INSTANCE_URL", "http://127.0.0.1:1"));
+        vars.push(("NOTIFIER_TYPE", "generic"));
+        vars.push(("NOTIFIER_WEBHOOK_URL", &webhook_url));
+        let manager = TestInstanceManager::new(Arc::new(AppConfig::from_vars(&vars).unwrap()), Client::new());
+
+        let now = manager.get_current_timestamp();
+        let instance = TestInstanceConfig {
+            created_at: now - 50 * 3600,
+            expired_at: now - 2 * 3600,
+            state: TestInstanceState::Created,
+            ..expired_instance()
+        };
+        *manager.test_instance.write().unwrap() = Some(instance);
+        (manager, messages)
+    }
+
+    #[tokio::test]
+    async fn reminder_uses_template_with_instance_fields() {
+        let (manager, messages) = expired_manager(&[
+            ("TEST_INSTANCE_REMINDER_TEMPLATE", "[{id}] {url} age={age_hours}h expired={expired_at}"),
+        ]).await;
+        manager.periodic_check().await.unwrap();
+
+        let instance = manager.get_test_instance().unwrap();
+        assert_eq!(instance.state, TestInstanceState::Expired);
+        let expected = format!("[test-1] http://127.0.0.1:1 age=50h expired={}", format_timestamp(instance.expired_at));
+        assert_eq!(*messages.lock().unwrap(), [expected]);
+    }
+
+    #[tokio::test]
+    async fn reminder_repeats_while_expired() {
+        let (manager, messages) = expired_manager(&[("TEST_INSTANCE_REMINDER_INTERVAL", "3600")]).await;
+        manager.periodic_check().await.unwrap();
+        assert_eq!(messages.lock().unwrap().len(), 1);
+        assert!(messages.lock().unwrap()[0].contains("Test实例 test-1（http://127.0.0.1:1）已存在50小时"));
+
+        // 提醒间隔内不重复发送
+        manager.periodic_check().await.unwrap();
+        assert_eq!(messages.lock().unwrap().len(), 1);
+
+        // 距上次提醒超过间隔后再次发送
+        if let Some(ref mut instance) = *manager.test_instance.write().unwrap() {
+            instance.last_reminded_at = instance.last_reminded_at.map(|last| last - 3600);
+        }
+        manager.periodic_check().await.unwrap();
+        assert_eq!(messages.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn reminder_sent_once_without_repeat_interval() {
+        let (manager, messages) = expired_manager(&[("TEST_INSTANCE_REMINDER_INTERVAL", "0")]).await;
+        manager.periodic_check().await.unwrap();
+        if let Some(ref mut instance) = *manager.test_instance.write().unwrap() {
+            instance.last_reminded_at = Some(0);
+        }
+        manager.periodic_check().await.unwrap();
+        assert_eq!(messages.lock().unwrap().len(), 1);
+    }
+}